@@ -1,6 +1,31 @@
 #!/usr/bin/env -S cargo +nightly -Zscript
-use core::str;
-use std::collections::HashSet;
+---cargo
+[dependencies]
+serde = { version = "1.0", features = ["derive"] }
+serde_json = "1.0"
+---
+//! Runs a libtest test executable(e.g. the `core` tests) built with the backend, and records the result of each test.
+//!
+//! Usage: `./autotest.rs EXEC_PATH [TIMEOUT] [--suite NAME] [--results FILE]`
+//!
+//! The tests are run with libtest's JSON output. A test which does not finish within `TIMEOUT` seconds(20 by default)
+//! is killed, and a test which crashes the test process is re-run in a process of its own, to confirm the crash. The
+//! remaining tests are then run in a new process. If the process keeps exiting before reaching some tests(e.g. by
+//! crashing between tests), those tests are listed, and recorded as not run.
+//!
+//! Results are stored per suite(the executable name by default) and per backend(.NET or C, depending on `C_MODE`) in
+//! a versioned JSON file(`test_results.json` by default). The previous results of a suite are used as the baseline:
+//! newly passing and newly failing tests are reported, before the baseline gets replaced.
+use std::{
+    collections::BTreeMap,
+    io::BufRead,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::mpsc::RecvTimeoutError,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
 #[macro_export]
 macro_rules! config {
     ($name:ident,bool,$default:expr) => {
@@ -50,103 +75,288 @@ macro_rules! config {
     };
 }
 config!(C_MODE, bool, false);
-fn main() {
-    let exec_path = std::env::args().nth(1).unwrap();
-    let mut ok: HashSet<String> = HashSet::default();
-    let mut failures: HashSet<String> = HashSet::default();
-    let mut broken: Vec<String> = Vec::default();
-    let timeout = std::env::args().nth(2).unwrap_or("20".to_owned());
-    let mut shuffles = 4;
-    loop {
-        let mut cmd = std::process::Command::new("timeout");
-        cmd.arg("-k");
-        cmd.arg(&timeout);
-        cmd.arg(&timeout);
-        if !*C_MODE {
-            cmd.arg("dotnet");
+/// Version of the result file format.
+const RESULTS_VERSION: u32 = 1;
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+enum Status {
+    Ok,
+    Failed,
+    Ignored,
+    /// The test crashed the test process, even when run on its own.
+    Crashed,
+    /// The test did not finish in time.
+    Timeout,
+    /// The test was never run, since the test process kept exiting before reaching it.
+    NotRun,
+}
+/// The results of a single suite, on a single backend.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SuiteResults {
+    /// Commit the tests were run on.
+    commit: String,
+    tests: BTreeMap<String, Status>,
+}
+impl SuiteResults {
+    fn count(&self, status: Status) -> usize {
+        self.tests.values().filter(|test| **test == status).count()
+    }
+    /// A row of the README test table.
+    fn table_row(&self, suite: &str) -> String {
+        let (ok, failed) = (self.count(Status::Ok), self.count(Status::Failed));
+        let broken =
+            self.count(Status::Crashed) + self.count(Status::Timeout) + self.count(Status::NotRun);
+        let percentage = 100.0 * ok as f64 / (ok + failed + broken).max(1) as f64;
+        format!("| {suite} | {ok} | {failed} | {broken} | {percentage:.2}% |")
+    }
+}
+#[derive(Debug, Serialize, Deserialize)]
+struct Results {
+    version: u32,
+    /// Keyed by `suite/backend`.
+    suites: BTreeMap<String, SuiteResults>,
+}
+impl Results {
+    fn load(path: &Path) -> Self {
+        let Ok(file) = std::fs::read_to_string(path) else {
+            return Self {
+                version: RESULTS_VERSION,
+                suites: BTreeMap::new(),
+            };
+        };
+        let results: Self = serde_json::from_str(&file)
+            .unwrap_or_else(|err| panic!("Could not parse the test results {path:?}: {err}"));
+        assert_eq!(
+            results.version, RESULTS_VERSION,
+            "Test results {path:?} have an unsupported version"
+        );
+        results
+    }
+    fn save(&self, path: &Path) {
+        std::fs::write(path, serde_json::to_string_pretty(self).unwrap())
+            .unwrap_or_else(|err| panic!("Could not save the test results {path:?}: {err}"));
+    }
+}
+/// What happened during a single run of the test executable.
+struct Batch {
+    finished: Vec<(String, Status)>,
+    /// The test which was running when the process crashed or got killed, and its status.
+    interrupted: Option<(String, Status)>,
+}
+fn test_command(exec_path: &str) -> Command {
+    if *C_MODE {
+        Command::new(exec_path)
+    } else {
+        let mut cmd = Command::new("dotnet");
+        cmd.arg(exec_path);
+        cmd
+    }
+}
+/// The names of all the tests in `exec_path`.
+fn list_tests(exec_path: &str) -> Vec<String> {
+    let out = test_command(exec_path)
+        .args(["--list", "--format", "terse"])
+        .output()
+        .expect("Could not run the test executable");
+    assert!(
+        out.status.success(),
+        "Could not list the tests: {}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    String::from_utf8_lossy(&out.stdout)
+        .lines()
+        .filter_map(|line| line.strip_suffix(": test"))
+        .map(str::to_owned)
+        .collect()
+}
+/// Runs the tests in `exec_path`, skipping all tests in `skip`, or only the test `only`.
+fn run_batch(exec_path: &str, skip: &[&String], only: Option<&str>, timeout: Duration) -> Batch {
+    let mut cmd = test_command(exec_path);
+    cmd.args(["--format", "json", "-Z", "unstable-options"])
+        .args(["--test-threads=1", "--exact"])
+        .args(skip.iter().flat_map(|test| ["--skip", test.as_str()]))
+        .args(only)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        // Allows `-Z unstable-options` even if the tests were not built by a nightly compiler.
+        .env("RUSTC_BOOTSTRAP", "1");
+    let mut child = cmd.spawn().expect("Could not run the test executable");
+    let stdout = child.stdout.take().unwrap();
+    let (sender, events) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        for line in std::io::BufReader::new(stdout).lines() {
+            let Ok(line) = line else { break };
+            if sender.send(line).is_err() {
+                break;
+            }
         }
-
-        cmd.arg(exec_path.clone());
-        if shuffles > 0 {
-            cmd.arg("--shuffle");
-            cmd.arg("-Z");
-            cmd.arg("unstable-options");
+    });
+    let mut finished = vec![];
+    let mut running: Option<String> = None;
+    loop {
+        let line = match events.recv_timeout(timeout) {
+            Ok(line) => line,
+            Err(RecvTimeoutError::Disconnected) => break,
+            Err(RecvTimeoutError::Timeout) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Batch {
+                    finished,
+                    interrupted: running.map(|test| (test, Status::Timeout)),
+                };
+            }
+        };
+        // Tests may print to stdout too, so lines which are not libtest events are ignored.
+        let Ok(event) = serde_json::from_str::<serde_json::Value>(&line) else {
+            continue;
+        };
+        if event["type"] != "test" {
+            continue;
         }
-        cmd.arg("--test-threads=1");
-        cmd.args(broken.iter().flat_map(|arg| ["--skip", arg]));
-        println!("\n{cmd:?}");
-        println!(
-            "\nsearch status: ok:{ok}, failures:{failures} broken:{broken}",
-            ok = ok.len(),
-            failures = failures.len(),
-            broken = broken.len()
-        );
-        let out = cmd.output().unwrap();
-        let stderr = str::from_utf8(&out.stderr).unwrap().to_string();
-        eprintln!("stderr:{stderr}");
-        let stdout = str::from_utf8(&out.stdout).unwrap().to_string();
-        for line in stdout.lines() {
-            if !line.contains("test ") || line.contains("finished in") {
+        let Some(name) = event["name"].as_str() else {
+            continue;
+        };
+        let status = match event["event"].as_str() {
+            Some("started") => {
+                running = Some(name.to_owned());
                 continue;
             }
-            let mut split = line.split("...");
-
-            let name = match split.next() {
-                Some(some) => some,
-                None => continue,
-            };
-            let name = name["test ".len()..].trim();
-            let name = name.split("-").next().unwrap().trim();
-            let status = split.next().unwrap_or("");
-
-            if status.contains("ok") {
-                ok.insert(name.to_owned());
-            } else if status.contains("FAILED") {
-                failures.insert(name.to_owned());
-            } else if status.contains("ignored") {
+            Some("ok") => Status::Ok,
+            Some("failed") => Status::Failed,
+            Some("ignored") => Status::Ignored,
+            _ => continue,
+        };
+        running = None;
+        finished.push((name.to_owned(), status));
+    }
+    let _ = child.wait();
+    Batch {
+        finished,
+        interrupted: running.map(|test| (test, Status::Crashed)),
+    }
+}
+/// Runs all the tests in `exec_path`, restarting the test process after each crash.
+fn run_all(exec_path: &str, timeout: Duration) -> SuiteResults {
+    let all_tests = list_tests(exec_path);
+    let mut tests = BTreeMap::new();
+    loop {
+        let skip: Vec<_> = tests.keys().collect();
+        println!("running tests, {} done", skip.len());
+        let batch = run_batch(exec_path, &skip, None, timeout);
+        let progress = !batch.finished.is_empty();
+        tests.extend(batch.finished);
+        let Some((test, status)) = batch.interrupted else {
+            if all_tests.iter().all(|test| tests.contains_key(test)) {
+                break;
+            }
+            // The process exited outside of any test. Restart it, unless it did not get any further this time.
+            if progress {
                 continue;
-            } else if !broken.iter().any(|exisitng| exisitng == name) {
-                broken.push(name.to_owned());
             }
-        }
-        if stderr.contains("failures:")
-            | stdout.contains("failures:")
-            | stderr.contains("finished")
-            | stdout.contains("finished")
-        {
-            if shuffles > 0 {
-                shuffles -= 1;
-            } else {
-                println!("search done.");
-                break;
+            let not_run: Vec<_> = all_tests
+                .iter()
+                .filter(|test| !tests.contains_key(*test))
+                .cloned()
+                .collect();
+            println!("{} tests could not be run:", not_run.len());
+            for test in not_run {
+                println!("{test}");
+                tests.insert(test, Status::NotRun);
             }
+            break;
+        };
+        // Re-run the test on its own, to check it is not just a victim of the state left by another test.
+        let alone = run_batch(exec_path, &[], Some(&test), timeout);
+        let status = alone
+            .finished
+            .into_iter()
+            .find(|(name, _)| *name == test)
+            .map_or(status, |(_, status)| status);
+        println!("{test}: {status:?}");
+        tests.insert(test, status);
+    }
+    SuiteResults {
+        commit: current_commit(),
+        tests,
+    }
+}
+fn current_commit() -> String {
+    Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .map(|out| String::from_utf8_lossy(&out.stdout).trim().to_owned())
+        .unwrap_or_default()
+}
+/// Prints the tests whose status changed since `baseline`.
+fn print_diff(baseline: &SuiteResults, current: &SuiteResults) {
+    let mut newly_passing = vec![];
+    let mut newly_failing = vec![];
+    for (test, status) in &current.tests {
+        let old = baseline.tests.get(test);
+        if *status == Status::Ok && old.is_some_and(|old| *old != Status::Ok) {
+            newly_passing.push(test);
+        } else if *status != Status::Ok && old == Some(&Status::Ok) {
+            newly_failing.push((test, status));
         }
     }
-    println!();
+    println!("Compared to the baseline from commit {}:", baseline.commit);
+    println!("NEWLY PASSING:");
+    for test in &newly_passing {
+        println!("{test}");
+    }
+    println!("NEWLY FAILING:");
+    for (test, status) in &newly_failing {
+        println!("{test} ({status:?})");
+    }
+    let removed = baseline
+        .tests
+        .keys()
+        .filter(|test| !current.tests.contains_key(*test))
+        .count();
+    println!(
+        "{} newly passing, {} newly failing, {removed} tests removed",
+        newly_passing.len(),
+        newly_failing.len()
+    );
+}
+fn main() {
+    let usage = "Usage: autotest EXEC_PATH [TIMEOUT] [--suite NAME] [--results FILE]";
+    let mut args = std::env::args().skip(1);
+    let exec_path = args.next().expect(usage);
+    let mut timeout = 20;
+    let mut suite = None;
+    let mut results_path = PathBuf::from("test_results.json");
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--suite" => suite = Some(args.next().expect(usage)),
+            "--results" => results_path = args.next().expect(usage).into(),
+            _ => timeout = arg.parse().expect(usage),
+        }
+    }
+    // Test executables are named like `coretests-0123456789abcdef`.
+    let suite = suite.unwrap_or_else(|| {
+        let stem = Path::new(&exec_path).file_stem().unwrap().to_string_lossy();
+        stem.split('-').next().unwrap().to_owned()
+    });
+    let key = format!("{suite}/{}", if *C_MODE { "c" } else { "dotnet" });
+    let current = run_all(&exec_path, Duration::from_secs(timeout));
+
     println!("BROKEN:");
-    for broken in &broken {
-        println!("{broken}");
+    for (test, status) in &current.tests {
+        if matches!(status, Status::Crashed | Status::Timeout | Status::NotRun) {
+            println!("{test}");
+        }
     }
     println!("FAILURES:");
-    for faliure in &failures {
-        println!("{faliure}");
+    for (test, status) in &current.tests {
+        if *status == Status::Failed {
+            println!("{test}");
+        }
     }
-    println!("COMMAND:");
-    if *C_MODE {
-        let mut cmd = std::process::Command::new(exec_path.clone());
-
-        cmd.args(broken.iter().flat_map(|arg| ["--skip", arg]));
-        println!("{cmd:?}");
-    } else {
-        let mut cmd = std::process::Command::new("dotnet");
-        cmd.arg(exec_path.clone());
-        cmd.args(broken.iter().flat_map(|arg| ["--skip", arg]));
-        println!("{cmd:?}");
+    let mut results = Results::load(&results_path);
+    if let Some(baseline) = results.suites.get(&key) {
+        print_diff(baseline, &current);
     }
-    println!(
-        "\nsearch result: ok:{ok}, failures:{failures} broken:{broken}",
-        ok = ok.len(),
-        failures = failures.len(),
-        broken = broken.len()
-    );
+    println!("{}", current.table_row(&suite));
+    results.suites.insert(key, current);
+    results.save(&results_path);
 }
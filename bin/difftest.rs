@@ -0,0 +1,548 @@
+#!/usr/bin/env -S cargo +nightly -Zscript
+---cargo
+[dependencies]
+rayon = "1.10.0"
+serde = { version = "1.0", features = ["derive"] }
+serde_json = "1.0"
+---
+//! Three-way differential tester: builds the same program with native `rustc`, with the backend exporting .NET assemblies
+//! and with the backend exporting C(`C_MODE`), and compares the stdout, exit code and panic message of all three.
+//!
+//! Usage: `./difftest.rs [--db FILE] [--modes dotnet,c] [--fuzz GENERATOR START END] [PATHS...]`
+//!
+//! `--modes` selects the backend modes compared against native(both by default).
+//! `PATHS` are Rust source files, or directories searched(recursively) for them. With `--fuzz`, `GENERATOR -p ID` is used
+//! to generate one test case for each `ID` in `START..END`, like in `fuzz.rs`.
+//! Divergences from the native build are grouped into buckets, by the first differing line. The results are recorded in a
+//! JSON database(`difftest.json` by default), under the current commit. Cases which agreed on the previously recorded
+//! commit, but diverge now, are reported as regressions.
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use serde::{Deserialize, Serialize};
+/// Version of the result database format.
+const DB_VERSION: u32 = 1;
+/// How long may a single test program run, in seconds.
+const TIMEOUT: &str = "10";
+/// Exit code returned by `timeout`, when the program took too long.
+const TIMEOUT_EXIT: i32 = 124;
+const TEST_DIR: &str = "/tmp/difftest";
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+enum Mode {
+    Native,
+    Dotnet,
+    C,
+}
+impl Mode {
+    const ALL: [Self; 3] = [Self::Native, Self::Dotnet, Self::C];
+    fn name(self) -> &'static str {
+        match self {
+            Self::Native => "native",
+            Self::Dotnet => "dotnet",
+            Self::C => "c",
+        }
+    }
+}
+/// The observable behaviour of a test program, built in one mode.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+struct Outcome {
+    /// Compiler error, if the program failed to build.
+    compile_error: Option<String>,
+    stdout: String,
+    /// `None` if the process was killed by a signal.
+    exit_code: Option<i32>,
+    /// The message of the first panic, if any.
+    panic: Option<String>,
+}
+impl Outcome {
+    fn compile_failure(stderr: &str) -> Self {
+        Self {
+            compile_error: Some(first_error(stderr)),
+            stdout: String::new(),
+            exit_code: None,
+            panic: None,
+        }
+    }
+    /// Describes the first difference between `self`(the expected behaviour) and `other`, or returns `None` if they agree.
+    fn first_difference(&self, other: &Self) -> Option<String> {
+        if let Some(err) = &other.compile_error {
+            return (self.compile_error.is_none()).then(|| format!("compile error: {err}"));
+        }
+        let expected: Vec<_> = self.stdout.lines().collect();
+        let got: Vec<_> = other.stdout.lines().collect();
+        if let Some(line) =
+            (0..expected.len().max(got.len())).find(|line| expected.get(*line) != got.get(*line))
+        {
+            return Some(format!(
+                "stdout: expected {:?}, got {:?}",
+                expected.get(line).unwrap_or(&"<EOF>"),
+                got.get(line).unwrap_or(&"<EOF>")
+            ));
+        }
+        if self.panic != other.panic {
+            return Some(format!(
+                "panic: expected {:?}, got {:?}",
+                self.panic, other.panic
+            ));
+        }
+        if self.exit_code != other.exit_code {
+            return Some(match other.exit_code {
+                Some(TIMEOUT_EXIT) => "timeout".into(),
+                Some(code) => format!("exit code: expected {:?}, got {code}", self.exit_code),
+                None => "killed by a signal".into(),
+            });
+        }
+        None
+    }
+}
+/// The result of comparing one test case across all modes.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+struct CaseResult {
+    /// For each non-native mode, the first difference from native, if any.
+    divergences: BTreeMap<String, String>,
+    outcomes: BTreeMap<String, Outcome>,
+}
+impl CaseResult {
+    fn agrees(&self) -> bool {
+        self.divergences.is_empty()
+    }
+}
+#[derive(Debug, Serialize, Deserialize)]
+struct Run {
+    commit: String,
+    /// Seconds since the UNIX epoch.
+    time: u64,
+    cases: BTreeMap<String, CaseResult>,
+}
+#[derive(Debug, Serialize, Deserialize)]
+struct Database {
+    version: u32,
+    runs: Vec<Run>,
+}
+impl Database {
+    fn load(path: &Path) -> Self {
+        let Ok(file) = std::fs::read_to_string(path) else {
+            return Self {
+                version: DB_VERSION,
+                runs: vec![],
+            };
+        };
+        let db: Self = serde_json::from_str(&file)
+            .unwrap_or_else(|err| panic!("Could not parse the result database {path:?}: {err}"));
+        assert_eq!(
+            db.version, DB_VERSION,
+            "Result database {path:?} has an unsupported version"
+        );
+        db
+    }
+    fn save(&self, path: &Path) {
+        std::fs::write(path, serde_json::to_string_pretty(self).unwrap())
+            .unwrap_or_else(|err| panic!("Could not save the result database {path:?}: {err}"));
+    }
+    /// The last recorded result of `case`, from a commit other than `commit`.
+    fn previous(&self, commit: &str, case: &str) -> Option<&CaseResult> {
+        self.runs
+            .iter()
+            .rev()
+            .filter(|run| run.commit != commit)
+            .find_map(|run| run.cases.get(case))
+    }
+    /// Records `cases` under `commit`, merging them with any results already recorded for that commit.
+    fn record(&mut self, commit: String, cases: BTreeMap<String, CaseResult>) {
+        let time = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        match self.runs.iter_mut().find(|run| run.commit == commit) {
+            Some(run) => {
+                run.time = time;
+                run.cases.extend(cases);
+            }
+            None => self.runs.push(Run {
+                commit,
+                time,
+                cases,
+            }),
+        }
+    }
+}
+/// A single test program.
+struct Case {
+    name: String,
+    src: PathBuf,
+}
+/// The current commit, with a `-dirty` suffix if the working tree has uncommitted changes.
+fn current_commit() -> String {
+    let out = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .expect("Could not run git");
+    let commit = String::from_utf8_lossy(&out.stdout).trim().to_owned();
+    let dirty = Command::new("git")
+        .args(["status", "--porcelain", "--untracked-files=no"])
+        .output()
+        .is_ok_and(|out| !out.stdout.is_empty());
+    if dirty {
+        format!("{commit}-dirty")
+    } else {
+        commit
+    }
+}
+/// The first line of the first error reported by the compiler.
+fn first_error(stderr: &str) -> String {
+    stderr
+        .lines()
+        .find(|line| line.starts_with("error"))
+        .or(stderr.lines().find(|line| !line.trim().is_empty()))
+        .unwrap_or("<no message>")
+        .to_owned()
+}
+/// Extracts the message of the first panic from the stderr of a test program.
+fn panic_message(stderr: &str) -> Option<String> {
+    let mut lines = stderr.lines();
+    let line = lines.find(|line| line.contains("panicked at"))?;
+    // Since Rust 1.73, the message is on the line after the location.
+    if line.trim_end().ends_with(':') {
+        lines.next().map(|msg| msg.trim().to_owned())
+    } else {
+        line.split_once("panicked at")
+            .map(|(_, msg)| msg.trim().to_owned())
+    }
+}
+fn build(case: &Case, mode: Mode) -> Result<PathBuf, String> {
+    let dir = Path::new(TEST_DIR).join(mode.name());
+    std::fs::create_dir_all(&dir).expect("Could not setup the test env");
+    let mut cmd = Command::new("rustc");
+    cmd.current_dir(&dir).arg("-O");
+    let exec = match mode {
+        Mode::Native => {
+            let exec = dir.join(format!("{}.elf", case.name));
+            cmd.args(["--edition", "2021"]);
+            exec
+        }
+        Mode::Dotnet | Mode::C => {
+            RUSTC_BUILD_STATUS.as_ref().expect("Could not build rustc!");
+            cmd.args(rustc_args().iter())
+                .env("C_MODE", if mode == Mode::C { "1" } else { "0" });
+            dir.join(format!("{}.exe", case.name))
+        }
+    };
+    let out = cmd
+        .arg(&case.src)
+        .arg("-o")
+        .arg(&exec)
+        .output()
+        .expect("failed to execute rustc");
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    if !out.status.success() {
+        return Err(stderr.into_owned());
+    }
+    Ok(exec)
+}
+fn run(exec: &Path, mode: Mode) -> Outcome {
+    let mut cmd = Command::new("timeout");
+    cmd.current_dir(exec.parent().unwrap())
+        .args(["-k", "5", TIMEOUT]);
+    if mode == Mode::Dotnet {
+        std::fs::write(
+            exec.with_extension("runtimeconfig.json"),
+            get_runtime_config(),
+        )
+        .expect("Could not write runtime config");
+        cmd.arg("dotnet");
+    }
+    let out = cmd.arg(exec).output().expect("failed to run test program!");
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    Outcome {
+        compile_error: None,
+        stdout: String::from_utf8_lossy(&out.stdout).into_owned(),
+        exit_code: out.status.code(),
+        panic: panic_message(&stderr),
+    }
+}
+fn test(case: &Case, modes: &[Mode]) -> CaseResult {
+    let outcomes: BTreeMap<_, _> = Mode::ALL
+        .iter()
+        .filter(|mode| **mode == Mode::Native || modes.contains(mode))
+        .map(|mode| {
+            let outcome = match build(case, *mode) {
+                Ok(exec) => run(&exec, *mode),
+                Err(stderr) => Outcome::compile_failure(&stderr),
+            };
+            (*mode, outcome)
+        })
+        .collect();
+    let native = &outcomes[&Mode::Native];
+    let divergences = outcomes
+        .iter()
+        .filter(|(mode, _)| **mode != Mode::Native)
+        .filter_map(|(mode, outcome)| {
+            Some((mode.name().to_owned(), native.first_difference(outcome)?))
+        })
+        .collect();
+    CaseResult {
+        divergences,
+        outcomes: outcomes
+            .into_iter()
+            .map(|(mode, outcome)| (mode.name().to_owned(), outcome))
+            .collect(),
+    }
+}
+/// Collects all Rust source files in `path`.
+fn collect_sources(path: &Path, cases: &mut Vec<Case>) {
+    if path.is_dir() {
+        let mut entries: Vec<_> = std::fs::read_dir(path)
+            .unwrap_or_else(|err| panic!("Could not read {path:?}: {err}"))
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        entries.sort();
+        for entry in entries {
+            collect_sources(&entry, cases);
+        }
+    } else if path.extension().is_some_and(|ext| ext == "rs") {
+        let src = std::fs::canonicalize(path).unwrap();
+        // Files with the same name in different directories must get distinct case names.
+        let name = path
+            .with_extension("")
+            .components()
+            .filter_map(|comp| match comp {
+                std::path::Component::Normal(part) => part.to_str(),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("_");
+        cases.push(Case { name, src });
+    }
+}
+fn gen_fuzz_case(test_id: u64, generator: &str) -> Case {
+    let src = Path::new(TEST_DIR).join(format!("fuzz{test_id}.rs"));
+    let out = Command::new(generator)
+        .arg("-p")
+        .arg(format!("{test_id}"))
+        .output()
+        .expect("Could not run the fuzz case generator");
+    assert!(out.stderr.is_empty());
+    let mut file = b"#![allow(dead_code,unused_variables)]".to_vec();
+    file.extend(out.stdout);
+    std::fs::write(&src, file).unwrap();
+    Case {
+        name: format!("fuzz{test_id}"),
+        src,
+    }
+}
+fn main() {
+    use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+    let usage =
+        "Usage: difftest [--db FILE] [--modes dotnet,c] [--fuzz GENERATOR START END] [PATHS...]";
+    let mut db_path = PathBuf::from("difftest.json");
+    let mut modes = vec![Mode::Dotnet, Mode::C];
+    let mut cases = vec![];
+    std::fs::create_dir_all(TEST_DIR).expect("Could not setup the test env");
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--db" => db_path = args.next().expect(usage).into(),
+            "--modes" => {
+                modes = args
+                    .next()
+                    .expect(usage)
+                    .split(',')
+                    .map(|mode| match mode {
+                        "dotnet" => Mode::Dotnet,
+                        "c" => Mode::C,
+                        _ => panic!("Unknown mode {mode:?}. {usage}"),
+                    })
+                    .collect();
+            }
+            "--fuzz" => {
+                let generator = args.next().expect(usage);
+                let start: u64 = args.next().expect(usage).parse().expect(usage);
+                let end: u64 = args.next().expect(usage).parse().expect(usage);
+                cases.extend((start..end).map(|id| gen_fuzz_case(id, &generator)));
+            }
+            path => collect_sources(Path::new(path), &mut cases),
+        }
+    }
+    assert!(!cases.is_empty(), "No test cases. {usage}");
+    let results: BTreeMap<String, CaseResult> = cases
+        .par_iter()
+        .map(|case| (case.name.clone(), test(case, &modes)))
+        .collect();
+    // Group the divergences by their first differing line
+    let mut buckets: BTreeMap<(&str, &str), Vec<&str>> = BTreeMap::new();
+    for (name, result) in &results {
+        for (mode, diff) in &result.divergences {
+            buckets.entry((mode, diff)).or_default().push(name);
+        }
+    }
+    let mut buckets: Vec<_> = buckets.into_iter().collect();
+    buckets.sort_by_key(|(_, cases)| std::cmp::Reverse(cases.len()));
+    for ((mode, diff), cases) in &buckets {
+        println!("[{mode}] {diff} ({} cases)", cases.len());
+        for case in cases {
+            println!("    {case}");
+        }
+    }
+    let commit = current_commit();
+    let mut db = Database::load(&db_path);
+    let mut regressions = vec![];
+    let mut fixed = vec![];
+    for (name, result) in &results {
+        match db.previous(&commit, name) {
+            Some(prev) if prev.agrees() && !result.agrees() => regressions.push(name),
+            Some(prev) if !prev.agrees() && result.agrees() => fixed.push(name),
+            _ => (),
+        }
+    }
+    if !fixed.is_empty() {
+        println!("FIXED:");
+        for name in &fixed {
+            println!("    {name}");
+        }
+    }
+    if !regressions.is_empty() {
+        println!("REGRESSIONS:");
+        for name in &regressions {
+            println!("    {name}: {:?}", results[*name].divergences);
+        }
+    }
+    let agree = results.values().filter(|result| result.agrees()).count();
+    println!(
+        "commit {commit}: {agree} of {total} cases agree, {buckets} divergence buckets, {fixed} fixed, {regressed} regressed",
+        total = results.len(),
+        buckets = buckets.len(),
+        fixed = fixed.len(),
+        regressed = regressions.len(),
+    );
+    let failed = !regressions.is_empty();
+    db.record(commit, results);
+    db.save(&db_path);
+    if failed {
+        std::process::exit(1);
+    }
+}
+fn backend_path() -> String {
+    format!("codegen-backend={}", absolute_backend_path().display())
+}
+/// A list of arguments needed for invoking `rustc` with this backend included.
+#[must_use]
+pub fn rustc_args() -> Box<[String]> {
+    [
+        "-Z".to_owned(),
+        backend_path(),
+        "-C".to_owned(),
+        format!("linker={}", RUSTC_CODEGEN_CLR_LINKER.display()),
+        "--edition".to_owned(),
+        "2021".to_owned(),
+    ]
+    .into()
+}
+/// Absolute path to the codegen backend shared library.
+#[must_use]
+pub fn absolute_backend_path() -> PathBuf {
+    if cfg!(debug_assertions) {
+        if cfg!(target_os = "linux") {
+            std::fs::canonicalize("../target/debug/librustc_codegen_clr.so").unwrap()
+        } else if cfg!(target_os = "windows") {
+            std::fs::canonicalize("../target/debug/rustc_codegen_clr.dll").unwrap()
+        } else if cfg!(target_os = "macos") {
+            std::fs::canonicalize("../target/debug/librustc_codegen_clr.dylib").unwrap()
+        } else {
+            panic!("Unsupported target OS");
+        }
+    } else if cfg!(target_os = "linux") {
+        std::fs::canonicalize("../target/release/librustc_codegen_clr.so").unwrap()
+    } else if cfg!(target_os = "windows") {
+        std::fs::canonicalize("../target/release/rustc_codegen_clr.dll").unwrap()
+    } else if cfg!(target_os = "macos") {
+        std::fs::canonicalize("../target/release/librustc_codegen_clr.dylib").unwrap()
+    } else {
+        panic!("Unsupported target OS");
+    }
+}
+static RUSTC_BUILD_STATUS: std::sync::LazyLock<Result<(), String>> =
+    std::sync::LazyLock::new(build_backend);
+static RUSTC_CODEGEN_CLR_LINKER: std::sync::LazyLock<PathBuf> = std::sync::LazyLock::new(|| {
+    let _ = *RUSTC_BUILD_STATUS;
+    if cfg!(debug_assertions) {
+        if cfg!(target_os = "windows") {
+            std::fs::canonicalize("../target/debug/linker.exe").unwrap()
+        } else {
+            std::fs::canonicalize("../target/debug/linker").unwrap()
+        }
+    } else if cfg!(target_os = "windows") {
+        std::fs::canonicalize("../target/release/linker.exe").unwrap()
+    } else {
+        std::fs::canonicalize("../target/release/linker").unwrap()
+    }
+});
+fn build_backend() -> Result<(), String> {
+    let profile: &[&str] = if cfg!(debug_assertions) {
+        &[]
+    } else {
+        &["--release"]
+    };
+    let check = |out: std::process::Output| {
+        if out.status.success() {
+            Ok(())
+        } else {
+            Err(String::from_utf8_lossy(&out.stderr).into_owned())
+        }
+    };
+    check(
+        std::process::Command::new("cargo")
+            .args(["build", "--lib"])
+            .args(profile)
+            .output()
+            .map_err(|err| err.to_string())?,
+    )?;
+    check(
+        std::process::Command::new("cargo")
+            .current_dir("../cilly")
+            .args(["build", "--bin", "linker"])
+            .args(profile)
+            .output()
+            .map_err(|err| err.to_string())?,
+    )
+}
+/// Cached runtime configuration string, obtained from calling the .NET runtime.
+#[must_use]
+pub fn get_runtime_config() -> &'static str {
+    RUNTIME_CONFIG.as_ref()
+}
+
+/// Cached runtime configuration file, obtained from calling the .NET runtime.
+static RUNTIME_CONFIG: std::sync::LazyLock<String> = std::sync::LazyLock::new(|| {
+    let info = std::process::Command::new("dotnet")
+        .arg("--info")
+        .output()
+        .expect("Could not run `dotnet --info`");
+    if !info.stderr.is_empty() {
+        let stderr = std::str::from_utf8(&info.stderr).expect("Error message not utf8");
+        panic!("dotnet --info panicked with {stderr}")
+    }
+    let info = std::str::from_utf8(&info.stdout).expect("Error message not utf8");
+    let version_start = info.find("Host:").unwrap_or_default();
+    let version_start = version_start + info[version_start..].find("Version:").unwrap();
+    let version_start = version_start + "Version:".len();
+    let version_end = info.find("Architecture:").unwrap();
+    let version = &info[version_start..version_end].trim();
+    format!(
+        "{{
+        \"runtimeOptions\": {{
+          \"tfm\": \"net8.0\",
+          \"framework\": {{
+            \"name\": \"Microsoft.NETCore.App\",
+            \"version\": \"{version}\"
+          }},
+          \"configProperties\": {{
+            \"System.Threading.ThreadPool.MinThreads\": 4,
+            \"System.Threading.ThreadPool.MaxThreads\": 25
+          }}
+        }}
+      }}"
+    )
+});
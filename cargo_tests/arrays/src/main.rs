@@ -0,0 +1,78 @@
+//! Checks the bridging between managed arrays and Rust slices.
+#![allow(incomplete_features)]
+#![feature(adt_const_params, unsized_const_params)]
+use mycorrhiza::{
+    intrinsics::{
+        rustc_clr_interop_managed_checked_cast, RustcCLRInteropManagedArray,
+        RustcCLRInteropManagedClass,
+    },
+    system::{array::SystemArray, gc::GC},
+};
+type Object = RustcCLRInteropManagedClass<"System.Runtime", "System.Object">;
+type DoubleArray = RustcCLRInteropManagedArray<f64, 1>;
+fn roundtrip() {
+    let values = [1.0, -2.5, 3.25, f64::MAX];
+    let arr = DoubleArray::from_slice(&values);
+    assert_eq!(arr.total_len(), values.len());
+    assert_eq!(arr.to_vec(), values);
+    let empty = DoubleArray::new(0);
+    assert_eq!(empty.total_len(), 0);
+    assert!(empty.to_vec().is_empty());
+}
+fn shared_with_dotnet() {
+    let arr = RustcCLRInteropManagedArray::<i32, 1>::from_slice(&[1, 2, 3, 4]);
+    // Writes made by .NET are visible from Rust...
+    SystemArray::static1::<"Reverse", SystemArray, ()>(rustc_clr_interop_managed_checked_cast(arr));
+    assert_eq!(arr.to_vec(), [4, 3, 2, 1]);
+    // ...and the other way around.
+    unsafe { arr.with_mut_slice(|elements| elements[0] = 42) };
+    let first = SystemArray::instance1::<"GetValue", i32, Object>(
+        rustc_clr_interop_managed_checked_cast(arr),
+        0,
+    );
+    assert!(
+        first
+            .instance0::<"ToString", mycorrhiza::system::MString>()
+            .to_string()
+            == "42"
+    );
+}
+fn pinned_does_not_move() {
+    let arr = DoubleArray::from_slice(&[0.5; 64]);
+    arr.with_ptr(|elements, len| {
+        // Allocate some garbage, to give the GC a reason to compact the heap.
+        for _ in 0..1024 {
+            Object::ctor0();
+        }
+        GC::collect_all();
+        assert_eq!(arr.with_ptr(|moved, _| moved), elements);
+        let elements = unsafe { core::slice::from_raw_parts(elements, len) };
+        assert!(elements.iter().all(|elem| *elem == 0.5));
+    });
+}
+fn multidimensional() {
+    let arr = RustcCLRInteropManagedArray::<u16, 2>::new_md([2, 3]);
+    assert_eq!(arr.dim_len(0), 2);
+    assert_eq!(arr.dim_len(1), 3);
+    assert_eq!(arr.total_len(), 6);
+    arr.copy_from_slice(&[1, 2, 3, 4, 5, 6]);
+    // Row-major order: the element at [1, 0] is the 4th one.
+    let elem = SystemArray::instance2::<"GetValue", i32, i32, Object>(
+        rustc_clr_interop_managed_checked_cast(arr),
+        1,
+        0,
+    );
+    assert!(
+        elem.instance0::<"ToString", mycorrhiza::system::MString>()
+            .to_string()
+            == "4"
+    );
+    assert_eq!(arr.to_vec(), [1, 2, 3, 4, 5, 6]);
+}
+fn main() {
+    roundtrip();
+    shared_with_dotnet();
+    pinned_does_not_move();
+    multidimensional();
+    println!("Array tests passed.");
+}
@@ -0,0 +1,56 @@
+// Generated by the cilly `bindgen` tool. Do not edit.
+#![allow(non_snake_case, non_camel_case_types, clippy::all)]
+pub mod Acme {
+    /// `Acme.Color`
+    pub type Color = ::mycorrhiza::intrinsics::RustcCLRInteropManagedStruct<"Acme.Lib", "Acme.Color", 4>;
+    /// The members of `Acme.Color`.
+    pub trait ColorApi: ::core::marker::Sized {
+        fn red() -> Self;
+    }
+    impl ColorApi for Color {
+        fn red() -> Self {
+            unsafe { ::core::mem::transmute::<i32, Self>(::core::hint::black_box(1)) }
+        }
+    }
+    /// `Acme.Point`
+    pub type Point = ::mycorrhiza::intrinsics::RustcCLRInteropManagedClass<"Acme.Lib", "Acme.Point">;
+    /// The members of `Acme.Point`.
+    pub trait PointApi: ::core::marker::Sized {
+        const MAX: i32;
+        fn y(self) -> i32;
+        fn set_y(self, value: i32);
+        fn default() -> ::mycorrhiza::intrinsics::RustcCLRInteropManagedStruct<"Acme.Lib", "Acme.Color", 4>;
+        fn set_default(value: ::mycorrhiza::intrinsics::RustcCLRInteropManagedStruct<"Acme.Lib", "Acme.Color", 4>);
+        fn z(self) -> i32;
+        fn new(x: i32) -> Self;
+        fn paint(name: ::mycorrhiza::intrinsics::RustcCLRInteropManagedClass<"System.Runtime", "System.String">) -> ::mycorrhiza::intrinsics::RustcCLRInteropManagedStruct<"Acme.Lib", "Acme.Color", 4>;
+        fn get_x(self) -> i32;
+    }
+    impl PointApi for Point {
+        const MAX: i32 = 5;
+        fn y(self) -> i32 {
+            Self::field::<"Y", _>(self)
+        }
+        fn set_y(self, value: i32) {
+            Self::set_field::<"Y", _>(self, value)
+        }
+        fn default() -> ::mycorrhiza::intrinsics::RustcCLRInteropManagedStruct<"Acme.Lib", "Acme.Color", 4> {
+            Self::static_field::<"Default", _>()
+        }
+        fn set_default(value: ::mycorrhiza::intrinsics::RustcCLRInteropManagedStruct<"Acme.Lib", "Acme.Color", 4>) {
+            Self::set_static_field::<"Default", _>(value)
+        }
+        fn z(self) -> i32 {
+            Self::field::<"Z", _>(self)
+        }
+        fn new(x: i32) -> Self {
+            Self::ctor_n((x,))
+        }
+        fn paint(name: ::mycorrhiza::intrinsics::RustcCLRInteropManagedClass<"System.Runtime", "System.String">) -> ::mycorrhiza::intrinsics::RustcCLRInteropManagedStruct<"Acme.Lib", "Acme.Color", 4> {
+            Self::static_n::<"Paint", _, _>((name,))
+        }
+        fn get_x(self) -> i32 {
+            Self::virt_n::<"GetX", _, _>(self, ())
+        }
+    }
+}
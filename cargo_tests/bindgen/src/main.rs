@@ -0,0 +1,22 @@
+//! Checks the bindings `bindgen` generates build against mycorrhiza. `src/acme.rs` is the output of the `generate` test
+//! of `bindgen`, which fails if it gets out of date.
+#![allow(incomplete_features)]
+#![feature(adt_const_params, unsized_const_params)]
+mod acme;
+use acme::Acme::{Color, ColorApi, Point, PointApi};
+use mycorrhiza::system::MString;
+/// Uses every binding. `Acme.Lib` does not exist, so this is never called.
+fn use_bindings() {
+    let point = Point::new(Point::MAX);
+    point.set_y(point.get_x() + point.z());
+    let _: i32 = point.y();
+    Point::set_default(Color::red());
+    let _: Color = Point::default();
+    let _: Color = Point::paint(MString::from_str("red"));
+}
+fn main() {
+    if std::env::args().any(|arg| arg == "--acme") {
+        use_bindings();
+    }
+    println!("Bindgen tests passed.");
+}
@@ -0,0 +1,70 @@
+//! Checks that Rust closures can be invoked as .NET delegates, including concurrently from many threads.
+#![allow(incomplete_features)]
+#![feature(adt_const_params, unsized_const_params)]
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use mycorrhiza::{
+    class::Class,
+    intrinsics::RustcCLRInteropManagedClass,
+    system::delegate::{Action, EventHandler},
+};
+type Object = RustcCLRInteropManagedClass<"System.Runtime", "System.Object">;
+type EventArgs = RustcCLRInteropManagedClass<"System.Runtime", "System.EventArgs">;
+const THREADS: usize = 4;
+const CALLS: usize = 256;
+fn invoke_action() {
+    let counter = Arc::new(AtomicUsize::new(0));
+    let closure_counter = counter.clone();
+    let action = Action::from_fn(move || {
+        closure_counter.fetch_add(1, Ordering::SeqCst);
+    });
+    action.virt0::<"Invoke", ()>();
+    action.virt0::<"Invoke", ()>();
+    assert_eq!(counter.load(Ordering::SeqCst), 2);
+}
+fn invoke_with_args() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let closure_calls = calls.clone();
+    let handler = EventHandler::from_fn(move |sender: Object, args: EventArgs| {
+        assert!(!sender.is_null());
+        assert!(!args.is_null());
+        closure_calls.fetch_add(1, Ordering::SeqCst);
+    });
+    handler.virt_n::<"Invoke", _, ()>((Object::ctor0(), EventArgs::ctor0()));
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+fn invoke_from_threads() {
+    let counter = Arc::new(AtomicUsize::new(0));
+    let closure_counter = counter.clone();
+    // Naked references can't cross threads, so the delegate is shared through a GC handle.
+    let action: Arc<Class<"System.Runtime", "System.Action">> =
+        Arc::new(Class::from_naked_ref(Action::from_fn(move || {
+            closure_counter.fetch_add(1, Ordering::SeqCst);
+        })));
+    let threads: Vec<_> = (0..THREADS)
+        .map(|_| {
+            let action = action.clone();
+            thread::spawn(move || {
+                for _ in 0..CALLS {
+                    unsafe { action.get_naked_ref() }.virt0::<"Invoke", ()>();
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    assert_eq!(counter.load(Ordering::SeqCst), THREADS * CALLS);
+}
+fn main() {
+    invoke_action();
+    invoke_with_args();
+    invoke_from_threads();
+    println!("Delegate tests passed.");
+}
@@ -0,0 +1,81 @@
+//! Checks the lifetimes of objects owned through GC handles, by forcing collections.
+#![allow(incomplete_features)]
+#![feature(adt_const_params, unsized_const_params)]
+use mycorrhiza::{
+    class::{Class, Pinned, Weak},
+    intrinsics::RustcCLRInteropManagedClass,
+    system::{gc::GC, MString},
+};
+type Object = RustcCLRInteropManagedClass<"System.Runtime", "System.Object">;
+type WeakObject = Weak<"System.Runtime", "System.Object">;
+// The JIT may keep naked references alive until the end of the method they are used in, so they are only ever
+// created in separate, non-inlined functions.
+#[inline(never)]
+fn weak_to_new_object() -> WeakObject {
+    Weak::new(Object::ctor0())
+}
+#[inline(never)]
+fn is_alive(weak: &WeakObject) -> bool {
+    weak.upgrade().is_some()
+}
+fn weak_does_not_keep_alive() {
+    let weak = weak_to_new_object();
+    GC::collect_all();
+    assert!(!is_alive(&weak));
+    // Clones of a weak handle to a collected object are still valid.
+    assert!(!is_alive(&weak.clone()));
+}
+fn strong_keeps_alive() {
+    let strong: Class<"System.Runtime", "System.Object"> = Class::ctor0();
+    let weak = strong.downgrade();
+    GC::collect_all();
+    assert!(is_alive(&weak));
+    let clone = strong.clone();
+    drop(strong);
+    GC::collect_all();
+    assert!(is_alive(&weak));
+    drop(clone);
+    GC::collect_all();
+    assert!(!is_alive(&weak));
+}
+fn handles_on_heap() {
+    // Unlike naked references, handles may be stored on the Rust heap.
+    let objects: Vec<Class<"System.Runtime", "System.Object">> =
+        (0..64).map(|_| Class::ctor0()).collect();
+    let weak: Vec<_> = objects.iter().map(Class::downgrade).collect();
+    GC::collect_all();
+    assert!(weak.iter().all(is_alive));
+    drop(objects);
+    GC::collect_all();
+    assert!(!weak.iter().any(is_alive));
+}
+#[inline(never)]
+fn pin_new_string(value: &str) -> Pinned<"System.Runtime", "System.String"> {
+    Pinned::new(MString::from_str(value))
+}
+fn pinned_does_not_move() {
+    let expected: Vec<u16> = "Pinned!".encode_utf16().collect();
+    let pinned = pin_new_string("Pinned!");
+    let addr = pinned.addr();
+    // Allocate some garbage, to give the GC a reason to compact the heap.
+    for _ in 0..1024 {
+        Object::ctor0();
+    }
+    GC::collect_all();
+    assert_eq!(pinned.addr(), addr);
+    let chars = unsafe { core::slice::from_raw_parts(addr as *const u16, expected.len()) };
+    assert_eq!(chars, &expected[..]);
+    // A pinned object can be turned into a strong handle, which keeps it alive once unpinned.
+    let strong = pinned.to_class();
+    let weak = Weak::new(unsafe { strong.get_naked_ref() });
+    drop(pinned);
+    GC::collect_all();
+    assert!(weak.upgrade().is_some());
+}
+fn main() {
+    weak_does_not_keep_alive();
+    strong_keeps_alive();
+    handles_on_heap();
+    pinned_does_not_move();
+    println!("GC handle tests passed.");
+}
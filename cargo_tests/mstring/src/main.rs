@@ -0,0 +1,40 @@
+//! Checks the conversions between Rust strings and `System.String`.
+#![allow(incomplete_features)]
+#![feature(adt_const_params, unsized_const_params)]
+use mycorrhiza::system::MString;
+fn roundtrip() {
+    for text in [
+        "",
+        "Hello, World!",
+        // Latin-1 and other 2 byte UTF-8 sequences
+        "Zażółć gęślą jaźń, ÄÖÜ ß",
+        // 3 byte UTF-8 sequences
+        "日本語のテキスト",
+        // 4 byte UTF-8 sequences, which become UTF-16 surrogate pairs
+        "🦀 Ferris 🦀, 𝄞",
+    ] {
+        let string = MString::from_str(text);
+        assert_eq!(string.len(), text.encode_utf16().count());
+        assert_eq!(
+            string.pin().as_slice(),
+            text.encode_utf16().collect::<Vec<_>>()
+        );
+        assert_eq!(string.to_string(), text);
+        assert_eq!(String::from(MString::from(text)), text);
+    }
+}
+fn surrogates() {
+    let crab = MString::from_str("🦀");
+    assert_eq!(crab.pin().as_slice(), [0xD83E, 0xDD80]);
+    // A lone surrogate is invalid UTF-16, so it gets replaced.
+    let high = crab.instance2::<"Substring", i32, i32, MString>(0, 1);
+    assert_eq!(high.pin().as_slice(), [0xD83E]);
+    assert_eq!(high.to_string(), "\u{FFFD}");
+    let low = crab.instance1::<"Substring", i32, MString>(1);
+    assert_eq!(low.to_string(), "\u{FFFD}");
+}
+fn main() {
+    roundtrip();
+    surrogates();
+    println!("MString tests passed.");
+}
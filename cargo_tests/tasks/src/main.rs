@@ -0,0 +1,170 @@
+//! Checks awaiting .NET tasks from Rust, and exposing Rust futures to .NET as tasks.
+#![allow(incomplete_features)]
+#![feature(adt_const_params, unsized_const_params)]
+use std::{
+    future::Future,
+    pin::{pin, Pin},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll, Wake, Waker},
+    thread,
+};
+
+use mycorrhiza::{
+    intrinsics::{
+        rustc_clr_interop_managed_checked_cast, rustc_clr_interop_managed_new_arr,
+        RustcCLRInteropManagedArray, RustcCLRInteropManagedClass,
+    },
+    system::{
+        exception::{Exception, SystemException},
+        threading::tasks::{future_to_task, SystemType, Task},
+        MString,
+    },
+};
+type Object = RustcCLRInteropManagedClass<"System.Runtime", "System.Object">;
+type MethodInfo = RustcCLRInteropManagedClass<"System.Runtime", "System.Reflection.MethodInfo">;
+type MethodBase = RustcCLRInteropManagedClass<"System.Runtime", "System.Reflection.MethodBase">;
+type StringReader = RustcCLRInteropManagedClass<"System.Runtime", "System.IO.StringReader">;
+const THREADS: usize = 4;
+const WAKES: usize = 256;
+/// Wakes the thread blocked on a future.
+struct ThreadWaker(thread::Thread);
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+/// Runs `future` to completion on the current thread.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+fn await_task() {
+    let delay = Task::static1::<"Delay", i32, Task>(10);
+    assert!(block_on(delay.into_future::<()>()).is_ok());
+}
+/// Calls the method `name` of `object`, which takes no arguments, through reflection. This is needed for methods
+/// returning a `Task<T>`, since their signature can't be named.
+fn invoke0(object: Object, name: &str) -> Object {
+    let no_types: RustcCLRInteropManagedArray<SystemType, 1> = rustc_clr_interop_managed_new_arr(0);
+    let no_args: RustcCLRInteropManagedArray<Object, 1> = rustc_clr_interop_managed_new_arr(0);
+    let method = object
+        .instance0::<"GetType", SystemType>()
+        .instance2::<"GetMethod", MString, RustcCLRInteropManagedArray<SystemType, 1>, MethodInfo>(
+            MString::from_str(name),
+            no_types,
+        );
+    let method: MethodBase = rustc_clr_interop_managed_checked_cast(method);
+    method.virt_n::<"Invoke", _, Object>((object, no_args))
+}
+fn await_typed_task() {
+    // `ReadToEndAsync` returns a `Task<string>`.
+    let reader = StringReader::ctor1(MString::from_str("Hello from a task"));
+    let task: Task = rustc_clr_interop_managed_checked_cast(invoke0(
+        rustc_clr_interop_managed_checked_cast(reader),
+        "ReadToEndAsync",
+    ));
+    let text = block_on(task.into_future::<MString>()).unwrap();
+    assert!(text.to_string() == "Hello from a task");
+}
+fn future_roundtrip() {
+    let task = future_to_task(async { 42_i32 });
+    // The task has the type `Task<int>` at runtime, so C# can await its result.
+    let tpe = task
+        .instance0::<"GetType", SystemType>()
+        .to_mstring()
+        .to_string();
+    assert_eq!(tpe, "System.Threading.Tasks.Task`1[System.Int32]");
+    assert_eq!(block_on(task.into_future::<i32>()).unwrap(), 42);
+    let unit = future_to_task(async {});
+    assert!(block_on(unit.into_future::<()>()).is_ok());
+    // A future awaiting a .NET task, awaited by Rust through .NET.
+    let nested = future_to_task(async {
+        let delay = Task::static1::<"Delay", i32, Task>(10);
+        delay.into_future::<()>().await?;
+        Ok::<_, Exception>(1.5_f64)
+    });
+    assert_eq!(block_on(nested.into_future::<f64>()).unwrap(), 1.5);
+}
+fn future_errors() {
+    let failed = future_to_task(async {
+        Err::<i32, _>(Exception::from_naked_ref(SystemException::ctor1(
+            MString::from_str("Rust failed"),
+        )))
+    });
+    let err = block_on(failed.into_future::<i32>()).unwrap_err();
+    assert_eq!(err.message(), "Rust failed");
+    let panicked = future_to_task(async {
+        if std::hint::black_box(true) {
+            panic!("Expected panic");
+        }
+    });
+    let err = block_on(panicked.into_future::<()>()).unwrap_err();
+    assert_eq!(err.message(), "A Rust future panicked");
+}
+/// A future, which is woken from many threads at once, and checks it is never polled concurrently.
+struct ConcurrentWakes {
+    in_poll: Arc<AtomicBool>,
+    finished: Arc<AtomicUsize>,
+    polls: i32,
+    started: bool,
+}
+impl Future for ConcurrentWakes {
+    type Output = i32;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<i32> {
+        assert!(
+            !self.in_poll.swap(true, Ordering::SeqCst),
+            "Future polled concurrently"
+        );
+        self.polls += 1;
+        if !self.started {
+            self.started = true;
+            for _ in 0..THREADS {
+                let waker = cx.waker().clone();
+                let finished = self.finished.clone();
+                thread::spawn(move || {
+                    for _ in 0..WAKES {
+                        waker.wake_by_ref();
+                    }
+                    finished.fetch_add(1, Ordering::SeqCst);
+                    waker.wake();
+                });
+            }
+        }
+        let res = if self.finished.load(Ordering::SeqCst) == THREADS {
+            Poll::Ready(self.polls)
+        } else {
+            Poll::Pending
+        };
+        self.in_poll.store(false, Ordering::SeqCst);
+        res
+    }
+}
+fn concurrent_wakes() {
+    let task = future_to_task(ConcurrentWakes {
+        in_poll: Arc::new(AtomicBool::new(false)),
+        finished: Arc::new(AtomicUsize::new(0)),
+        polls: 0,
+        started: false,
+    });
+    // The final wake of each thread happens after it is counted as finished, so the future always completes.
+    let polls = block_on(task.into_future::<i32>()).unwrap();
+    assert!(polls >= 2);
+}
+fn main() {
+    await_task();
+    await_typed_task();
+    future_roundtrip();
+    future_errors();
+    concurrent_wakes();
+    println!("Task tests passed.");
+}
@@ -173,6 +173,32 @@ fn main() {
                 #[cfg(not(miri))]
                 asm.export(path, CillyIRExpoter::default())
             }
+            "loadcillyir" => {
+                let path = body;
+                let path = path.trim().trim_matches('\'').trim();
+                println!("Loading a cilly IR assembly");
+                let loaded_asm = match Assembly::load_cillyir(path) {
+                    Ok(loaded_asm) => loaded_asm,
+                    Err(err) => {
+                        eprintln!("ERROR: Could not load {path:?}: {err}");
+                        continue;
+                    }
+                };
+                if asm.class_defs().is_empty() {
+                    asm = loaded_asm;
+                } else {
+                    println!("Linking an assembly");
+                    asm = asm.clone().link(loaded_asm);
+                }
+                println!("Loaded assembly");
+            }
+            "savecillyir" => {
+                let path = body;
+                let path = path.trim().trim_matches('\'').trim();
+                println!("Preparing to save the assembly as cilly IR");
+                asm.save_cillyir(path).unwrap();
+                println!("Saved the assembly");
+            }
             "mmakemissing" => {
                 let id = parse_id(body, &asm);
                 let Some(id) = asm.method_ref_to_def(id) else {
@@ -0,0 +1,1005 @@
+//! Generation of mycorrhiza bindings from assembly metadata.
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Write,
+};
+
+use crate::{
+    metadata::{table, Metadata},
+    sig::{field_sig, method_sig, SigType},
+};
+/// `TypeAttributes.Interface`
+const TYPE_INTERFACE: u32 = 0x20;
+/// `TypeAttributes.Abstract`
+const TYPE_ABSTRACT: u32 = 0x80;
+/// `TypeAttributes.VisibilityMask`
+const TYPE_VISIBILITY: u32 = 0x7;
+/// `TypeAttributes.Public`
+const TYPE_PUBLIC: u32 = 0x1;
+/// `MethodAttributes.MemberAccessMask` and `FieldAttributes.FieldAccessMask`
+const MEMBER_ACCESS: u32 = 0x7;
+/// `MethodAttributes.Public` and `FieldAttributes.Public`
+const MEMBER_PUBLIC: u32 = 0x6;
+/// `MethodAttributes.Static` and `FieldAttributes.Static`
+const MEMBER_STATIC: u32 = 0x10;
+/// `MethodAttributes.Virtual`
+const METHOD_VIRTUAL: u32 = 0x40;
+/// `MethodAttributes.Abstract`
+const METHOD_ABSTRACT: u32 = 0x400;
+/// `FieldAttributes.Literal`
+const FIELD_LITERAL: u32 = 0x40;
+/// `FieldAttributes.InitOnly`
+const FIELD_INIT_ONLY: u32 = 0x20;
+/// The maximal number of arguments `ManagedArgs` is implemented for.
+const MAX_ARGS: usize = 15;
+/// The kind of a .NET type, which decides how it is represented in Rust.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Kind {
+    Class,
+    Interface,
+    /// An enum, with the given underlying type.
+    Enum(SigType),
+    /// Value types other than enums. Their size is not known, so they can't be bound.
+    ValueType,
+}
+/// A .NET type, with its kind known.
+struct Resolved {
+    assembly: String,
+    name: String,
+    kind: Kind,
+}
+/// A loaded assembly.
+struct Assembly<'a> {
+    name: String,
+    metadata: Metadata<'a>,
+    /// `TypeDef` rows of nested types, which are not supported.
+    nested: HashSet<u32>,
+    /// Interfaces implemented by each `TypeDef`.
+    interfaces: HashMap<u32, Vec<(usize, u32)>>,
+    /// The type and value of the constant of each field, by `Field` row.
+    constants: HashMap<u32, (u8, &'a [u8])>,
+}
+impl<'a> Assembly<'a> {
+    fn new(metadata: Metadata<'a>, fallback_name: &str) -> Self {
+        let nested = (1..=metadata.rows(table::NESTED_CLASS))
+            .map(|row| metadata.get(table::NESTED_CLASS, row, 0))
+            .collect();
+        let mut interfaces: HashMap<u32, Vec<_>> = HashMap::new();
+        for row in 1..=metadata.rows(table::INTERFACE_IMPL) {
+            let class = metadata.get(table::INTERFACE_IMPL, row, 0);
+            if let Some(interface) = metadata.get_coded(table::INTERFACE_IMPL, row, 1) {
+                interfaces.entry(class).or_default().push(interface);
+            }
+        }
+        let constants = (1..=metadata.rows(table::CONSTANT))
+            .filter_map(|row| {
+                let Some((table::FIELD, field)) = metadata.get_coded(table::CONSTANT, row, 1)
+                else {
+                    return None;
+                };
+                let tpe = (metadata.get(table::CONSTANT, row, 0) & 0xFF) as u8;
+                Some((field, (tpe, metadata.get_blob(table::CONSTANT, row, 2))))
+            })
+            .collect();
+        Self {
+            name: metadata.assembly_name().unwrap_or(fallback_name).to_owned(),
+            metadata,
+            nested,
+            interfaces,
+            constants,
+        }
+    }
+    fn type_name(&self, row: u32) -> String {
+        full_name(
+            self.metadata.get_string(table::TYPE_DEF, row, 2),
+            self.metadata.get_string(table::TYPE_DEF, row, 1),
+        )
+    }
+    /// The full name of a `TypeDef` or non-nested `TypeRef`.
+    fn name_of(&self, (tpe_table, row): (usize, u32)) -> Option<String> {
+        match tpe_table {
+            table::TYPE_DEF if !self.nested.contains(&row) => Some(self.type_name(row)),
+            table::TYPE_REF => {
+                if let Some((table::TYPE_REF, _)) = self.metadata.get_coded(table::TYPE_REF, row, 0)
+                {
+                    return None;
+                }
+                Some(full_name(
+                    self.metadata.get_string(table::TYPE_REF, row, 2),
+                    self.metadata.get_string(table::TYPE_REF, row, 1),
+                ))
+            }
+            _ => None,
+        }
+    }
+    /// The assembly defining a `TypeDef` or `TypeRef`.
+    fn assembly_of(&self, (tpe_table, row): (usize, u32)) -> String {
+        if tpe_table == table::TYPE_REF {
+            if let Some((table::ASSEMBLY_REF, asm_ref)) =
+                self.metadata.get_coded(table::TYPE_REF, row, 0)
+            {
+                return self
+                    .metadata
+                    .get_string(table::ASSEMBLY_REF, asm_ref, 6)
+                    .to_owned();
+            }
+        }
+        self.name.clone()
+    }
+}
+fn full_name(namespace: &str, name: &str) -> String {
+    if namespace.is_empty() {
+        name.to_owned()
+    } else {
+        format!("{namespace}.{name}")
+    }
+}
+/// Converts a .NET `PascalCase` name to a Rust `snake_case` one.
+pub fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut res = String::with_capacity(name.len() + 4);
+    for (idx, c) in chars.iter().enumerate() {
+        if c.is_uppercase() && idx > 0 {
+            let prev = chars[idx - 1];
+            let next_lower = chars.get(idx + 1).is_some_and(|next| next.is_lowercase());
+            if prev.is_lowercase() || prev.is_ascii_digit() || (prev.is_uppercase() && next_lower) {
+                res.push('_');
+            }
+        }
+        res.extend(c.to_lowercase());
+    }
+    while res.contains("__") {
+        res = res.replace("__", "_");
+    }
+    res.trim_matches('_').to_owned()
+}
+/// Turns a name into a valid Rust identifier, escaping keywords.
+pub fn escape_ident(name: &str) -> String {
+    const KEYWORDS: &[&str] = &[
+        "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do",
+        "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in",
+        "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+        "return", "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe",
+        "unsized", "use", "virtual", "where", "while", "yield",
+    ];
+    let mut ident: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+    match ident.as_str() {
+        // Those can't be raw identifiers.
+        "self" | "Self" | "super" | "crate" | "_" => ident + "_",
+        _ if KEYWORDS.contains(&ident.as_str()) => format!("r#{ident}"),
+        _ => ident,
+    }
+}
+/// A member of a type, which gets bound as a method of its trait.
+struct Member {
+    /// The name, before overload disambiguation.
+    name: String,
+    /// Parameter types, used to disambiguate overloads.
+    params: Vec<SigType>,
+    /// The signature of the method, with `{name}` in place of its final name.
+    decl: String,
+    /// The body of the method.
+    body: String,
+}
+/// Generates mycorrhiza bindings for a set of assemblies.
+pub struct Bindgen<'a> {
+    assemblies: Vec<Assembly<'a>>,
+    /// The assembly and `TypeDef` row of every type, by full name.
+    types: HashMap<String, (usize, u32)>,
+    /// Path to the mycorrhiza crate, e.g. `::mycorrhiza`, or `crate` within mycorrhiza itself.
+    crate_path: String,
+    /// The number of types and members which could not be bound.
+    pub skipped: usize,
+}
+impl<'a> Bindgen<'a> {
+    /// Creates a generator for the `assemblies`, given with their file names.
+    #[must_use]
+    pub fn new(assemblies: Vec<(String, Metadata<'a>)>, crate_path: &str) -> Self {
+        let assemblies: Vec<_> = assemblies
+            .into_iter()
+            .map(|(file_name, metadata)| Assembly::new(metadata, &file_name))
+            .collect();
+        let mut types = HashMap::new();
+        for (idx, asm) in assemblies.iter().enumerate() {
+            for row in 1..=asm.metadata.rows(table::TYPE_DEF) {
+                types.entry(asm.type_name(row)).or_insert((idx, row));
+            }
+        }
+        Self {
+            assemblies,
+            types,
+            crate_path: crate_path.to_owned(),
+            skipped: 0,
+        }
+    }
+    /// The kind of the type `row` of the assembly `asm`.
+    fn kind(&self, asm: usize, row: u32) -> Kind {
+        let assembly = &self.assemblies[asm];
+        let metadata = &assembly.metadata;
+        if metadata.get(table::TYPE_DEF, row, 0) & TYPE_INTERFACE != 0 {
+            return Kind::Interface;
+        }
+        let base = metadata
+            .get_coded(table::TYPE_DEF, row, 3)
+            .and_then(|base| assembly.name_of(base));
+        match base.as_deref() {
+            Some("System.Enum") => {
+                let underlying = metadata
+                    .list(table::TYPE_DEF, row, 4, table::FIELD)
+                    .find(|field| metadata.get(table::FIELD, *field, 0) & MEMBER_STATIC == 0)
+                    .and_then(|field| field_sig(metadata.get_blob(table::FIELD, field, 2)));
+                underlying.map_or(Kind::ValueType, Kind::Enum)
+            }
+            Some("System.ValueType") if assembly.type_name(row) != "System.Enum" => Kind::ValueType,
+            _ => Kind::Class,
+        }
+    }
+    /// Resolves a type referenced by the assembly `asm`.
+    fn resolve(&self, asm: usize, tpe: (usize, u32), is_valuetype: bool) -> Option<Resolved> {
+        let assembly = &self.assemblies[asm];
+        let name = assembly.name_of(tpe)?;
+        let kind = match self.types.get(&name) {
+            Some((def_asm, row)) => self.kind(*def_asm, *row),
+            // Reference types need no definition, but the layout of value types is unknown.
+            None if !is_valuetype => Kind::Class,
+            None => return None,
+        };
+        Some(Resolved {
+            assembly: assembly.assembly_of(tpe),
+            name,
+            kind,
+        })
+    }
+    fn class_type(&self, assembly: &str, name: &str) -> String {
+        format!(
+            "{}::intrinsics::RustcCLRInteropManagedClass<\"{assembly}\", \"{name}\">",
+            self.crate_path
+        )
+    }
+    fn resolved_type(&self, resolved: &Resolved) -> Option<String> {
+        match &resolved.kind {
+            Kind::Class | Kind::Interface => {
+                Some(self.class_type(&resolved.assembly, &resolved.name))
+            }
+            Kind::Enum(underlying) => Some(format!(
+                "{}::intrinsics::RustcCLRInteropManagedStruct<\"{}\", \"{}\", {}>",
+                self.crate_path,
+                resolved.assembly,
+                resolved.name,
+                enum_size(underlying)?
+            )),
+            Kind::ValueType => None,
+        }
+    }
+    /// The Rust type representing `tpe`, or `None` if it can't be represented.
+    fn rust_type(&self, asm: usize, tpe: &SigType) -> Option<String> {
+        Some(match tpe {
+            SigType::Void => "()".into(),
+            SigType::Char => format!(
+                "{}::intrinsics::RustcCLRInteropManagedChar",
+                self.crate_path
+            ),
+            SigType::String => self.class_type("System.Runtime", "System.String"),
+            SigType::Object => self.class_type("System.Runtime", "System.Object"),
+            SigType::Class(table, row) => {
+                self.resolved_type(&self.resolve(asm, (*table, *row), false)?)?
+            }
+            SigType::ValueType(table, row) => {
+                self.resolved_type(&self.resolve(asm, (*table, *row), true)?)?
+            }
+            SigType::SZArray(elem) => format!(
+                "{}::intrinsics::RustcCLRInteropManagedArray<{}, 1>",
+                self.crate_path,
+                self.rust_type(asm, elem)?
+            ),
+            SigType::Array(elem, rank) => format!(
+                "{}::intrinsics::RustcCLRInteropManagedArray<{}, {rank}>",
+                self.crate_path,
+                self.rust_type(asm, elem)?
+            ),
+            SigType::Ptr(inner) if **inner == SigType::Void => "*mut ::core::ffi::c_void".into(),
+            SigType::Ptr(inner) => format!("*mut {}", self.rust_type(asm, inner)?),
+            SigType::ByRef(_)
+            | SigType::Var(_)
+            | SigType::MVar(_)
+            | SigType::GenericInst(_, _)
+            | SigType::FnPtr
+            | SigType::TypedByRef => return None,
+            primitive => primitive_type(primitive)?.into(),
+        })
+    }
+    /// A short name of a type, used to disambiguate overloads.
+    fn type_suffix(&self, asm: usize, tpe: &SigType) -> String {
+        match tpe {
+            SigType::Char => "char".into(),
+            SigType::String => "string".into(),
+            SigType::Object => "object".into(),
+            SigType::Class(table, row) | SigType::ValueType(table, row) => self.assemblies[asm]
+                .name_of((*table, *row))
+                .map(|name| snake_case(name.rsplit('.').next().unwrap_or(&name)))
+                .unwrap_or_default(),
+            SigType::SZArray(elem) => format!("{}_array", self.type_suffix(asm, elem)),
+            SigType::Array(elem, rank) => format!("{}_array{rank}", self.type_suffix(asm, elem)),
+            SigType::Ptr(inner) if **inner == SigType::Void => "ptr".into(),
+            SigType::Ptr(inner) => format!("{}_ptr", self.type_suffix(asm, inner)),
+            other => primitive_type(other).unwrap_or_default().into(),
+        }
+    }
+    /// Generates the bindings of all public, non-generic and non-nested types.
+    pub fn generate(&mut self) -> String {
+        // Items of each namespace
+        let mut namespaces: BTreeMap<Vec<String>, Vec<String>> = BTreeMap::new();
+        for asm in 0..self.assemblies.len() {
+            for row in 1..=self.assemblies[asm].metadata.rows(table::TYPE_DEF) {
+                let assembly = &self.assemblies[asm];
+                let metadata = &assembly.metadata;
+                let flags = metadata.get(table::TYPE_DEF, row, 0);
+                if flags & TYPE_VISIBILITY != TYPE_PUBLIC || assembly.nested.contains(&row) {
+                    continue;
+                }
+                let namespace = metadata.get_string(table::TYPE_DEF, row, 2);
+                let Some(code) = self.type_bindings(asm, row) else {
+                    self.skipped += 1;
+                    continue;
+                };
+                let path = namespace
+                    .split('.')
+                    .filter(|segment| !segment.is_empty())
+                    .map(escape_ident)
+                    .collect();
+                namespaces.entry(path).or_default().push(code);
+            }
+        }
+        let mut out = String::from(
+            "// Generated by the cilly `bindgen` tool. Do not edit.\n#![allow(non_snake_case, non_camel_case_types, clippy::all)]\n",
+        );
+        let mut open: Vec<String> = vec![];
+        for (path, items) in namespaces {
+            // Close the modules not containing this namespace, then open the missing ones.
+            let common = open.iter().zip(&path).take_while(|(a, b)| a == b).count();
+            while open.len() > common {
+                open.pop();
+                let _ = writeln!(out, "{}}}", "    ".repeat(open.len()));
+            }
+            for segment in &path[common..] {
+                let _ = writeln!(out, "{}pub mod {segment} {{", "    ".repeat(open.len()));
+                open.push(segment.clone());
+            }
+            let indent = "    ".repeat(open.len());
+            for item in items {
+                for line in item.lines() {
+                    if line.is_empty() {
+                        out.push('\n');
+                    } else {
+                        let _ = writeln!(out, "{indent}{line}");
+                    }
+                }
+            }
+        }
+        while open.pop().is_some() {
+            let _ = writeln!(out, "{}}}", "    ".repeat(open.len()));
+        }
+        out
+    }
+    /// Generates a type alias for the type `row`, and a trait exposing its members. Returns `None` if the type can't be
+    /// bound.
+    fn type_bindings(&mut self, asm: usize, row: u32) -> Option<String> {
+        let assembly = &self.assemblies[asm];
+        let metadata = &assembly.metadata;
+        let name = metadata.get_string(table::TYPE_DEF, row, 1);
+        // Generic types have names like ``List`1``.
+        if name.contains('`') {
+            return None;
+        }
+        let full_name = assembly.type_name(row);
+        let kind = self.kind(asm, row);
+        let tpe = self.resolved_type(&Resolved {
+            assembly: assembly.name.clone(),
+            name: full_name.clone(),
+            kind: kind.clone(),
+        })?;
+        let alias = escape_ident(name);
+        let mut members = vec![];
+        match &kind {
+            Kind::Enum(underlying) => members.extend(self.enum_values(asm, row, underlying)),
+            Kind::Class | Kind::Interface => {
+                members.extend(self.upcasts(asm, row));
+                members.extend(self.fields(asm, row));
+                members.extend(self.methods(asm, row, &kind));
+            }
+            Kind::ValueType => unreachable!(),
+        }
+        let consts = self.consts(asm, row);
+        let mut decls = String::new();
+        let mut impls = String::new();
+        for (decl, body) in &consts {
+            let _ = writeln!(decls, "    {decl};");
+            let _ = writeln!(impls, "    {body}");
+        }
+        for (name, member) in disambiguate(members, |tpe| self.type_suffix(asm, tpe)) {
+            let _ = writeln!(decls, "    {};", member.decl.replace("{name}", &name));
+            let _ = writeln!(
+                impls,
+                "    {} {{\n        {}\n    }}",
+                member.decl.replace("{name}", &name),
+                member.body
+            );
+        }
+        let mut code = String::new();
+        let _ = writeln!(code, "/// `{full_name}`");
+        let _ = writeln!(code, "pub type {alias} = {tpe};");
+        let _ = writeln!(
+            code,
+            "/// The members of `{full_name}`.\npub trait {alias}Api: ::core::marker::Sized {{"
+        );
+        code.push_str(&decls);
+        let _ = writeln!(code, "}}\nimpl {alias}Api for {alias} {{");
+        code.push_str(&impls);
+        code.push_str("}\n");
+        Some(code)
+    }
+    /// Functions returning the values of an enum.
+    fn enum_values(&self, asm: usize, row: u32, underlying: &SigType) -> Vec<Member> {
+        let assembly = &self.assemblies[asm];
+        let metadata = &assembly.metadata;
+        let Some(int) = primitive_type(underlying) else {
+            return vec![];
+        };
+        metadata
+            .list(table::TYPE_DEF, row, 4, table::FIELD)
+            .filter(|field| metadata.get(table::FIELD, *field, 0) & FIELD_LITERAL != 0)
+            .filter_map(|field| {
+                let (_, value) = assembly
+                    .constants
+                    .get(&field)
+                    .and_then(|(tpe, blob)| constant(*tpe, blob))?;
+                Some(Member {
+                    name: snake_case(metadata.get_string(table::FIELD, field, 1)),
+                    params: vec![],
+                    decl: "fn {name}() -> Self".into(),
+                    // `black_box` prevents this from being turned into a constant of a managed type.
+                    body: format!(
+                        "unsafe {{ ::core::mem::transmute::<{int}, Self>(::core::hint::black_box({value})) }}"
+                    ),
+                })
+            })
+            .collect()
+    }
+    /// Associated constants, for public literal fields of primitive types.
+    fn consts(&mut self, asm: usize, row: u32) -> Vec<(String, String)> {
+        let assembly = &self.assemblies[asm];
+        let metadata = &assembly.metadata;
+        if matches!(self.kind(asm, row), Kind::Enum(_)) {
+            return vec![];
+        }
+        let mut consts = vec![];
+        let mut names = HashSet::new();
+        for field in metadata.list(table::TYPE_DEF, row, 4, table::FIELD) {
+            let flags = metadata.get(table::FIELD, field, 0);
+            if flags & MEMBER_ACCESS != MEMBER_PUBLIC {
+                continue;
+            }
+            // Other fields get accessors instead.
+            if flags & FIELD_LITERAL == 0 {
+                continue;
+            }
+            let constant = assembly
+                .constants
+                .get(&field)
+                .and_then(|(tpe, blob)| constant(*tpe, blob));
+            let Some((tpe, value)) = constant else {
+                self.skipped += 1;
+                continue;
+            };
+            let name = escape_ident(
+                &snake_case(metadata.get_string(table::FIELD, field, 1)).to_uppercase(),
+            );
+            if !names.insert(name.clone()) {
+                self.skipped += 1;
+                continue;
+            }
+            consts.push((
+                format!("const {name}: {tpe}"),
+                format!("const {name}: {tpe} = {value};"),
+            ));
+        }
+        consts
+    }
+    /// Accessors of the public non-literal fields of a class or interface. Read-only fields only get a getter.
+    fn fields(&mut self, asm: usize, row: u32) -> Vec<Member> {
+        let metadata = &self.assemblies[asm].metadata;
+        let mut members = vec![];
+        for field in metadata.list(table::TYPE_DEF, row, 4, table::FIELD) {
+            let flags = metadata.get(table::FIELD, field, 0);
+            if flags & MEMBER_ACCESS != MEMBER_PUBLIC || flags & FIELD_LITERAL != 0 {
+                continue;
+            }
+            let Some(sig) = field_sig(metadata.get_blob(table::FIELD, field, 2)) else {
+                self.skipped += 1;
+                continue;
+            };
+            let Some(tpe) = self.rust_type(asm, &sig) else {
+                self.skipped += 1;
+                continue;
+            };
+            let dotnet_name = metadata.get_string(table::FIELD, field, 1);
+            let name = snake_case(dotnet_name);
+            let (receiver, accessor, setter_params, setter_args) = if flags & MEMBER_STATIC != 0 {
+                ("", "static_field", format!("value: {tpe}"), "value")
+            } else {
+                (
+                    "self",
+                    "field",
+                    format!("self, value: {tpe}"),
+                    "self, value",
+                )
+            };
+            members.push(Member {
+                name: name.clone(),
+                params: vec![],
+                decl: format!("fn {{name}}({receiver}) -> {tpe}"),
+                body: format!("Self::{accessor}::<\"{dotnet_name}\", _>({receiver})"),
+            });
+            if flags & FIELD_INIT_ONLY == 0 {
+                members.push(Member {
+                    name: format!("set_{name}"),
+                    params: vec![sig],
+                    decl: format!("fn {{name}}({setter_params})"),
+                    body: format!("Self::set_{accessor}::<\"{dotnet_name}\", _>({setter_args})"),
+                });
+            }
+        }
+        members
+    }
+    /// Methods converting a type into its base class and the interfaces it implements.
+    fn upcasts(&self, asm: usize, row: u32) -> Vec<Member> {
+        let assembly = &self.assemblies[asm];
+        let base = assembly.metadata.get_coded(table::TYPE_DEF, row, 3);
+        base.into_iter()
+            .chain(assembly.interfaces.get(&row).into_iter().flatten().copied())
+            .filter_map(|target| {
+                let resolved = self.resolve(asm, target, false)?;
+                if resolved.name == "System.Object" {
+                    return None;
+                }
+                let tpe = self.resolved_type(&resolved)?;
+                let simple = resolved.name.rsplit('.').next().unwrap_or(&resolved.name);
+                Some(Member {
+                    name: format!("as_{}", snake_case(simple)),
+                    params: vec![],
+                    decl: format!("fn {{name}}(self) -> {tpe}"),
+                    body: format!(
+                        "{}::intrinsics::rustc_clr_interop_managed_checked_cast::<{tpe}, Self>(self)",
+                        self.crate_path
+                    ),
+                })
+            })
+            .collect()
+    }
+    /// Bindings of the public methods of a class or interface.
+    fn methods(&mut self, asm: usize, row: u32, kind: &Kind) -> Vec<Member> {
+        let metadata = &self.assemblies[asm].metadata;
+        let type_flags = metadata.get(table::TYPE_DEF, row, 0);
+        let mut members = vec![];
+        for method in metadata.list(table::TYPE_DEF, row, 5, table::METHOD_DEF) {
+            let flags = metadata.get(table::METHOD_DEF, method, 2);
+            if flags & MEMBER_ACCESS != MEMBER_PUBLIC {
+                continue;
+            }
+            match self.method(asm, method, flags, type_flags, kind) {
+                Some(member) => members.push(member),
+                None => self.skipped += 1,
+            }
+        }
+        members
+    }
+    fn method(
+        &self,
+        asm: usize,
+        method: u32,
+        flags: u32,
+        type_flags: u32,
+        kind: &Kind,
+    ) -> Option<Member> {
+        let metadata = &self.assemblies[asm].metadata;
+        let dotnet_name = metadata.get_string(table::METHOD_DEF, method, 3);
+        let sig = method_sig(metadata.get_blob(table::METHOD_DEF, method, 4))?;
+        let is_static = flags & MEMBER_STATIC != 0;
+        // Generic methods, and static abstract interface members, can't be called yet.
+        if sig.generic_params > 0
+            || (is_static && flags & METHOD_ABSTRACT != 0)
+            || sig.params.len() > MAX_ARGS
+        {
+            return None;
+        }
+        let is_ctor = dotnet_name == ".ctor";
+        if is_ctor && type_flags & TYPE_ABSTRACT != 0 {
+            return None;
+        }
+        let ret = self.rust_type(asm, &sig.ret)?;
+        let params = sig
+            .params
+            .iter()
+            .map(|param| self.rust_type(asm, param))
+            .collect::<Option<Vec<_>>>()?;
+        let names = self.param_names(asm, method, params.len());
+        let args: Vec<_> = names
+            .iter()
+            .zip(&params)
+            .map(|(name, tpe)| format!("{name}: {tpe}"))
+            .collect();
+        let tuple = match names.len() {
+            1 => format!("({},)", names[0]),
+            _ => format!("({})", names.join(", ")),
+        };
+        let (name, decl, body) = if is_ctor {
+            (
+                "new".to_owned(),
+                format!("fn {{name}}({}) -> Self", args.join(", ")),
+                format!("Self::ctor_n({tuple})"),
+            )
+        } else {
+            let ret = if sig.ret == SigType::Void {
+                String::new()
+            } else {
+                format!(" -> {ret}")
+            };
+            let (receiver, call) = if is_static {
+                (vec![], "static_n")
+            } else if flags & METHOD_VIRTUAL != 0 || *kind == Kind::Interface {
+                (vec!["self".to_owned()], "virt_n")
+            } else {
+                (vec!["self".to_owned()], "instance_n")
+            };
+            let this = if is_static { "" } else { "self, " };
+            (
+                snake_case(dotnet_name),
+                format!(
+                    "fn {{name}}({}){ret}",
+                    receiver
+                        .into_iter()
+                        .chain(args)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                format!("Self::{call}::<\"{dotnet_name}\", _, _>({this}{tuple})"),
+            )
+        };
+        Some(Member {
+            name,
+            params: sig.params,
+            decl,
+            body,
+        })
+    }
+    /// The names of the parameters of a method, as unique Rust identifiers.
+    fn param_names(&self, asm: usize, method: u32, count: usize) -> Vec<String> {
+        let metadata = &self.assemblies[asm].metadata;
+        let mut names: Vec<Option<String>> = vec![None; count];
+        for param in metadata.list(table::METHOD_DEF, method, 5, table::PARAM) {
+            let sequence = metadata.get(table::PARAM, param, 1) as usize;
+            let name = metadata.get_string(table::PARAM, param, 2);
+            if (1..=count).contains(&sequence) && !name.is_empty() {
+                names[sequence - 1] = Some(escape_ident(&snake_case(name)));
+            }
+        }
+        let mut used = HashSet::new();
+        names
+            .into_iter()
+            .enumerate()
+            .map(|(idx, name)| {
+                let name = name
+                    .filter(|name| !used.contains(name))
+                    .unwrap_or_else(|| format!("arg{idx}"));
+                used.insert(name.clone());
+                name
+            })
+            .collect()
+    }
+}
+/// Assigns a unique name to each member. Overloads get the names of their parameter types appended, and any remaining
+/// duplicates get numbered.
+fn disambiguate(
+    members: Vec<Member>,
+    suffix: impl Fn(&SigType) -> String,
+) -> Vec<(String, Member)> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for member in &members {
+        *counts.entry(member.name.clone()).or_default() += 1;
+    }
+    let mut used = HashSet::new();
+    members
+        .into_iter()
+        .map(|member| {
+            let mut name = member.name.clone();
+            if counts[&member.name] > 1 && !member.params.is_empty() {
+                for param in &member.params {
+                    name.push('_');
+                    name.push_str(&suffix(param));
+                }
+            }
+            let base = name.clone();
+            let mut idx = 1;
+            while !used.insert(name.clone()) {
+                idx += 1;
+                name = format!("{base}_{idx}");
+            }
+            (escape_ident(&name), member)
+        })
+        .collect()
+}
+/// The Rust name of a primitive type.
+fn primitive_type(tpe: &SigType) -> Option<&'static str> {
+    Some(match tpe {
+        SigType::Bool => "bool",
+        SigType::I8 => "i8",
+        SigType::U8 => "u8",
+        SigType::I16 => "i16",
+        SigType::U16 => "u16",
+        SigType::I32 => "i32",
+        SigType::U32 => "u32",
+        SigType::I64 => "i64",
+        SigType::U64 => "u64",
+        SigType::F32 => "f32",
+        SigType::F64 => "f64",
+        SigType::ISize => "isize",
+        SigType::USize => "usize",
+        _ => return None,
+    })
+}
+/// The size of an enum with the underlying type `tpe`.
+fn enum_size(tpe: &SigType) -> Option<String> {
+    Some(match tpe {
+        SigType::Bool | SigType::I8 | SigType::U8 => "1".into(),
+        SigType::I16 | SigType::U16 | SigType::Char => "2".into(),
+        SigType::I32 | SigType::U32 => "4".into(),
+        SigType::I64 | SigType::U64 => "8".into(),
+        SigType::ISize | SigType::USize => "{ ::core::mem::size_of::<usize>() }".into(),
+        _ => return None,
+    })
+}
+/// Decodes a constant of a primitive type(ECMA-335 II.22.9), returning its Rust type and value.
+fn constant(tpe: u8, blob: &[u8]) -> Option<(&'static str, String)> {
+    fn bytes<const N: usize>(blob: &[u8]) -> Option<[u8; N]> {
+        blob.get(..N)?.try_into().ok()
+    }
+    Some(match tpe {
+        0x02 => ("bool", (*blob.first()? != 0).to_string()),
+        0x04 => ("i8", i8::from_le_bytes(bytes(blob)?).to_string()),
+        0x05 => ("u8", u8::from_le_bytes(bytes(blob)?).to_string()),
+        0x06 => ("i16", i16::from_le_bytes(bytes(blob)?).to_string()),
+        0x07 => ("u16", u16::from_le_bytes(bytes(blob)?).to_string()),
+        0x08 => ("i32", i32::from_le_bytes(bytes(blob)?).to_string()),
+        0x09 => ("u32", u32::from_le_bytes(bytes(blob)?).to_string()),
+        0x0A => ("i64", i64::from_le_bytes(bytes(blob)?).to_string()),
+        0x0B => ("u64", u64::from_le_bytes(bytes(blob)?).to_string()),
+        // Bit patterns preserve NaNs and infinities.
+        0x0C => (
+            "f32",
+            format!("f32::from_bits({:#x})", u32::from_le_bytes(bytes(blob)?)),
+        ),
+        0x0D => (
+            "f64",
+            format!("f64::from_bits({:#x})", u64::from_le_bytes(bytes(blob)?)),
+        ),
+        _ => return None,
+    })
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::tests::build_root;
+    #[test]
+    fn names() {
+        assert_eq!(snake_case("GetHashCode"), "get_hash_code");
+        assert_eq!(snake_case("get_IsCompleted"), "get_is_completed");
+        assert_eq!(snake_case("IOStream"), "io_stream");
+        assert_eq!(snake_case("IDisposable"), "i_disposable");
+        assert_eq!(snake_case("UTF8"), "utf8");
+        assert_eq!(snake_case("ToInt32"), "to_int32");
+        assert_eq!(snake_case("value__"), "value");
+        assert_eq!(escape_ident("type"), "r#type");
+        assert_eq!(escape_ident("self"), "self_");
+        assert_eq!(escape_ident("2D"), "_2D");
+        assert_eq!(escape_ident("op_Addition"), "op_Addition");
+    }
+    #[test]
+    fn constants() {
+        assert_eq!(constant(0x08, &[0xFF; 4]), Some(("i32", "-1".into())));
+        assert_eq!(constant(0x02, &[1]), Some(("bool", "true".into())));
+        assert_eq!(
+            constant(0x0C, &1.0_f32.to_le_bytes()),
+            Some(("f32", "f32::from_bits(0x3f800000)".into()))
+        );
+        // Strings are not supported.
+        assert_eq!(constant(0x0E, &[]), None);
+        // Truncated
+        assert_eq!(constant(0x0A, &[0; 4]), None);
+    }
+    #[test]
+    fn overloads() {
+        let member = |name: &str, params: Vec<SigType>| Member {
+            name: name.into(),
+            params,
+            decl: String::new(),
+            body: String::new(),
+        };
+        let names: Vec<_> = disambiguate(
+            vec![
+                member("append", vec![SigType::I32]),
+                member("append", vec![SigType::String]),
+                member("append", vec![SigType::SZArray(Box::new(SigType::Char))]),
+                member("clear", vec![]),
+                member("new", vec![]),
+                member("new", vec![SigType::I32]),
+                member("type", vec![]),
+            ],
+            |tpe| match tpe {
+                SigType::I32 => "i32".into(),
+                SigType::String => "string".into(),
+                SigType::SZArray(_) => "char_array".into(),
+                _ => String::new(),
+            },
+        )
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+        assert_eq!(
+            names,
+            [
+                "append_i32",
+                "append_string",
+                "append_char_array",
+                "clear",
+                "new",
+                "new_i32",
+                "r#type"
+            ]
+        );
+    }
+    /// Generates bindings for a tiny assembly, containing:
+    /// ```csharp
+    /// namespace Acme;
+    /// public enum Color { Red = 1 }
+    /// public class Point : System.Object {
+    ///     public const int Max = 5;
+    ///     public int Y;
+    ///     public static Color Default;
+    ///     public readonly int Z;
+    ///     public Point(int x) {}
+    ///     public static Color Paint(string name) {}
+    ///     public virtual int GetX() {}
+    /// }
+    /// ```
+    #[test]
+    fn generate() {
+        let strings = b"\0acme.dll\0System\0Enum\0Object\0Acme\0Color\0Point\0value__\0Red\0Max\0.ctor\0Paint\0GetX\0x\0name\0Acme.Lib\0Y\0Default\0Z\0";
+        let offset = |name: &str| {
+            let needle = format!("\0{name}\0");
+            strings
+                .windows(needle.len())
+                .position(|window| window == needle.as_bytes())
+                .unwrap() as u32
+                + 1
+        };
+        let blobs: &[u8] = &[
+            0, // Empty
+            2, 0x06, 0x08, // 1: int32 field
+            3, 0x06, 0x11, 0x08, // 4: valuetype Color field
+            4, 0x01, 0, 0, 0, // 8: constant 1
+            4, 5, 0, 0, 0, // 13: constant 5
+            4, 0x20, 0x01, 0x01, 0x08, // 18: instance void (int32)
+            5, 0x00, 0x01, 0x11, 0x08, 0x0E, // 23: static Color (string)
+            3, 0x20, 0x00, 0x08, // 29: instance int32 ()
+        ];
+        let type_ref = |name| vec![(1 << 2) | 2, offset(name), offset("System")];
+        let root = build_root(
+            strings,
+            blobs,
+            &[
+                (table::MODULE, vec![vec![0, offset("acme.dll"), 0, 0, 0]]),
+                (table::TYPE_REF, vec![type_ref("Enum"), type_ref("Object")]),
+                (
+                    table::TYPE_DEF,
+                    vec![
+                        vec![0, 1, 0, 0, 1, 1],
+                        // Extends TypeRef 1(`System.Enum`)
+                        vec![0x101, offset("Color"), offset("Acme"), (1 << 2) | 1, 1, 1],
+                        // Extends TypeRef 2(`System.Object`)
+                        vec![0x1, offset("Point"), offset("Acme"), (2 << 2) | 1, 3, 1],
+                    ],
+                ),
+                (
+                    table::FIELD,
+                    vec![
+                        vec![0x6, offset("value__"), 1],
+                        vec![0x56, offset("Red"), 4],
+                        vec![0x56, offset("Max"), 1],
+                        vec![0x6, offset("Y"), 1],
+                        vec![0x16, offset("Default"), 4],
+                        vec![0x26, offset("Z"), 1],
+                    ],
+                ),
+                (
+                    table::METHOD_DEF,
+                    vec![
+                        vec![0, 0, 0x1806, offset(".ctor"), 18, 1],
+                        vec![0, 0, 0x16, offset("Paint"), 23, 2],
+                        vec![0, 0, 0x46, offset("GetX"), 29, 3],
+                    ],
+                ),
+                (
+                    table::PARAM,
+                    vec![vec![0, 1, offset("x")], vec![0, 1, offset("name")]],
+                ),
+                (
+                    table::CONSTANT,
+                    vec![
+                        // Field 2
+                        vec![0x08, 2 << 2, 8],
+                        // Field 3
+                        vec![0x08, 3 << 2, 13],
+                    ],
+                ),
+                (
+                    table::ASSEMBLY,
+                    vec![vec![0, 1, 0, 0, 0, 0, 0, offset("Acme.Lib"), 0]],
+                ),
+                (
+                    table::ASSEMBLY_REF,
+                    vec![vec![1, 0, 0, 0, 0, 0, offset("System"), 0, 0]],
+                ),
+            ],
+        );
+        let metadata = Metadata::from_root(&root).unwrap();
+        let mut bindgen = Bindgen::new(vec![("acme.dll".into(), metadata)], "::mycorrhiza");
+        let code = bindgen.generate();
+        assert_eq!(bindgen.skipped, 0);
+        let color = "::mycorrhiza::intrinsics::RustcCLRInteropManagedStruct<\"Acme.Lib\", \"Acme.Color\", 4>";
+        for expected in [
+            "pub mod Acme {",
+            &format!("    pub type Color = {color};"),
+            "        fn red() -> Self {",
+            "            unsafe { ::core::mem::transmute::<i32, Self>(::core::hint::black_box(1)) }",
+            "    pub type Point = ::mycorrhiza::intrinsics::RustcCLRInteropManagedClass<\"Acme.Lib\", \"Acme.Point\">;",
+            "        const MAX: i32 = 5;",
+            "        fn new(x: i32) -> Self {",
+            "            Self::ctor_n((x,))",
+            &format!("        fn paint(name: ::mycorrhiza::intrinsics::RustcCLRInteropManagedClass<\"System.Runtime\", \"System.String\">) -> {color} {{"),
+            "            Self::static_n::<\"Paint\", _, _>((name,))",
+            "        fn get_x(self) -> i32 {",
+            "            Self::virt_n::<\"GetX\", _, _>(self, ())",
+            "        fn y(self) -> i32 {",
+            "            Self::field::<\"Y\", _>(self)",
+            "        fn set_y(self, value: i32) {",
+            "            Self::set_field::<\"Y\", _>(self, value)",
+            &format!("        fn default() -> {color} {{"),
+            "            Self::static_field::<\"Default\", _>()",
+            &format!("        fn set_default(value: {color}) {{"),
+            "            Self::set_static_field::<\"Default\", _>(value)",
+            "        fn z(self) -> i32 {",
+        ] {
+            assert!(code.contains(expected), "Missing {expected:?} in:\n{code}");
+        }
+        // Read-only fields have no setter.
+        assert!(!code.contains("fn set_z"));
+        // `cargo_tests/bindgen` checks these bindings build against mycorrhiza.
+        assert_eq!(
+            code,
+            include_str!("../../../../cargo_tests/bindgen/src/acme.rs"),
+            "The bindings checked by `cargo_tests/bindgen` are out of date"
+        );
+    }
+}
@@ -0,0 +1,66 @@
+//! Generates mycorrhiza bindings from the metadata of .NET assemblies(e.g. reference assemblies, or the contents of a
+//! NuGet package).
+//!
+//! Usage: `bindgen [--crate PATH] [-o OUT] ASSEMBLY...`
+//!
+//! Each public type becomes a type alias, and a trait(named like the type, with an `Api` suffix) implemented for it.
+//! The trait exposes the constructors, methods(including property and event accessors), fields(as getters and
+//! setters, or associated constants for literals) and enum values of the type, with overloads disambiguated by their
+//! parameter types. Types from all the given assemblies can be
+//! used in the signatures, so the assemblies a library depends on should be passed too.
+//!
+//! `--crate` sets the path to mycorrhiza used by the generated code(`::mycorrhiza` by default, `crate` for bindings
+//! within mycorrhiza itself).
+//!
+//! Generic types and methods, nested types, value types other than enums and by-ref parameters can't be bound yet, and
+//! are skipped. Generics need the interop types to support generic instantiations, value types need their size to be
+//! computed from the layout metadata, and by-ref parameters need an interop type for managed references.
+//!
+//! `cargo_tests/bindgen` builds the bindings generated by the `generate` test against mycorrhiza.
+use metadata::Metadata;
+
+mod gen;
+mod metadata;
+mod sig;
+fn main() {
+    let usage = "Usage: bindgen [--crate PATH] [-o OUT] ASSEMBLY...";
+    let mut args = std::env::args().skip(1);
+    let mut crate_path = "::mycorrhiza".to_owned();
+    let mut out = None;
+    let mut inputs = vec![];
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--crate" => crate_path = args.next().expect(usage),
+            "-o" => out = Some(args.next().expect(usage)),
+            _ => inputs.push(arg),
+        }
+    }
+    assert!(!inputs.is_empty(), "{usage}");
+    let files: Vec<(String, Vec<u8>)> = inputs
+        .into_iter()
+        .map(|path| {
+            let file = std::fs::read(&path)
+                .unwrap_or_else(|err| panic!("Could not read the assembly {path:?}: {err}"));
+            (path, file)
+        })
+        .collect();
+    let assemblies = files
+        .iter()
+        .map(|(path, file)| {
+            let metadata = Metadata::from_pe(file)
+                .unwrap_or_else(|err| panic!("Could not load the assembly {path:?}: {err}"));
+            let name = std::path::Path::new(path)
+                .file_stem()
+                .map_or_else(|| path.clone(), |stem| stem.to_string_lossy().into_owned());
+            (name, metadata)
+        })
+        .collect();
+    let mut bindgen = gen::Bindgen::new(assemblies, &crate_path);
+    let code = bindgen.generate();
+    eprintln!("Skipped {} unsupported types and members.", bindgen.skipped);
+    match out {
+        Some(out) => std::fs::write(&out, code)
+            .unwrap_or_else(|err| panic!("Could not write the bindings to {out:?}: {err}")),
+        None => print!("{code}"),
+    }
+}
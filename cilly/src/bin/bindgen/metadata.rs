@@ -0,0 +1,651 @@
+//! A minimal reader of ECMA-335 metadata, supporting just the parts needed to generate bindings.
+//!
+//! Only the optimized(`#~`) table stream is supported, which is what compilers emit for reference assemblies.
+
+/// Ids of metadata tables, as defined in ECMA-335 II.22.
+pub mod table {
+    pub const MODULE: usize = 0x00;
+    pub const TYPE_REF: usize = 0x01;
+    pub const TYPE_DEF: usize = 0x02;
+    pub const FIELD_PTR: usize = 0x03;
+    pub const FIELD: usize = 0x04;
+    pub const METHOD_PTR: usize = 0x05;
+    pub const METHOD_DEF: usize = 0x06;
+    pub const PARAM_PTR: usize = 0x07;
+    pub const PARAM: usize = 0x08;
+    pub const INTERFACE_IMPL: usize = 0x09;
+    pub const MEMBER_REF: usize = 0x0A;
+    pub const CONSTANT: usize = 0x0B;
+    pub const CUSTOM_ATTRIBUTE: usize = 0x0C;
+    pub const FIELD_MARSHAL: usize = 0x0D;
+    pub const DECL_SECURITY: usize = 0x0E;
+    pub const CLASS_LAYOUT: usize = 0x0F;
+    pub const FIELD_LAYOUT: usize = 0x10;
+    pub const STAND_ALONE_SIG: usize = 0x11;
+    pub const EVENT_MAP: usize = 0x12;
+    pub const EVENT_PTR: usize = 0x13;
+    pub const EVENT: usize = 0x14;
+    pub const PROPERTY_MAP: usize = 0x15;
+    pub const PROPERTY_PTR: usize = 0x16;
+    pub const PROPERTY: usize = 0x17;
+    pub const METHOD_SEMANTICS: usize = 0x18;
+    pub const METHOD_IMPL: usize = 0x19;
+    pub const MODULE_REF: usize = 0x1A;
+    pub const TYPE_SPEC: usize = 0x1B;
+    pub const IMPL_MAP: usize = 0x1C;
+    pub const FIELD_RVA: usize = 0x1D;
+    pub const ENC_LOG: usize = 0x1E;
+    pub const ENC_MAP: usize = 0x1F;
+    pub const ASSEMBLY: usize = 0x20;
+    pub const ASSEMBLY_PROCESSOR: usize = 0x21;
+    pub const ASSEMBLY_OS: usize = 0x22;
+    pub const ASSEMBLY_REF: usize = 0x23;
+    pub const ASSEMBLY_REF_PROCESSOR: usize = 0x24;
+    pub const ASSEMBLY_REF_OS: usize = 0x25;
+    pub const FILE: usize = 0x26;
+    pub const EXPORTED_TYPE: usize = 0x27;
+    pub const MANIFEST_RESOURCE: usize = 0x28;
+    pub const NESTED_CLASS: usize = 0x29;
+    pub const GENERIC_PARAM: usize = 0x2A;
+    pub const METHOD_SPEC: usize = 0x2B;
+    pub const GENERIC_PARAM_CONSTRAINT: usize = 0x2C;
+}
+/// Kinds of coded indices(ECMA-335 II.24.2.6): indices into one of several tables, with the table encoded in the low bits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Coded {
+    TypeDefOrRef,
+    HasConstant,
+    HasCustomAttribute,
+    HasFieldMarshal,
+    HasDeclSecurity,
+    MemberRefParent,
+    HasSemantics,
+    MethodDefOrRef,
+    MemberForwarded,
+    Implementation,
+    CustomAttributeType,
+    ResolutionScope,
+    TypeOrMethodDef,
+}
+impl Coded {
+    /// The tables this index may refer to, in tag order. `None` marks unused tags.
+    fn tables(self) -> &'static [Option<usize>] {
+        use table::*;
+        match self {
+            Self::TypeDefOrRef => &[Some(TYPE_DEF), Some(TYPE_REF), Some(TYPE_SPEC)],
+            Self::HasConstant => &[Some(FIELD), Some(PARAM), Some(PROPERTY)],
+            Self::HasCustomAttribute => &[
+                Some(METHOD_DEF),
+                Some(FIELD),
+                Some(TYPE_REF),
+                Some(TYPE_DEF),
+                Some(PARAM),
+                Some(INTERFACE_IMPL),
+                Some(MEMBER_REF),
+                Some(MODULE),
+                Some(DECL_SECURITY),
+                Some(PROPERTY),
+                Some(EVENT),
+                Some(STAND_ALONE_SIG),
+                Some(MODULE_REF),
+                Some(TYPE_SPEC),
+                Some(ASSEMBLY),
+                Some(ASSEMBLY_REF),
+                Some(FILE),
+                Some(EXPORTED_TYPE),
+                Some(MANIFEST_RESOURCE),
+                Some(GENERIC_PARAM),
+                Some(GENERIC_PARAM_CONSTRAINT),
+                Some(METHOD_SPEC),
+            ],
+            Self::HasFieldMarshal => &[Some(FIELD), Some(PARAM)],
+            Self::HasDeclSecurity => &[Some(TYPE_DEF), Some(METHOD_DEF), Some(ASSEMBLY)],
+            Self::MemberRefParent => &[
+                Some(TYPE_DEF),
+                Some(TYPE_REF),
+                Some(MODULE_REF),
+                Some(METHOD_DEF),
+                Some(TYPE_SPEC),
+            ],
+            Self::HasSemantics => &[Some(EVENT), Some(PROPERTY)],
+            Self::MethodDefOrRef => &[Some(METHOD_DEF), Some(MEMBER_REF)],
+            Self::MemberForwarded => &[Some(FIELD), Some(METHOD_DEF)],
+            Self::Implementation => &[Some(FILE), Some(ASSEMBLY_REF), Some(EXPORTED_TYPE)],
+            Self::CustomAttributeType => &[None, None, Some(METHOD_DEF), Some(MEMBER_REF), None],
+            Self::ResolutionScope => &[
+                Some(MODULE),
+                Some(MODULE_REF),
+                Some(ASSEMBLY_REF),
+                Some(TYPE_REF),
+            ],
+            Self::TypeOrMethodDef => &[Some(TYPE_DEF), Some(METHOD_DEF)],
+        }
+    }
+    /// Number of low bits used to encode the table.
+    fn tag_bits(self) -> u32 {
+        usize::BITS - (self.tables().len() - 1).leading_zeros()
+    }
+}
+/// The kind of a single table column.
+#[derive(Clone, Copy, Debug)]
+enum Col {
+    U16,
+    U32,
+    Str,
+    Guid,
+    Blob,
+    Index(usize),
+    Coded(Coded),
+}
+/// The columns of a table(ECMA-335 II.22). Single-byte columns(the type of a constant and its padding) are read as one
+/// `U16`.
+fn schema(table: usize) -> &'static [Col] {
+    use table::*;
+    use Col::*;
+    match table {
+        MODULE => &[U16, Str, Guid, Guid, Guid],
+        TYPE_REF => &[Coded(self::Coded::ResolutionScope), Str, Str],
+        TYPE_DEF => &[
+            U32,
+            Str,
+            Str,
+            Coded(self::Coded::TypeDefOrRef),
+            Index(FIELD),
+            Index(METHOD_DEF),
+        ],
+        FIELD_PTR => &[Index(FIELD)],
+        FIELD => &[U16, Str, Blob],
+        METHOD_PTR => &[Index(METHOD_DEF)],
+        METHOD_DEF => &[U32, U16, U16, Str, Blob, Index(PARAM)],
+        PARAM_PTR => &[Index(PARAM)],
+        PARAM => &[U16, U16, Str],
+        INTERFACE_IMPL => &[Index(TYPE_DEF), Coded(self::Coded::TypeDefOrRef)],
+        MEMBER_REF => &[Coded(self::Coded::MemberRefParent), Str, Blob],
+        CONSTANT => &[U16, Coded(self::Coded::HasConstant), Blob],
+        CUSTOM_ATTRIBUTE => &[
+            Coded(self::Coded::HasCustomAttribute),
+            Coded(self::Coded::CustomAttributeType),
+            Blob,
+        ],
+        FIELD_MARSHAL => &[Coded(self::Coded::HasFieldMarshal), Blob],
+        DECL_SECURITY => &[U16, Coded(self::Coded::HasDeclSecurity), Blob],
+        CLASS_LAYOUT => &[U16, U32, Index(TYPE_DEF)],
+        FIELD_LAYOUT => &[U32, Index(FIELD)],
+        STAND_ALONE_SIG => &[Blob],
+        EVENT_MAP => &[Index(TYPE_DEF), Index(EVENT)],
+        EVENT_PTR => &[Index(EVENT)],
+        EVENT => &[U16, Str, Coded(self::Coded::TypeDefOrRef)],
+        PROPERTY_MAP => &[Index(TYPE_DEF), Index(PROPERTY)],
+        PROPERTY_PTR => &[Index(PROPERTY)],
+        PROPERTY => &[U16, Str, Blob],
+        METHOD_SEMANTICS => &[U16, Index(METHOD_DEF), Coded(self::Coded::HasSemantics)],
+        METHOD_IMPL => &[
+            Index(TYPE_DEF),
+            Coded(self::Coded::MethodDefOrRef),
+            Coded(self::Coded::MethodDefOrRef),
+        ],
+        MODULE_REF => &[Str],
+        TYPE_SPEC => &[Blob],
+        IMPL_MAP => &[
+            U16,
+            Coded(self::Coded::MemberForwarded),
+            Str,
+            Index(MODULE_REF),
+        ],
+        FIELD_RVA => &[U32, Index(FIELD)],
+        ENC_LOG => &[U32, U32],
+        ENC_MAP => &[U32],
+        ASSEMBLY => &[U32, U16, U16, U16, U16, U32, Blob, Str, Str],
+        ASSEMBLY_PROCESSOR => &[U32],
+        ASSEMBLY_OS => &[U32, U32, U32],
+        ASSEMBLY_REF => &[U16, U16, U16, U16, U32, Blob, Str, Str, Blob],
+        ASSEMBLY_REF_PROCESSOR => &[U32, Index(ASSEMBLY_REF)],
+        ASSEMBLY_REF_OS => &[U32, U32, U32, Index(ASSEMBLY_REF)],
+        FILE => &[U32, Str, Blob],
+        EXPORTED_TYPE => &[U32, U32, Str, Str, Coded(self::Coded::Implementation)],
+        MANIFEST_RESOURCE => &[U32, U32, Str, Coded(self::Coded::Implementation)],
+        NESTED_CLASS => &[Index(TYPE_DEF), Index(TYPE_DEF)],
+        GENERIC_PARAM => &[U16, U16, Coded(self::Coded::TypeOrMethodDef), Str],
+        METHOD_SPEC => &[Coded(self::Coded::MethodDefOrRef), Blob],
+        GENERIC_PARAM_CONSTRAINT => &[Index(GENERIC_PARAM), Coded(self::Coded::TypeDefOrRef)],
+        _ => &[],
+    }
+}
+#[derive(Debug)]
+pub enum MetadataError {
+    /// The file ends before a structure it contains.
+    Truncated,
+    NotPE,
+    /// The PE file has no CLI header.
+    NotDotnet,
+    BadSignature,
+    MissingStream(&'static str),
+    /// The assembly contains a table not defined by ECMA-335, whose size is unknown.
+    UnknownTable(usize),
+}
+impl std::fmt::Display for MetadataError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Truncated => write!(f, "the file is truncated"),
+            Self::NotPE => write!(f, "not a PE file"),
+            Self::NotDotnet => write!(f, "not a .NET assembly"),
+            Self::BadSignature => write!(f, "invalid metadata signature"),
+            Self::MissingStream(name) => write!(f, "missing the {name} metadata stream"),
+            Self::UnknownTable(table) => write!(f, "unknown metadata table {table:#x}"),
+        }
+    }
+}
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, MetadataError> {
+    data.get(offset..offset + 2)
+        .map(|bytes| u16::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or(MetadataError::Truncated)
+}
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, MetadataError> {
+    data.get(offset..offset + 4)
+        .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or(MetadataError::Truncated)
+}
+/// Decodes a compressed unsigned integer(ECMA-335 II.23.2), returning it and its encoded size.
+pub fn decompress_u32(data: &[u8]) -> Option<(u32, usize)> {
+    let first = *data.first()?;
+    if first & 0x80 == 0 {
+        Some((u32::from(first), 1))
+    } else if first & 0xC0 == 0x80 {
+        Some(((u32::from(first & 0x3F) << 8) | u32::from(*data.get(1)?), 2))
+    } else if first & 0xE0 == 0xC0 {
+        let rest = data.get(1..4)?;
+        Some((
+            (u32::from(first & 0x1F) << 24)
+                | (u32::from(rest[0]) << 16)
+                | (u32::from(rest[1]) << 8)
+                | u32::from(rest[2]),
+            4,
+        ))
+    } else {
+        None
+    }
+}
+#[derive(Clone, Debug, Default)]
+struct Table {
+    rows: u32,
+    /// Offset of the first row, in the table stream.
+    offset: usize,
+    row_size: usize,
+    /// Offset and size of each column, within a row.
+    columns: Vec<(usize, usize)>,
+}
+/// The metadata of a .NET assembly.
+pub struct Metadata<'a> {
+    tables_stream: &'a [u8],
+    strings: &'a [u8],
+    blobs: &'a [u8],
+    tables: Vec<Table>,
+}
+impl<'a> Metadata<'a> {
+    /// Reads the metadata of a PE file(a .NET `.dll` or `.exe`).
+    pub fn from_pe(file: &'a [u8]) -> Result<Self, MetadataError> {
+        if file.get(0..2) != Some(b"MZ") {
+            return Err(MetadataError::NotPE);
+        }
+        let pe = read_u32(file, 0x3C)? as usize;
+        if file.get(pe..pe + 4) != Some(b"PE\0\0") {
+            return Err(MetadataError::NotPE);
+        }
+        let coff = pe + 4;
+        let section_count = read_u16(file, coff + 2)? as usize;
+        let optional_size = read_u16(file, coff + 16)? as usize;
+        let optional = coff + 20;
+        // The data directories of PE32+ files start 16 bytes later, due to their 64 bit fields.
+        let directories = match read_u16(file, optional)? {
+            0x10b => optional + 96,
+            0x20b => optional + 112,
+            _ => return Err(MetadataError::NotPE),
+        };
+        let sections: Vec<(u32, u32, u32)> = (0..section_count)
+            .map(|section| {
+                let header = optional + optional_size + section * 40;
+                let virtual_size = read_u32(file, header + 8)?;
+                let virtual_address = read_u32(file, header + 12)?;
+                let raw_size = read_u32(file, header + 16)?;
+                let raw_pointer = read_u32(file, header + 20)?;
+                Ok((
+                    virtual_address,
+                    virtual_size.max(raw_size),
+                    raw_pointer.wrapping_sub(virtual_address),
+                ))
+            })
+            .collect::<Result<_, MetadataError>>()?;
+        let rva_to_offset = |rva: u32| {
+            sections
+                .iter()
+                .find(|(address, size, _)| (*address..address + size).contains(&rva))
+                .map(|(_, _, delta)| rva.wrapping_add(*delta) as usize)
+                .ok_or(MetadataError::Truncated)
+        };
+        // The CLI header is described by the 15th data directory.
+        let cli_rva = read_u32(file, directories + 14 * 8)?;
+        if cli_rva == 0 {
+            return Err(MetadataError::NotDotnet);
+        }
+        let cli = rva_to_offset(cli_rva)?;
+        let metadata = rva_to_offset(read_u32(file, cli + 8)?)?;
+        let metadata_size = read_u32(file, cli + 12)? as usize;
+        let root = file
+            .get(metadata..metadata + metadata_size)
+            .ok_or(MetadataError::Truncated)?;
+        Self::from_root(root)
+    }
+    /// Reads metadata starting at its root(the `BSJB` signature).
+    pub fn from_root(root: &'a [u8]) -> Result<Self, MetadataError> {
+        if read_u32(root, 0)? != 0x424A_5342 {
+            return Err(MetadataError::BadSignature);
+        }
+        let version_len = read_u32(root, 12)? as usize;
+        let mut header = 16 + version_len + 2;
+        let stream_count = read_u16(root, header)?;
+        header += 2;
+        let mut tables_stream = None;
+        let mut strings = &[][..];
+        let mut blobs = &[][..];
+        for _ in 0..stream_count {
+            let offset = read_u32(root, header)? as usize;
+            let size = read_u32(root, header + 4)? as usize;
+            let name_start = header + 8;
+            let name_len = root[name_start..]
+                .iter()
+                .position(|byte| *byte == 0)
+                .ok_or(MetadataError::Truncated)?;
+            let name = &root[name_start..name_start + name_len];
+            // Names are null-terminated, and padded to 4 bytes.
+            header = name_start + (name_len + 4) / 4 * 4;
+            let data = root
+                .get(offset..offset + size)
+                .ok_or(MetadataError::Truncated)?;
+            match name {
+                b"#~" => tables_stream = Some(data),
+                b"#Strings" => strings = data,
+                b"#Blob" => blobs = data,
+                _ => (),
+            }
+        }
+        let tables_stream = tables_stream.ok_or(MetadataError::MissingStream("#~"))?;
+        let mut res = Self {
+            tables_stream,
+            strings,
+            blobs,
+            tables: vec![Table::default(); 64],
+        };
+        res.read_table_layout()?;
+        Ok(res)
+    }
+    fn read_table_layout(&mut self) -> Result<(), MetadataError> {
+        let stream = self.tables_stream;
+        let heap_sizes = *stream.get(6).ok_or(MetadataError::Truncated)?;
+        let valid = u64::from(read_u32(stream, 8)?) | (u64::from(read_u32(stream, 12)?) << 32);
+        let mut offset = 24;
+        for (idx, table) in self.tables.iter_mut().enumerate() {
+            if valid & (1 << idx) != 0 {
+                table.rows = read_u32(stream, offset)?;
+                offset += 4;
+            }
+        }
+        let str_size = if heap_sizes & 0x1 != 0 { 4 } else { 2 };
+        let guid_size = if heap_sizes & 0x2 != 0 { 4 } else { 2 };
+        let blob_size = if heap_sizes & 0x4 != 0 { 4 } else { 2 };
+        for table in 0..64 {
+            if self.tables[table].rows == 0 {
+                continue;
+            }
+            let schema = schema(table);
+            if schema.is_empty() {
+                return Err(MetadataError::UnknownTable(table));
+            }
+            let mut columns = Vec::with_capacity(schema.len());
+            let mut row_size = 0;
+            for col in schema {
+                let size = match col {
+                    Col::U16 => 2,
+                    Col::U32 => 4,
+                    Col::Str => str_size,
+                    Col::Guid => guid_size,
+                    Col::Blob => blob_size,
+                    Col::Index(target) => {
+                        if self.tables[*target].rows < 1 << 16 {
+                            2
+                        } else {
+                            4
+                        }
+                    }
+                    Col::Coded(coded) => {
+                        let max_rows = coded
+                            .tables()
+                            .iter()
+                            .flatten()
+                            .map(|table| self.tables[*table].rows)
+                            .max()
+                            .unwrap_or(0);
+                        if max_rows < 1 << (16 - coded.tag_bits()) {
+                            2
+                        } else {
+                            4
+                        }
+                    }
+                };
+                columns.push((row_size, size));
+                row_size += size;
+            }
+            let table = &mut self.tables[table];
+            table.offset = offset;
+            table.row_size = row_size;
+            table.columns = columns;
+            offset += row_size * table.rows as usize;
+        }
+        if offset > stream.len() {
+            return Err(MetadataError::Truncated);
+        }
+        Ok(())
+    }
+    /// The number of rows in `table`.
+    #[must_use]
+    pub fn rows(&self, table: usize) -> u32 {
+        self.tables[table].rows
+    }
+    /// Reads the column `col` of the row `row`(1-based, like metadata tokens) of `table`.
+    #[must_use]
+    pub fn get(&self, table: usize, row: u32, col: usize) -> u32 {
+        let table = &self.tables[table];
+        assert!(
+            row >= 1 && row <= table.rows,
+            "Row {row} is out of range of a table with {} rows",
+            table.rows
+        );
+        let (col_offset, size) = table.columns[col];
+        let offset = table.offset + (row as usize - 1) * table.row_size + col_offset;
+        let data = &self.tables_stream[offset..offset + size];
+        match size {
+            2 => u32::from(u16::from_le_bytes(data.try_into().unwrap())),
+            4 => u32::from_le_bytes(data.try_into().unwrap()),
+            _ => unreachable!(),
+        }
+    }
+    /// Reads the coded index in column `col`, returning the table and row it refers to. Returns `None` for null indices.
+    #[must_use]
+    pub fn get_coded(&self, table: usize, row: u32, col: usize) -> Option<(usize, u32)> {
+        let Col::Coded(coded) = schema(table)[col] else {
+            panic!("Column {col} of table {table:#x} is not a coded index")
+        };
+        Self::decode_coded(coded, self.get(table, row, col))
+    }
+    /// Splits a coded index into the table and row it refers to. Returns `None` for null indices.
+    #[must_use]
+    pub fn decode_coded(coded: Coded, value: u32) -> Option<(usize, u32)> {
+        let bits = coded.tag_bits();
+        let tag = (value & ((1 << bits) - 1)) as usize;
+        let row = value >> bits;
+        if row == 0 {
+            return None;
+        }
+        coded
+            .tables()
+            .get(tag)
+            .copied()
+            .flatten()
+            .map(|table| (table, row))
+    }
+    /// Reads a string from the `#Strings` heap.
+    #[must_use]
+    pub fn string(&self, idx: u32) -> &'a str {
+        let data = &self.strings[idx as usize..];
+        let len = data
+            .iter()
+            .position(|byte| *byte == 0)
+            .unwrap_or(data.len());
+        std::str::from_utf8(&data[..len]).unwrap_or("")
+    }
+    /// Reads the string in column `col`.
+    #[must_use]
+    pub fn get_string(&self, table: usize, row: u32, col: usize) -> &'a str {
+        self.string(self.get(table, row, col))
+    }
+    /// Reads a blob from the `#Blob` heap.
+    #[must_use]
+    pub fn blob(&self, idx: u32) -> &'a [u8] {
+        let data = &self.blobs[idx as usize..];
+        let Some((len, len_size)) = decompress_u32(data) else {
+            return &[];
+        };
+        &data[len_size..(len_size + len as usize).min(data.len())]
+    }
+    /// Reads the blob in column `col`.
+    #[must_use]
+    pub fn get_blob(&self, table: usize, row: u32, col: usize) -> &'a [u8] {
+        self.blob(self.get(table, row, col))
+    }
+    /// Returns the rows of `target` owned by `row` of `table`, where the column `col` holds the index of the first one.
+    /// Used for lists like the fields or methods of a type.
+    #[must_use]
+    pub fn list(&self, table: usize, row: u32, col: usize, target: usize) -> std::ops::Range<u32> {
+        let start = self.get(table, row, col);
+        let end = if row < self.rows(table) {
+            self.get(table, row + 1, col)
+        } else {
+            self.rows(target) + 1
+        };
+        start..end.max(start)
+    }
+    /// The name of this assembly.
+    #[must_use]
+    pub fn assembly_name(&self) -> Option<&'a str> {
+        (self.rows(table::ASSEMBLY) > 0).then(|| self.get_string(table::ASSEMBLY, 1, 7))
+    }
+}
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    /// Builds the metadata root of a tiny assembly, with all heap and table indices 2 bytes wide.
+    ///
+    /// `tables` holds the id of each table, and its rows. Each row is a list of column values.
+    pub fn build_root(strings: &[u8], blobs: &[u8], tables: &[(usize, Vec<Vec<u32>>)]) -> Vec<u8> {
+        let mut stream = vec![0, 0, 0, 0, 2, 0, 0, 1];
+        let valid: u64 = tables.iter().map(|(table, _)| 1 << table).sum();
+        stream.extend(valid.to_le_bytes());
+        stream.extend(0_u64.to_le_bytes());
+        for (_, rows) in tables {
+            stream.extend((rows.len() as u32).to_le_bytes());
+        }
+        for (table, rows) in tables {
+            for row in rows {
+                for (col, value) in schema(*table).iter().zip(row) {
+                    match col {
+                        Col::U32 => stream.extend(value.to_le_bytes()),
+                        _ => stream.extend((*value as u16).to_le_bytes()),
+                    }
+                }
+            }
+        }
+        let streams: [(&[u8], &[u8]); 3] = [
+            (b"#~\0\0", &stream),
+            (b"#Strings\0\0\0\0", strings),
+            (b"#Blob\0\0\0", blobs),
+        ];
+        let version = b"v4.0.30319\0\0";
+        let header_size = 20
+            + version.len()
+            + streams
+                .iter()
+                .map(|(name, _)| 8 + name.len())
+                .sum::<usize>();
+        let mut root = vec![];
+        root.extend(0x424A_5342_u32.to_le_bytes());
+        root.extend([1, 0, 1, 0, 0, 0, 0, 0]);
+        root.extend((version.len() as u32).to_le_bytes());
+        root.extend(version);
+        root.extend([0, 0]);
+        root.extend((streams.len() as u16).to_le_bytes());
+        let mut offset = header_size;
+        for (name, data) in &streams {
+            root.extend((offset as u32).to_le_bytes());
+            root.extend((data.len() as u32).to_le_bytes());
+            root.extend(*name);
+            offset += data.len();
+        }
+        assert_eq!(root.len(), header_size);
+        for (_, data) in &streams {
+            root.extend(*data);
+        }
+        root
+    }
+    #[test]
+    fn compressed() {
+        assert_eq!(decompress_u32(&[0x03]), Some((0x03, 1)));
+        assert_eq!(decompress_u32(&[0x7F]), Some((0x7F, 1)));
+        assert_eq!(decompress_u32(&[0x80, 0x80]), Some((0x80, 2)));
+        assert_eq!(decompress_u32(&[0xBF, 0xFF]), Some((0x3FFF, 2)));
+        assert_eq!(decompress_u32(&[0xC0, 0x00, 0x40, 0x00]), Some((0x4000, 4)));
+        assert_eq!(
+            decompress_u32(&[0xDF, 0xFF, 0xFF, 0xFF]),
+            Some((0x1FFF_FFFF, 4))
+        );
+        assert_eq!(decompress_u32(&[0xFF]), None);
+    }
+    #[test]
+    fn coded() {
+        assert_eq!(Coded::TypeDefOrRef.tag_bits(), 2);
+        assert_eq!(Coded::HasCustomAttribute.tag_bits(), 5);
+        assert_eq!(Coded::MethodDefOrRef.tag_bits(), 1);
+        assert_eq!(Coded::CustomAttributeType.tag_bits(), 3);
+        // TypeRef, row 3
+        assert_eq!(
+            Metadata::decode_coded(Coded::TypeDefOrRef, (3 << 2) | 1),
+            Some((table::TYPE_REF, 3))
+        );
+        assert_eq!(Metadata::decode_coded(Coded::TypeDefOrRef, 1), None);
+    }
+    #[test]
+    fn tables() {
+        let strings = b"\0Mod\0System\0Object\0Acme\0Point\0";
+        let blobs = b"\0\x03\x06\x08\x00";
+        let root = build_root(
+            strings,
+            blobs,
+            &[
+                (table::MODULE, vec![vec![0, 1, 0, 0, 0]]),
+                (table::TYPE_REF, vec![vec![0, 12, 5]]),
+                (table::TYPE_DEF, vec![vec![0x1, 24, 19, (1 << 2) | 1, 1, 1]]),
+                (table::FIELD, vec![vec![6, 1, 1]]),
+            ],
+        );
+        let metadata = Metadata::from_root(&root).unwrap();
+        assert_eq!(metadata.rows(table::TYPE_DEF), 1);
+        assert_eq!(metadata.get_string(table::TYPE_DEF, 1, 1), "Point");
+        assert_eq!(metadata.get_string(table::TYPE_DEF, 1, 2), "Acme");
+        assert_eq!(
+            metadata.get_coded(table::TYPE_DEF, 1, 3),
+            Some((table::TYPE_REF, 1))
+        );
+        assert_eq!(metadata.get_string(table::TYPE_REF, 1, 1), "Object");
+        assert_eq!(metadata.list(table::TYPE_DEF, 1, 4, table::FIELD), 1..2);
+        assert_eq!(metadata.get_blob(table::FIELD, 1, 2), &[0x06, 0x08, 0x00]);
+        assert_eq!(metadata.assembly_name(), None);
+    }
+}
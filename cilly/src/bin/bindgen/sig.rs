@@ -0,0 +1,224 @@
+//! Decoding of signature blobs(ECMA-335 II.23.2).
+#[cfg(test)]
+use crate::metadata::table;
+use crate::metadata::{decompress_u32, Coded, Metadata};
+
+/// A type, as encoded in a signature.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SigType {
+    Void,
+    Bool,
+    Char,
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    I64,
+    U64,
+    F32,
+    F64,
+    ISize,
+    USize,
+    String,
+    Object,
+    /// A reference type, given by the table and row of its `TypeDef`, `TypeRef` or `TypeSpec`.
+    Class(usize, u32),
+    /// A value type, given by the table and row of its `TypeDef`, `TypeRef` or `TypeSpec`.
+    ValueType(usize, u32),
+    SZArray(Box<SigType>),
+    /// A multidimensional array, with the given rank.
+    Array(Box<SigType>, u32),
+    Ptr(Box<SigType>),
+    ByRef(Box<SigType>),
+    /// A generic parameter of a type.
+    Var(u32),
+    /// A generic parameter of a method.
+    MVar(u32),
+    GenericInst(Box<SigType>, Vec<SigType>),
+    FnPtr,
+    TypedByRef,
+}
+/// The signature of a method or property.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MethodSig {
+    pub has_this: bool,
+    /// The number of generic parameters of a method.
+    pub generic_params: u32,
+    pub ret: SigType,
+    pub params: Vec<SigType>,
+}
+/// Calling convention flags.
+const HAS_THIS: u8 = 0x20;
+const GENERIC: u8 = 0x10;
+const FIELD: u8 = 0x06;
+struct SigReader<'a> {
+    data: &'a [u8],
+}
+impl SigReader<'_> {
+    fn byte(&mut self) -> Option<u8> {
+        let (first, rest) = self.data.split_first()?;
+        self.data = rest;
+        Some(*first)
+    }
+    fn peek(&self) -> Option<u8> {
+        self.data.first().copied()
+    }
+    fn compressed(&mut self) -> Option<u32> {
+        let (value, size) = decompress_u32(self.data)?;
+        self.data = &self.data[size..];
+        Some(value)
+    }
+    fn type_def_or_ref(&mut self) -> Option<(usize, u32)> {
+        // Unlike coded indices in tables, a null index is not valid here.
+        let encoded = self.compressed()?;
+        Metadata::decode_coded(Coded::TypeDefOrRef, encoded)
+    }
+    /// Skips custom modifiers, which do not matter for bindings.
+    fn skip_custom_mods(&mut self) -> Option<()> {
+        while matches!(self.peek(), Some(0x1F | 0x20)) {
+            self.byte()?;
+            self.type_def_or_ref()?;
+        }
+        Some(())
+    }
+    fn tpe(&mut self) -> Option<SigType> {
+        self.skip_custom_mods()?;
+        Some(match self.byte()? {
+            0x01 => SigType::Void,
+            0x02 => SigType::Bool,
+            0x03 => SigType::Char,
+            0x04 => SigType::I8,
+            0x05 => SigType::U8,
+            0x06 => SigType::I16,
+            0x07 => SigType::U16,
+            0x08 => SigType::I32,
+            0x09 => SigType::U32,
+            0x0A => SigType::I64,
+            0x0B => SigType::U64,
+            0x0C => SigType::F32,
+            0x0D => SigType::F64,
+            0x0E => SigType::String,
+            0x0F => SigType::Ptr(Box::new(self.tpe()?)),
+            0x10 => SigType::ByRef(Box::new(self.tpe()?)),
+            0x11 => {
+                let (table, row) = self.type_def_or_ref()?;
+                SigType::ValueType(table, row)
+            }
+            0x12 => {
+                let (table, row) = self.type_def_or_ref()?;
+                SigType::Class(table, row)
+            }
+            0x13 => SigType::Var(self.compressed()?),
+            0x14 => {
+                let elem = self.tpe()?;
+                let rank = self.compressed()?;
+                // Sizes and lower bounds are not part of the .NET type.
+                for _ in 0..self.compressed()? {
+                    self.compressed()?;
+                }
+                for _ in 0..self.compressed()? {
+                    self.compressed()?;
+                }
+                SigType::Array(Box::new(elem), rank)
+            }
+            0x15 => {
+                let generic = self.tpe()?;
+                let argc = self.compressed()?;
+                let args = (0..argc).map(|_| self.tpe()).collect::<Option<Vec<_>>>()?;
+                SigType::GenericInst(Box::new(generic), args)
+            }
+            0x16 => SigType::TypedByRef,
+            0x18 => SigType::ISize,
+            0x19 => SigType::USize,
+            0x1B => {
+                self.method()?;
+                SigType::FnPtr
+            }
+            0x1C => SigType::Object,
+            0x1D => SigType::SZArray(Box::new(self.tpe()?)),
+            0x1E => SigType::MVar(self.compressed()?),
+            _ => return None,
+        })
+    }
+    fn method(&mut self) -> Option<MethodSig> {
+        let conv = self.byte()?;
+        let generic_params = if conv & GENERIC != 0 {
+            self.compressed()?
+        } else {
+            0
+        };
+        let argc = self.compressed()?;
+        let ret = self.tpe()?;
+        let params = (0..argc).map(|_| self.tpe()).collect::<Option<Vec<_>>>()?;
+        Some(MethodSig {
+            has_this: conv & HAS_THIS != 0,
+            generic_params,
+            ret,
+            params,
+        })
+    }
+}
+/// Decodes the signature of a method or property. Returns `None` for malformed signatures, or ones using features
+/// this decoder does not understand(e.g. varargs).
+#[must_use]
+pub fn method_sig(blob: &[u8]) -> Option<MethodSig> {
+    SigReader { data: blob }.method()
+}
+/// Decodes the signature of a field, returning its type.
+#[must_use]
+pub fn field_sig(blob: &[u8]) -> Option<SigType> {
+    let mut reader = SigReader { data: blob };
+    if reader.byte()? != FIELD {
+        return None;
+    }
+    reader.tpe()
+}
+#[test]
+fn decode_method() {
+    // instance void (int32, string)
+    assert_eq!(
+        method_sig(&[0x20, 0x02, 0x01, 0x08, 0x0E]),
+        Some(MethodSig {
+            has_this: true,
+            generic_params: 0,
+            ret: SigType::Void,
+            params: vec![SigType::I32, SigType::String],
+        })
+    );
+    // static class TypeRef(2) (valuetype TypeDef(1)[], float64*)
+    assert_eq!(
+        method_sig(&[0x00, 0x02, 0x12, 0x09, 0x1D, 0x11, 0x04, 0x0F, 0x0D]),
+        Some(MethodSig {
+            has_this: false,
+            generic_params: 0,
+            ret: SigType::Class(table::TYPE_REF, 2),
+            params: vec![
+                SigType::SZArray(Box::new(SigType::ValueType(table::TYPE_DEF, 1))),
+                SigType::Ptr(Box::new(SigType::F64)),
+            ],
+        })
+    );
+    // static !!0 <1>(int32 modreq(TypeRef(1)) &)
+    assert_eq!(
+        method_sig(&[0x10, 0x01, 0x01, 0x1E, 0x00, 0x1F, 0x05, 0x10, 0x08]),
+        Some(MethodSig {
+            has_this: false,
+            generic_params: 1,
+            ret: SigType::MVar(0),
+            params: vec![SigType::ByRef(Box::new(SigType::I32))],
+        })
+    );
+}
+#[test]
+fn decode_field() {
+    assert_eq!(field_sig(&[0x06, 0x08]), Some(SigType::I32));
+    // int32[0...,0...]
+    assert_eq!(
+        field_sig(&[0x06, 0x14, 0x08, 0x02, 0x00, 0x02, 0x00, 0x00]),
+        Some(SigType::Array(Box::new(SigType::I32), 2))
+    );
+    // Not a field signature
+    assert_eq!(field_sig(&[0x00, 0x08]), None);
+}
@@ -6,6 +6,7 @@ use cilly::{
     v2::{
         asm::{MissingMethodPatcher, ILASM_FLAVOUR},
         cilnode::MethodKind,
+        typecheck::TypeCheckPolicy,
         Assembly, BasicBlock, CILNode, CILRoot, ClassDef, ClassRef, Const, IlasmFlavour, Int,
         MethodImpl, Type,
     },
@@ -502,6 +503,9 @@ fn main() {
     final_assembly.opt(&mut fuel);
     final_assembly.eliminate_dead_code();
     final_assembly.fix_aligement();
+    if let Some(policy) = TypeCheckPolicy::from_env() {
+        final_assembly.enforce_typecheck_policy(policy);
+    }
     final_assembly
         .save_tmp(&mut std::fs::File::create(path.with_extension("cilly2")).unwrap())
         .unwrap();
@@ -1,29 +1,115 @@
+//! Finds the optimization which breaks a program.
+//!
+//! Usage: `optsect ASSEMBLY FAIL_STRING ARGS [MODE]`, where `ASSEMBLY` is a serialized assembly, `FAIL_STRING` is
+//! the string which appears in the output of a broken program, and `ARGS` are the arguments passed to it.
+//! `MODE` is one of:
+//! - `FUEL_START FUEL_END`(or nothing) - bisects the fuel given to the whole optimizer.
+//! - `passes` - bisects the passes of the optimization pipeline.
+//! - `methods` - bisects the set of optimized methods, and then the fuel given to the single method responsible.
+//!   Prints the method before and after the rewrite which broke it.
+//!
+//! The program is exported to .NET, or to C and run natively if `C_MODE` is set.
 use std::{
     io::{Read, Write},
-    path::Path,
+    path::{Path, PathBuf},
 };
 
-use cilly::v2::{asm::ILASM_FLAVOUR, il_exporter::ILExporter, opt::OptFuel, Assembly};
-fn asm_with_fuel(asm: &Assembly, path: &Path, fuel: u32) {
+use cilly::{
+    config,
+    v2::{
+        asm::ILASM_FLAVOUR,
+        c_exporter::CExporter,
+        cillyir_exporter::print_method,
+        il_exporter::ILExporter,
+        opt::{pipeline::Pipeline, OptFuel},
+        Assembly, MethodDefIdx,
+    },
+};
+config!(C_MODE, bool, false);
+/// Exports and runs versions of the program being bisected.
+struct Runner<'a> {
+    fail: &'a str,
+    args: &'a [&'a str],
+    dir: PathBuf,
+}
+impl<'a> Runner<'a> {
+    fn new(fail: &'a str, args: &'a [&'a str]) -> Self {
+        let dir = std::env::temp_dir().join(format!("optsect_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("Could not create the temporary directory");
+        Self { fail, args, dir }
+    }
+    /// Exports `asm` to `path`, returning the path of the resulting executable.
+    fn export(asm: &Assembly, path: &Path) -> PathBuf {
+        let export_time = std::time::Instant::now();
+        if *C_MODE {
+            asm.export(path, CExporter::new(false));
+        } else {
+            #[cfg(not(miri))]
+            asm.export(path, ILExporter::new(*ILASM_FLAVOUR, false));
+            let mut config_path = path.to_owned();
+            config_path.set_extension("runtimeconfig.json");
+            let cfg = cilly::v2::il_exporter::get_runtime_config();
+            std::fs::File::create(config_path)
+                .unwrap()
+                .write_all(cfg.as_bytes())
+                .unwrap();
+        }
+        eprintln!("Exported in {} ms", export_time.elapsed().as_millis());
+        path.to_owned()
+    }
+    /// Exports `asm` to the current directory, so that the user can inspect it.
+    fn keep(asm: &Assembly, name: &str) {
+        let mut path = std::env::current_dir().unwrap();
+        path.push(name);
+        if !*C_MODE {
+            path.set_extension("exe");
+        }
+        Self::export(asm, &path);
+    }
+    /// Exports and runs `asm`, and checks if its output contains the fail string.
+    fn fails(&self, asm: &Assembly) -> bool {
+        let mut path = self.dir.join("asm");
+        if !*C_MODE {
+            path.set_extension("exe");
+        }
+        let exe = Self::export(asm, &path);
+        let run_time = std::time::Instant::now();
+        let out = if *C_MODE {
+            std::process::Command::new(exe).args(self.args).output()
+        } else {
+            std::process::Command::new("dotnet")
+                .arg(exe)
+                .args(self.args)
+                .output()
+        }
+        .unwrap();
+        let stdout = String::from_utf8_lossy(&out.stdout);
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        let fail = stdout.contains(self.fail) || stderr.contains(self.fail);
+        eprintln!(
+            "Run the result in in {} ms. fail:{fail}",
+            run_time.elapsed().as_millis()
+        );
+        fail
+    }
+}
+impl Drop for Runner<'_> {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+fn optimized(asm: &Assembly, fuel: u32, pipeline: &mut Pipeline) -> Assembly {
     let mut asm = asm.clone();
     let opt_time = std::time::Instant::now();
-    asm.opt(&mut OptFuel::from_raw(fuel));
+    asm.opt_with(&mut OptFuel::from_raw(fuel), pipeline);
     eprintln!(
         "Optimization done in {} ms, preparing to export the assembly...",
         opt_time.elapsed().as_millis()
     );
-    let export_time = std::time::Instant::now();
-    eprintln!("Prepraing to export.");
-    #[cfg(not(miri))]
-    asm.export(path, ILExporter::new(*ILASM_FLAVOUR, false));
-    eprintln!("Exported in {} ms", export_time.elapsed().as_millis());
-    let mut config_path = path.to_owned();
-    config_path.set_extension("runtimeconfig.json");
-    let cfg = cilly::v2::il_exporter::get_runtime_config();
-    std::fs::File::create(config_path)
-        .unwrap()
-        .write_all(cfg.as_bytes())
-        .unwrap();
+    asm
+}
+fn with_fuel(asm: &Assembly, fuel: u32) -> Assembly {
+    optimized(asm, fuel, &mut Pipeline::from_env())
 }
 fn main() {
     let asm_path = std::env::args().nth(1).expect("no cilly path");
@@ -39,6 +125,14 @@ fn main() {
     let fail = std::env::args().nth(2).expect("no fail string");
     let args = std::env::args().nth(3).expect("no dotnet args");
     let args: Vec<&str> = args.split_whitespace().collect();
+    let runner = Runner::new(&fail, &args);
+    match std::env::args().nth(4).as_deref() {
+        Some("passes") => bisect_passes(&asm, &runner),
+        Some("methods") => bisect_methods(&asm, &runner),
+        _ => bisect_fuel(&asm, &runner),
+    }
+}
+fn bisect_fuel(asm: &Assembly, runner: &Runner) {
     let mut fuel_start = std::env::args()
         .nth(4)
         .map(|s| s.parse::<u32>().unwrap())
@@ -50,38 +144,173 @@ fn main() {
     while fuel_start < fuel_end - 1 {
         let fuel_mid = (fuel_start + fuel_end) / 2;
         eprintln!("Testing range {fuel_start} {fuel_end}, curr {fuel_mid}");
-
-        let mut path = std::env::temp_dir();
-        path.push("asm");
-        path.set_extension("exe");
-        asm_with_fuel(&asm, &path, fuel_mid);
-        let run_time = std::time::Instant::now();
-        let out = std::process::Command::new("dotnet")
-            .arg(path)
-            .args(&args)
-            .output()
-            .unwrap();
-        let stdout = std::str::from_utf8(&out.stdout).unwrap();
-        let stderr = std::str::from_utf8(&out.stderr).unwrap();
-        let fail = stdout.contains(&fail) || stderr.contains(&fail);
-        if fail {
+        if runner.fails(&with_fuel(asm, fuel_mid)) {
             fuel_end = fuel_mid;
         } else {
             fuel_start = fuel_mid;
         }
-        eprintln!(
-            "Run the result in in {} ms. fail:{fail}",
-            run_time.elapsed().as_millis()
-        );
     }
     eprintln!("Done. Preparing for compare.");
-    let mut path = std::env::current_dir().unwrap();
-    path.push("asm_ok");
-    path.set_extension("exe");
-    asm_with_fuel(&asm, &path, fuel_start);
-    let mut path = std::env::current_dir().unwrap();
-    path.push("asm_bad");
-    path.set_extension("exe");
-    asm_with_fuel(&asm, &path, fuel_end);
+    Runner::keep(&with_fuel(asm, fuel_start), "asm_ok");
+    Runner::keep(&with_fuel(asm, fuel_end), "asm_bad");
     eprintln!("Done. fuel_start:{fuel_start} fuel_end:{fuel_end}");
 }
+/// Finds the shortest prefix of the optimization pipeline which still makes the program fail.
+/// The last pass of that prefix is the one which introduced the failure.
+fn bisect_passes(asm: &Assembly, runner: &Runner) {
+    let passes = Pipeline::from_env().passes().to_vec();
+    assert!(!passes.is_empty(), "No optimization passes to bisect");
+    let fuel = asm.default_fuel().raw();
+    let with_passes = |count: usize| {
+        let mut pipeline = Pipeline::from_env();
+        pipeline.truncate(count);
+        optimized(asm, fuel, &mut pipeline)
+    };
+    let (mut pass_start, mut pass_end) = (0, passes.len());
+    while pass_start < pass_end - 1 {
+        let pass_mid = (pass_start + pass_end) / 2;
+        eprintln!("Testing passes {pass_start} {pass_end}, curr {pass_mid}");
+        if runner.fails(&with_passes(pass_mid)) {
+            pass_end = pass_mid;
+        } else {
+            pass_start = pass_mid;
+        }
+    }
+    Runner::keep(&with_passes(pass_start), "asm_ok");
+    Runner::keep(&with_passes(pass_end), "asm_bad");
+    eprintln!(
+        "Done. The failure is introduced by pass {} ({pass_end} passes enabled).",
+        passes[pass_end - 1]
+    );
+}
+/// Optimizes only `methods`, using `fuel`.
+fn with_methods(asm: &Assembly, methods: &[MethodDefIdx], fuel: u32) -> Assembly {
+    let mut pipeline = Pipeline::from_env();
+    pipeline.restrict_to(methods.iter().copied());
+    optimized(asm, fuel, &mut pipeline)
+}
+fn method_name(asm: &Assembly, method: MethodDefIdx) -> String {
+    let def = &asm[method];
+    format!("{}::{}", &asm[asm[def.class()].name()], &asm[def.name()])
+}
+/// Narrows the set of optimized methods down to a single method, and then finds the rewrite which broke it.
+fn bisect_methods(asm: &Assembly, runner: &Runner) {
+    let fuel = asm.default_fuel().raw();
+    let mut candidates: Vec<_> = asm
+        .methods_with(|_, _, def| def.implementation().blocks().is_some())
+        .map(|(idx, _)| *idx)
+        .collect();
+    candidates.sort_by_cached_key(|method| method_name(asm, *method));
+    assert!(
+        runner.fails(&with_methods(asm, &candidates, fuel)),
+        "Optimizing all methods does not make the program fail"
+    );
+    assert!(
+        !runner.fails(asm),
+        "The program fails even without any optimizations"
+    );
+    while candidates.len() > 1 {
+        eprintln!("{} candidate methods remain", candidates.len());
+        let (first, second) = candidates.split_at(candidates.len() / 2);
+        if runner.fails(&with_methods(asm, first, fuel)) {
+            candidates = first.to_vec();
+        } else if runner.fails(&with_methods(asm, second, fuel)) {
+            candidates = second.to_vec();
+        } else {
+            eprintln!("The failure requires optimizing methods from both halves.");
+            break;
+        }
+    }
+    if candidates.len() > 1 {
+        eprintln!("Done. The failure requires optimizing all of these methods:");
+        for method in &candidates {
+            eprintln!("  {}", method_name(asm, *method));
+        }
+        Runner::keep(&with_methods(asm, &candidates, fuel), "asm_bad");
+        return;
+    }
+    let method = candidates[0];
+    eprintln!(
+        "Optimizing {} breaks the program.",
+        method_name(asm, method)
+    );
+    // Optimizing with no fuel leaves the method untouched, so it must work.
+    let (mut fuel_start, mut fuel_end) = (0, fuel);
+    while fuel_start < fuel_end - 1 {
+        let fuel_mid = (fuel_start + fuel_end) / 2;
+        eprintln!("Testing range {fuel_start} {fuel_end}, curr {fuel_mid}");
+        if runner.fails(&with_methods(asm, &[method], fuel_mid)) {
+            fuel_end = fuel_mid;
+        } else {
+            fuel_start = fuel_mid;
+        }
+    }
+    let ok = with_methods(asm, &[method], fuel_start);
+    let bad = with_methods(asm, &[method], fuel_end);
+    println!(
+        "{}",
+        diff_lines(
+            &print_method(&ok, &ok[method]),
+            &print_method(&bad, &bad[method])
+        )
+    );
+    Runner::keep(&ok, "asm_ok");
+    Runner::keep(&bad, "asm_bad");
+    eprintln!(
+        "Done. The rewrite consuming fuel unit {fuel_end} of {} breaks the program.",
+        method_name(asm, method)
+    );
+}
+/// A line-based diff of `old` and `new`, with lines prefixed by `-`, `+` or a space.
+fn diff_lines(old: &str, new: &str) -> String {
+    let old: Vec<_> = old.lines().collect();
+    let new: Vec<_> = new.lines().collect();
+    // Only the part between the common prefix and suffix needs to be compared.
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (old_mid, new_mid) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+    // Longest common subsequence lengths of the suffixes of both sides.
+    let mut lcs = vec![vec![0_u32; new_mid.len() + 1]; old_mid.len() + 1];
+    for i in (0..old_mid.len()).rev() {
+        for j in (0..new_mid.len()).rev() {
+            lcs[i][j] = if old_mid[i] == new_mid[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let mut out = String::new();
+    let mut line = |mark: char, text: &str| {
+        out.push(mark);
+        out.push_str(text);
+        out.push('\n');
+    };
+    old[..prefix].iter().for_each(|text| line(' ', text));
+    let (mut i, mut j) = (0, 0);
+    while i < old_mid.len() || j < new_mid.len() {
+        if i < old_mid.len() && j < new_mid.len() && old_mid[i] == new_mid[j] {
+            line(' ', old_mid[i]);
+            i += 1;
+            j += 1;
+        } else if j == new_mid.len() || (i < old_mid.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            line('-', old_mid[i]);
+            i += 1;
+        } else {
+            line('+', new_mid[j]);
+            j += 1;
+        }
+    }
+    old[old.len() - suffix..]
+        .iter()
+        .for_each(|text| line(' ', text));
+    out
+}
@@ -0,0 +1,83 @@
+//! Shrinks an assembly, while keeping it "interesting".
+//!
+//! Usage: `reduce INPUT OUTPUT COMMAND [ARGS...]`.
+//!
+//! `INPUT` is a serialized assembly(or a `.cillyir` file). Each candidate is saved to a temporary file, and `COMMAND`
+//! is run with `ARGS`, with every `{}` argument replaced by the path to that file(or the path appended, if there is no `{}`).
+//! The candidate is interesting if the command exits successfully. The smallest interesting assembly found so far is
+//! saved to `OUTPUT` after every successful step, so the reduction can be stopped at any point.
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use cilly::v2::{reduce::reduce, Assembly};
+
+fn load(path: &Path) -> Assembly {
+    if path.extension().is_some_and(|ext| ext == "cillyir") {
+        return Assembly::load_cillyir(path).expect("ERROR: Could not load the assembly file!");
+    }
+    let mut asm_file = std::fs::File::open(path).expect("ERROR:Could not open the assembly file!");
+    let mut asm_bytes = Vec::with_capacity(0x10000);
+    asm_file
+        .read_to_end(&mut asm_bytes)
+        .expect("ERROR: Could not load the assembly file!");
+    postcard::from_bytes(&asm_bytes).unwrap()
+}
+fn save(asm: &Assembly, path: &Path) {
+    if path.extension().is_some_and(|ext| ext == "cillyir") {
+        asm.save_cillyir(path).unwrap();
+    } else {
+        asm.save_tmp(&mut std::fs::File::create(path).unwrap())
+            .unwrap();
+    }
+}
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let usage = "Usage: reduce INPUT OUTPUT COMMAND [ARGS...]";
+    let input = PathBuf::from(args.next().expect(usage));
+    let output = PathBuf::from(args.next().expect(usage));
+    let command = args.next().expect(usage);
+    let command_args: Vec<String> = args.collect();
+    let asm = load(&input);
+    let candidate_path = std::env::temp_dir()
+        .join(format!("reduce_{}", std::process::id()))
+        .with_extension(input.extension().unwrap_or_default());
+    let interesting = |asm: &Assembly| {
+        save(asm, &candidate_path);
+        let mut cmd = std::process::Command::new(&command);
+        if command_args.iter().any(|arg| arg == "{}") {
+            cmd.args(command_args.iter().map(|arg| {
+                if arg == "{}" {
+                    candidate_path.as_os_str().to_owned()
+                } else {
+                    arg.into()
+                }
+            }));
+        } else {
+            cmd.args(&command_args).arg(&candidate_path);
+        }
+        let status = cmd
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()
+            .expect("Could not run the interestingness test");
+        if status.success() {
+            save(asm, &output);
+        }
+        status.success()
+    };
+    assert!(
+        interesting(&asm),
+        "The input assembly is not interesting, nothing to reduce"
+    );
+    let start = std::time::Instant::now();
+    let reduced = reduce(asm, interesting);
+    save(&reduced, &output);
+    let _ = std::fs::remove_file(&candidate_path);
+    eprintln!(
+        "Reduced in {} s. Result saved to {}",
+        start.elapsed().as_secs(),
+        output.display()
+    );
+}
@@ -60,7 +60,7 @@ macro_rules! config {
                         value
                     ),
                 })
-                .unwrap_or($default)
+                .unwrap_or_else(|| $default)
         });
     };
     ($name:ident,bool,$default:expr,$comment:literal) => {
@@ -83,7 +83,7 @@ macro_rules! config {
                         value
                     ),
                 })
-                .unwrap_or($default)
+                .unwrap_or_else(|| $default)
         });
     };
     ($name:ident,$tpe:ty,$default:expr) => {
@@ -97,7 +97,7 @@ macro_rules! config {
                     }
                 })
                 .map(|value| value.parse().unwrap())
-                .unwrap_or($default)
+                .unwrap_or_else(|| $default)
         });
     };
 }
@@ -1,7 +1,7 @@
 use super::{
     bimap::{BiMap, BiMapIndex, IntoBiMapIndex},
     cilnode::{BinOp, ExtendKind, MethodKind, PtrCastRes, UnOp},
-    opt::{OptFuel, SideEffectInfoCache},
+    opt::{pipeline::Pipeline, OptFuel, SideEffectInfoCache},
     Access, CILNode, CILRoot, ClassDef, ClassDefIdx, ClassRef, ClassRefIdx, Const, Exporter,
     FieldDesc, FieldIdx, FnSig, Int, IntoAsmIndex, MethodDef, MethodDefIdx, MethodRef,
     MethodRefIdx, NodeIdx, RootIdx, SigIdx, StaticFieldDesc, StaticFieldIdx, StringIdx, Type,
@@ -115,11 +115,11 @@ impl Index<FieldIdx> for Assembly {
     }
 }
 impl Assembly {
+    /// Typechecks all methods, and prints a report of all the errors found.
     pub fn typecheck(&mut self) {
-        let method_def_idxs: Box<[_]> = self.method_defs.keys().copied().collect();
-        for method in method_def_idxs {
-            let mut tmp_method = self.method_def(method).clone();
-            tmp_method.typecheck(self);
+        let report = self.typecheck_report();
+        if !report.is_empty() {
+            eprintln!("{}", report.to_text());
         }
     }
     #[must_use]
@@ -158,31 +158,28 @@ impl Assembly {
             "Could not return a methoddef, because a method def is already present."
         );
     }
-    /// Optimizes the assembly uitill all fuel is consumed, or no more progress can be made
+    /// Optimizes the assembly uitill all fuel is consumed, or no more progress can be made.
+    /// The passes run are configured by the `OPT_*` enviroment variables, see [`Pipeline`].
     pub fn opt(&mut self, fuel: &mut OptFuel) {
+        let mut pipeline = Pipeline::from_env();
+        self.opt_with(fuel, &mut pipeline);
+        pipeline.write_report_from_env();
+    }
+    /// Optimizes the assembly using `pipeline`, uitill all fuel is consumed, or no more progress can be made.
+    pub fn opt_with(&mut self, fuel: &mut OptFuel, pipeline: &mut Pipeline) {
         let mut cache = SideEffectInfoCache::default();
         while !fuel.exchausted() {
             let prev = fuel.clone();
-            self.opt_sigle_pass(fuel, &mut cache);
+            pipeline.run_once(self, fuel, &mut cache);
             // No fuel consumed, progress can't be made, break.
             if *fuel == prev {
                 break;
             }
-            //let _pass_min_cost: bool = fuel.consume(1);
         }
     }
     /// Optimizes the assembly, cosuming some fuel. This performs a single optimization pass.
     pub fn opt_sigle_pass(&mut self, fuel: &mut OptFuel, cache: &mut SideEffectInfoCache) {
-        let method_def_idxs: Box<[_]> = self.method_defs.keys().copied().collect();
-        for method in method_def_idxs {
-            let mut tmp_method = self.borrow_methoddef(method);
-            tmp_method.optimize(self, cache, fuel);
-            tmp_method.remove_dead_blocks(self);
-            self.return_methoddef(method, tmp_method);
-            if fuel.exchausted() {
-                break;
-            }
-        }
+        Pipeline::from_env().run_once(self, fuel, cache);
     }
     /// Finds all methods matching the closure
     pub fn methods_with<'a>(
@@ -1115,7 +1112,8 @@ impl Assembly {
                 | CILNode::LdLen(_)
                 | CILNode::LocAllocAlgined { .. }
                 | CILNode::LdElelemRef { .. }
-                | CILNode::UnboxAny { .. } => None,
+                | CILNode::UnboxAny { .. }
+                | CILNode::NewArr { .. } => None,
             })
             .chain(self.iter_roots().filter_map(|root| match root {
                 CILRoot::Call(boxed) => Some(boxed.0),
@@ -1223,7 +1221,8 @@ impl Assembly {
     pub fn alignof_type(&self, tpe: TypeIdx) -> u64 {
         match self[tpe] {
             Type::FnPtr(_) | Type::Ptr(_) | Type::Ref(_) => 8, // ASSUMES alignof<*T>() = 8.
-            Type::Int(int) => int.size().unwrap_or(8) as u64,  // ASSUMES alignof<usize>() = 8.
+            Type::Pinned(inner) => self.alignof_type(inner),
+            Type::Int(int) => int.size().unwrap_or(8) as u64, // ASSUMES alignof<usize>() = 8.
             Type::ClassRef(class_ref_idx) => match self.class_ref_to_def(class_ref_idx) {
                 Some(def) => self[def]
                     .align()
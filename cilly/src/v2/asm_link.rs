@@ -14,6 +14,10 @@ impl Assembly {
                 let inner = self.translate_type(source, source[inner]);
                 self.nref(inner)
             }
+            Type::Pinned(inner) => {
+                let inner = self.translate_type(source, source[inner]);
+                Type::Pinned(self.alloc_type(inner))
+            }
             Type::Int(_)
             | Type::Float(_)
             | Type::PlatformString
@@ -295,6 +299,13 @@ impl Assembly {
                 let tpe = self.alloc_type(tpe);
                 CILNode::UnboxAny { object, tpe }
             }
+            CILNode::NewArr { elem, len } => {
+                let len = self.translate_node(source, source.get_node(*len).clone());
+                let len = self.alloc_node(len);
+                let elem = self.translate_type(source, source[*elem]);
+                let elem = self.alloc_type(elem);
+                CILNode::NewArr { elem, len }
+            }
         }
     }
     // The complexity of this function is unavoidable.
@@ -568,7 +579,7 @@ impl Assembly {
             .iter()
             .map(|arg| arg.map(|arg| self.alloc_string(source[arg].as_ref())))
             .collect();
-        MethodDef::new(
+        let mut translated = MethodDef::new(
             *def.access(),
             class,
             name,
@@ -576,7 +587,9 @@ impl Assembly {
             def.kind(),
             method_impl,
             arg_names,
-        )
+        );
+        translated.set_no_inlining(def.no_inlining());
+        translated
     }
     pub(crate) fn translate_class_def(&mut self, source: &Assembly, def: &ClassDef) -> ClassDef {
         let name = self.alloc_string(source[def.name()].as_ref());
@@ -0,0 +1,172 @@
+use super::super::{
+    cilnode::MethodKind, Access, Assembly, BasicBlock, CILNode, CILRoot, ClassDef, ClassRef,
+    ClassRefIdx, FieldDesc, MethodDef, MethodImpl, MethodRefIdx, Type,
+};
+/// Prefix of the names of classes holding Rust closures.
+pub const CLOSURE_HOLDER: &str = "RustClosure";
+/// Returns the class which holds Rust closures with the signature `inputs -> output`, defining it if needed.
+///
+/// The class stores a pointer to the environment of a boxed closure, a function pointer which calls that closure, and a
+/// function pointer which drops it. It has an `Invoke` method, with the signature of the closure, which can be bound to a
+/// delegate. The closure is dropped by the finalizer of the class, once .NET no longer references the delegate.
+pub fn closure_holder(asm: &mut Assembly, inputs: &[Type], output: Type) -> ClassRefIdx {
+    let name = format!(
+        "{CLOSURE_HOLDER}{argc}{inputs}{output}",
+        argc = inputs.len(),
+        inputs = inputs.iter().map(|tpe| tpe.mangle(asm)).collect::<String>(),
+        output = output.mangle(asm)
+    )
+    .replace('.', "_");
+    let name = asm.alloc_string(name);
+    let cref = asm.alloc_class_ref(ClassRef::new(name, None, false, [].into()));
+    if asm.class_ref_to_def(cref).is_some() {
+        return cref;
+    }
+    let void_ptr = asm.nptr(Type::Void);
+    let invoke_sig = asm.sig(
+        [void_ptr]
+            .into_iter()
+            .chain(inputs.iter().copied())
+            .collect::<Vec<_>>(),
+        output,
+    );
+    let drop_sig = asm.sig([void_ptr], Type::Void);
+    let invoke_fn = asm.alloc_string("invoke_fn");
+    let env = asm.alloc_string("env");
+    let drop_fn = asm.alloc_string("drop_fn");
+    let object = ClassRef::object(asm);
+    let holder = asm.class_def(ClassDef::new(
+        name,
+        false,
+        0,
+        Some(object),
+        vec![
+            (Type::FnPtr(invoke_sig), invoke_fn, None),
+            (void_ptr, env, None),
+            (Type::FnPtr(drop_sig), drop_fn, None),
+        ],
+        vec![],
+        // Same as `UnmanagedThreadStart`: the class is only used trough `ldftn`, and must not get removed.
+        Access::Extern,
+        None,
+        None,
+    ));
+    let invoke_field = asm.alloc_field(FieldDesc::new(cref, invoke_fn, Type::FnPtr(invoke_sig)));
+    let env_field = asm.alloc_field(FieldDesc::new(cref, env, void_ptr));
+    let drop_field = asm.alloc_field(FieldDesc::new(cref, drop_fn, Type::FnPtr(drop_sig)));
+    let this = asm.alloc_node(CILNode::LdArg(0));
+    // Constructor
+    let set_fields: Vec<_> = [invoke_field, env_field, drop_field]
+        .into_iter()
+        .enumerate()
+        .map(|(idx, field)| {
+            let value = asm.alloc_node(CILNode::LdArg(idx as u32 + 1));
+            asm.alloc_root(CILRoot::SetField(Box::new((field, this, value))))
+        })
+        .collect();
+    let ret = asm.alloc_root(CILRoot::VoidRet);
+    let ctor = asm.alloc_string(".ctor");
+    let ctor_sig = asm.sig(
+        [
+            Type::ClassRef(cref),
+            Type::FnPtr(invoke_sig),
+            void_ptr,
+            Type::FnPtr(drop_sig),
+        ],
+        Type::Void,
+    );
+    asm.new_method(MethodDef::new(
+        Access::Public,
+        holder,
+        ctor,
+        ctor_sig,
+        MethodKind::Constructor,
+        MethodImpl::MethodBody {
+            blocks: vec![BasicBlock::new(
+                set_fields.into_iter().chain([ret]).collect(),
+                0,
+                None,
+            )],
+            locals: vec![],
+        },
+        vec![None, Some(invoke_fn), Some(env), Some(drop_fn)],
+    ));
+    // Invoke: calls `invoke_fn(env, args...)`
+    let env_val = asm.alloc_node(CILNode::LdField {
+        addr: this,
+        field: env_field,
+    });
+    let invoke_fn_val = asm.alloc_node(CILNode::LdField {
+        addr: this,
+        field: invoke_field,
+    });
+    let args: Box<[_]> = [env_val]
+        .into_iter()
+        .chain((1..=inputs.len() as u32).map(|arg| asm.alloc_node(CILNode::LdArg(arg))))
+        .collect();
+    let body = if output == Type::Void {
+        let call = asm.alloc_root(CILRoot::CallI(Box::new((invoke_fn_val, invoke_sig, args))));
+        vec![call, ret]
+    } else {
+        let call = asm.alloc_node(CILNode::CallI(Box::new((invoke_fn_val, invoke_sig, args))));
+        vec![asm.alloc_root(CILRoot::Ret(call))]
+    };
+    let invoke = asm.alloc_string("Invoke");
+    let invoke_method_sig = asm.sig(
+        [Type::ClassRef(cref)]
+            .into_iter()
+            .chain(inputs.iter().copied())
+            .collect::<Vec<_>>(),
+        output,
+    );
+    asm.new_method(MethodDef::new(
+        Access::Public,
+        holder,
+        invoke,
+        invoke_method_sig,
+        MethodKind::Instance,
+        MethodImpl::MethodBody {
+            blocks: vec![BasicBlock::new(body, 0, None)],
+            locals: vec![],
+        },
+        vec![None; inputs.len() + 1],
+    ));
+    // Finalizer: calls `drop_fn(env)`
+    let drop_fn_val = asm.alloc_node(CILNode::LdField {
+        addr: this,
+        field: drop_field,
+    });
+    let drop = asm.alloc_root(CILRoot::CallI(Box::new((
+        drop_fn_val,
+        drop_sig,
+        [env_val].into(),
+    ))));
+    let finalize = asm.alloc_string("Finalize");
+    let finalize_sig = asm.sig([Type::ClassRef(cref)], Type::Void);
+    asm.new_method(MethodDef::new(
+        // Never called directly, so it must be extern to not be removed.
+        Access::Extern,
+        holder,
+        finalize,
+        finalize_sig,
+        MethodKind::Virtual,
+        MethodImpl::MethodBody {
+            blocks: vec![BasicBlock::new(vec![drop, ret], 0, None)],
+            locals: vec![],
+        },
+        vec![None],
+    ));
+    cref
+}
+/// Returns the `Invoke` method of a closure holder class, with the signature `inputs -> output`.
+pub fn closure_holder_invoke(
+    asm: &mut Assembly,
+    holder: ClassRefIdx,
+    inputs: &[Type],
+    output: Type,
+) -> MethodRefIdx {
+    let invoke = asm.alloc_string("Invoke");
+    asm.class_ref(holder)
+        .clone()
+        .instance(inputs, output, invoke, asm)
+}
@@ -0,0 +1,696 @@
+//! Software emulation of 128 bit integers, for C compilers which don't support `__int128`(e.g. `tcc`, `sdcc` or MSVC).
+//! Each 128 bit integer is represented as a struct holding two 64 bit words. Signed integers use two's complement, so most
+//! operations are shared between `cilly_i128` and `cilly_u128`.
+//! The generated code avoids `//` comments and mixed declarations and code, so that it can be used with very old compilers.
+use std::fmt::Write;
+
+use crate::v2::{cilnode::ExtendKind, BinOp, Int, Type};
+/// The 2^64 constant, as a C floating-point literal.
+const TWO_POW_64: &str = "18446744073709551616.0";
+/// The 2^127 constant, as a C floating-point literal.
+const TWO_POW_127: &str = "170141183460469231731687303715884105728.0";
+/// The 2^128 constant, as a C floating-point literal.
+const TWO_POW_128: &str = "340282366920938463463374607431768211456.0";
+/// Names of methods implemented by the software int128 code, whose cilly bodies should not be exported.
+pub const SOFT_INT128_PROVIDED: &[&str] = &["i128_mul_ovf_check"];
+/// Returns the name of the C type used to emulate `int`.
+/// ```
+/// # use cilly::v2::builtins::int128::c_soft::soft_type_name;
+/// # use cilly::v2::Int;
+/// assert_eq!(soft_type_name(Int::I128),"cilly_i128");
+/// assert_eq!(soft_type_name(Int::U128),"cilly_u128");
+/// ```
+#[must_use]
+pub fn soft_type_name(int: Int) -> &'static str {
+    match int {
+        Int::I128 => "cilly_i128",
+        Int::U128 => "cilly_u128",
+        _ => panic!("{int:?} is not a 128 bit integer"),
+    }
+}
+/// Returns the C source of the software implementation of 128 bit integers.
+#[must_use]
+pub fn soft_int128_c_source() -> String {
+    let mut out = String::new();
+    write_common(&mut out).unwrap();
+    for int in [Int::U128, Int::I128] {
+        write_shared_ops(&mut out, int).unwrap();
+    }
+    write_reinterpret(&mut out).unwrap();
+    write_unsigned_ops(&mut out).unwrap();
+    write_signed_ops(&mut out).unwrap();
+    for int in [Int::U128, Int::I128] {
+        write_casts(&mut out, int).unwrap();
+        write_ovf_checks(&mut out, int).unwrap();
+    }
+    write_bit_ops(&mut out).unwrap();
+    write_dotnet_api(&mut out).unwrap();
+    out
+}
+fn write_common(out: &mut impl Write) -> std::fmt::Result {
+    writeln!(out, "/* Software emulated 128 bit integers. */")?;
+    for int in [Int::U128, Int::I128] {
+        let tpe = soft_type_name(int);
+        writeln!(
+            out,
+            "typedef struct {tpe} {{uint64_t lo; uint64_t hi;}} {tpe};"
+        )?;
+    }
+    // The high half of a 64x64 multiplication, computed using 32 bit limbs.
+    writeln!(
+        out,
+        "static inline uint64_t cilly_mul64_hi(uint64_t a, uint64_t b)
+{{
+    uint64_t a_lo = a & 0xFFFFFFFFu;
+    uint64_t a_hi = a >> 32;
+    uint64_t b_lo = b & 0xFFFFFFFFu;
+    uint64_t b_hi = b >> 32;
+    uint64_t lo_lo = a_lo * b_lo;
+    uint64_t hi_lo = a_hi * b_lo;
+    uint64_t lo_hi = a_lo * b_hi;
+    uint64_t cross = (lo_lo >> 32) + (hi_lo & 0xFFFFFFFFu) + lo_hi;
+    return (hi_lo >> 32) + (cross >> 32) + a_hi * b_hi;
+}}
+static inline uint32_t cilly_clz64(uint64_t val)
+{{
+    uint32_t count = 0;
+    if (val == 0) return 64;
+    while (!(val & ((uint64_t)1 << 63))) {{ val <<= 1; count++; }}
+    return count;
+}}
+static inline uint32_t cilly_ctz64(uint64_t val)
+{{
+    uint32_t count = 0;
+    if (val == 0) return 64;
+    while (!(val & 1)) {{ val >>= 1; count++; }}
+    return count;
+}}
+static inline uint32_t cilly_popcount64(uint64_t val)
+{{
+    uint32_t count = 0;
+    while (val != 0) {{ val &= val - 1; count++; }}
+    return count;
+}}
+static inline uint64_t cilly_bswap64(uint64_t val)
+{{
+    uint64_t res = 0;
+    int byte;
+    for (byte = 0; byte < 8; byte++) {{ res = (res << 8) | (val & 0xFF); val >>= 8; }}
+    return res;
+}}"
+    )
+}
+/// Operations which behave the same for signed and unsigned integers.
+fn write_shared_ops(out: &mut impl Write, int: Int) -> std::fmt::Result {
+    let tpe = soft_type_name(int);
+    let name = int.name();
+    writeln!(
+        out,
+        "static inline {tpe} cilly_{name}_new(uint64_t hi, uint64_t lo)
+{{
+    {tpe} res;
+    res.lo = lo;
+    res.hi = hi;
+    return res;
+}}
+static inline {tpe} cilly_{name}_add({tpe} a, {tpe} b)
+{{
+    uint64_t lo = a.lo + b.lo;
+    return cilly_{name}_new(a.hi + b.hi + (lo < a.lo), lo);
+}}
+static inline {tpe} cilly_{name}_sub({tpe} a, {tpe} b)
+{{
+    return cilly_{name}_new(a.hi - b.hi - (a.lo < b.lo), a.lo - b.lo);
+}}
+static inline {tpe} cilly_{name}_mul({tpe} a, {tpe} b)
+{{
+    return cilly_{name}_new(cilly_mul64_hi(a.lo, b.lo) + a.lo * b.hi + a.hi * b.lo, a.lo * b.lo);
+}}
+static inline {tpe} cilly_{name}_and({tpe} a, {tpe} b) {{ return cilly_{name}_new(a.hi & b.hi, a.lo & b.lo); }}
+static inline {tpe} cilly_{name}_or({tpe} a, {tpe} b) {{ return cilly_{name}_new(a.hi | b.hi, a.lo | b.lo); }}
+static inline {tpe} cilly_{name}_xor({tpe} a, {tpe} b) {{ return cilly_{name}_new(a.hi ^ b.hi, a.lo ^ b.lo); }}
+static inline {tpe} cilly_{name}_not({tpe} a) {{ return cilly_{name}_new(~a.hi, ~a.lo); }}
+static inline {tpe} cilly_{name}_neg({tpe} a) {{ return cilly_{name}_sub(cilly_{name}_new(0, 0), a); }}
+static inline bool cilly_{name}_eq({tpe} a, {tpe} b) {{ return a.lo == b.lo && a.hi == b.hi; }}
+static inline {tpe} cilly_{name}_shl({tpe} a, uint32_t amount)
+{{
+    amount &= 127;
+    if (amount == 0) return a;
+    if (amount >= 64) return cilly_{name}_new(a.lo << (amount - 64), 0);
+    return cilly_{name}_new((a.hi << amount) | (a.lo >> (64 - amount)), a.lo << amount);
+}}"
+    )
+}
+fn write_reinterpret(out: &mut impl Write) -> std::fmt::Result {
+    writeln!(
+        out,
+        "static inline cilly_i128 cilly_i128_from_u128(cilly_u128 a) {{ return cilly_i128_new(a.hi, a.lo); }}
+static inline cilly_u128 cilly_u128_from_i128(cilly_i128 a) {{ return cilly_u128_new(a.hi, a.lo); }}
+static inline bool cilly_i128_is_neg(cilly_i128 a) {{ return (a.hi >> 63) != 0; }}"
+    )
+}
+fn write_unsigned_ops(out: &mut impl Write) -> std::fmt::Result {
+    writeln!(
+        out,
+        "static inline bool cilly_u128_lt(cilly_u128 a, cilly_u128 b) {{ return a.hi < b.hi || (a.hi == b.hi && a.lo < b.lo); }}
+static inline bool cilly_u128_gt(cilly_u128 a, cilly_u128 b) {{ return cilly_u128_lt(b, a); }}
+static inline cilly_u128 cilly_u128_shr(cilly_u128 a, uint32_t amount)
+{{
+    amount &= 127;
+    if (amount == 0) return a;
+    if (amount >= 64) return cilly_u128_new(0, a.hi >> (amount - 64));
+    return cilly_u128_new(a.hi >> amount, (a.lo >> amount) | (a.hi << (64 - amount)));
+}}
+static inline cilly_u128 cilly_u128_divrem(cilly_u128 num, cilly_u128 den, cilly_u128 *rem)
+{{
+    cilly_u128 quot = cilly_u128_new(0, 0);
+    cilly_u128 acc = cilly_u128_new(0, 0);
+    uint64_t carry;
+    int bit;
+    if (den.hi == 0 && den.lo == 0) cilly_abort();
+    if (num.hi == 0 && den.hi == 0)
+    {{
+        *rem = cilly_u128_new(0, num.lo % den.lo);
+        return cilly_u128_new(0, num.lo / den.lo);
+    }}
+    for (bit = 127; bit >= 0; bit--)
+    {{
+        /* The accumulator may briefly need 129 bits, if `den` is bigger than 2^127. */
+        carry = acc.hi >> 63;
+        acc = cilly_u128_shl(acc, 1);
+        acc.lo |= bit >= 64 ? (num.hi >> (bit - 64)) & 1 : (num.lo >> bit) & 1;
+        if (carry || !cilly_u128_lt(acc, den))
+        {{
+            acc = cilly_u128_sub(acc, den);
+            if (bit >= 64) quot.hi |= (uint64_t)1 << (bit - 64);
+            else quot.lo |= (uint64_t)1 << bit;
+        }}
+    }}
+    *rem = acc;
+    return quot;
+}}
+static inline double cilly_u128_to_f64(cilly_u128 val)
+{{
+    /* Keeps the top 64 bits, with a sticky bit, so that the value gets rounded only once. */
+    uint32_t bits;
+    uint64_t top;
+    if (val.hi == 0) return (double)val.lo;
+    bits = 64 - cilly_clz64(val.hi);
+    if (bits == 64) return ((double)(val.hi | (val.lo != 0))) * {TWO_POW_64};
+    top = (val.hi << (64 - bits)) | (val.lo >> bits) | ((val.lo << (64 - bits)) != 0);
+    return (double)top * (double)((uint64_t)1 << bits);
+}}
+static inline cilly_u128 cilly_u128_div(cilly_u128 a, cilly_u128 b) {{ cilly_u128 rem; return cilly_u128_divrem(a, b, &rem); }}
+static inline cilly_u128 cilly_u128_rem(cilly_u128 a, cilly_u128 b) {{ cilly_u128 rem; cilly_u128_divrem(a, b, &rem); return rem; }}"
+    )
+}
+fn write_signed_ops(out: &mut impl Write) -> std::fmt::Result {
+    writeln!(
+        out,
+        "static inline bool cilly_i128_lt(cilly_i128 a, cilly_i128 b)
+{{
+    uint64_t sign = (uint64_t)1 << 63;
+    return (a.hi ^ sign) < (b.hi ^ sign) || (a.hi == b.hi && a.lo < b.lo);
+}}
+static inline bool cilly_i128_gt(cilly_i128 a, cilly_i128 b) {{ return cilly_i128_lt(b, a); }}
+static inline cilly_i128 cilly_i128_shr(cilly_i128 a, uint32_t amount)
+{{
+    uint64_t fill = cilly_i128_is_neg(a) ? ~(uint64_t)0 : 0;
+    amount &= 127;
+    if (amount == 0) return a;
+    if (amount >= 64) return cilly_i128_new(fill, amount == 64 ? a.hi : (a.hi >> (amount - 64)) | (fill << (128 - amount)));
+    return cilly_i128_new((a.hi >> amount) | (fill << (64 - amount)), (a.lo >> amount) | (a.hi << (64 - amount)));
+}}
+static inline cilly_u128 cilly_i128_abs_u128(cilly_i128 a) {{ return cilly_u128_from_i128(cilly_i128_is_neg(a) ? cilly_i128_neg(a) : a); }}
+static inline cilly_i128 cilly_i128_div(cilly_i128 a, cilly_i128 b)
+{{
+    cilly_u128 rem;
+    cilly_i128 quot = cilly_i128_from_u128(cilly_u128_divrem(cilly_i128_abs_u128(a), cilly_i128_abs_u128(b), &rem));
+    return cilly_i128_is_neg(a) != cilly_i128_is_neg(b) ? cilly_i128_neg(quot) : quot;
+}}
+static inline cilly_i128 cilly_i128_rem(cilly_i128 a, cilly_i128 b)
+{{
+    cilly_u128 rem;
+    cilly_u128_divrem(cilly_i128_abs_u128(a), cilly_i128_abs_u128(b), &rem);
+    return cilly_i128_is_neg(a) ? cilly_i128_neg(cilly_i128_from_u128(rem)) : cilly_i128_from_u128(rem);
+}}"
+    )
+}
+/// Conversions between a 128 bit integer and the other primitive types.
+fn write_casts(out: &mut impl Write, int: Int) -> std::fmt::Result {
+    let tpe = soft_type_name(int);
+    let name = int.name();
+    // Integer conversions: narrowing is a truncation, widening depends on the signedness of the source.
+    writeln!(
+        out,
+        "static inline {tpe} cilly_{name}_from_u64(uint64_t val) {{ return cilly_{name}_new(0, val); }}
+static inline {tpe} cilly_{name}_from_i64(int64_t val) {{ return cilly_{name}_new(val < 0 ? ~(uint64_t)0 : 0, (uint64_t)val); }}
+static inline uint64_t cilly_{name}_to_u64({tpe} val) {{ return val.lo; }}"
+    )?;
+    // Float conversions: saturating, with NaN mapped to 0, like in Rust.
+    if int.is_signed() {
+        writeln!(
+            out,
+            "static inline double cilly_i128_to_f64(cilly_i128 val)
+{{
+    cilly_u128 abs = cilly_i128_abs_u128(val);
+    double res = cilly_u128_to_f64(abs);
+    return cilly_i128_is_neg(val) ? -res : res;
+}}
+static inline cilly_i128 cilly_i128_from_f64(double val)
+{{
+    cilly_i128 res;
+    double abs = val < 0.0 ? -val : val;
+    if (val != val) return cilly_i128_new(0, 0);
+    if (val >= {TWO_POW_127}) return cilly_i128_new(~(uint64_t)0 >> 1, ~(uint64_t)0);
+    if (val <= -{TWO_POW_127}) return cilly_i128_new((uint64_t)1 << 63, 0);
+    res.hi = (uint64_t)(abs / {TWO_POW_64});
+    res.lo = (uint64_t)(abs - (double)res.hi * {TWO_POW_64});
+    return val < 0.0 ? cilly_i128_neg(res) : res;
+}}"
+        )
+    } else {
+        writeln!(
+            out,
+            "static inline cilly_u128 cilly_u128_from_f64(double val)
+{{
+    cilly_u128 res;
+    if (!(val > 0.0)) return cilly_u128_new(0, 0);
+    if (val >= {TWO_POW_128}) return cilly_u128_new(~(uint64_t)0, ~(uint64_t)0);
+    res.hi = (uint64_t)(val / {TWO_POW_64});
+    res.lo = (uint64_t)(val - (double)res.hi * {TWO_POW_64});
+    return res;
+}}"
+        )
+    }
+}
+/// Overflow checks, used by checked arithmetic. Return `true` if the operation overflows.
+fn write_ovf_checks(out: &mut impl Write, int: Int) -> std::fmt::Result {
+    let tpe = soft_type_name(int);
+    let name = int.name();
+    if int.is_signed() {
+        writeln!(
+            out,
+            "static inline bool cilly_i128_add_ovf(cilly_i128 a, cilly_i128 b)
+{{
+    cilly_i128 res = cilly_i128_add(a, b);
+    return cilly_i128_is_neg(a) == cilly_i128_is_neg(b) && cilly_i128_is_neg(res) != cilly_i128_is_neg(a);
+}}
+static inline bool cilly_i128_sub_ovf(cilly_i128 a, cilly_i128 b)
+{{
+    cilly_i128 res = cilly_i128_sub(a, b);
+    return cilly_i128_is_neg(a) != cilly_i128_is_neg(b) && cilly_i128_is_neg(res) != cilly_i128_is_neg(a);
+}}"
+        )?;
+    } else {
+        writeln!(
+            out,
+            "static inline bool cilly_u128_add_ovf(cilly_u128 a, cilly_u128 b) {{ return cilly_u128_lt(cilly_u128_add(a, b), a); }}
+static inline bool cilly_u128_sub_ovf(cilly_u128 a, cilly_u128 b) {{ return cilly_u128_lt(a, b); }}"
+        )?;
+    }
+    // Multiplication overflows iff the product of the magnitudes does not fit, with special handling for the sign.
+    writeln!(
+        out,
+        "static inline bool cilly_{name}_mul_ovf({tpe} a, {tpe} b)
+{{
+    cilly_u128 mag_a = {mag_a};
+    cilly_u128 mag_b = {mag_b};
+    cilly_u128 prod = cilly_u128_mul(mag_a, mag_b);
+    cilly_u128 rem;
+    if ((mag_a.hi == 0 && mag_a.lo == 0) || (mag_b.hi == 0 && mag_b.lo == 0)) return false;
+    if (!cilly_u128_eq(cilly_u128_divrem(prod, mag_b, &rem), mag_a)) return true;
+    {sign_check}
+}}",
+        mag_a = if int.is_signed() {
+            "cilly_i128_abs_u128(a)"
+        } else {
+            "a"
+        },
+        mag_b = if int.is_signed() {
+            "cilly_i128_abs_u128(b)"
+        } else {
+            "b"
+        },
+        sign_check = if int.is_signed() {
+            "if (cilly_i128_is_neg(a) != cilly_i128_is_neg(b)) return cilly_u128_gt(prod, cilly_u128_new((uint64_t)1 << 63, 0));
+    return (prod.hi >> 63) != 0;"
+        } else {
+            "return false;"
+        }
+    )
+}
+/// Bit manipulation operations, which are only exposed for unsigned integers.
+fn write_bit_ops(out: &mut impl Write) -> std::fmt::Result {
+    writeln!(
+        out,
+        "static inline uint32_t cilly_u128_clz(cilly_u128 val) {{ return val.hi != 0 ? cilly_clz64(val.hi) : 64 + cilly_clz64(val.lo); }}
+static inline uint32_t cilly_u128_ctz(cilly_u128 val) {{ return val.lo != 0 ? cilly_ctz64(val.lo) : 64 + cilly_ctz64(val.hi); }}
+static inline uint32_t cilly_u128_popcount(cilly_u128 val) {{ return cilly_popcount64(val.hi) + cilly_popcount64(val.lo); }}
+static inline cilly_u128 cilly_u128_bswap(cilly_u128 val) {{ return cilly_u128_new(cilly_bswap64(val.lo), cilly_bswap64(val.hi)); }}
+static inline cilly_u128 cilly_u128_rotl(cilly_u128 val, uint32_t amount)
+{{
+    amount &= 127;
+    if (amount == 0) return val;
+    return cilly_u128_or(cilly_u128_shl(val, amount), cilly_u128_shr(val, 128 - amount));
+}}
+static inline cilly_u128 cilly_u128_rotr(cilly_u128 val, uint32_t amount)
+{{
+    amount &= 127;
+    if (amount == 0) return val;
+    return cilly_u128_or(cilly_u128_shr(val, amount), cilly_u128_shl(val, 128 - amount));
+}}
+static inline bool i128_mul_ovf_check(cilly_i128 a, cilly_i128 b) {{ return !cilly_i128_mul_ovf(a, b); }}"
+    )
+}
+/// Replacements for the .NET `System.Int128` and `System.UInt128` APIs, which the native header implements using `__int128`.
+fn write_dotnet_api(out: &mut impl Write) -> std::fmt::Result {
+    const BINOPS: [(&str, &str); 8] = [
+        ("Addition", "add"),
+        ("Subtraction", "sub"),
+        ("Multiply", "mul"),
+        ("Division", "div"),
+        ("Modulus", "rem"),
+        ("BitwiseOr", "or"),
+        ("ExclusiveOr", "xor"),
+        ("BitwiseAnd", "and"),
+    ];
+    const SMALL_INTS: [Int; 10] = [
+        Int::U8,
+        Int::U16,
+        Int::U32,
+        Int::U64,
+        Int::USize,
+        Int::I8,
+        Int::I16,
+        Int::I32,
+        Int::I64,
+        Int::ISize,
+    ];
+    for (int, class) in [(Int::U128, "UInt128"), (Int::I128, "Int128")] {
+        let name = int.name();
+        let other = if int.is_signed() {
+            Int::U128
+        } else {
+            Int::I128
+        };
+        let other_name = other.name();
+        for (dotnet, op) in BINOPS {
+            writeln!(
+                out,
+                "#define System_{class}_op_{dotnet}{name}{name}{name}(lhs, rhs) cilly_{name}_{op}(lhs, rhs)"
+            )?;
+        }
+        for (dotnet, op) in [
+            ("LessThan", "lt"),
+            ("GreaterThan", "gt"),
+            ("Equality", "eq"),
+        ] {
+            writeln!(
+                out,
+                "#define System_{class}_op_{dotnet}{name}{name}b(lhs, rhs) cilly_{name}_{op}(lhs, rhs)"
+            )?;
+        }
+        for (dotnet, op) in [("LeftShift", "shl"), ("RightShift", "shr")] {
+            writeln!(
+                out,
+                "#define System_{class}_op_{dotnet}{name}i32{name}(val, amount) cilly_{name}_{op}(val, (uint32_t)(amount))"
+            )?;
+        }
+        writeln!(
+            out,
+            "#define System_{class}_op_OnesComplement{name}{name}(val) cilly_{name}_not(val)
+#define System_{class}_op_UnaryNegation{name}{name}(val) cilly_{name}_neg(val)
+#define System_{class}_get_Zero{name}() cilly_{name}_new(0, 0)
+#define System_{class}_op_Explicit{name}{other_name}(val) cilly_{other_name}_from_{name}(val)
+#define System_{class}_op_Explicit{name}f64(val) cilly_{name}_to_f64(val)
+#define System_{class}_op_Explicit{name}f32(val) (float)cilly_{name}_to_f64(val)
+#define System_{class}_op_Explicitf64{name}(val) cilly_{name}_from_f64(val)
+#define System_{class}_op_Explicitf32{name}(val) cilly_{name}_from_f64((double)(val))"
+        )?;
+        for small in SMALL_INTS {
+            let small_name = small.name();
+            let c_small = small_c_type(small);
+            // Narrowing is always a truncation of the low word.
+            writeln!(
+                out,
+                "#define System_{class}_op_Explicit{name}{small_name}(val) ({c_small})((val).lo)"
+            )?;
+            // Widening depends on the signedness of the source. .NET only exposes lossless widening as implicit.
+            let widen = if small.is_signed() {
+                format!("cilly_{name}_from_i64((int64_t)(val))")
+            } else {
+                format!("cilly_{name}_from_u64((uint64_t)(val))")
+            };
+            let kind = if small.is_signed() && !int.is_signed() {
+                "Explicit"
+            } else {
+                "Implicit"
+            };
+            writeln!(
+                out,
+                "#define System_{class}_op_{kind}{small_name}{name}(val) {widen}"
+            )?;
+        }
+    }
+    writeln!(
+        out,
+        "#define System_Int128_get_MaxValuei128() cilly_i128_new(~(uint64_t)0 >> 1, ~(uint64_t)0)
+#define System_Int128_get_MinValuei128() cilly_i128_new((uint64_t)1 << 63, 0)
+#define System_UInt128_get_MaxValueu128() cilly_u128_new(~(uint64_t)0, ~(uint64_t)0)
+#define System_UInt128_get_MinValueu128() cilly_u128_new(0, 0)
+#define System_UInt128_TrailingZeroCountu128u128(val) cilly_u128_from_u64(cilly_u128_ctz(val))
+#define System_UInt128_LeadingZeroCountu128u128(val) cilly_u128_from_u64(cilly_u128_clz(val))
+#define System_UInt128_PopCountu128u128(val) cilly_u128_from_u64(cilly_u128_popcount(val))
+#define System_UInt128_RotateLeftu128i32u128(val, amount) cilly_u128_rotl(val, (uint32_t)(amount))
+#define System_UInt128_RotateRightu128i32u128(val, amount) cilly_u128_rotr(val, (uint32_t)(amount))
+#define System_Buffers_Binary_BinaryPrimitives_ReverseEndiannessu128u128(val) cilly_u128_bswap(val)
+#define System_Buffers_Binary_BinaryPrimitives_ReverseEndiannessi128i128(val) cilly_i128_from_u128(cilly_u128_bswap(cilly_u128_from_i128(val)))
+static inline cilly_i128 System_Int128_Clampi128i128i128i128(cilly_i128 val, cilly_i128 min, cilly_i128 max)
+{{
+    if (cilly_i128_gt(val, max)) return max;
+    if (cilly_i128_lt(val, min)) return min;
+    return val;
+}}"
+    )
+}
+/// Returns a C expression, applying `op` to `lhs` and `rhs` using the software implementation of `int`.
+/// `rhs_is_128` is only relevant for shifts, which may use 128 bit shift amounts.
+#[must_use]
+pub fn soft_binop(lhs: &str, rhs: &str, rhs_is_128: bool, op: BinOp, int: Int) -> String {
+    let name = int.name();
+    // Unsigned variants of signed operations are performed on reinterpreted values.
+    let (un_lhs, un_rhs) = if int.is_signed() {
+        (
+            format!("cilly_u128_from_i128({lhs})"),
+            format!("cilly_u128_from_i128({rhs})"),
+        )
+    } else {
+        (lhs.to_owned(), rhs.to_owned())
+    };
+    let to_int = |res: String| {
+        if int.is_signed() {
+            format!("cilly_i128_from_u128({res})")
+        } else {
+            res
+        }
+    };
+    let amount = if rhs_is_128 {
+        format!("(uint32_t)(({rhs}).lo)")
+    } else {
+        format!("(uint32_t)({rhs})")
+    };
+    match op {
+        BinOp::Add => format!("cilly_{name}_add({lhs}, {rhs})"),
+        BinOp::Sub => format!("cilly_{name}_sub({lhs}, {rhs})"),
+        BinOp::Mul => format!("cilly_{name}_mul({lhs}, {rhs})"),
+        BinOp::Or => format!("cilly_{name}_or({lhs}, {rhs})"),
+        BinOp::XOr => format!("cilly_{name}_xor({lhs}, {rhs})"),
+        BinOp::And => format!("cilly_{name}_and({lhs}, {rhs})"),
+        BinOp::Eq => format!("cilly_{name}_eq({lhs}, {rhs})"),
+        BinOp::Lt => format!("cilly_{name}_lt({lhs}, {rhs})"),
+        BinOp::Gt => format!("cilly_{name}_gt({lhs}, {rhs})"),
+        BinOp::LtUn => format!("cilly_u128_lt({un_lhs}, {un_rhs})"),
+        BinOp::GtUn => format!("cilly_u128_gt({un_lhs}, {un_rhs})"),
+        BinOp::Rem => format!("cilly_{name}_rem({lhs}, {rhs})"),
+        BinOp::RemUn => to_int(format!("cilly_u128_rem({un_lhs}, {un_rhs})")),
+        BinOp::Div => format!("cilly_{name}_div({lhs}, {rhs})"),
+        BinOp::DivUn => to_int(format!("cilly_u128_div({un_lhs}, {un_rhs})")),
+        BinOp::Shl => format!("cilly_{name}_shl({lhs}, {amount})"),
+        BinOp::Shr => format!("cilly_{name}_shr({lhs}, {amount})"),
+        BinOp::ShrUn => to_int(format!("cilly_u128_shr({un_lhs}, {amount})")),
+    }
+}
+/// Returns a C expression converting `input` of type `input_tpe` to `target`, if the conversion involves a software emulated integer.
+#[must_use]
+pub fn soft_int_cast(
+    input: &str,
+    input_tpe: Type,
+    target: Int,
+    extend: ExtendKind,
+) -> Option<String> {
+    let target_name = target.name();
+    match (input_tpe, target) {
+        (Type::Int(src @ (Int::I128 | Int::U128)), Int::I128 | Int::U128) => {
+            if src == target {
+                Some(input.to_owned())
+            } else {
+                Some(format!(
+                    "cilly_{target_name}_from_{src}({input})",
+                    src = src.name()
+                ))
+            }
+        }
+        // Narrowing is always a truncation of the low word.
+        (Type::Int(Int::I128 | Int::U128), _) => {
+            Some(format!("({tpe})(({input}).lo)", tpe = small_c_type(target)))
+        }
+        (Type::Float(_), Int::I128 | Int::U128) => {
+            Some(format!("cilly_{target_name}_from_f64((double)({input}))"))
+        }
+        (Type::Int(src), Int::I128 | Int::U128) => Some(match extend {
+            ExtendKind::ZeroExtend => format!(
+                "cilly_{target_name}_from_u64((uint64_t)({src_tpe})({input}))",
+                src_tpe = small_c_type(src.as_unsigned())
+            ),
+            ExtendKind::SignExtend => format!(
+                "cilly_{target_name}_from_i64((int64_t)({src_tpe})({input}))",
+                src_tpe = small_c_type(src.as_signed())
+            ),
+        }),
+        // Pointers and bools
+        (_, Int::I128 | Int::U128) => Some(format!(
+            "cilly_{target_name}_from_u64((uint64_t)(uintptr_t)({input}))"
+        )),
+        _ => None,
+    }
+}
+/// The C type of a non-128 bit integer.
+pub(crate) fn small_c_type(int: Int) -> &'static str {
+    match int {
+        Int::U8 => "uint8_t",
+        Int::U16 => "uint16_t",
+        Int::U32 => "uint32_t",
+        Int::U64 => "uint64_t",
+        Int::USize => "uintptr_t",
+        Int::I8 => "int8_t",
+        Int::I16 => "int16_t",
+        Int::I32 => "int32_t",
+        Int::I64 => "int64_t",
+        Int::ISize => "intptr_t",
+        Int::U128 | Int::I128 => soft_type_name(int),
+    }
+}
+#[cfg(test)]
+/// Compiles `source`(together with the soft int128 implementation) using the system C compiler, and runs it.
+fn run_c_test(name: &str, source: &str) {
+    let dir = std::env::temp_dir();
+    let c_path = dir.join(format!("{name}.c"));
+    let exe_path = dir.join(name);
+    let source = format!(
+        "#include <stdint.h>\n#include <stdbool.h>\n#include <stdlib.h>\n#include <stdio.h>\n#define cilly_abort() abort()\n{soft}\n{source}",
+        soft = soft_int128_c_source()
+    );
+    std::fs::write(&c_path, source).unwrap();
+    let out = std::process::Command::new(std::env::var("CC").unwrap_or("cc".to_owned()))
+        .arg(&c_path)
+        .arg("-o")
+        .arg(&exe_path)
+        .arg("-std=c99")
+        .arg("-Wall")
+        .output()
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let out = std::process::Command::new(&exe_path).output().unwrap();
+    assert!(
+        out.status.success(),
+        "{}{}",
+        String::from_utf8_lossy(&out.stdout),
+        String::from_utf8_lossy(&out.stderr)
+    );
+}
+#[test]
+#[cfg(not(miri))]
+fn soft_int128_matches_native() {
+    // Compares the software implementation against the compilers native `__int128`, using pseudo-random values.
+    run_c_test(
+        "cilly_soft_int128",
+        r#"
+typedef unsigned __int128 u128;
+typedef __int128 i128;
+static uint64_t state = 0x853c49e6748fea9b;
+static uint64_t next(void) { state = state * 6364136223846793005u + 1442695040888963407u; return state ^ (state >> 29); }
+static u128 from_u(cilly_u128 v) { return ((u128)v.hi << 64) | v.lo; }
+static i128 from_i(cilly_i128 v) { return (i128)from_u(cilly_u128_from_i128(v)); }
+static cilly_u128 to_u(u128 v) { return cilly_u128_new((uint64_t)(v >> 64), (uint64_t)v); }
+static cilly_i128 to_i(i128 v) { return cilly_i128_from_u128(to_u((u128)v)); }
+static u128 gen(void) {
+    u128 val = ((u128)next() << 64) | next();
+    switch (next() % 5) {
+        case 0: return val >> (next() % 128);
+        case 1: return (u128)(i128)(int64_t)next();
+        case 2: return next() % 4;
+        case 3: return ((u128)1 << 127) + (next() % 3) - 1;
+        default: return val;
+    }
+}
+#define CHECK(COND) if (!(COND)) { printf("Failed %s at iter %d\n", #COND, iter); return 1; }
+int main(void) {
+    int iter;
+    for (iter = 0; iter < 200000; iter++) {
+        u128 a = gen(), b = gen();
+        i128 sa = (i128)a, sb = (i128)b;
+        uint32_t amount = (uint32_t)(next() % 128);
+        cilly_u128 ua = to_u(a), ub = to_u(b);
+        cilly_i128 ia = to_i(sa), ib = to_i(sb);
+        double f = (double)(int64_t)next() * (double)(next() % 1000000);
+        CHECK(from_u(cilly_u128_add(ua, ub)) == a + b);
+        CHECK(from_u(cilly_u128_sub(ua, ub)) == a - b);
+        CHECK(from_u(cilly_u128_mul(ua, ub)) == a * b);
+        CHECK(from_i(cilly_i128_mul(ia, ib)) == (i128)((u128)sa * (u128)sb));
+        CHECK(from_u(cilly_u128_shl(ua, amount)) == a << amount);
+        CHECK(from_u(cilly_u128_shr(ua, amount)) == a >> amount);
+        CHECK(from_i(cilly_i128_shr(ia, amount)) == sa >> amount);
+        CHECK(cilly_u128_lt(ua, ub) == (a < b));
+        CHECK(cilly_i128_lt(ia, ib) == (sa < sb));
+        CHECK(cilly_i128_gt(ia, ib) == (sa > sb));
+        CHECK(cilly_u128_eq(ua, ub) == (a == b));
+        CHECK(from_i(cilly_i128_neg(ia)) == (i128)(0 - (u128)sa));
+        CHECK(from_u(cilly_u128_not(ua)) == ~a);
+        if (b != 0) {
+            CHECK(from_u(cilly_u128_div(ua, ub)) == a / b);
+            CHECK(from_u(cilly_u128_rem(ua, ub)) == a % b);
+        }
+        if (sb != 0 && !(sb == -1 && sa == (i128)((u128)1 << 127))) {
+            CHECK(from_i(cilly_i128_div(ia, ib)) == sa / sb);
+            CHECK(from_i(cilly_i128_rem(ia, ib)) == sa % sb);
+        }
+        CHECK(cilly_u128_add_ovf(ua, ub) == __builtin_add_overflow(a, b, &(u128){0}));
+        CHECK(cilly_u128_sub_ovf(ua, ub) == __builtin_sub_overflow(a, b, &(u128){0}));
+        CHECK(cilly_u128_mul_ovf(ua, ub) == __builtin_mul_overflow(a, b, &(u128){0}));
+        CHECK(cilly_i128_add_ovf(ia, ib) == __builtin_add_overflow(sa, sb, &(i128){0}));
+        CHECK(cilly_i128_sub_ovf(ia, ib) == __builtin_sub_overflow(sa, sb, &(i128){0}));
+        CHECK(cilly_i128_mul_ovf(ia, ib) == __builtin_mul_overflow(sa, sb, &(i128){0}));
+        CHECK(from_i(cilly_i128_from_i64((int64_t)a)) == (i128)(int64_t)a);
+        CHECK(cilly_u128_to_f64(ua) == (double)a);
+        CHECK(cilly_i128_to_f64(ia) == (double)sa);
+        CHECK(from_i(cilly_i128_from_f64(f)) == (i128)f);
+        CHECK(from_u(cilly_u128_from_f64(f < 0 ? -f : f)) == (u128)(f < 0 ? -f : f));
+        CHECK(cilly_u128_clz(ua) == (a == 0 ? 128 : (a >> 64) != 0 ? (uint32_t)__builtin_clzll((uint64_t)(a >> 64)) : 64 + (uint32_t)__builtin_clzll((uint64_t)a)));
+        CHECK(cilly_u128_popcount(ua) == (uint32_t)(__builtin_popcountll((uint64_t)a) + __builtin_popcountll((uint64_t)(a >> 64))));
+        CHECK(from_u(cilly_u128_rotl(ua, amount)) == (amount == 0 ? a : (a << amount) | (a >> (128 - amount))));
+        CHECK(from_u(cilly_u128_bswap(ua)) == __builtin_bswap128(a));
+    }
+    CHECK(from_u(cilly_u128_from_f64(-1.0)) == 0);
+    CHECK(from_u(cilly_u128_from_f64(1e40)) == ~(u128)0);
+    CHECK(from_i(cilly_i128_from_f64(-1e40)) == (i128)((u128)1 << 127));
+    CHECK(from_i(cilly_i128_from_f64(0.0 / 0.0)) == 0);
+    return 0;
+}
+"#,
+    );
+}
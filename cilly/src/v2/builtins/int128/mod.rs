@@ -4,6 +4,7 @@ use crate::{
     },
     BranchCond, ClassRef, Const, Type,
 };
+pub mod c_soft;
 
 fn op_direct(
     asm: &mut Assembly,
@@ -138,23 +139,44 @@ pub fn i128_mul_ovf_check(asm: &mut Assembly, patcher: &mut MissingMethodPatcher
             1,
             Some(BranchCond::False(rhs_zero)),
         ))));
-        let ret_false = asm.alloc_node(Const::Bool(false));
-        let ret_false = asm.alloc_root(CILRoot::Ret(ret_false));
+        // Multiplying by zero never overflows.
+        let ret_true = asm.alloc_node(Const::Bool(true));
+        let ret_true = asm.alloc_root(CILRoot::Ret(ret_true));
+        // `i128::MIN / -1` throws, so when `lhs` is `i128::MIN` the product is divided by `lhs` instead.
+        let get_min = asm.alloc_string("get_MinValue");
+        let const_min = i128_classref.static_mref(&[], Type::Int(Int::I128), get_min, asm);
+        let const_min = asm.alloc_node(CILNode::Call(Box::new((const_min, [].into()))));
+        let lhs_min = asm.alloc_node(CILNode::Call(Box::new((i128_eq, [lhs, const_min].into()))));
+        let jmp_not_min = asm.alloc_root(CILRoot::Branch(Box::new((
+            0,
+            2,
+            Some(BranchCond::False(lhs_min)),
+        ))));
         let lhs_mul_rhs = asm.alloc_node(CILNode::Call(Box::new((i128_mul, [lhs, rhs].into()))));
         let recomputed_rhs = asm.alloc_node(CILNode::Call(Box::new((
             i128_div,
-            [lhs_mul_rhs, rhs].into(),
+            [lhs_mul_rhs, lhs].into(),
         ))));
-        let ovf = asm.alloc_node(CILNode::Call(Box::new((
+        let min_no_ovf = asm.alloc_node(CILNode::Call(Box::new((
             i128_eq,
             [recomputed_rhs, rhs].into(),
         ))));
-        let ret_ovf = asm.alloc_root(CILRoot::Ret(ovf));
+        let ret_min_no_ovf = asm.alloc_root(CILRoot::Ret(min_no_ovf));
+        let recomputed_lhs = asm.alloc_node(CILNode::Call(Box::new((
+            i128_div,
+            [lhs_mul_rhs, rhs].into(),
+        ))));
+        let no_ovf = asm.alloc_node(CILNode::Call(Box::new((
+            i128_eq,
+            [recomputed_lhs, lhs].into(),
+        ))));
+        let ret_no_ovf = asm.alloc_root(CILRoot::Ret(no_ovf));
 
         MethodImpl::MethodBody {
             blocks: vec![
-                BasicBlock::new(vec![jmp_nz, ret_false], 0, None),
-                BasicBlock::new(vec![ret_ovf], 1, None),
+                BasicBlock::new(vec![jmp_nz, ret_true], 0, None),
+                BasicBlock::new(vec![jmp_not_min, ret_min_no_ovf], 1, None),
+                BasicBlock::new(vec![ret_no_ovf], 2, None),
             ],
             locals: vec![],
         }
@@ -12,6 +12,7 @@ use super::{
 
 pub mod atomics;
 pub mod casts;
+pub mod delegate;
 pub mod math;
 pub mod select;
 pub mod thread;
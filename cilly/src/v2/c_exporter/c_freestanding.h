@@ -0,0 +1,151 @@
+/* Freestanding C profile: the generated code does not depend on libc. Instead, it relies on these hooks, provided by the user:
+void *cilly_alloc(uintptr_t size, uintptr_t align);
+void *cilly_realloc(void *ptr, uintptr_t new_size, uintptr_t align);
+void cilly_free(void *ptr);
+void cilly_panic(const char *msg); -- must not return.
+The code is C89, with the exception of `long long`, which is needed for 64 bit integers.
+Floating-point remainders call `fmod`, which must be provided by the user if they are used.
+`f16` and `f128` are only supported by GCC-compatible compilers, which provide `_Float16` and `_Float128`.
+CILLY_POINTER_WIDTH must match the pointer width the Rust code was compiled for, since field offsets are computed by rustc. */
+#ifndef CILLY_POINTER_WIDTH
+#define CILLY_POINTER_WIDTH 64
+#endif
+#if defined(__STDC_VERSION__) && __STDC_VERSION__ >= 199901L
+#include <stdint.h>
+#include <stdbool.h>
+#if (CILLY_POINTER_WIDTH == 16 && UINTPTR_MAX != 0xFFFFu) || (CILLY_POINTER_WIDTH == 32 && UINTPTR_MAX != 0xFFFFFFFFu) || (CILLY_POINTER_WIDTH == 64 && UINTPTR_MAX != 0xFFFFFFFFFFFFFFFFu)
+#error "CILLY_POINTER_WIDTH does not match the pointer size of this target."
+#endif
+#else
+#include <limits.h>
+typedef unsigned char uint8_t;
+typedef signed char int8_t;
+typedef unsigned short uint16_t;
+typedef short int16_t;
+#if UINT_MAX == 0xFFFFFFFFu
+typedef unsigned int uint32_t;
+typedef int int32_t;
+#else
+typedef unsigned long uint32_t;
+typedef long int32_t;
+#endif
+typedef unsigned long long uint64_t;
+typedef long long int64_t;
+#if CILLY_POINTER_WIDTH == 64
+typedef uint64_t uintptr_t;
+typedef int64_t intptr_t;
+#elif CILLY_POINTER_WIDTH == 32
+typedef uint32_t uintptr_t;
+typedef int32_t intptr_t;
+#elif CILLY_POINTER_WIDTH == 16
+typedef uint16_t uintptr_t;
+typedef int16_t intptr_t;
+#else
+#error "Unsupported CILLY_POINTER_WIDTH: expected 16, 32 or 64."
+#endif
+typedef unsigned char bool;
+#define true 1
+#define false 0
+#define inline
+/* Fails to compile if CILLY_POINTER_WIDTH does not match the pointer size of this target. */
+typedef char cilly_pointer_width_check[sizeof(void *) * CHAR_BIT == CILLY_POINTER_WIDTH ? 1 : -1];
+#endif
+#if defined(__GNUC__) && defined(__FLT16_MAX__)
+__extension__ typedef _Float16 cilly_f16;
+#endif
+#if defined(__GNUC__) && defined(__FLT128_MAX__)
+__extension__ typedef _Float128 cilly_f128;
+#endif
+/* User-provided hooks */
+void *cilly_alloc(uintptr_t size, uintptr_t align);
+void *cilly_realloc(void *ptr, uintptr_t new_size, uintptr_t align);
+void cilly_free(void *ptr);
+void cilly_panic(const char *msg);
+double fmod(double lhs, double rhs);
+/* Freestanding targets are assumed to be single threaded. */
+#define CILLY_THREAD_LOCAL
+#define cilly_abort() cilly_panic("Aborted.")
+/* Allocator APIs */
+#define malloc(size) cilly_alloc((size), sizeof(void *))
+#define realloc(ptr, new_size) cilly_realloc((ptr), (new_size), sizeof(void *))
+#define free(ptr) cilly_free(ptr)
+#define System_Runtime_InteropServices_Marshal_AllocHGlobali32isize(size) cilly_alloc((size), sizeof(void *))
+#define System_Runtime_InteropServices_Marshal_AllocHGlobalisizeisize(size) cilly_alloc((size), sizeof(void *))
+#define System_Runtime_InteropServices_Marshal_ReAllocHGlobalisizeisizeisize(ptr, new_size) cilly_realloc((ptr), (new_size), sizeof(void *))
+#define System_Runtime_InteropServices_Marshal_FreeHGlobalisizev(ptr) cilly_free(ptr)
+#define System_Runtime_InteropServices_NativeMemory_AlignedAllocusizeusizepv(size, align) cilly_alloc((size), (align))
+#define System_Runtime_InteropServices_NativeMemory_AlignedFreepvv(ptr) cilly_free(ptr)
+#define System_Runtime_InteropServices_NativeMemory_AlignedReallocpvusizeuspv(ptr, size, align) cilly_realloc((ptr), (size), (align))
+/* Memory operations, used instead of the ones provided by libc. */
+static inline void *cilly_memcpy(void *dst, const void *src, uintptr_t len)
+{
+    uint8_t *dst_bytes = (uint8_t *)dst;
+    const uint8_t *src_bytes = (const uint8_t *)src;
+    uintptr_t byte;
+    for (byte = 0; byte < len; byte++) dst_bytes[byte] = src_bytes[byte];
+    return dst;
+}
+static inline void *cilly_memset(void *dst, int32_t val, uintptr_t len)
+{
+    uint8_t *dst_bytes = (uint8_t *)dst;
+    uintptr_t byte;
+    for (byte = 0; byte < len; byte++) dst_bytes[byte] = (uint8_t)val;
+    return dst;
+}
+static inline void *cilly_memmove(void *dst, const void *src, uintptr_t len)
+{
+    uint8_t *dst_bytes = (uint8_t *)dst;
+    const uint8_t *src_bytes = (const uint8_t *)src;
+    uintptr_t byte;
+    if (dst_bytes < src_bytes)
+    {
+        for (byte = 0; byte < len; byte++) dst_bytes[byte] = src_bytes[byte];
+    }
+    else
+    {
+        for (byte = len; byte > 0; byte--) dst_bytes[byte - 1] = src_bytes[byte - 1];
+    }
+    return dst;
+}
+static inline int32_t cilly_memcmp(const void *lhs, const void *rhs, uintptr_t len)
+{
+    const uint8_t *lhs_bytes = (const uint8_t *)lhs;
+    const uint8_t *rhs_bytes = (const uint8_t *)rhs;
+    uintptr_t byte;
+    for (byte = 0; byte < len; byte++)
+    {
+        if (lhs_bytes[byte] != rhs_bytes[byte]) return (int32_t)lhs_bytes[byte] - (int32_t)rhs_bytes[byte];
+    }
+    return 0;
+}
+static inline uintptr_t cilly_strlen(const char *str)
+{
+    uintptr_t len = 0;
+    while (str[len] != 0) len++;
+    return len;
+}
+/* Rust code calls these libc functions directly. */
+#define memcpy(dst, src, len) cilly_memcpy((dst), (src), (len))
+#define memset(dst, val, len) cilly_memset((dst), (val), (len))
+#define memmove(dst, src, len) cilly_memmove((dst), (src), (len))
+#define memcmp(lhs, rhs, len) cilly_memcmp((lhs), (rhs), (len))
+#define strlen(str) cilly_strlen(str)
+/* Dynamically sized stack allocations are placed on the heap. Each method keeps a list of them, which it frees before returning. */
+static inline void *cilly_local_alloc(void **frame, uintptr_t size)
+{
+    uint8_t *block = (uint8_t *)cilly_alloc(size + 16, 16);
+    if (block == 0) cilly_panic("Could not allocate a dynamically sized stack buffer.");
+    *(void **)block = *frame;
+    *frame = block;
+    return block + 16;
+}
+static inline void cilly_local_free(void *frame)
+{
+    void *prev;
+    while (frame != 0)
+    {
+        prev = *(void **)frame;
+        cilly_free(frame);
+        frame = prev;
+    }
+}
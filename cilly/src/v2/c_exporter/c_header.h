@@ -8,10 +8,6 @@
 #include <mm_malloc.h>
 
 #include <alloca.h>
-/* Backup for targets that don't support i128 - TODO: replace this with software emulation!*/
-#ifndef __SIZEOF_INT128__
-#define __int128 long long
-#endif
 /* Allocator APIs*/
 #define System_Runtime_InteropServices_Marshal_AllocHGlobali32isize(size) malloc(size)
 #define System_Runtime_InteropServices_Marshal_AllocHGlobalisizeisize(size) malloc(size)
@@ -29,6 +25,21 @@ static inline void *System_Runtime_InteropServices_NativeMemory_AlignedReallocpv
 /*Utility macros*/
 #define eprintf(...) fprintf(stderr, __VA_ARGS__)
 #define BUILTIN_UNSUPORTED(NAME,OUTPUT, ARGLIST) static inline OUTPUT NAME ARGLIST { eprintf("Function " #NAME "is not yet supported!"); abort();}
+/* Hooks used by the portable runtime(`c_runtime.h`). The freestanding profile expects the user to provide them instead. */
+#define CILLY_THREAD_LOCAL _Thread_local
+#define cilly_alloc(size, align) malloc(size)
+#define cilly_free(ptr) free(ptr)
+#define cilly_abort() abort()
+static inline void cilly_panic(const char *msg)
+{
+    eprintf("%s\n", msg);
+    abort();
+}
+/* Native 128 bit integers. If the C compiler lacks `__int128`, the exporter defines CILLY_SOFT_INT128 and emits a software implementation instead. */
+#ifndef CILLY_SOFT_INT128
+#ifndef __SIZEOF_INT128__
+#error "This C compiler does not support __int128: export with SOFT_INT128=1 to emulate 128 bit integers."
+#endif
 /*Wrappers for certain 128 bit ops: TODO: remove this once all ops are ported to new cilly builtins*/
 #define System_UInt128_op_Additionu128u128u128(lhs, rhs) (lhs + rhs)
 #define System_Int128_op_Additioni128i128i128(lhs, rhs) (__int128)((unsigned __int128)lhs + (unsigned __int128)rhs)
@@ -127,6 +138,42 @@ static inline void *System_Runtime_InteropServices_NativeMemory_AlignedReallocpv
 
 #define System_Buffers_Binary_BinaryPrimitives_ReverseEndiannessi128i128(val) (__int128_t) __builtin_bswap128((__uint128_t)val)
 #define System_Buffers_Binary_BinaryPrimitives_ReverseEndiannessu128u128 __builtin_bswap128
+static inline int32_t System_UInt128_TrailingZeroCountu128u128(__uint128_t val) {if (val == 0) return sizeof(__uint128_t) * 8; return (int32_t) __builtin_ctzl((__uint128_t)val);}
+__uint128_t __builtin_bswap128(__uint128_t val);
+#define System_UInt128_PopCountu128u128(val) __builtin_popcountl((uint64_t)val)
+#define System_Int128_get_MaxValuei128() ((__int128_t)(((__uint128_t)((__int128_t)(-1))) >> 1))
+#define System_Int128_get_Zeroi128(v) ((__int128_t)0)
+static inline unsigned __int128 System_UInt128_RotateLeftu128i32u128(unsigned __int128 val, int32_t amount)
+{
+    amount = amount % (sizeof(unsigned __int128)*8);
+     if(amount == 0) return val;
+    return ((val << amount) | (val >> ( (sizeof(unsigned __int128)*8) - amount)));
+}
+static inline unsigned __int128 System_UInt128_RotateRightu128i32u128(unsigned __int128 val, int32_t amount)
+{
+    amount = amount % (sizeof(unsigned __int128)*8);
+     if(amount == 0) return val;
+    return ((val >> amount) | (val << ( (sizeof(unsigned __int128)*8) - amount)));
+}
+static inline unsigned __int128 System_UInt128_LeadingZeroCountu128u128(unsigned __int128 val){ if (val == 0) return 128; return __builtin_clzl(val); }
+
+static inline __int128 System_Int128_Clampi128i128i128i128(__int128 val, __int128 min, __int128 max)
+{
+    if (val > max)
+    {
+        return max;
+    }
+    else if (val < min)
+    {
+        return min;
+    }
+    else
+    {
+        return val;
+    }
+}
+#define System_Int128_get_MinValuei128() (-System_Int128_get_MaxValuei128() - 1)
+#endif
 #define System_Buffers_Binary_BinaryPrimitives_ReverseEndiannessi8i8(val) val
 
 #define System_Buffers_Binary_BinaryPrimitives_ReverseEndiannessu64u64 __builtin_bswap64
@@ -143,15 +190,12 @@ static inline int32_t System_Numerics_BitOperations_TrailingZeroCountusizei32(ui
 static inline int32_t System_Numerics_BitOperations_TrailingZeroCountu32i32(uint32_t val) {if (val == 0) return sizeof(uint32_t) * 8; return (int32_t) __builtin_ctzl((uint32_t)val);}
 static inline int32_t System_Numerics_BitOperations_TrailingZeroCounti32i32(int32_t val) {if (val == 0) return sizeof(int32_t) * 8; return (int32_t) __builtin_ctzl((uint32_t)val);}
 static inline int32_t System_Numerics_BitOperations_TrailingZeroCountu64i32(uint64_t val) {if (val == 0) return sizeof(uint64_t) * 8; return (int32_t) __builtin_ctzl((uint64_t)val);}
-static inline int32_t System_UInt128_TrailingZeroCountu128u128(__uint128_t val) {if (val == 0) return sizeof(__uint128_t) * 8; return (int32_t) __builtin_ctzl((__uint128_t)val);}
 static inline int32_t System_Numerics_BitOperations_LeadingZeroCountu64i32(uint64_t val) { if (val == 0) return 64; return __builtin_clzl(val); }
 static inline int32_t System_Numerics_BitOperations_LeadingZeroCountusizei32(uintptr_t val) { if (val == 0) return sizeof(uintptr_t) * 8; return __builtin_clzl((uint64_t)val); }
-__uint128_t __builtin_bswap128(__uint128_t val);
 
 #define System_Numerics_BitOperations_PopCountusizei32(val) __builtin_popcountl((uint64_t)val)
 #define System_Numerics_BitOperations_PopCountu32i32(val) __builtin_popcountl((uint32_t)val)
 #define System_Numerics_BitOperations_PopCountu64i32(val) __builtin_popcountl((uint64_t)val)
-#define System_UInt128_PopCountu128u128(val) __builtin_popcountl((uint64_t)val)
 
 #define System_Console_WriteLinestv(msg) printf("%s\n", msg)
 #define System_Console_WriteLinef64v(val) printf("%f\n", val)
@@ -204,7 +248,6 @@ static inline double System_Double_FusedMultiplyAddf64f64f64f64(double left, dou
 #define System_Object_GetHashCode14System_Runtime11System_Typei32
 #define System_Object_GetHashCodep14System_Runtime11System_Typei32
 
-#define System_Int128_get_MinValuei128() ((__uint128_t)((__int128_t)(-1)) >> 1)
 static inline float System_Single_MaxNumberf32f32f32(float a, float b)
 {
     if (a != a) return b;
@@ -294,7 +337,6 @@ double fabsf64(double val);
 #define System_Single_Powf32f32f32(a, b) (float)pow(a, b)
 #define System_Single_Powf64f64f64 pow
 #define System_Double_Powf64f64f64 pow
-#define System_Int128_get_Zeroi128(v) ((__int128_t)0)
 #define System_Math_Minisizeisizeisize(x, y) (((x) < (y)) ? (x) : (y))
 #define System_Math_Maxisizeisizeisize(x, y) (((x) > (y)) ? (x) : (y))
 #define System_Math_Minusizeusizeusize(x, y) (((x) < (y)) ? (x) : (y))
@@ -478,31 +520,18 @@ static inline uint64_t System_UInt64_RotateRightu64i32u64(uint64_t val, int32_t
      if(amount == 0) return val;
     return (val >> amount) | (val << (64 - amount));
 }
-static inline unsigned __int128 System_UInt128_RotateLeftu128i32u128(unsigned __int128 val, int32_t amount)
-{
-    amount = amount % (sizeof(unsigned __int128)*8);
-     if(amount == 0) return val;
-    return ((val << amount) | (val >> ( (sizeof(unsigned __int128)*8) - amount)));
-}
 static inline uint64_t System_UInt64_RotateLeftu64i32u64(uint64_t val, int32_t amount)
 {
     amount = amount % 64;
      if(amount == 0) return val;
     return (val << amount) | (val >> (64 - amount));
 }
-static inline unsigned __int128 System_UInt128_RotateRightu128i32u128(unsigned __int128 val, int32_t amount)
-{
-    amount = amount % (sizeof(unsigned __int128)*8);
-     if(amount == 0) return val;
-    return ((val >> amount) | (val << ( (sizeof(unsigned __int128)*8) - amount)));
-}
 static inline uint8_t System_Byte_RotateLeftu8i32u8(uint8_t val, int32_t amount)
 {
      amount = amount % (sizeof(uint8_t)*8);
       if(amount == 0) return val;
     return ((val << amount) | (val >> ( (sizeof(uint8_t)*8) - amount)));
 }
-static inline unsigned __int128 System_UInt128_LeadingZeroCountu128u128(unsigned __int128 val){ if (val == 0) return 128; return __builtin_clzl(val); }
 static inline uint32_t System_Math_Minu32u32u32(uint32_t lhs, uint32_t rhs)
 {
     if (lhs > rhs)
@@ -544,26 +573,10 @@ static inline int64_t System_Math_Clampi64i64i64i64(int64_t val, int64_t min, in
         return val;
     }
 }
-
-static inline __int128 System_Int128_Clampi128i128i128i128(__int128 val, __int128 min, __int128 max)
-{
-    if (val > max)
-    {
-        return max;
-    }
-    else if (val < min)
-    {
-        return min;
-    }
-    else
-    {
-        return val;
-    }
-}
-#define System_Int128_get_MaxValuei128() (-(((__uint128_t)((__int128_t)(-1L))) >> 1) - 1)
 static inline void System_Threading_Thread_MemoryBarrierv() {}
-static int argc;
-static char **argv;
+/* Defined once, next to the static fields of the assembly. */
+extern int argc;
+extern char **argv;
 static inline char **System_Environment_GetCommandLineArgsa1st() { return argv; }
 static inline uintptr_t ld_len(void *arr)
 {
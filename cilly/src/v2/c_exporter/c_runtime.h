@@ -0,0 +1,63 @@
+/* Minimal managed object runtime, giving C semantics to nodes which only make sense in .NET.
+Every managed object starts with a `cilly_object` header, holding the id of its type. Type ids are the same as the ones used by type tokens.
+This file is shared between all C profiles, so it only uses the hooks(`cilly_alloc`, `cilly_panic`, `CILLY_THREAD_LOCAL`) defined by the prelude. */
+typedef struct cilly_object
+{
+    uint32_t type_id;
+} cilly_object;
+/* The fields of an object start this many bytes after its header, which keeps them aligned like the fields of a value type. */
+#define CILLY_OBJECT_DATA_OFFSET 16
+/* Returns the id of the type `type_id` extends, or 0 if it has no known base type. Generated by the exporter. */
+static inline uint32_t cilly_parent_type(uint32_t type_id);
+/* The exception currently being thrown. C has no unwinding, and handlers are not exported, so this is only read when reporting an uncaught exception. */
+extern CILLY_THREAD_LOCAL cilly_object *cilly_current_exception;
+/* Allocates a zeroed object of `size` bytes, including its header. Objects are never freed, since there is no GC. */
+static inline cilly_object *cilly_alloc_object(uintptr_t size, uint32_t type_id)
+{
+    cilly_object *obj;
+    uint8_t *bytes;
+    uintptr_t byte;
+    if (size < CILLY_OBJECT_DATA_OFFSET) size = CILLY_OBJECT_DATA_OFFSET;
+    obj = (cilly_object *)cilly_alloc(size, CILLY_OBJECT_DATA_OFFSET);
+    if (obj == 0) cilly_panic("Could not allocate a managed object.");
+    bytes = (uint8_t *)obj;
+    for (byte = 0; byte < size; byte++) bytes[byte] = 0;
+    obj->type_id = type_id;
+    return obj;
+}
+/* Allocates a zeroed array of `len` elements of `elem_size` bytes. Arrays have no header, and end with an extra zeroed element, which `ld_len` uses to find their end. */
+static inline void *cilly_new_arr(uintptr_t len, uintptr_t elem_size)
+{
+    uint8_t *bytes;
+    uintptr_t size, byte;
+    size = (len + 1) * elem_size;
+    bytes = (uint8_t *)cilly_alloc(size, CILLY_OBJECT_DATA_OFFSET);
+    if (bytes == 0) cilly_panic("Could not allocate a managed array.");
+    for (byte = 0; byte < size; byte++) bytes[byte] = 0;
+    return bytes;
+}
+static inline bool cilly_is_inst(cilly_object *obj, uint32_t type_id)
+{
+    uint32_t current;
+    if (obj == 0) return false;
+    for (current = obj->type_id; current != 0; current = cilly_parent_type(current))
+    {
+        if (current == type_id) return true;
+    }
+    return false;
+}
+static inline cilly_object *cilly_checked_cast(cilly_object *obj, uint32_t type_id)
+{
+    if (obj == 0 || cilly_is_inst(obj, type_id)) return obj;
+    cilly_panic("Invalid cast: an object can't be cast to an unrelated type.");
+    return 0;
+}
+static inline void cilly_throw(cilly_object *exception)
+{
+    cilly_current_exception = exception;
+    cilly_panic("An exception was thrown, but there is no handler to catch it.");
+}
+static inline void cilly_rethrow(void)
+{
+    cilly_throw(cilly_current_exception);
+}
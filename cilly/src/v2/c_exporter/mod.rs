@@ -7,7 +7,14 @@ use fxhash::{hash64, FxHashSet, FxHasher};
 use crate::{
     config, typecheck,
     utilis::{assert_unique, encode},
-    v2::{asm::LINKER_RECOVER, BiMap, MethodImpl, StringIdx},
+    v2::{
+        asm::LINKER_RECOVER,
+        builtins::int128::c_soft::{
+            soft_binop, soft_int128_c_source, soft_int_cast, soft_type_name, SOFT_INT128_PROVIDED,
+        },
+        cilnode::MethodKind,
+        BiMap, FieldDesc, MethodImpl, StringIdx,
+    },
 };
 config!(NO_SFI, bool, false);
 config!(ANSI_C, bool, false);
@@ -16,11 +23,96 @@ config!(NO_OPT, bool, false);
 config!(UB_CHECKS, bool, true);
 config!(SHORT_TYPENAMES, bool, false);
 config!(PARTS, u32, 1);
+config!(
+    C_BUILD_FILES,
+    bool,
+    false,
+    "Writes a Makefile and a `compile_commands.json` next to C code split into multiple parts."
+);
+config!(
+    FREESTANDING,
+    bool,
+    false,
+    "Exports freestanding C, which does not depend on libc. Allocation and panics go through user-provided hooks, declared in `c_freestanding.h`."
+);
+// The pointer width of the freestanding C target. It must match the target the Rust code was compiled for, which the generated header checks.
+config!(C_POINTER_WIDTH, u32, 64);
+config!(
+    SOFT_INT128,
+    bool,
+    *FREESTANDING || !c_compiler_has_int128(),
+    "Emulates 128 bit integers in software. Enabled by default if the C compiler does not support `__int128`, or the output is freestanding. The C compiler is only probed when this is first read by the C exporter, and only if the variable is not set."
+);
+thread_local! {
+    /// Declarations of the stack buffers used by the method being exported. Only used by the freestanding profile, which can't use `alloca`.
+    static LOCAL_BUFFERS: std::cell::RefCell<Vec<String>> = const { std::cell::RefCell::new(Vec::new()) };
+    /// Set if the method being exported makes dynamically sized stack allocations, which are placed on the heap by the freestanding profile, and must be freed before it returns.
+    static LOCAL_FRAME: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+/// Returns the size of a stack allocation, if it is known at compile time.
+fn const_loc_alloc_size(size: &CILNode) -> Option<u64> {
+    match size {
+        CILNode::Const(cst) => match cst.as_ref() {
+            Const::U32(size) => Some(u64::from(*size)),
+            Const::I32(size) => u64::try_from(*size).ok(),
+            Const::U64(size) | Const::USize(size) => Some(*size),
+            Const::I64(size) | Const::ISize(size) => u64::try_from(*size).ok(),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+/// Declares a new stack buffer of type `tpe` in the method being exported, and returns its name.
+fn alloc_local_buffer(tpe: &str) -> String {
+    LOCAL_BUFFERS.with_borrow_mut(|buffers| {
+        let name = format!("cilly_buf{}", buffers.len());
+        buffers.push(format!("{tpe} {name};"));
+        name
+    })
+}
+/// Returns a C statement, which reports `msg`(a C string expression) and aborts.
+fn c_panic(msg: &str) -> String {
+    if *FREESTANDING {
+        format!("cilly_panic({msg});")
+    } else {
+        format!("eprintf(\"%s\\n\",{msg}); abort();")
+    }
+}
+/// Returns the name of a memory operation(`memcpy` or `memset`).
+fn mem_fn(name: &'static str) -> &'static str {
+    match (*FREESTANDING, name) {
+        (true, "memcpy") => "cilly_memcpy",
+        (true, "memset") => "cilly_memset",
+        _ => name,
+    }
+}
+/// Checks if the C compiler(`$CC`, or `cc` by default) supports the `__int128` extension.
+fn c_compiler_has_int128() -> bool {
+    let dir = std::env::temp_dir().join(format!("cilly_int128_probe_{}", std::process::id()));
+    if std::fs::create_dir_all(&dir).is_err() {
+        return false;
+    }
+    let c_path = dir.join("probe.c");
+    let supported = std::fs::write(
+        &c_path,
+        "__int128 probe(__int128 a, unsigned __int128 b){return a * (__int128)b;}",
+    )
+    .is_ok()
+        && std::process::Command::new(c_compiler())
+            .arg("-c")
+            .arg(&c_path)
+            .arg("-o")
+            .arg(dir.join("probe.o"))
+            .output()
+            .is_ok_and(|out| out.status.success());
+    let _ = std::fs::remove_dir_all(&dir);
+    supported
+}
 use super::{
     asm::MAIN_MODULE,
     bimap::IntoBiMapIndex,
     cilnode::{ExtendKind, PtrCastRes},
-    cilroot::BranchCond,
+    cilroot::{BranchCond, CmpKind},
     method::LocalDef,
     tpe::simd::SIMDVector,
     typecheck::TypeCheckError,
@@ -90,22 +182,30 @@ fn c_tpe(field_tpe: Type, asm: &Assembly) -> String {
             Int::U16 => "uint16_t".into(),
             Int::U32 => "uint32_t".into(),
             Int::U64 => "uint64_t".into(),
+            Int::U128 if *SOFT_INT128 => soft_type_name(int).into(),
             Int::U128 => "__uint128_t".into(),
             Int::USize => "uintptr_t".into(),
             Int::I8 => "int8_t".into(),
             Int::I16 => "int16_t".into(),
             Int::I32 => "int32_t".into(),
             Int::I64 => "int64_t".into(),
+            Int::I128 if *SOFT_INT128 => soft_type_name(int).into(),
             Int::I128 => "__int128".into(),
             Int::ISize => "intptr_t".into(),
         },
+        // Managed reference types are represented as pointers to an object header.
+        Type::ClassRef(class_ref_idx) if !asm[class_ref_idx].is_valuetype() => {
+            "cilly_object*".into()
+        }
         Type::ClassRef(class_ref_idx) => {
             format!("union {}", escape_ident(&asm[asm[class_ref_idx].name()]))
         }
         Type::Float(float) => match float {
+            super::Float::F16 if *FREESTANDING => "cilly_f16".into(),
             super::Float::F16 => "_Float16".into(),
             super::Float::F32 => "float".into(),
             super::Float::F64 => "double".into(),
+            super::Float::F128 if *FREESTANDING => "cilly_f128".into(),
             super::Float::F128 => "_Float128".into(),
         },
         Type::PlatformString => "char*".into(),
@@ -120,6 +220,8 @@ fn c_tpe(field_tpe: Type, asm: &Assembly) -> String {
             dims = "*".repeat(dims.get() as usize)
         ),
         Type::FnPtr(_) => "void*".into(),
+        // Nothing is ever moved in C, so there is no need to pin anything.
+        Type::Pinned(inner) => c_tpe(asm[inner], asm),
         Type::SIMDVector(vec) => {
             format!(
                 "__simdvec{elem}_{count}",
@@ -156,6 +258,20 @@ fn mref_to_name(mref: &MethodRef, asm: &Assembly) -> String {
         class_member_name(&class_name, &mname)
     }
 }
+/// Returns a pointer to the fields of `addr`. Objects are passed around as `cilly_object*`, so they get cast to their class first.
+fn field_owner(addr: &str, addr_tpe: Type, field: FieldDesc, asm: &Assembly) -> String {
+    match addr_tpe {
+        Type::ClassRef(class_ref_idx) if !asm[class_ref_idx].is_valuetype() => format!(
+            "((union {owner}*)({addr}))",
+            owner = escape_ident(&asm[asm[field.owner()].name()])
+        ),
+        _ => format!("({addr})"),
+    }
+}
+/// The name of the function allocating an object and calling the constructor `ctor` on it.
+fn ctor_alloc_name(ctor: &str) -> String {
+    format!("{ctor}_new")
+}
 fn class_member_name(class_name: &str, method_name: &str) -> String {
     if class_name == MAIN_MODULE {
         method_name.into()
@@ -180,9 +296,13 @@ impl CExporter {
         if method_name == "malloc" || method_name == "realloc" || method_name == "free" {
             return Ok(());
         }
-        let output = c_tpe(mref.output(asm), asm);
-        let inputs = mref
-            .stack_inputs(asm)
+        if *SOFT_INT128 && SOFT_INT128_PROVIDED.contains(&method_name.as_str()) {
+            return Ok(());
+        }
+        // In C, constructors are plain functions, which take `this` and return nothing.
+        let output = c_tpe(*asm[mref.sig()].output(), asm);
+        let inputs = asm[mref.sig()]
+            .inputs()
             .iter()
             .map(|i| nonvoid_c_type(*i, asm))
             .intersperse(",".into())
@@ -201,15 +321,28 @@ impl CExporter {
         inputs: &[(Type, Option<StringIdx>)],
         sig: SigIdx,
     ) -> Result<String, TypeCheckError> {
+        if *SOFT_INT128 {
+            if let Type::Int(int @ (Int::I128 | Int::U128)) =
+                lhs.clone().typecheck(sig, locals, asm)?
+            {
+                let rhs_is_128 = matches!(
+                    rhs.clone().typecheck(sig, locals, asm)?,
+                    Type::Int(Int::I128 | Int::U128)
+                );
+                let lhs = Self::node_to_string(lhs, asm, locals, inputs, sig)?;
+                let rhs = Self::node_to_string(rhs, asm, locals, inputs, sig)?;
+                return Ok(soft_binop(&lhs, &rhs, rhs_is_128, op, int));
+            }
+        }
         let lhs = Self::node_to_string(lhs, asm, locals, inputs, sig)?;
         let rhs = Self::node_to_string(rhs, asm, locals, inputs, sig)?;
         Ok(match op {
             BinOp::Add => match tpe {
                 Type::Ptr(type_idx) | Type::Ref(type_idx) => format!(
-                    "({tpe}*)((void*)({lhs}) + (uintptr_t)({rhs}))",
+                    "({tpe}*)((uint8_t*)({lhs}) + (uintptr_t)({rhs}))",
                     tpe = c_tpe(asm[type_idx], asm)
                 ),
-                Type::FnPtr(_) => format!("({lhs}) + ({rhs})"),
+                Type::FnPtr(_) => format!("(void*)((uintptr_t)({lhs}) + (uintptr_t)({rhs}))"),
                 Type::Float(_) => format!("({lhs}) + ({rhs})"),
                 Type::Int(Int::ISize) => {
                     format!("(intptr_t)((uintptr_t)({lhs}) + (uintptr_t)({rhs}))")
@@ -235,10 +368,10 @@ impl CExporter {
             },
             BinOp::Sub => match tpe {
                 Type::Ptr(type_idx) | Type::Ref(type_idx) => format!(
-                    "({tpe}*)((void*)({lhs}) - (uintptr_t)({rhs}))",
+                    "({tpe}*)((uint8_t*)({lhs}) - (uintptr_t)({rhs}))",
                     tpe = c_tpe(asm[type_idx], asm)
                 ),
-                Type::FnPtr(_) => format!("({lhs}) - ({rhs})"),
+                Type::FnPtr(_) => format!("(void*)((uintptr_t)({lhs}) - (uintptr_t)({rhs}))"),
                 Type::Int(Int::I128) => {
                     format!("(__int128)((__uint128_t)({lhs}) - (__uint128_t)({rhs}))")
                 }
@@ -262,10 +395,10 @@ impl CExporter {
             },
             BinOp::Mul => match tpe {
                 Type::Ptr(type_idx) | Type::Ref(type_idx) => format!(
-                    "({tpe}*)((void*)({lhs}) * (uintptr_t)({rhs}))",
+                    "({tpe}*)((uintptr_t)({lhs}) * (uintptr_t)({rhs}))",
                     tpe = c_tpe(asm[type_idx], asm)
                 ),
-                Type::FnPtr(_) => format!("({lhs}) * ({rhs})"),
+                Type::FnPtr(_) => format!("(void*)((uintptr_t)({lhs}) * (uintptr_t)({rhs}))"),
                 Type::Float(_) => format!("({lhs}) * ({rhs})"),
                 Type::Int(int) => match int {
                     // Signed multiply is seemingly equivalent to unsigned multiply, looking at the assembly: TODO: check this.
@@ -317,38 +450,41 @@ impl CExporter {
                 _ => todo!(),
             },
             BinOp::Or => match tpe {
-                Type::Ptr(type_idx) | Type::Ref(type_idx) => {
-                    format!("(void*)({lhs}) | (void*)({rhs})",)
-                }
-                Type::FnPtr(_) => format!("({lhs}) | ({rhs})"),
+                Type::Ptr(type_idx) | Type::Ref(type_idx) => format!(
+                    "({tpe}*)((uintptr_t)({lhs}) | (uintptr_t)({rhs}))",
+                    tpe = c_tpe(asm[type_idx], asm)
+                ),
+                Type::FnPtr(_) => format!("(void*)((uintptr_t)({lhs}) | (uintptr_t)({rhs}))"),
                 Type::Int(_) => format!("({lhs}) | ({rhs})"),
                 Type::Bool => format!("({lhs}) || ({rhs})"),
                 _ => todo!(),
             },
             BinOp::XOr => match tpe {
-                Type::Ptr(type_idx) | Type::Ref(type_idx) => {
-                    format!("(void*)({lhs}) ^ (void*)({rhs})",)
-                }
-                Type::FnPtr(_) => format!("({lhs}) ^ ({rhs})"),
+                Type::Ptr(type_idx) | Type::Ref(type_idx) => format!(
+                    "({tpe}*)((uintptr_t)({lhs}) ^ (uintptr_t)({rhs}))",
+                    tpe = c_tpe(asm[type_idx], asm)
+                ),
+                Type::FnPtr(_) => format!("(void*)((uintptr_t)({lhs}) ^ (uintptr_t)({rhs}))"),
                 Type::Int(_) => format!("({lhs}) ^ ({rhs})"),
                 Type::Bool => format!("({lhs}) != ({rhs})"),
                 _ => todo!(),
             },
             BinOp::And => match tpe {
-                Type::Ptr(type_idx) | Type::Ref(type_idx) => {
-                    format!("(void*)({lhs}) & (void*)({rhs})",)
-                }
-                Type::FnPtr(_) => format!("({lhs}) & ({rhs})"),
+                Type::Ptr(type_idx) | Type::Ref(type_idx) => format!(
+                    "({tpe}*)((uintptr_t)({lhs}) & (uintptr_t)({rhs}))",
+                    tpe = c_tpe(asm[type_idx], asm)
+                ),
+                Type::FnPtr(_) => format!("(void*)((uintptr_t)({lhs}) & (uintptr_t)({rhs}))"),
                 Type::Int(_) => format!("({lhs}) & ({rhs})"),
                 Type::Bool => format!("({lhs}) && ({rhs})"),
                 _ => todo!(),
             },
             BinOp::Rem | BinOp::RemUn => match tpe {
                 Type::Ptr(type_idx) | Type::Ref(type_idx) => format!(
-                    "({tpe}*)((void*)({lhs}) % (uintptr_t)({rhs}))",
+                    "({tpe}*)((uintptr_t)({lhs}) % (uintptr_t)({rhs}))",
                     tpe = c_tpe(asm[type_idx], asm)
                 ),
-                Type::FnPtr(_) => format!("({lhs}) % ({rhs})"),
+                Type::FnPtr(_) => format!("(void*)((uintptr_t)({lhs}) % (uintptr_t)({rhs}))"),
                 Type::Int(_) => format!("({lhs}) % ({rhs})"),
                 Type::Float(flt) => match flt {
                     super::Float::F16 => todo!(),
@@ -378,10 +514,10 @@ impl CExporter {
             },
             BinOp::DivUn | BinOp::Div => match tpe {
                 Type::Ptr(type_idx) | Type::Ref(type_idx) => format!(
-                    "({tpe}*)((void*)({lhs}) / (uintptr_t)({rhs}))",
+                    "({tpe}*)((uintptr_t)({lhs}) / (uintptr_t)({rhs}))",
                     tpe = c_tpe(asm[type_idx], asm)
                 ),
-                Type::FnPtr(_) => format!("({lhs}) / ({rhs})"),
+                Type::FnPtr(_) => format!("(void*)((uintptr_t)({lhs}) / (uintptr_t)({rhs}))"),
                 Type::Float(_) | Type::Int(_) => format!("({lhs}) / ({rhs})"),
                 _ => todo!(),
             },
@@ -400,6 +536,11 @@ impl CExporter {
                 Const::I16(v) => format!("(int16_t)0x{v:x}"),
                 Const::I32(v) => format!("((int32_t)0x{v:x})"),
                 Const::I64(v) => format!("((int64_t)0x{v:x}L)"),
+                Const::I128(v) if *SOFT_INT128 => {
+                    let low = *v as u128 as u64;
+                    let high = ((*v as u128) >> 64) as u64;
+                    format!("cilly_i128_new(0x{high:x}uL, 0x{low:x}uL)")
+                }
                 Const::I128(v) => {
                     let low = *v as u128 as u64;
                     let high = ((*v as u128) >> 64) as u64;
@@ -410,6 +551,11 @@ impl CExporter {
                 Const::U16(v) => format!("(uint16_t)0x{v:x}"),
                 Const::U32(v) => format!("0x{v:x}u"),
                 Const::U64(v) => format!("0x{v:x}uL"),
+                Const::U128(v) if *SOFT_INT128 => {
+                    let low = *v as u64;
+                    let high = ({ *v } >> 64) as u64;
+                    format!("cilly_u128_new(0x{high:x}uL, 0x{low:x}uL)")
+                }
                 Const::U128(v) => {
                     let low = *v as u64;
                     let high = ({ *v } >> 64) as u64;
@@ -438,7 +584,7 @@ impl CExporter {
                         "NAN".into()
                     }
                 }
-                Const::Null(class_ref_idx) => todo!(),
+                Const::Null(class_ref_idx) => "((cilly_object*)0)".into(),
             },
             CILNode::BinOp(lhs, rhs, bin_op) => {
                 let tpe = node.typecheck(sig, locals, asm)?;
@@ -454,6 +600,16 @@ impl CExporter {
                 )?
             }
             CILNode::UnOp(node_idx, ref un_op) => match un_op {
+                super::cilnode::UnOp::Not if *SOFT_INT128 => {
+                    let val =
+                        Self::node_to_string(asm[node_idx].clone(), asm, locals, inputs, sig)?;
+                    match node.typecheck(sig, locals, asm)? {
+                        Type::Int(int @ (Int::I128 | Int::U128)) => {
+                            format!("cilly_{name}_not({val})", name = int.name())
+                        }
+                        _ => format!("~({val})"),
+                    }
+                }
                 super::cilnode::UnOp::Not => format!(
                     "~({})",
                     Self::node_to_string(asm[node_idx].clone(), asm, locals, inputs, sig)?
@@ -489,6 +645,11 @@ impl CExporter {
                             "(int64_t)(0 - ((uint64_t)({})))",
                             Self::node_to_string(asm[node_idx].clone(), asm, locals, inputs, sig)?
                         ),
+                        Type::Int(int @ (Int::I128 | Int::U128)) if *SOFT_INT128 => format!(
+                            "cilly_{name}_neg({})",
+                            Self::node_to_string(asm[node_idx].clone(), asm, locals, inputs, sig)?,
+                            name = int.name()
+                        ),
                         Type::Int(Int::I128) => format!(
                             "(__int128_t)(0 - ((__uint128_t)({})))",
                             Self::node_to_string(asm[node_idx].clone(), asm, locals, inputs, sig)?
@@ -530,9 +691,14 @@ impl CExporter {
                     .intersperse(",".into())
                     .collect::<String>();
                 let class = &asm[method.class()];
-                let class_name = escape_ident(&asm[class.name()]);
-                let mname = escape_ident(&asm[method.name()]);
                 let method_name = mref_to_name(&method, asm);
+                // Objects of classes defined in this assembly are allocated, and then initialized by their constructor.
+                if method.kind() == MethodKind::Constructor
+                    && !class.is_valuetype()
+                    && asm.class_ref_to_def(method.class()).is_some()
+                {
+                    return Ok(format!("{}({call_args})", ctor_alloc_name(&method_name)));
+                }
                 format!("{method_name}({call_args})")
             }
             CILNode::IntCast {
@@ -540,6 +706,13 @@ impl CExporter {
                 target,
                 extend,
             } => {
+                if *SOFT_INT128 {
+                    let input_tpe = asm[input].clone().typecheck(sig, locals, asm)?;
+                    let input = Self::node_to_string(asm[input].clone(), asm, locals, inputs, sig)?;
+                    if let Some(cast) = soft_int_cast(&input, input_tpe, target, extend) {
+                        return Ok(cast);
+                    }
+                }
                 let input = Self::node_to_string(asm[input].clone(), asm, locals, inputs, sig)?;
                 match (target, extend) {
                     (Int::U8, ExtendKind::ZeroExtend) => format!("(uint8_t)({input})"),
@@ -577,7 +750,18 @@ impl CExporter {
                 target,
                 is_signed,
             } => {
-                let input = Self::node_to_string(asm[input].clone(), asm, locals, inputs, sig)?;
+                let soft_int = if *SOFT_INT128 {
+                    match asm[input].clone().typecheck(sig, locals, asm)? {
+                        Type::Int(int @ (Int::I128 | Int::U128)) => Some(int),
+                        _ => None,
+                    }
+                } else {
+                    None
+                };
+                let mut input = Self::node_to_string(asm[input].clone(), asm, locals, inputs, sig)?;
+                if let Some(int) = soft_int {
+                    input = format!("cilly_{name}_to_f64({input})", name = int.name());
+                }
                 match target {
                     super::Float::F16 => todo!(),
                     super::Float::F32 => format!("(float)({input})"),
@@ -601,10 +785,14 @@ impl CExporter {
             }
             CILNode::LdFieldAdress { addr, field } => {
                 let addr = asm[addr].clone();
+                let addr_tpe = addr.typecheck(sig, locals, asm)?;
                 let addr = Self::node_to_string(addr, asm, locals, inputs, sig)?;
                 let field = asm[field];
                 let name = escape_ident(&asm[field.name()]);
-                format!("&({addr})->{name}.f")
+                format!(
+                    "&{owner}->{name}.f",
+                    owner = field_owner(&addr, addr_tpe, field, asm)
+                )
             }
             CILNode::LdField { addr, field } => {
                 let addr = asm[addr].clone();
@@ -614,6 +802,12 @@ impl CExporter {
                 let name = escape_ident(&asm[field.name()]);
                 match addr_tpe {
                     Type::Ref(_) | Type::Ptr(_) => format!("({addr})->{name}.f"),
+                    Type::ClassRef(class_ref_idx) if !asm[class_ref_idx].is_valuetype() => {
+                        format!(
+                            "{owner}->{name}.f",
+                            owner = field_owner(&addr, addr_tpe, field, asm)
+                        )
+                    }
                     Type::ClassRef(_) => format!("({addr}).{name}.f"),
                     _ => panic!(),
                 }
@@ -637,9 +831,29 @@ impl CExporter {
                 }
             }
             CILNode::SizeOf(type_idx) => format!("sizeof({tpe})", tpe = c_tpe(asm[type_idx], asm)),
-            CILNode::GetException => todo!(),
-            CILNode::IsInst(node_idx, type_idx) => todo!(),
-            CILNode::CheckedCast(node_idx, type_idx) => todo!(),
+            CILNode::GetException => "cilly_current_exception".into(),
+            CILNode::IsInst(node_idx, type_idx) => {
+                let obj = Self::node_to_string(asm[node_idx].clone(), asm, locals, inputs, sig)?;
+                match asm[type_idx] {
+                    // Every non-null object is an instance of `System.Object`
+                    Type::PlatformObject => format!("(({obj}) != 0)"),
+                    _ => format!(
+                        "cilly_is_inst((cilly_object*)({obj}), {type_id})",
+                        type_id = type_idx.as_bimap_index()
+                    ),
+                }
+            }
+            CILNode::CheckedCast(node_idx, type_idx) => {
+                let obj = Self::node_to_string(asm[node_idx].clone(), asm, locals, inputs, sig)?;
+                match asm[type_idx] {
+                    Type::PlatformObject => format!("((void*)({obj}))"),
+                    tpe => format!(
+                        "(({tpe})cilly_checked_cast((cilly_object*)({obj}), {type_id}))",
+                        tpe = c_tpe(tpe, asm),
+                        type_id = type_idx.as_bimap_index()
+                    ),
+                }
+            }
             CILNode::CallI(info) => {
                 let (fn_ptr, fn_ptr_sig, args) = info.as_ref();
                 let fn_ptr_sig = asm[*fn_ptr_sig].clone();
@@ -664,6 +878,23 @@ impl CExporter {
                 let fn_ptr = Self::node_to_string(asm[*fn_ptr].clone(), asm, locals, inputs, sig)?;
                 format!("((*({ret}(*)({args}))({fn_ptr})))({call_args})")
             }
+            CILNode::LocAlloc { size } if *FREESTANDING => {
+                match const_loc_alloc_size(&asm[size]) {
+                    // The union forces the buffer to be aligned, like memory returned by `alloca`.
+                    Some(size) => format!(
+                        "({name}.bytes)",
+                        name = alloc_local_buffer(&format!(
+                            "union {{uint8_t bytes[{size}]; uint64_t align_u64; void *align_ptr; double align_f64;}}",
+                            size = size.max(1)
+                        ))
+                    ),
+                    // Dynamically sized stack allocations are rare in Rust, so they are placed on the heap, and freed when the method returns.
+                    None => format!(
+                        "((uint8_t*)cilly_local_alloc(&cilly_local_frame, {}))",
+                        Self::node_to_string(asm[size].clone(), asm, locals, inputs, sig)?
+                    ),
+                }
+            }
             CILNode::LocAlloc { size } => format!(
                 "((uint8_t*)alloca({}))",
                 Self::node_to_string(asm[size].clone(), asm, locals, inputs, sig)?
@@ -688,6 +919,9 @@ impl CExporter {
                 arr = Self::node_to_string(asm[node_idx].clone(), asm, locals, inputs, sig)?
             ),
             // TODO: loc alloc aligned does not respect the aligement ATM.
+            CILNode::LocAllocAlgined { tpe, align } if *FREESTANDING => {
+                format!("(&{})", alloc_local_buffer(&c_tpe(asm[tpe], asm)))
+            }
             CILNode::LocAllocAlgined { tpe, align } => {
                 format!(
                     "({tpe}*)(alloca(sizeof({tpe})))",
@@ -705,8 +939,46 @@ impl CExporter {
                 "({object})",
                 object = Self::node_to_string(asm[object].clone(), asm, locals, inputs, sig)?
             ),
+            CILNode::NewArr { elem, len } => format!(
+                "(({elem}*)cilly_new_arr((uintptr_t)({len}), sizeof({elem})))",
+                elem = c_tpe(asm[elem], asm),
+                len = Self::node_to_string(asm[len].clone(), asm, locals, inputs, sig)?
+            ),
         })
     }
+    /// Converts a comparison of software emulated 128 bit integers into a C condition.
+    /// Returns `None` if `cond` does not compare 128 bit integers.
+    fn soft_int128_cond(
+        cond: &BranchCond,
+        asm: &mut Assembly,
+        locals: &[LocalDef],
+        inputs: &[(Type, Option<StringIdx>)],
+        sig: SigIdx,
+    ) -> Result<Option<String>, TypeCheckError> {
+        let (lhs, rhs, op, negate) = match cond {
+            BranchCond::True(_) | BranchCond::False(_) => return Ok(None),
+            BranchCond::Eq(lhs, rhs) => (lhs, rhs, BinOp::Eq, false),
+            BranchCond::Ne(lhs, rhs) => (lhs, rhs, BinOp::Eq, true),
+            BranchCond::Lt(lhs, rhs, CmpKind::Unsigned) => (lhs, rhs, BinOp::LtUn, false),
+            BranchCond::Lt(lhs, rhs, _) => (lhs, rhs, BinOp::Lt, false),
+            BranchCond::Gt(lhs, rhs, CmpKind::Unsigned) => (lhs, rhs, BinOp::GtUn, false),
+            BranchCond::Gt(lhs, rhs, _) => (lhs, rhs, BinOp::Gt, false),
+            // a <= b is equivalent to !(a > b), since integers are always ordered.
+            BranchCond::Le(lhs, rhs, CmpKind::Unsigned) => (lhs, rhs, BinOp::GtUn, true),
+            BranchCond::Le(lhs, rhs, _) => (lhs, rhs, BinOp::Gt, true),
+            BranchCond::Ge(lhs, rhs, CmpKind::Unsigned) => (lhs, rhs, BinOp::LtUn, true),
+            BranchCond::Ge(lhs, rhs, _) => (lhs, rhs, BinOp::Lt, true),
+        };
+        let Type::Int(int @ (Int::I128 | Int::U128)) =
+            asm[*lhs].clone().typecheck(sig, locals, asm)?
+        else {
+            return Ok(None);
+        };
+        let lhs = Self::node_to_string(asm[*lhs].clone(), asm, locals, inputs, sig)?;
+        let rhs = Self::node_to_string(asm[*rhs].clone(), asm, locals, inputs, sig)?;
+        let cmp = soft_binop(&lhs, &rhs, true, op, int);
+        Ok(Some(if negate { format!("!{cmp}") } else { cmp }))
+    }
     fn root_to_string(
         root: CILRoot,
         asm: &mut Assembly,
@@ -730,6 +1002,11 @@ impl CExporter {
                     node = Self::node_to_string(asm[node_idx].clone(), asm, locals, inputs, sig)?,
                 ),
             },
+            CILRoot::Ret(node_idx) if LOCAL_FRAME.get() => format!(
+                "{{{tpe} cilly_ret = {node}; cilly_local_free(cilly_local_frame); return cilly_ret;}}",
+                tpe = nonvoid_c_type(*asm[sig].output(), asm),
+                node = Self::node_to_string(asm[node_idx].clone(), asm, locals, inputs, sig)?
+            ),
             CILRoot::Ret(node_idx) => format!(
                 "return {node};",
                 node = Self::node_to_string(asm[node_idx].clone(), asm, locals, inputs, sig)?
@@ -738,10 +1015,18 @@ impl CExporter {
                 "{node};",
                 node = Self::node_to_string(asm[node_idx].clone(), asm, locals, inputs, sig)?
             ),
+            CILRoot::Throw(node_idx) if *FREESTANDING => c_panic(&Self::node_to_string(asm[node_idx].clone(), asm, locals, inputs, sig)?),
+            CILRoot::Throw(node_idx) if matches!(asm[node_idx].clone().typecheck(sig, locals, asm)?, Type::ClassRef(cref) if !asm[cref].is_valuetype() && asm.class_ref_to_def(cref).is_some()) => format!(
+                "cilly_throw({node});",
+                node = Self::node_to_string(asm[node_idx].clone(), asm, locals, inputs, sig)?
+            ),
             CILRoot::Throw(node_idx) =>  format!(
                 "eprintf(\"An error was encoutrered in %s, at %s:%d\\n\",__func__,__FILE__,__LINE__);eprintf(\"%s\\n\",{node}); abort();",
                 node = Self::node_to_string(asm[node_idx].clone(), asm, locals, inputs, sig)?
             ),
+            CILRoot::VoidRet if LOCAL_FRAME.get() => {
+                "cilly_local_free(cilly_local_frame); return;".into()
+            }
             CILRoot::VoidRet => "return;".into(),
             CILRoot::Break => "".into(),
             CILRoot::Nop => "".into(),
@@ -753,6 +1038,11 @@ impl CExporter {
                 let Some(cond) = cond else {
                     return Ok(format!("goto bb{target};"));
                 };
+                if *SOFT_INT128 {
+                    if let Some(cond) = Self::soft_int128_cond(cond, asm, locals, inputs, sig)? {
+                        return Ok(format!("if({cond}) goto bb{target};"));
+                    }
+                }
                 match cond {
                     BranchCond::True(node_idx) => format!(
                         "if({node}) goto bb{target};",
@@ -805,11 +1095,12 @@ impl CExporter {
             },
             CILRoot::SetField(info) =>{
                 let (field,addr,value) = info.as_ref();
+                let addr_tpe = asm[*addr].clone().typecheck(sig, locals, asm)?;
                 let addr = Self::node_to_string(asm[*addr].clone(), asm, locals, inputs, sig)?;
                 let value = Self::node_to_string(asm[*value].clone(), asm, locals, inputs, sig)?;
                 let field = asm[*field];
                 let name = escape_ident(&asm[field.name()]);
-                format!("({addr})->{name}.f = ({value});")
+                format!("{owner}->{name}.f = ({value});", owner = field_owner(&addr, addr_tpe, field, asm))
             }
             CILRoot::Call(info) => {
                 let (method, args) = info.as_ref();
@@ -830,8 +1121,9 @@ impl CExporter {
             CILRoot::InitObj(addr,tpe) => {
                 let addr = Self::node_to_string(asm[addr].clone(), asm, locals, inputs, sig)?;
                     format!(
-                        "memset({addr},0,sizeof({tpe}));",
-                        tpe = c_tpe(asm[tpe], asm)
+                        "{memset}({addr},0,sizeof({tpe}));",
+                        tpe = c_tpe(asm[tpe], asm),
+                        memset = mem_fn("memset")
                     )
             }
             CILRoot::StInd(info) => {
@@ -852,14 +1144,14 @@ impl CExporter {
                 let dst = Self::node_to_string(asm[*dst].clone(), asm, locals, inputs, sig)?;
                 let val = Self::node_to_string(asm[*val].clone(), asm, locals, inputs, sig)?;
                 let count = Self::node_to_string(asm[*count].clone(), asm, locals, inputs, sig)?;
-                format!("memset(({dst}),({val}),({count}));")
+                format!("{memset}(({dst}),({val}),({count}));", memset = mem_fn("memset"))
             }
             CILRoot::CpBlk(blk) => {
                 let (dst, src, len) = blk.as_ref();
                 let dst = Self::node_to_string(asm[*dst].clone(), asm, locals, inputs, sig)?;
                 let src = Self::node_to_string(asm[*src].clone(), asm, locals, inputs, sig)?;
                 let len = Self::node_to_string(asm[*len].clone(), asm, locals, inputs, sig)?;
-                format!("memcpy(({dst}),({src}),({len}));")
+                format!("{memcpy}(({dst}),({src}),({len}));", memcpy = mem_fn("memcpy"))
             }
             CILRoot::CallI(info) => {
                 let (fn_ptr, fn_ptr_sig, args) = info.as_ref();
@@ -885,7 +1177,7 @@ impl CExporter {
                 format!("((*({ret}(*)({args}))({fn_ptr})))({call_args});")
             }
             CILRoot::ExitSpecialRegion { target, source } => format!("goto bb{target};"),
-            CILRoot::ReThrow => todo!(),
+            CILRoot::ReThrow => "cilly_rethrow();".into(),
             CILRoot::SetStaticField { field, val } => {
                 let field = asm[field];
                 let class = asm[field.owner()].clone();
@@ -893,8 +1185,13 @@ impl CExporter {
                 let val = Self::node_to_string(asm[val].clone(), asm, locals, inputs, sig)?;
                 format!("{fname} = {val};")
             }
-            CILRoot::CpObj { src, dst, tpe } => todo!(),
-            CILRoot::Unreachable(string_idx) => format!("\neprintf({:?});\nabort();\n",&asm[string_idx]),
+            CILRoot::CpObj { src, dst, tpe } => format!(
+                "*({tpe}*)({dst}) = *({tpe}*)({src});",
+                tpe = c_tpe(asm[tpe], asm),
+                dst = Self::node_to_string(asm[dst].clone(), asm, locals, inputs, sig)?,
+                src = Self::node_to_string(asm[src].clone(), asm, locals, inputs, sig)?
+            ),
+            CILRoot::Unreachable(string_idx) => c_panic(&format!("{:?}",&asm[string_idx])),
         })
     }
     fn export_method_def(
@@ -911,7 +1208,11 @@ impl CExporter {
             return Ok(());
         }
         let method_name = mref_to_name(&def.ref_to(), asm);
-        let output = c_tpe(def.ref_to().output(asm), asm);
+        // Provided by the software int128 implementation.
+        if *SOFT_INT128 && SOFT_INT128_PROVIDED.contains(&method_name.as_str()) {
+            return Ok(());
+        }
+        let output = c_tpe(*asm[def.sig()].output(), asm);
         match def.resolved_implementation(asm) {
             MethodImpl::MethodBody { blocks, locals } => (),
             MethodImpl::Extern {
@@ -940,9 +1241,8 @@ impl CExporter {
                 | "sched_getaffinity"
                 | "poll" => return Ok(()),
                 _ => {
-                    let inputs = def
-                        .ref_to()
-                        .stack_inputs(asm)
+                    let inputs = asm[def.sig()]
+                        .inputs()
                         .iter()
                         .map(|i| nonvoid_c_type(*i, asm))
                         .intersperse(",".into())
@@ -952,16 +1252,16 @@ impl CExporter {
                 }
             },
             MethodImpl::Missing => {
-                let inputs = def
-                    .ref_to()
-                    .stack_inputs(asm)
+                let inputs = asm[def.sig()]
+                    .inputs()
                     .iter()
                     .map(|i| nonvoid_c_type(*i, asm))
                     .intersperse(",".into())
                     .collect::<String>();
                 writeln!(
                     method_defs,
-                    "{output} {method_name}({inputs}){{eprintf(\"Missing method {method_name}\\n\");abort();}}"
+                    "{output} {method_name}({inputs}){{{panic}}}",
+                    panic = c_panic(&format!("\"Missing method {method_name}\""))
                 )?;
                 return Ok(());
             }
@@ -994,13 +1294,33 @@ impl CExporter {
                 local_type = nonvoid_c_type(asm[*local_type], asm),
             )?;
         }
+        // The body is written separately, since stack buffers must be declared before any statements.
+        LOCAL_BUFFERS.with_borrow_mut(Vec::clear);
+        let has_local_frame = *FREESTANDING
+            && def.iter_cil(asm).is_some_and(|mut cil| {
+                cil.any(|elem| {
+                    matches!(elem, CILIterElem::Node(CILNode::LocAlloc { size }) if const_loc_alloc_size(&asm[size]).is_none())
+                })
+            });
+        LOCAL_FRAME.set(has_local_frame);
+        if has_local_frame {
+            writeln!(method_defs, "void *cilly_local_frame = 0;")?;
+        }
+        let mut body = Vec::new();
         let blocks = def.blocks(asm).unwrap().to_vec();
         for block in blocks {
-            writeln!(method_defs, "bb{}:", block.block_id())?;
+            writeln!(body, "bb{}:", block.block_id())?;
             for root_idx in block.roots() {
                 if let Err(err) = asm[*root_idx].clone().typecheck(sig, &locals, asm) {
                     eprintln!("Typecheck error:{err:?}");
-                    writeln!(method_defs, "fprintf(stderr,\"Attempted to execute a statement which failed to compile.\" {err:?}); abort();",err = format!("{err:?}"))?;
+                    writeln!(
+                        body,
+                        "{}",
+                        c_panic(&format!(
+                            "\"Attempted to execute a statement which failed to compile.\" {:?}",
+                            format!("{err:?}")
+                        ))
+                    )?;
                     continue;
                 }
 
@@ -1017,43 +1337,84 @@ impl CExporter {
                         if root.is_empty() {
                             continue;
                         }
-                        writeln!(method_defs, "{root}")?
+                        writeln!(body, "{root}")?
                     }
                     Err(err) => {
                         eprintln!("Typecheck error:{err:?}");
-                        writeln!(method_defs, "fprintf(stderr,\"Attempted to execute a statement which failed to compile.\" {err:?}); abort();",err = format!("{err:?}"))?
+                        writeln!(body, "{}", c_panic(&format!("\"Attempted to execute a statement which failed to compile.\" {:?}", format!("{err:?}"))))?
                     }
                 }
             }
         }
+        for buffer in LOCAL_BUFFERS.take() {
+            writeln!(method_defs, "{buffer}")?;
+        }
+        method_defs.write_all(&body)?;
         writeln!(method_defs, "}}")
     }
-    #[allow(clippy::too_many_arguments)]
+    /// Exports the function allocating an object of `class_name`, and initializing it with the constructor `def`.
+    fn export_ctor_alloc(
+        asm: &mut Assembly,
+        def: &MethodDef,
+        class_name: &str,
+        method_defs: &mut impl Write,
+        method_decls: &mut impl Write,
+    ) -> std::io::Result<()> {
+        let ctor = mref_to_name(&def.ref_to(), asm);
+        let alloc = ctor_alloc_name(&ctor);
+        let type_id = asm
+            .alloc_type(Type::ClassRef(*def.class()))
+            .as_bimap_index();
+        // Skips `this`, which is the allocated object.
+        let inputs = &asm[def.sig()].inputs()[1..];
+        let params = inputs
+            .iter()
+            .enumerate()
+            .map(|(idx, tpe)| format!("{} A{idx}", nonvoid_c_type(*tpe, asm)))
+            .intersperse(",".into())
+            .collect::<String>();
+        let args = (0..inputs.len())
+            .map(|idx| format!(",A{idx}"))
+            .collect::<String>();
+        writeln!(method_decls, "cilly_object* {alloc}({params});")?;
+        writeln!(
+            method_defs,
+            "cilly_object* {alloc}({params}){{cilly_object* obj = cilly_alloc_object(sizeof(union {class_name}), {type_id}); {ctor}(obj{args}); return obj;}}"
+        )
+    }
     fn export_class(
         &self,
         asm: &mut super::Assembly,
         defid: ClassDefIdx,
-        method_decls: &mut impl Write,
-        method_defs: &mut impl Write,
-        type_defs: &mut impl Write,
+        src: &mut CSource,
         defined_types: &mut FxHashSet<ClassDefIdx>,
         delayed_defs: &mut FxHashSet<ClassDefIdx>,
-        extrn: bool,
     ) -> std::io::Result<()> {
         let class = asm[defid].clone();
-        // Checks if this def needs to be delayed, if one of its fields is not yet defined
+        // Checks if this def needs to be delayed, if one of its fields is not yet defined. Objects are stored as pointers, so they don't need to be defined.
         if !class
             .fields()
             .iter()
             .filter_map(|(tpe, _, _)| tpe.as_class_ref())
+            .filter(|cref| asm[*cref].is_valuetype())
             .filter_map(|cref| asm.class_ref_to_def(cref))
             .all(|cdef| defined_types.contains(&cdef))
         {
             delayed_defs.insert(defid);
             return Ok(());
         }
+        let type_defs = &mut src.type_defs;
         let class_name = escape_ident(&asm[class.name()]);
+        // The fields of an object are placed after its header.
+        let data_offset = if class.is_valuetype() {
+            ""
+        } else {
+            "CILLY_OBJECT_DATA_OFFSET + "
+        };
         writeln!(type_defs, "typedef union {class_name}{{")?;
+        if !class.is_valuetype() {
+            writeln!(type_defs, "cilly_object header;")?;
+        }
         for (field_tpe, fname, offset) in class.fields() {
             let fname = escape_ident(&asm[*fname]);
             let Some(offset) = offset else {
@@ -1063,52 +1424,82 @@ impl CExporter {
                 continue;
             };
             let field_tpe = c_tpe(*field_tpe, asm);
-            let pad = if *offset != 0 {
-                format!("char pad[{offset}];")
+            let pad = if *offset != 0 || !class.is_valuetype() {
+                format!("char pad[{data_offset}{offset}];")
             } else {
                 "".into()
             };
             writeln!(type_defs, "struct {{{pad} {field_tpe} f;}}{fname};")?;
         }
         if let Some(size) = class.explict_size() {
-            writeln!(type_defs, "char force_size[{size}];", size = size.get())?;
+            writeln!(
+                type_defs,
+                "char force_size[{data_offset}{size}];",
+                size = size.get()
+            )?;
         }
         writeln!(type_defs, "}} {class_name};")?;
         for (sfield_tpe, sfname, is_thread_local) in class.static_fields() {
             let fname = escape_ident(&asm[*sfname]);
             let field_tpe = c_tpe(*sfield_tpe, asm);
             let fname = class_member_name(&class_name, &fname);
-            let extrn = if extrn { "extern" } else { "" };
-            if *is_thread_local {
-                writeln!(type_defs, "{extrn} _Thread_local {field_tpe} {fname};")?;
+            let thread_local = if *is_thread_local {
+                "CILLY_THREAD_LOCAL "
             } else {
-                writeln!(type_defs, "{extrn} {field_tpe} {fname};")?;
+                ""
+            };
+            writeln!(src.type_defs, "extern {thread_local}{field_tpe} {fname};")?;
+            writeln!(src.static_defs, "{thread_local}{field_tpe} {fname};")?;
+        }
+        if !class.is_valuetype() {
+            let type_id = asm.alloc_type(Type::ClassRef(*defid)).as_bimap_index();
+            if let Some(parent) = class.extends() {
+                let parent_id = asm.alloc_type(Type::ClassRef(parent)).as_bimap_index();
+                src.type_parents.push((type_id.get(), parent_id.get()));
             }
         }
         for method in class.methods() {
             let mref = &asm[method.0].clone();
             let def = asm[*method].clone();
             let is_extern = def.resolved_implementation(asm).is_extern();
-            Self::export_method_def(asm, &def, method_defs, method_decls)?;
+            let mut method_def = Vec::new();
+            Self::export_method_def(asm, &def, &mut method_def, &mut src.method_decls)?;
+            if !method_def.is_empty() {
+                src.method_defs.push(method_def);
+            }
+            if def.kind() == MethodKind::Constructor && !class.is_valuetype() {
+                let mut alloc_def = Vec::new();
+                Self::export_ctor_alloc(
+                    asm,
+                    &def,
+                    &class_name,
+                    &mut alloc_def,
+                    &mut src.method_decls,
+                )?;
+                src.method_defs.push(alloc_def);
+            }
             if !is_extern {
-                Self::export_method_decl(asm, mref, method_decls)?;
+                Self::export_method_decl(asm, mref, &mut src.method_decls)?;
+                if def.access().is_extern() {
+                    Self::export_method_decl(asm, mref, &mut src.public_decls)?;
+                }
             }
         }
         defined_types.insert(defid);
         Ok(())
     }
-    fn export_to_write(
-        &self,
-        asm: &super::Assembly,
-        out: &mut impl Write,
-        lib: bool,
-        extrn: bool,
-    ) -> std::io::Result<()> {
+    /// Converts `asm` into C source, which can then be written as a single file, or split into multiple translation units.
+    fn export_source(&self, asm: &super::Assembly) -> std::io::Result<CSource> {
         let mut asm = asm.clone();
-
-        let mut method_defs = Vec::new();
-        let mut method_decls = Vec::new();
-        let mut type_defs = Vec::new();
+        let mut src = CSource::default();
+        // Globals used by the builtin header, which are only declared there, so that all units share them.
+        if !*FREESTANDING {
+            writeln!(src.static_defs, "int argc;\nchar **argv;")?;
+        }
+        writeln!(
+            src.static_defs,
+            "CILLY_THREAD_LOCAL cilly_object *cilly_current_exception;"
+        )?;
         let mut defined_types: FxHashSet<ClassDefIdx> = FxHashSet::default();
         let mut delayed_defs: FxHashSet<ClassDefIdx> = asm.iter_class_def_ids().cloned().collect();
         let mut delayed_defs_copy: FxHashSet<ClassDefIdx> = FxHashSet::default();
@@ -1118,35 +1509,104 @@ impl CExporter {
                 self.export_class(
                     &mut asm,
                     *class_def,
-                    &mut method_decls,
-                    &mut method_defs,
-                    &mut type_defs,
+                    &mut src,
                     &mut defined_types,
                     &mut delayed_defs,
-                    extrn,
                 )?;
             }
             delayed_defs_copy.clear();
         }
-        let mut header: String = include_str!("c_header.h").into();
-        if !asm.has_tcctor() {
-            header = header.replace("void _tcctor();", "");
-            header = header.replace("_tcctor();", "");
-        }
-
-        out.write_all(header.as_bytes())?;
+        let parent_types = parent_type_source(&src.type_parents);
+        src.type_defs.extend(parent_types.as_bytes());
+        Ok(src)
+    }
+    fn export_to_write(
+        &self,
+        asm: &super::Assembly,
+        out: &mut impl Write,
+        lib: bool,
+    ) -> std::io::Result<()> {
+        let src = self.export_source(asm)?;
+        out.write_all(builtin_header(asm).as_bytes())?;
         out.write_all(b"\n/*END OF BUILTIN HEADER*/\n")?;
-        out.write_all(&type_defs)?;
+        out.write_all(&src.type_defs)?;
+        out.write_all(&src.static_defs)?;
         out.write_all(b"\n/*END OF TYPEDEFS*/\n")?;
-        out.write_all(&method_decls)?;
+        out.write_all(&src.method_decls)?;
         out.write_all(b"\n/*END OF METHODECLS*/\n")?;
-        out.write_all(&method_defs)?;
+        for method_def in &src.method_defs {
+            out.write_all(method_def)?;
+        }
         if !lib {
-            call_entry(out, &asm)?;
+            call_entry(out, asm)?;
         }
         Ok(())
     }
 }
+/// The C source of an assembly, before it gets written out.
+#[derive(Default)]
+struct CSource {
+    /// Type definitions, and `extern` declarations of static fields.
+    type_defs: Vec<u8>,
+    /// Definitions of static fields.
+    static_defs: Vec<u8>,
+    /// Prototypes of all methods.
+    method_decls: Vec<u8>,
+    /// Prototypes of methods with [`super::Access::Extern`], which make up the public API of the assembly.
+    public_decls: Vec<u8>,
+    /// The definitions of each method.
+    method_defs: Vec<Vec<u8>>,
+    /// The type id of each class defined in this assembly, and the type id of the class it extends.
+    type_parents: Vec<(u32, u32)>,
+}
+/// Returns the definition of `cilly_parent_type`, used by the runtime to check if an object is an instance of a class.
+fn parent_type_source(type_parents: &[(u32, u32)]) -> String {
+    let mut src = String::from(
+        "static inline uint32_t cilly_parent_type(uint32_t type_id){switch(type_id){\n",
+    );
+    for (type_id, parent_id) in type_parents {
+        src.push_str(&format!("case {type_id}: return {parent_id};\n"));
+    }
+    src.push_str("default: return 0;}}\n");
+    src
+}
+/// Returns the builtin header, which provides the C implementations of .NET APIs and the managed runtime shims.
+fn builtin_header(asm: &Assembly) -> String {
+    builtin_header_for(
+        *FREESTANDING,
+        *C_POINTER_WIDTH,
+        *SOFT_INT128,
+        asm.has_tcctor(),
+    )
+}
+/// Assembles the builtin header for a given C profile: the prelude(hosted or freestanding), the managed runtime, and the optional software 128 bit integers.
+fn builtin_header_for(
+    freestanding: bool,
+    pointer_width: u32,
+    soft_int128: bool,
+    has_tcctor: bool,
+) -> String {
+    let mut header = String::new();
+    if soft_int128 {
+        header.push_str("#define CILLY_SOFT_INT128 1\n");
+    }
+    if freestanding {
+        header.push_str(&format!("#define CILLY_POINTER_WIDTH {pointer_width}\n"));
+        header.push_str(include_str!("c_freestanding.h"));
+    } else {
+        let mut hosted: String = include_str!("c_header.h").into();
+        if !has_tcctor {
+            hosted = hosted.replace("void _tcctor();", "");
+            hosted = hosted.replace("_tcctor();", "");
+        }
+        header.push_str(&hosted);
+    }
+    header.push_str(include_str!("c_runtime.h"));
+    if soft_int128 {
+        header.push_str(&soft_int128_c_source());
+    }
+    header
+}
 fn call_entry(out: &mut impl Write, asm: &Assembly) -> Result<(), std::io::Error> {
     let cctor_call = if asm.has_cctor() { "_cctor();" } else { "" };
 
@@ -1154,49 +1614,48 @@ fn call_entry(out: &mut impl Write, asm: &Assembly) -> Result<(), std::io::Error
     Ok(())
 }
 impl CExporter {
-    fn export_to_file(
-        &self,
-        c_path: &Path,
-        asm: &Assembly,
-        target: &Path,
-        lib: bool,
-        extrn: bool,
-    ) -> Result<(), std::io::Error> {
-        let mut c_out = std::io::BufWriter::new(std::fs::File::create(c_path)?);
-        println!("Exporting {c_path:?}");
-
-        self.export_to_write(asm, &mut c_out, lib, extrn)?;
-        println!("Exported {c_path:?}");
-        // Needed to ensure the IL file is valid!
-        c_out.flush().unwrap();
-        drop(c_out);
-
-        let mut cmd = std::process::Command::new(std::env::var("CC").unwrap_or("cc".to_owned()));
-        cmd.arg(c_path).arg("-o").arg(target).arg("-g");
-
-        if *UB_CHECKS && *PARTS == 1 {
-            cmd.args([
-                "-fsanitize=undefined,alignment",
-                "-fno-sanitize=leak",
-                "-fno-sanitize-recover",
-                "-O0",
-            ]);
+    /// Returns the arguments passed to the C compiler, when compiling `c_path` into `target`.
+    /// If `compile_only` is set, `target` is an object file. `ub_checks` enables sanitizers.
+    fn cc_args(c_path: &Path, target: &Path, compile_only: bool, ub_checks: bool) -> Vec<String> {
+        let mut args: Vec<String> = vec![
+            c_path.display().to_string(),
+            "-o".into(),
+            target.display().to_string(),
+            "-g".into(),
+        ];
+        if ub_checks {
+            args.extend(
+                [
+                    "-fsanitize=undefined,alignment",
+                    "-fno-sanitize=leak",
+                    "-fno-sanitize-recover",
+                    "-O0",
+                ]
+                .map(String::from),
+            );
         } else if !*NO_OPT {
-            cmd.arg("-Ofast");
+            args.push("-Ofast".into());
         } else {
-            cmd.arg("-O0");
+            args.push("-O0".into());
         };
-        if lib {
-            cmd.arg("-c");
+        if *FREESTANDING {
+            args.push("-ffreestanding".into());
+        }
+        if compile_only {
+            args.push("-c".into());
         } else {
-            cmd.arg("-lm");
+            args.push("-lm".into());
         }
         if *ANSI_C {
-            cmd.arg("-std=c89");
+            args.push("-std=c89".into());
         }
-        println!("Compiling {c_path:?}");
-        let out = cmd.output().unwrap();
-        println!("Compiled {c_path:?}");
+        args
+    }
+    /// Runs the C compiler, checking its output for errors.
+    fn run_cc(args: &[String]) -> std::io::Result<()> {
+        let mut cmd = std::process::Command::new(c_compiler());
+        cmd.args(args);
+        let out = cmd.output()?;
         let stdout = String::from_utf8_lossy(&out.stdout);
         let stderr = String::from_utf8_lossy(&out.stderr);
         if !*LINKER_RECOVER {
@@ -1207,9 +1666,163 @@ impl CExporter {
                 String::from_utf8_lossy(&out.stderr)
             );
         }
+        Ok(())
+    }
+    fn export_to_file(
+        &self,
+        c_path: &Path,
+        asm: &Assembly,
+        target: &Path,
+        lib: bool,
+    ) -> Result<(), std::io::Error> {
+        let mut c_out = std::io::BufWriter::new(std::fs::File::create(c_path)?);
+        println!("Exporting {c_path:?}");
 
+        self.export_to_write(asm, &mut c_out, lib)?;
+        println!("Exported {c_path:?}");
+        // Needed to ensure the IL file is valid!
+        c_out.flush().unwrap();
+        drop(c_out);
+        println!("Compiling {c_path:?}");
+        Self::run_cc(&Self::cc_args(
+            c_path,
+            target,
+            lib,
+            *UB_CHECKS && !*FREESTANDING,
+        ))?;
+        println!("Compiled {c_path:?}");
         Ok(())
     }
+    /// Exports `asm` as a header, and `parts` translation units, which get compiled in parallel.
+    /// The header(`target.h`) contains the type definitions and prototypes of `Access::Extern` methods.
+    fn export_to_parts(
+        &self,
+        asm: &Assembly,
+        target: &Path,
+        parts: u32,
+    ) -> Result<(), std::io::Error> {
+        let stem = target.file_stem().unwrap().to_string_lossy().into_owned();
+        let src = self.export_source(asm)?;
+        let header_name = format!("{stem}.h");
+        let guard = format!("CILLY_{}_H", escape_ident(&stem).to_uppercase());
+        let mut header = Vec::new();
+        writeln!(header, "#ifndef {guard}\n#define {guard}")?;
+        header.write_all(builtin_header(asm).as_bytes())?;
+        header.write_all(b"\n/*END OF BUILTIN HEADER*/\n")?;
+        header.write_all(&src.type_defs)?;
+        header.write_all(&src.public_decls)?;
+        writeln!(header, "#endif")?;
+        std::fs::write(target.with_file_name(&header_name), header)?;
+        // Each method goes to the smallest unit, so that all units take roughly the same time to compile.
+        let mut units: Vec<Vec<u8>> = (0..parts)
+            .map(|_| {
+                let mut unit = format!("#include \"{header_name}\"\n").into_bytes();
+                unit.extend(&src.method_decls);
+                unit
+            })
+            .collect();
+        let mut method_defs = src.method_defs;
+        method_defs.sort_by_key(|def| std::cmp::Reverse(def.len()));
+        for method_def in method_defs {
+            units
+                .iter_mut()
+                .min_by_key(|unit| unit.len())
+                .unwrap()
+                .extend(method_def);
+        }
+        units[0].extend(&src.static_defs);
+        let lib = self.is_lib || *FREESTANDING;
+        if !lib {
+            call_entry(&mut units[0], asm)?;
+        }
+        let mut compile_args = vec![];
+        let mut objects = vec![];
+        for (id, unit) in units.iter().enumerate() {
+            let c_path = target.with_file_name(format!("{stem}_{id}.c"));
+            std::fs::write(&c_path, unit)?;
+            let object = c_path.with_extension("o");
+            compile_args.push((c_path.clone(), Self::cc_args(&c_path, &object, true, false)));
+            objects.push(object);
+        }
+        println!("Compiling {} units", compile_args.len());
+        std::thread::scope(|scope| {
+            compile_args
+                .iter()
+                .map(|(_, args)| scope.spawn(|| Self::run_cc(args)))
+                .collect::<Vec<_>>()
+                .into_iter()
+                .try_for_each(|handle| handle.join().unwrap())
+        })?;
+        let mut link_args: Vec<String> = objects.iter().map(|o| o.display().to_string()).collect();
+        link_args.extend(["-o".into(), target.display().to_string()]);
+        if lib {
+            // Merge all the units into a single relocatable object.
+            link_args.push("-r".into());
+        } else {
+            link_args.extend(["-g".into(), "-lm".into()]);
+        }
+        println!("Linking {target:?}");
+        Self::run_cc(&link_args)?;
+        println!("Linked {target:?}");
+        if *C_BUILD_FILES {
+            write_build_files(target, &compile_args, &link_args)?;
+        }
+        Ok(())
+    }
+}
+/// Writes a Makefile(`target.mk`) and a `compile_commands.json`, describing how to build the exported C code.
+fn write_build_files(
+    target: &Path,
+    compile_args: &[(std::path::PathBuf, Vec<String>)],
+    link_args: &[String],
+) -> std::io::Result<()> {
+    let dir = std::path::absolute(target.parent().unwrap_or(Path::new(".")))?;
+    let stem = target.file_stem().unwrap().to_string_lossy().into_owned();
+    let cc = c_compiler();
+    let mut makefile = Vec::new();
+    let objects = compile_args
+        .iter()
+        .map(|(c_path, _)| c_path.with_extension("o").display().to_string())
+        .intersperse(" ".into())
+        .collect::<String>();
+    writeln!(makefile, "CC ?= {cc}")?;
+    writeln!(makefile, "{target}: {objects}", target = target.display())?;
+    writeln!(makefile, "\t$(CC) {}", link_args.join(" "))?;
+    for (c_path, args) in compile_args {
+        writeln!(
+            makefile,
+            "{object}: {c_path} {header}",
+            object = c_path.with_extension("o").display(),
+            c_path = c_path.display(),
+            header = target.with_file_name(format!("{stem}.h")).display()
+        )?;
+        writeln!(makefile, "\t$(CC) {}", args.join(" "))?;
+    }
+    std::fs::write(target.with_file_name(format!("{stem}.mk")), makefile)?;
+    let commands = compile_args
+        .iter()
+        .map(|(c_path, args)| {
+            let arguments = std::iter::once(cc.as_str())
+                .chain(args.iter().map(String::as_str))
+                .map(|arg| format!("{arg:?}"))
+                .intersperse(", ".into())
+                .collect::<String>();
+            format!(
+                "  {{\"directory\": {dir:?}, \"file\": {file:?}, \"arguments\": [{arguments}]}}",
+                dir = dir.display().to_string(),
+                file = c_path.display().to_string()
+            )
+        })
+        .intersperse(",\n".into())
+        .collect::<String>();
+    std::fs::write(
+        target.with_file_name("compile_commands.json"),
+        format!("[\n{commands}\n]\n"),
+    )
+}
+/// The C compiler used to build the exported code.
+fn c_compiler() -> String {
+    std::env::var("CC").unwrap_or("cc".to_owned())
 }
 impl Exporter for CExporter {
     type Error = std::io::Error;
@@ -1218,59 +1831,13 @@ impl Exporter for CExporter {
         if *PARTS == 1 {
             // The IL file should be next to the target
             let c_path = target.with_extension("c");
-            self.export_to_file(&c_path, asm, target, self.is_lib, false)
+            // Freestanding code has no `main`, and gets linked by the user.
+            self.export_to_file(&c_path, asm, target, self.is_lib || *FREESTANDING)
         } else {
-            let mut parts = vec![];
-            for (id, part) in asm.split_to_parts(*PARTS).enumerate() {
-                let name = target.file_stem().unwrap().to_string_lossy().into_owned();
-                let target = target
-                    .with_file_name(format!("{name}_{id}"))
-                    .with_extension("o");
-                let c_path = target.with_extension("c");
-                self.export_to_file(&c_path, &part, &target, true, true)?;
-                parts.push(target);
-            }
-
-            let mut cmd =
-                std::process::Command::new(std::env::var("CC").unwrap_or("cc".to_owned()));
-
-            cmd.args(parts);
-            cmd.arg("-o").arg(target).arg("-g").arg("-lm");
-
-            let c_path = target.with_extension("c");
-            let only_statics = asm.only_statics();
-
-            self.export_to_file(&c_path, &only_statics, target, true, false)?;
-            let mut option = std::fs::OpenOptions::new();
-            option.read(true);
-            option.append(true);
-            if !self.is_lib {
-                let mut c_file = option.open(&c_path).unwrap();
-                call_entry(&mut c_file, asm).unwrap();
-            } else {
-                cmd.arg("-c");
-            }
-            cmd.arg(c_path);
-
-            println!("Linking {target:?}");
-            let out = cmd.output().unwrap();
-            println!("Linked {target:?}");
-            let stdout = String::from_utf8_lossy(&out.stdout);
-            let stderr = String::from_utf8_lossy(&out.stderr);
-            if !*LINKER_RECOVER {
-                assert!(
-                    !(stderr.contains("error") || stderr.contains("fatal")),
-                    "stdout:{} stderr:{} cmd:{cmd:?}",
-                    stdout,
-                    String::from_utf8_lossy(&out.stderr)
-                );
-            }
-
-            Ok(())
+            self.export_to_parts(asm, target, *PARTS)
         }
     }
 }
-
 #[must_use]
 pub fn class_to_mangled(class: &super::ClassRef, asm: &Assembly) -> String {
     let assembly = match class.asm() {
@@ -1291,3 +1858,496 @@ pub fn name_sig_class_to_mangled(
         None => todo!(),
     };
 }
+#[test]
+#[cfg(not(miri))]
+fn export_managed_nodes() {
+    use super::{cilnode::MethodKind, Access, BasicBlock};
+    // Checks that nodes which only make sense in .NET still export to valid C.
+    let mut asm = Assembly::default();
+    let main_module = asm.main_module();
+    let exception_ref = ClassRef::exception(&mut asm);
+    let exception = Type::ClassRef(exception_ref);
+    let exception_idx = asm.alloc_type(exception);
+    let i32_idx = asm.alloc_type(Type::Int(Int::I32));
+    let i32_arr = asm.alloc_type(Type::PlatformArray {
+        elem: i32_idx,
+        dims: 1.try_into().unwrap(),
+    });
+    let u8_ptr = asm.nptr(Type::Int(Int::U8));
+    let sig = asm.sig([u8_ptr], Type::Void);
+    let null = asm.alloc_node(Const::Null(exception_ref));
+    let set_obj = asm.alloc_root(CILRoot::StLoc(0, null));
+    let obj = asm.alloc_node(CILNode::LdLoc(0));
+    let is_inst = asm.alloc_node(CILNode::IsInst(obj, exception_idx));
+    let branch = asm.alloc_root(CILRoot::Branch(Box::new((
+        1,
+        0,
+        Some(BranchCond::True(is_inst)),
+    ))));
+    let cast = asm.alloc_node(CILNode::CheckedCast(obj, exception_idx));
+    let set_cast = asm.alloc_root(CILRoot::StLoc(0, cast));
+    let val = asm.alloc_node(Const::I32(7));
+    let set_val = asm.alloc_root(CILRoot::StLoc(1, val));
+    let src = asm.alloc_node(CILNode::LdLocA(1));
+    let dst = asm.alloc_node(CILNode::LdLocA(2));
+    let cpobj = asm.alloc_root(CILRoot::CpObj {
+        src,
+        dst,
+        tpe: i32_idx,
+    });
+    let len = asm.alloc_node(Const::USize(4));
+    let arr = asm.alloc_node(CILNode::NewArr { elem: i32_idx, len });
+    let set_arr = asm.alloc_root(CILRoot::StLoc(3, arr));
+    let ret = asm.alloc_root(CILRoot::VoidRet);
+    let rethrow = asm.alloc_root(CILRoot::ReThrow);
+    let name = asm.alloc_string("entrypoint");
+    asm.new_method(MethodDef::new(
+        Access::Extern,
+        main_module,
+        name,
+        sig,
+        MethodKind::Static,
+        MethodImpl::MethodBody {
+            blocks: vec![
+                BasicBlock::new(
+                    vec![set_obj, branch, set_cast, set_val, cpobj, set_arr, ret],
+                    0,
+                    None,
+                ),
+                BasicBlock::new(vec![rethrow], 1, None),
+            ],
+            locals: vec![
+                (None, exception_idx),
+                (None, i32_idx),
+                (None, i32_idx),
+                (None, i32_arr),
+            ],
+        },
+        vec![None],
+    ));
+    let exe = std::env::temp_dir().join("cilly_export_managed_nodes");
+    asm.export(&exe, CExporter::new(false));
+    let out = std::process::Command::new(&exe).output().unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+}
+#[test]
+#[cfg(not(miri))]
+fn export_objects() {
+    use super::{cilnode::MethodKind, Access, BasicBlock, ClassDef, FieldDesc};
+    // Allocates an object of a class with a base class, and round-trips its fields.
+    let mut asm = Assembly::default();
+    let main_module = asm.main_module();
+    let base_name = asm.alloc_string("Base");
+    let base = asm.class_def(ClassDef::new(
+        base_name,
+        false,
+        0,
+        None,
+        vec![],
+        vec![],
+        Access::Public,
+        None,
+        None,
+    ));
+    let base_idx = asm.alloc_type(Type::ClassRef(*base));
+    let pair_name = asm.alloc_string("Pair");
+    let (a, b) = (asm.alloc_string("a"), asm.alloc_string("b"));
+    let pair = asm.class_def(ClassDef::new(
+        pair_name,
+        false,
+        0,
+        Some(*base),
+        vec![
+            (Type::Int(Int::I32), a, Some(0)),
+            (Type::Int(Int::I64), b, Some(8)),
+        ],
+        vec![],
+        Access::Public,
+        None,
+        None,
+    ));
+    let pair_tpe = Type::ClassRef(*pair);
+    let pair_idx = asm.alloc_type(pair_tpe);
+    let a = asm.alloc_field(FieldDesc::new(*pair, a, Type::Int(Int::I32)));
+    let b = asm.alloc_field(FieldDesc::new(*pair, b, Type::Int(Int::I64)));
+    // Pair::.ctor(this, a, b)
+    let ctor_sig = asm.sig(
+        [pair_tpe, Type::Int(Int::I32), Type::Int(Int::I64)],
+        Type::Void,
+    );
+    let this = asm.alloc_node(CILNode::LdArg(0));
+    let arg_a = asm.alloc_node(CILNode::LdArg(1));
+    let arg_b = asm.alloc_node(CILNode::LdArg(2));
+    let set_a = asm.alloc_root(CILRoot::SetField(Box::new((a, this, arg_a))));
+    let set_b = asm.alloc_root(CILRoot::SetField(Box::new((b, this, arg_b))));
+    let ret = asm.alloc_root(CILRoot::VoidRet);
+    let ctor_name = asm.alloc_string(".ctor");
+    let ctor = asm.new_method(MethodDef::new(
+        Access::Public,
+        pair,
+        ctor_name,
+        ctor_sig,
+        MethodKind::Constructor,
+        MethodImpl::MethodBody {
+            blocks: vec![BasicBlock::new(vec![set_a, set_b, ret], 0, None)],
+            locals: vec![],
+        },
+        vec![None, None, None],
+    ));
+    // obj = new Pair(3, 40); obj.a += 1; if (obj.a + obj.b != 44 || !(obj is Base)) abort();
+    let three = asm.alloc_node(Const::I32(3));
+    let forty = asm.alloc_node(Const::I64(40));
+    let new_pair = asm.alloc_node(CILNode::Call(Box::new((*ctor, [three, forty].into()))));
+    let set_obj = asm.alloc_root(CILRoot::StLoc(0, new_pair));
+    let obj = asm.alloc_node(CILNode::LdLoc(0));
+    let ld_a = asm.alloc_node(CILNode::LdField {
+        addr: obj,
+        field: a,
+    });
+    let one = asm.alloc_node(Const::I32(1));
+    let inc_a = asm.alloc_node(CILNode::BinOp(ld_a, one, BinOp::Add));
+    let store_a = asm.alloc_root(CILRoot::SetField(Box::new((a, obj, inc_a))));
+    let wide_a = asm.alloc_node(CILNode::IntCast {
+        input: ld_a,
+        target: Int::I64,
+        extend: super::cilnode::ExtendKind::SignExtend,
+    });
+    let ld_b = asm.alloc_node(CILNode::LdField {
+        addr: obj,
+        field: b,
+    });
+    let sum = asm.alloc_node(CILNode::BinOp(wide_a, ld_b, BinOp::Add));
+    let expected = asm.alloc_node(Const::I64(44));
+    let check_sum = asm.alloc_root(CILRoot::Branch(Box::new((
+        1,
+        0,
+        Some(BranchCond::Ne(sum, expected)),
+    ))));
+    let base_cast = asm.alloc_node(CILNode::CheckedCast(obj, base_idx));
+    let is_base = asm.alloc_node(CILNode::IsInst(base_cast, base_idx));
+    let check_base = asm.alloc_root(CILRoot::Branch(Box::new((
+        1,
+        0,
+        Some(BranchCond::False(is_base)),
+    ))));
+    let is_pair = asm.alloc_node(CILNode::IsInst(obj, pair_idx));
+    let check_pair = asm.alloc_root(CILRoot::Branch(Box::new((
+        1,
+        0,
+        Some(BranchCond::False(is_pair)),
+    ))));
+    let ret = asm.alloc_root(CILRoot::VoidRet);
+    let msg = asm.alloc_string("Wrong object state");
+    let unreachable = asm.alloc_root(CILRoot::Unreachable(msg));
+    let u8_ptr = asm.nptr(Type::Int(Int::U8));
+    let sig = asm.sig([u8_ptr], Type::Void);
+    let name = asm.alloc_string("entrypoint");
+    asm.new_method(MethodDef::new(
+        Access::Extern,
+        main_module,
+        name,
+        sig,
+        MethodKind::Static,
+        MethodImpl::MethodBody {
+            blocks: vec![
+                BasicBlock::new(
+                    vec![set_obj, store_a, check_sum, check_base, check_pair, ret],
+                    0,
+                    None,
+                ),
+                BasicBlock::new(vec![unreachable], 1, None),
+            ],
+            locals: vec![(None, pair_idx)],
+        },
+        vec![None],
+    ));
+    let exe = std::env::temp_dir().join("cilly_export_objects");
+    asm.export(&exe, CExporter::new(false));
+    let out = std::process::Command::new(&exe).output().unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+}
+#[test]
+#[cfg(not(miri))]
+fn export_parts() {
+    use super::{cilnode::MethodKind, Access, BasicBlock, MethodRefIdx};
+    let mut asm = Assembly::default();
+    let main_module = asm.main_module();
+    // A chain of public functions, each calling the previous one, to spread across units.
+    let i32_sig = asm.sig([Type::Int(Int::I32)], Type::Int(Int::I32));
+    let mut prev: Option<MethodRefIdx> = None;
+    for id in 0..8 {
+        let arg = asm.alloc_node(CILNode::LdArg(0));
+        let arg = match prev {
+            Some(prev) => asm.alloc_node(CILNode::Call(Box::new((prev, [arg].into())))),
+            None => arg,
+        };
+        let one = asm.alloc_node(Const::I32(1));
+        let sum = asm.alloc_node(CILNode::BinOp(arg, one, BinOp::Add));
+        let ret = asm.alloc_root(CILRoot::Ret(sum));
+        let name = asm.alloc_string(format!("add_{id}"));
+        let def = asm.new_method(MethodDef::new(
+            Access::Extern,
+            main_module,
+            name,
+            i32_sig,
+            MethodKind::Static,
+            MethodImpl::MethodBody {
+                blocks: vec![BasicBlock::new(vec![ret], 0, None)],
+                locals: vec![],
+            },
+            vec![None],
+        ));
+        prev = Some(*def);
+    }
+    let u8_ptr = asm.nptr(Type::Int(Int::U8));
+    let sig = asm.sig([u8_ptr], Type::Void);
+    let zero = asm.alloc_node(Const::I32(0));
+    let call = asm.alloc_node(CILNode::Call(Box::new((prev.unwrap(), [zero].into()))));
+    let eight = asm.alloc_node(Const::I32(8));
+    let branch = asm.alloc_root(CILRoot::Branch(Box::new((
+        1,
+        0,
+        Some(BranchCond::Ne(call, eight)),
+    ))));
+    let ret = asm.alloc_root(CILRoot::VoidRet);
+    let msg = asm.alloc_string("Wrong result");
+    let unreachable = asm.alloc_root(CILRoot::Unreachable(msg));
+    let name = asm.alloc_string("entrypoint");
+    asm.new_method(MethodDef::new(
+        Access::Extern,
+        main_module,
+        name,
+        sig,
+        MethodKind::Static,
+        MethodImpl::MethodBody {
+            blocks: vec![
+                BasicBlock::new(vec![branch, ret], 0, None),
+                BasicBlock::new(vec![unreachable], 1, None),
+            ],
+            locals: vec![],
+        },
+        vec![None],
+    ));
+    let dir = std::env::temp_dir().join("cilly_export_parts");
+    std::fs::create_dir_all(&dir).unwrap();
+    let exe = dir.join("parts");
+    CExporter::new(false)
+        .export_to_parts(&asm, &exe, 3)
+        .unwrap();
+    let header = std::fs::read_to_string(dir.join("parts.h")).unwrap();
+    assert!(header.contains("int32_t add_7(int32_t);"));
+    for id in 0..3 {
+        assert!(dir.join(format!("parts_{id}.c")).exists());
+    }
+    let out = std::process::Command::new(&exe).output().unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+}
+/// Compiles `c_path` into an object file, as strict, freestanding C.
+#[cfg(test)]
+fn compile_freestanding(c_path: &Path, std: &str) -> std::process::Output {
+    std::process::Command::new(c_compiler())
+        .arg(c_path)
+        .args([
+            std,
+            "-pedantic-errors",
+            "-Wno-long-long",
+            "-Werror=declaration-after-statement",
+            "-ffreestanding",
+            "-nostdlib",
+            "-c",
+            "-o",
+        ])
+        .arg(c_path.with_extension("o"))
+        .output()
+        .unwrap()
+}
+#[test]
+#[cfg(not(miri))]
+fn freestanding_header_is_c89() {
+    // The freestanding prelude, managed runtime and soft 128 bit integers must compile as C89, without libc.
+    let dir = std::env::temp_dir().join("cilly_freestanding_header");
+    std::fs::create_dir_all(&dir).unwrap();
+    let pointer_width = usize::BITS;
+    for std in ["-std=c89", "-std=c99"] {
+        let c_path = dir.join(format!("header{std}.c"));
+        let header =
+            builtin_header_for(true, pointer_width, true, false) + &parent_type_source(&[]);
+        std::fs::write(&c_path, header).unwrap();
+        let out = compile_freestanding(&c_path, std);
+        assert!(
+            out.status.success(),
+            "{std}: {}",
+            String::from_utf8_lossy(&out.stderr)
+        );
+        // A pointer width which does not match the target is rejected.
+        let header = builtin_header_for(true, pointer_width / 2, true, false);
+        std::fs::write(&c_path, header).unwrap();
+        assert!(!compile_freestanding(&c_path, std).status.success());
+    }
+}
+#[test]
+#[cfg(not(miri))]
+fn freestanding_program() {
+    use super::{cilnode::MethodKind, Access, BasicBlock};
+    // The C profile is global, so the program gets exported by a child process, with `FREESTANDING` set.
+    if !*FREESTANDING {
+        let out = std::process::Command::new(std::env::current_exe().unwrap())
+            .args(["--exact", "v2::c_exporter::freestanding_program"])
+            .env("FREESTANDING", "1")
+            .output()
+            .unwrap();
+        assert!(
+            out.status.success(),
+            "{}",
+            String::from_utf8_lossy(&out.stdout)
+        );
+        return;
+    }
+    // fill(n): fills a stack buffer of n bytes with 3, and returns buf[n - 1] * n, computed using 128 bit integers.
+    let mut asm = Assembly::default();
+    let main_module = asm.main_module();
+    let u8_ptr = asm.nptr(Type::Int(Int::U8));
+    let u8_ptr_idx = asm.alloc_type(u8_ptr);
+    let n = asm.alloc_node(CILNode::LdArg(0));
+    let len = asm.alloc_node(CILNode::IntCast {
+        input: n,
+        target: Int::USize,
+        extend: ExtendKind::ZeroExtend,
+    });
+    let buf = asm.alloc_node(CILNode::LocAlloc { size: len });
+    let set_buf = asm.alloc_root(CILRoot::StLoc(0, buf));
+    let buf = asm.alloc_node(CILNode::LdLoc(0));
+    let three = asm.alloc_node(Const::U8(3));
+    let fill = asm.alloc_root(CILRoot::InitBlk(Box::new((buf, three, len))));
+    // memcmp(buf, buf, n) must be 0.
+    let memcmp_sig = asm.sig([u8_ptr, u8_ptr, Type::Int(Int::USize)], Type::Int(Int::I32));
+    let memcmp = asm.alloc_string("memcmp");
+    let libc = asm.alloc_string("c");
+    let memcmp = asm.new_method(MethodDef::new(
+        Access::Extern,
+        main_module,
+        memcmp,
+        memcmp_sig,
+        MethodKind::Static,
+        MethodImpl::Extern {
+            lib: libc,
+            preserve_errno: false,
+        },
+        vec![None, None, None],
+    ));
+    let cmp = asm.alloc_node(CILNode::Call(Box::new((*memcmp, [buf, buf, len].into()))));
+    let zero = asm.alloc_node(Const::I32(0));
+    let check_cmp = asm.alloc_root(CILRoot::Branch(Box::new((
+        1,
+        0,
+        Some(BranchCond::Ne(cmp, zero)),
+    ))));
+    let one = asm.alloc_node(Const::USize(1));
+    let last_offset = asm.alloc_node(CILNode::BinOp(len, one, BinOp::Sub));
+    let last_addr = asm.alloc_node(CILNode::BinOp(buf, last_offset, BinOp::Add));
+    let u8_idx = asm.alloc_type(Type::Int(Int::U8));
+    let last = asm.alloc_node(CILNode::LdInd {
+        addr: last_addr,
+        tpe: u8_idx,
+        volatile: false,
+    });
+    let wide = |asm: &mut Assembly, input| {
+        asm.alloc_node(CILNode::IntCast {
+            input,
+            target: Int::U128,
+            extend: ExtendKind::ZeroExtend,
+        })
+    };
+    let (wide_last, wide_n) = (wide(&mut asm, last), wide(&mut asm, n));
+    let product = asm.alloc_node(CILNode::BinOp(wide_last, wide_n, BinOp::Mul));
+    let product = asm.alloc_node(CILNode::IntCast {
+        input: product,
+        target: Int::U64,
+        extend: ExtendKind::ZeroExtend,
+    });
+    let ret = asm.alloc_root(CILRoot::Ret(product));
+    let msg = asm.alloc_string("memcmp failed");
+    let unreachable = asm.alloc_root(CILRoot::Unreachable(msg));
+    let sig = asm.sig([Type::Int(Int::U32)], Type::Int(Int::U64));
+    let name = asm.alloc_string("fill");
+    asm.new_method(MethodDef::new(
+        Access::Extern,
+        main_module,
+        name,
+        sig,
+        MethodKind::Static,
+        MethodImpl::MethodBody {
+            blocks: vec![
+                BasicBlock::new(vec![set_buf, fill, check_cmp, ret], 0, None),
+                BasicBlock::new(vec![unreachable], 1, None),
+            ],
+            locals: vec![(None, u8_ptr_idx)],
+        },
+        vec![None],
+    ));
+    let dir = std::env::temp_dir().join("cilly_freestanding_program");
+    std::fs::create_dir_all(&dir).unwrap();
+    let c_path = dir.join("program.c");
+    let mut src = Vec::new();
+    CExporter::new(true)
+        .export_to_write(&asm, &mut src, true)
+        .unwrap();
+    std::fs::write(&c_path, src).unwrap();
+    let out = compile_freestanding(&c_path, "-std=c89");
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    // A hosted driver provides the hooks, and checks that the stack buffer gets freed.
+    let driver = dir.join("driver.c");
+    std::fs::write(
+        &driver,
+        r#"#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+static int live_allocs = 0;
+void *cilly_alloc(uintptr_t size, uintptr_t align){live_allocs++; return aligned_alloc(align, (size + align - 1) / align * align);}
+void *cilly_realloc(void *ptr, uintptr_t new_size, uintptr_t align){return realloc(ptr, new_size);}
+void cilly_free(void *ptr){if (ptr) live_allocs--; free(ptr);}
+void cilly_panic(const char *msg){fprintf(stderr, "%s\n", msg); abort();}
+uint64_t fill(uint32_t n);
+int main(void){
+    if (fill(16) != 48 || fill(1) != 3) {fprintf(stderr, "wrong result\n"); return 1;}
+    if (live_allocs != 0) {fprintf(stderr, "%d stack buffers leaked\n", live_allocs); return 1;}
+    return 0;
+}"#,
+    )
+    .unwrap();
+    let exe = dir.join("program");
+    let out = std::process::Command::new(c_compiler())
+        .arg(&driver)
+        .arg(c_path.with_extension("o"))
+        .arg("-o")
+        .arg(&exe)
+        .output()
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let out = std::process::Command::new(&exe).output().unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+}
@@ -1,129 +1,315 @@
-#[allow(dead_code)]
-use super::{Assembly, ClassRef, Exporter, FnSig, Type};
+//! A stable, human-readable, textual form of an [`Assembly`].
+//!
+//! The format is made out of S-expressions. Comments start with `;` and last until the end of the line.
+//! Strings use Rust-like escapes(`\"`, `\\`, `\n`, `\r`, `\t` and `\u{hex}`). Interned indices are never
+//! written out: every type, signature, field, method reference, node and root is spelled out in full,
+//! and gets interned again when the file is read back.
+//!
+//! A file starts with a version header, followed by class definitions:
+//! ```text
+//! (cillyir 1)
+//! (class "Name" ACCESS [valuetype] [(generics N)] [(extends CREF)] [(size N)] [(align N)]
+//!   (field TYPE "name" [OFFSET])
+//!   (static TYPE "name" [thread_local])
+//!   (method ACCESS KIND "name" SIG (args "name" _ ...) IMPL))
+//! ```
+//! where:
+//! * `ACCESS` is one of `extern`, `public` or `private`, and `KIND` one of `static`, `instance`, `virtual` or `ctor`.
+//! * `TYPE` is a primitive(`i8`..`i128`, `isize`, `u8`..`u128`, `usize`, `f16`..`f128`, `bool`, `void`, `char`,
+//!   `string`, `object`), or one of `(ptr TYPE)`, `(ref TYPE)`, `(array TYPE DIMS)`, `(fnptr SIG)`,
+//!   `(generic N method|call|type)`, `(simd ELEM COUNT)` or `CREF`.
+//! * `CREF` is `(cref "name" [valuetype] [(asm "assembly")] [(generics TYPE...)])`.
+//! * `SIG` is `(sig (TYPE...) TYPE)`.
+//! * `IMPL` is `(body (locals (local "name"|_ TYPE)...) BLOCK...)`, `(extern "lib" [preserve_errno])`,
+//!   `(alias METHODREF)` or `(missing)`.
+//! * `BLOCK` is `(block ID ROOT... [(handler BLOCK...)])`.
+//! * `METHODREF` is `(methodref CREF "name" SIG KIND [(generics TYPE...)])`, a field is
+//!   `(fieldref CREF "name" TYPE)` and a static field is `(staticref CREF "name" TYPE)`.
+//!
+//! Nodes and roots are lists named after their [`CILNode`](crate::v2::CILNode) and [`CILRoot`](crate::v2::CILRoot)
+//! variants, for example `(ret (add (ldarg 0) (const i32 1)))`. Floating point constants
+//! which are not finite are written as their bits(`#x7ff8000000000000`).
+use super::{
+    cilnode::MethodKind, cilroot::CmpKind, tpe::GenericKind, Access, Assembly, BinOp, Exporter,
+    Float, Int,
+};
 use std::io::Write;
+
+pub mod parse;
+pub mod print;
+pub mod sexpr;
+pub use parse::parse_assembly;
+pub use print::{print_assembly, print_method};
+pub use sexpr::ParseError;
+
+/// Version of the textual format. Bumped on every backwards-incompatible change.
+pub const FORMAT_VERSION: u32 = 1;
+const INT_NAMES: &[(Int, &str)] = &[
+    (Int::U8, "u8"),
+    (Int::U16, "u16"),
+    (Int::U32, "u32"),
+    (Int::U64, "u64"),
+    (Int::U128, "u128"),
+    (Int::USize, "usize"),
+    (Int::I8, "i8"),
+    (Int::I16, "i16"),
+    (Int::I32, "i32"),
+    (Int::I64, "i64"),
+    (Int::I128, "i128"),
+    (Int::ISize, "isize"),
+];
+const FLOAT_NAMES: &[(Float, &str)] = &[
+    (Float::F16, "f16"),
+    (Float::F32, "f32"),
+    (Float::F64, "f64"),
+    (Float::F128, "f128"),
+];
+const ACCESS_NAMES: &[(Access, &str)] = &[
+    (Access::Extern, "extern"),
+    (Access::Public, "public"),
+    (Access::Private, "private"),
+];
+const METHOD_KIND_NAMES: &[(MethodKind, &str)] = &[
+    (MethodKind::Static, "static"),
+    (MethodKind::Instance, "instance"),
+    (MethodKind::Virtual, "virtual"),
+    (MethodKind::Constructor, "ctor"),
+];
+const GENERIC_KIND_NAMES: &[(GenericKind, &str)] = &[
+    (GenericKind::MethodGeneric, "method"),
+    (GenericKind::CallGeneric, "call"),
+    (GenericKind::TypeGeneric, "type"),
+];
+const CMP_KIND_NAMES: &[(CmpKind, &str)] = &[
+    (CmpKind::Ordered, "ordered"),
+    (CmpKind::Unordered, "unordered"),
+    (CmpKind::Signed, "signed"),
+    (CmpKind::Unsigned, "unsigned"),
+];
+const BINOP_NAMES: &[(BinOp, &str)] = &[
+    (BinOp::Add, "add"),
+    (BinOp::Eq, "eq"),
+    (BinOp::Sub, "sub"),
+    (BinOp::Mul, "mul"),
+    (BinOp::LtUn, "lt_un"),
+    (BinOp::Lt, "lt"),
+    (BinOp::GtUn, "gt_un"),
+    (BinOp::Gt, "gt"),
+    (BinOp::Or, "or"),
+    (BinOp::XOr, "xor"),
+    (BinOp::And, "and"),
+    (BinOp::Rem, "rem"),
+    (BinOp::RemUn, "rem_un"),
+    (BinOp::Shl, "shl"),
+    (BinOp::Shr, "shr"),
+    (BinOp::ShrUn, "shr_un"),
+    (BinOp::DivUn, "div_un"),
+    (BinOp::Div, "div"),
+];
+/// Exports an assembly in the textual cilly IR format, to a file with the `cillyir` extension.
 #[derive(Default)]
 pub struct CillyIRExpoter {}
 impl Exporter for CillyIRExpoter {
     type Error = std::io::Error;
 
-    fn export(&self, asm: &super::Assembly, target: &std::path::Path) -> Result<(), Self::Error> {
-        let il_path = target.with_extension("rs");
-
-        if let Err(err) = std::fs::remove_file(&il_path) {
-            match err.kind() {
-                std::io::ErrorKind::NotFound => (),
-                _ => {
-                    panic!("Could not remove tmp file because {err:?}")
-                }
-            }
-        };
-        let mut il_out = std::io::BufWriter::new(std::fs::File::create(&il_path)?);
-        writeln!(il_out, "fn add_cilly(asm:&mut Assembly){{")?;
-        for def in asm.class_defs().values() {
-            let name = &asm[def.name()];
-            let escaped_name = escape_class_name(name);
-            writeln!(il_out, "fn {escaped_name}(asm:&mut Assembly){{",)?;
-            let extends = if let Some(extends) = def.extends() {
-                class_ref(asm.class_ref(extends), asm)
-            } else {
-                "None".into()
-            };
-            let fields: String = def
-                .fields()
-                .iter()
-                .map(|(tpe, name, offset)| {
-                    let tpe = tpe_to(tpe, asm);
-                    let name = &asm[*name];
-                    let offset = if let Some(offset) = offset {
-                        format!("Some(NonZeroU32::new({offset}).unwrap())")
-                    } else {
-                        "None".into()
-                    };
-                    format!("({tpe},{{asm.alloc_string({name:?})}},{offset})")
-                })
-                .intersperse(",".to_owned())
-                .collect();
-            let fields = format!("vec![{fields}]");
-            let static_fields: String = def
-                .static_fields()
-                .iter()
-                .map(|(tpe, name, thread_local)| {
-                    let tpe = tpe_to(tpe, asm);
-                    let name = &asm[*name];
-                    format!("({tpe},{{asm.alloc_string({name:?})}},{thread_local})")
-                })
-                .intersperse(",".to_owned())
-                .collect();
-            let static_fields = format!("vec![{static_fields}]");
-            let methods = "vec![]";
-            let access = "Access::Public";
-            let explict_size = if let Some(explict_size) = def.explict_size() {
-                format!("Some(NonZeroU32::new({explict_size}).unwrap())")
-            } else {
-                "None".into()
-            };
-            writeln!(
-                il_out,
-                "let {escaped_name} = ClassDef::new(asm.alloc_string({name:?}),{is_valuetype},{generics},{extends},{fields},{static_fields},{methods},{access},{explict_size});",
-                is_valuetype = def.is_valuetype(),
-                generics = def.generics(),
-            )?;
-            // let mut class = crate::v2::class::ClassDef::new();
-            writeln!(il_out, "}}")?;
-        }
-        writeln!(il_out, "}}")?;
-        let _ = std::process::Command::new("rustfmt").arg(il_path).output();
-        Ok(())
+    fn export(&self, asm: &Assembly, target: &std::path::Path) -> Result<(), Self::Error> {
+        let ir_path = target.with_extension("cillyir");
+        let mut ir_out = std::io::BufWriter::new(std::fs::File::create(&ir_path)?);
+        ir_out.write_all(print_assembly(asm).as_bytes())?;
+        ir_out.flush()
     }
 }
-fn tpe_to(tpe: &Type, asm: &Assembly) -> String {
-    match tpe {
-        Type::Ptr(inner) => format!("{{asm.nptr({inner})}}", inner = tpe_to(&asm[*inner], asm)),
-        Type::Ref(inner) => format!("{{asm.nref({inner})}}", inner = tpe_to(&asm[*inner], asm)),
-        Type::ClassRef(cref) => format!(
-            "Type::ClassRef({{asm.alloc_class_ref({cref})}})",
-            cref = class_ref(asm.class_ref(*cref), asm)
-        ),
-        Type::PlatformGeneric(_, _) => todo!(),
-        Type::PlatformString
-        | Type::PlatformChar
-        | Type::PlatformObject
-        | Type::Float(_)
-        | Type::Int(_)
-        | Type::Bool
-        | Type::Void => format!("{tpe:?}"),
-        Type::PlatformArray { .. } => todo!(),
-        Type::FnPtr(sig) => format!("Type::FnPtr({sig})", sig = sig_to(asm[*sig].clone(), asm)),
-        Type::SIMDVector(_) => panic!("SMID is not supported when dumping cilly IR"),
+impl Assembly {
+    /// Loads an assembly from a file in the textual cilly IR format.
+    /// # Errors
+    /// Returns an error if the file could not be read, or is not valid cilly IR.
+    pub fn load_cillyir(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        let src = std::fs::read_to_string(path)?;
+        parse_assembly(&src)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+    }
+    /// Saves this assembly to a file, in the textual cilly IR format.
+    /// # Errors
+    /// Returns an error if the file could not be written.
+    pub fn save_cillyir(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        std::fs::write(path, print_assembly(self))
     }
 }
-fn sig_to(sig: FnSig, asm: &Assembly) -> String {
-    let inputs: String = sig
-        .inputs()
-        .iter()
-        .map(|input| tpe_to(input, asm))
-        .intersperse(",".into())
-        .collect();
-    format!(
-        "{{asm.sig([{inputs}],{output})}}",
-        output = tpe_to(sig.output(), asm)
-    )
+#[cfg(test)]
+fn sample_asm() -> Assembly {
+    use super::{
+        cilnode::{ExtendKind, PtrCastRes},
+        cilroot::BranchCond,
+        BasicBlock, CILNode, CILRoot, ClassDef, ClassRef, Const, FieldDesc, MethodDef, MethodImpl,
+        StaticFieldDesc, Type,
+    };
+    let mut asm = Assembly::default();
+    let main_module = asm.main_module();
+    let name = asm.alloc_string("Vec2");
+    let x = asm.alloc_string("x");
+    let y = asm.alloc_string("y");
+    let vec2 = asm.class_def(ClassDef::new(
+        name,
+        true,
+        0,
+        None,
+        vec![
+            (Type::Float(Float::F32), x, Some(0)),
+            (Type::Float(Float::F32), y, Some(4)),
+        ],
+        vec![(Type::Int(Int::U64), x, true)],
+        Access::Public,
+        std::num::NonZeroU32::new(8),
+        std::num::NonZeroU32::new(4),
+    ));
+    let vec2_ref = *vec2;
+    let x_fld = asm.alloc_field(FieldDesc::new(vec2_ref, x, Type::Float(Float::F32)));
+    let sfld = asm.alloc_sfld(StaticFieldDesc::new(vec2_ref, x, Type::Int(Int::U64)));
+    let vec2_ptr = asm.nptr(Type::ClassRef(vec2_ref));
+    let sig = asm.sig([vec2_ptr, Type::Int(Int::I32)], Type::Float(Float::F32));
+    let string_name = asm.alloc_string("System.String");
+    let sys_asm = asm.alloc_string("System.Runtime");
+    let string_cref = asm.alloc_class_ref(ClassRef::new(
+        string_name,
+        Some(sys_asm),
+        false,
+        [Type::Int(Int::I8)].into(),
+    ));
+    let arg0 = asm.alloc_node(CILNode::LdArg(0));
+    let arg1 = asm.alloc_node(CILNode::LdArg(1));
+    let fld = asm.alloc_node(CILNode::LdField {
+        addr: arg0,
+        field: x_fld,
+    });
+    let nan = asm.alloc_node(Const::F32(super::hashable::HashableF32(f32::NAN)));
+    let float = asm.alloc_node(Const::F32(super::hashable::HashableF32(0.1)));
+    let sum = asm.alloc_node(CILNode::BinOp(fld, float, BinOp::Add));
+    let big = asm.alloc_node(Const::I128(i128::MIN));
+    let cast = asm.alloc_node(CILNode::IntCast {
+        input: arg1,
+        target: Int::I128,
+        extend: ExtendKind::SignExtend,
+    });
+    let cmp = asm.alloc_node(CILNode::BinOp(cast, big, BinOp::Lt));
+    let tpe_idx = asm.alloc_type(Type::ClassRef(string_cref));
+    let null = asm.alloc_node(Const::Null(string_cref));
+    let isinst = asm.alloc_node(CILNode::IsInst(null, tpe_idx));
+    let msg = asm.alloc_string("tab\t\"quoted\"\n");
+    let ptr = asm.alloc_node(CILNode::PtrCast(arg0, Box::new(PtrCastRes::USize)));
+    let sfld_node = asm.alloc_node(CILNode::LdStaticField(sfld));
+    let i32_idx = asm.alloc_type(Type::Int(Int::I32));
+    let new_arr = asm.alloc_node(CILNode::NewArr {
+        elem: i32_idx,
+        len: arg1,
+    });
+    let roots = [
+        asm.alloc_root(CILRoot::Branch(Box::new((
+            2,
+            0,
+            Some(BranchCond::Lt(arg1, arg1, super::cilroot::CmpKind::Signed)),
+        )))),
+        asm.alloc_root(CILRoot::Pop(cmp)),
+        asm.alloc_root(CILRoot::Pop(isinst)),
+        asm.alloc_root(CILRoot::Pop(ptr)),
+        asm.alloc_root(CILRoot::StLoc(2, new_arr)),
+        asm.alloc_root(CILRoot::SetStaticField {
+            field: sfld,
+            val: sfld_node,
+        }),
+        asm.alloc_root(CILRoot::Ret(sum)),
+    ];
+    let handler_roots = vec![
+        asm.alloc_root(CILRoot::Pop(nan)),
+        asm.alloc_root(CILRoot::Unreachable(msg)),
+    ];
+    let unreachable = asm.alloc_root(CILRoot::Unreachable(msg));
+    let name = asm.alloc_string("get_x");
+    let arg_name = asm.alloc_string("self");
+    let local_tpe = asm.alloc_type(Type::Bool);
+    let i32_arr = asm.alloc_type(Type::PlatformArray {
+        elem: i32_idx,
+        dims: std::num::NonZeroU8::new(1).unwrap(),
+    });
+    let pinned_tpe = asm.alloc_type(Type::Pinned(i32_arr));
+    let get_x = asm.new_method(MethodDef::new(
+        Access::Extern,
+        main_module,
+        name,
+        sig,
+        super::cilnode::MethodKind::Static,
+        MethodImpl::MethodBody {
+            blocks: vec![
+                BasicBlock::new(
+                    roots.into(),
+                    0,
+                    Some(vec![BasicBlock::new(handler_roots, 1, None)]),
+                ),
+                BasicBlock::new(vec![unreachable], 2, None),
+            ],
+            locals: vec![
+                (Some(arg_name), local_tpe),
+                (None, local_tpe),
+                (None, pinned_tpe),
+            ],
+        },
+        vec![Some(arg_name), None],
+    ));
+    let name = asm.alloc_string("alias_x");
+    asm.new_method(MethodDef::new(
+        Access::Private,
+        main_module,
+        name,
+        sig,
+        super::cilnode::MethodKind::Static,
+        MethodImpl::AliasFor(*get_x),
+        vec![None, None],
+    ));
+    let name = asm.alloc_string("puts");
+    let lib = asm.alloc_string("libc");
+    let puts_sig = asm.sig([Type::Int(Int::I32)], Type::Void);
+    asm.new_method(MethodDef::new(
+        Access::Public,
+        main_module,
+        name,
+        puts_sig,
+        super::cilnode::MethodKind::Static,
+        MethodImpl::Extern {
+            lib,
+            preserve_errno: true,
+        },
+        vec![None],
+    ));
+    asm
 }
-fn escape_class_name(name: &str) -> String {
-    name.replace('.', "dot")
+#[test]
+fn roundtrip() {
+    let asm = sample_asm();
+    let text = print_assembly(&asm);
+    let parsed = parse_assembly(&text).unwrap_or_else(|err| panic!("{err}\n{text}"));
+    assert_eq!(print_assembly(&parsed), text);
+    assert_eq!(parsed.class_defs().len(), asm.class_defs().len());
+    // The NaN constant must keep its exact bits.
+    assert!(text.contains(&format!("#x{:x}", f32::NAN.to_bits())));
+    assert!(text.contains("(const f32 0.1)"));
+    assert!(text.contains("(pinned (array i32 1))"), "{text}");
+    assert!(text.contains("(newarr i32"), "{text}");
 }
-fn class_ref(cref: &ClassRef, asm: &Assembly) -> String {
-    let name = &asm[cref.name()];
-    let ref_asm = if let Some(ref_asm) = cref.asm() {
-        format!("Some(asm.alloc_string({:?}))", &asm[ref_asm])
-    } else {
-        "None".into()
-    };
-    let generics: String = cref
-        .generics()
-        .iter()
-        .map(|tpe| tpe_to(tpe, asm))
-        .intersperse(",".into())
-        .collect::<String>();
-    format!("{{let name = asm.alloc_string({name:?}); let ref_asm = {ref_asm};let generics = vec![{generics}].into(); let cref = ClassRef::new(name,ref_asm,{is_valuetype},generics);}}",is_valuetype = cref.is_valuetype())
+#[test]
+fn save_and_load() {
+    let asm = sample_asm();
+    let path = std::env::temp_dir().join(format!("cilly_ir_{}.cillyir", std::process::id()));
+    asm.save_cillyir(&path).unwrap();
+    let loaded = Assembly::load_cillyir(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(print_assembly(&loaded), print_assembly(&asm));
+}
+#[test]
+fn parse_errors() {
+    let err = parse_assembly("(cillyir 1)\n(class \"A\" public\n (method public static \"f\" (sig () void) (args) (body (locals) (block 0 (frobnicate)))))")
+        .err()
+        .unwrap();
+    assert_eq!(err.line(), 3);
+    assert!(err.msg().contains("frobnicate"), "{err}");
+    assert!(parse_assembly("(cillyir 2)").is_err());
+    assert!(parse_assembly("(cillyir 1)\n(class \"A\" public (field i33 \"x\"))").is_err());
 }
@@ -0,0 +1,725 @@
+//! Reads the textual cilly IR back into an [`Assembly`].
+use std::{
+    num::{NonZeroU32, NonZeroU8},
+    str::FromStr,
+};
+
+use super::{
+    sexpr::{parse_sexprs, ParseError, SExpr},
+    ACCESS_NAMES, BINOP_NAMES, CMP_KIND_NAMES, FLOAT_NAMES, FORMAT_VERSION, GENERIC_KIND_NAMES,
+    INT_NAMES, METHOD_KIND_NAMES,
+};
+use crate::v2::{
+    cilnode::{ExtendKind, PtrCastRes, UnOp},
+    cilroot::BranchCond,
+    hashable::{HashableF32, HashableF64},
+    method::LocalDef,
+    tpe::simd::{SIMDElem, SIMDVector},
+    Assembly, BasicBlock, CILNode, CILRoot, ClassDef, ClassRef, ClassRefIdx, Const, FieldDesc,
+    FieldIdx, FnSig, MethodDef, MethodImpl, MethodRef, MethodRefIdx, NodeIdx, RootIdx, SigIdx,
+    StaticFieldDesc, StaticFieldIdx, Type,
+};
+type Result<T> = std::result::Result<T, ParseError>;
+/// Looks up the value named `name` in one of the name tables.
+fn value_of<T: Clone>(table: &[(T, &'static str)], name: &str) -> Option<T> {
+    table
+        .iter()
+        .find(|(_, val)| *val == name)
+        .map(|(key, _)| key.clone())
+}
+/// A cursor over the elements of a list.
+struct Items<'a> {
+    head: &'a str,
+    items: std::slice::Iter<'a, SExpr>,
+    line: u32,
+}
+impl<'a> Items<'a> {
+    /// Opens `expr`, which must be a list starting with an atom.
+    fn new(expr: &'a SExpr, line: u32) -> Result<Self> {
+        let SExpr::List(items, list_line) = expr else {
+            return Err(ParseError::new(
+                line,
+                format!("expected a list, got `{expr}`"),
+            ));
+        };
+        let Some(SExpr::Atom(head)) = items.first() else {
+            return Err(ParseError::new(
+                *list_line,
+                format!("expected a list starting with an atom, got `{expr}`"),
+            ));
+        };
+        Ok(Self {
+            head,
+            items: items[1..].iter(),
+            line: *list_line,
+        })
+    }
+    /// Opens `expr`, which must be a list starting with `head`.
+    fn expect(expr: &'a SExpr, line: u32, head: &str) -> Result<Self> {
+        let items = Self::new(expr, line)?;
+        if items.head != head {
+            return Err(items.err(format!(
+                "expected `({head} ...)`, got `({} ...)`",
+                items.head
+            )));
+        }
+        Ok(items)
+    }
+    fn err(&self, msg: impl Into<String>) -> ParseError {
+        ParseError::new(self.line, msg)
+    }
+    fn next(&mut self) -> Result<&'a SExpr> {
+        let head = self.head;
+        self.items
+            .next()
+            .ok_or_else(|| self.err(format!("`{head}` is missing arguments")))
+    }
+    fn peek(&self) -> Option<&'a SExpr> {
+        self.items.clone().next()
+    }
+    fn atom(&mut self) -> Result<&'a str> {
+        match self.next()? {
+            SExpr::Atom(atom) => Ok(atom),
+            other => Err(self.err(format!("expected an atom, got `{other}`"))),
+        }
+    }
+    fn string(&mut self) -> Result<&'a str> {
+        match self.next()? {
+            SExpr::Str(string) => Ok(string),
+            other => Err(self.err(format!("expected a string, got `{other}`"))),
+        }
+    }
+    /// Reads either a string, or `_`, which stands for no string.
+    fn opt_string(&mut self) -> Result<Option<&'a str>> {
+        match self.next()? {
+            SExpr::Str(string) => Ok(Some(string)),
+            SExpr::Atom(atom) if atom == "_" => Ok(None),
+            other => Err(self.err(format!("expected a string or `_`, got `{other}`"))),
+        }
+    }
+    fn num<T: FromStr>(&mut self) -> Result<T> {
+        let atom = self.atom()?;
+        atom.parse()
+            .map_err(|_| self.err(format!("invalid number `{atom}`")))
+    }
+    /// Reads an atom, and looks it up in a name table.
+    fn named<T: Clone>(&mut self, table: &[(T, &'static str)]) -> Result<T> {
+        let atom = self.atom()?;
+        value_of(table, atom).ok_or_else(|| self.err(format!("unexpected `{atom}`")))
+    }
+    /// Consumes the next element, if it is the atom `flag`.
+    fn flag(&mut self, flag: &str) -> bool {
+        if matches!(self.peek(), Some(SExpr::Atom(atom)) if atom == flag) {
+            self.items.next();
+            true
+        } else {
+            false
+        }
+    }
+    /// Checks that all the elements were consumed.
+    fn finish(mut self) -> Result<()> {
+        match self.items.next() {
+            None => Ok(()),
+            Some(extra) => Err(self.err(format!("unexpected `{extra}` in `{}`", self.head))),
+        }
+    }
+    fn tpe(&mut self, asm: &mut Assembly) -> Result<Type> {
+        let line = self.line;
+        tpe(asm, self.next()?, line)
+    }
+    fn node(&mut self, asm: &mut Assembly) -> Result<NodeIdx> {
+        let line = self.line;
+        node(asm, self.next()?, line)
+    }
+    /// Reads all the remaining elements as nodes.
+    fn nodes(&mut self, asm: &mut Assembly) -> Result<Box<[NodeIdx]>> {
+        let line = self.line;
+        self.items
+            .by_ref()
+            .map(|arg| node(asm, arg, line))
+            .collect()
+    }
+    fn class_ref(&mut self, asm: &mut Assembly) -> Result<ClassRefIdx> {
+        let line = self.line;
+        class_ref(asm, self.next()?, line)
+    }
+    fn sig(&mut self, asm: &mut Assembly) -> Result<SigIdx> {
+        let line = self.line;
+        sig(asm, self.next()?, line)
+    }
+    fn method_ref(&mut self, asm: &mut Assembly) -> Result<MethodRefIdx> {
+        let line = self.line;
+        method_ref(asm, self.next()?, line)
+    }
+    fn field(&mut self, asm: &mut Assembly) -> Result<FieldIdx> {
+        let mut field = Items::expect(self.next()?, self.line, "fieldref")?;
+        let owner = field.class_ref(asm)?;
+        let name = asm.alloc_string(field.string()?);
+        let tpe = field.tpe(asm)?;
+        field.finish()?;
+        Ok(asm.alloc_field(FieldDesc::new(owner, name, tpe)))
+    }
+    fn static_field(&mut self, asm: &mut Assembly) -> Result<StaticFieldIdx> {
+        let mut field = Items::expect(self.next()?, self.line, "staticref")?;
+        let owner = field.class_ref(asm)?;
+        let name = asm.alloc_string(field.string()?);
+        let tpe = field.tpe(asm)?;
+        field.finish()?;
+        Ok(asm.alloc_sfld(StaticFieldDesc::new(owner, name, tpe)))
+    }
+    /// Reads an optional `(generics ...)` list.
+    fn generics(&mut self, asm: &mut Assembly) -> Result<Box<[Type]>> {
+        match self.peek() {
+            Some(expr) if expr.head() == Some("generics") => {
+                self.items.next();
+                let generics = Items::new(expr, self.line)?;
+                let line = generics.line;
+                generics
+                    .items
+                    .map(|generic| tpe(asm, generic, line))
+                    .collect()
+            }
+            _ => Ok([].into()),
+        }
+    }
+}
+fn tpe(asm: &mut Assembly, expr: &SExpr, line: u32) -> Result<Type> {
+    if let SExpr::Atom(atom) = expr {
+        if let Some(int) = value_of(INT_NAMES, atom) {
+            return Ok(Type::Int(int));
+        }
+        if let Some(float) = value_of(FLOAT_NAMES, atom) {
+            return Ok(Type::Float(float));
+        }
+        return match atom.as_str() {
+            "string" => Ok(Type::PlatformString),
+            "char" => Ok(Type::PlatformChar),
+            "object" => Ok(Type::PlatformObject),
+            "bool" => Ok(Type::Bool),
+            "void" => Ok(Type::Void),
+            _ => Err(ParseError::new(line, format!("unknown type `{atom}`"))),
+        };
+    }
+    if expr.head() == Some("cref") {
+        return Ok(Type::ClassRef(class_ref(asm, expr, line)?));
+    }
+    let mut items = Items::new(expr, line)?;
+    let res = match items.head {
+        "ptr" => {
+            let inner = items.tpe(asm)?;
+            Type::Ptr(asm.alloc_type(inner))
+        }
+        "ref" => {
+            let inner = items.tpe(asm)?;
+            Type::Ref(asm.alloc_type(inner))
+        }
+        "pinned" => {
+            let inner = items.tpe(asm)?;
+            Type::Pinned(asm.alloc_type(inner))
+        }
+        "generic" => Type::PlatformGeneric(items.num()?, items.named(GENERIC_KIND_NAMES)?),
+        "array" => {
+            let elem = items.tpe(asm)?;
+            let elem = asm.alloc_type(elem);
+            let dims: NonZeroU8 = items.num()?;
+            Type::PlatformArray { elem, dims }
+        }
+        "fnptr" => Type::FnPtr(items.sig(asm)?),
+        "simd" => {
+            let elem = items.atom()?;
+            let elem = value_of(INT_NAMES, elem)
+                .map(SIMDElem::Int)
+                .or_else(|| value_of(FLOAT_NAMES, elem).map(SIMDElem::Float))
+                .ok_or_else(|| items.err(format!("invalid SIMD element `{elem}`")))?;
+            Type::SIMDVector(SIMDVector::new(elem, items.num()?))
+        }
+        head => return Err(items.err(format!("unknown type `({head} ...)`"))),
+    };
+    items.finish()?;
+    Ok(res)
+}
+fn class_ref(asm: &mut Assembly, expr: &SExpr, line: u32) -> Result<ClassRefIdx> {
+    let mut items = Items::expect(expr, line, "cref")?;
+    let name = asm.alloc_string(items.string()?);
+    let is_valuetype = items.flag("valuetype");
+    let cref_asm = match items.peek() {
+        Some(expr) if expr.head() == Some("asm") => {
+            items.items.next();
+            let mut cref_asm = Items::new(expr, items.line)?;
+            let cref_asm_name = cref_asm.string()?;
+            cref_asm.finish()?;
+            Some(asm.alloc_string(cref_asm_name))
+        }
+        _ => None,
+    };
+    let generics = items.generics(asm)?;
+    items.finish()?;
+    Ok(asm.alloc_class_ref(ClassRef::new(name, cref_asm, is_valuetype, generics)))
+}
+fn sig(asm: &mut Assembly, expr: &SExpr, line: u32) -> Result<SigIdx> {
+    let mut items = Items::expect(expr, line, "sig")?;
+    let SExpr::List(inputs, _) = items.next()? else {
+        return Err(items.err("expected a list of argument types"));
+    };
+    let inputs = inputs
+        .iter()
+        .map(|input| tpe(asm, input, items.line))
+        .collect::<Result<Box<[_]>>>()?;
+    let output = items.tpe(asm)?;
+    items.finish()?;
+    Ok(asm.alloc_sig(FnSig::new(inputs, output)))
+}
+fn method_ref(asm: &mut Assembly, expr: &SExpr, line: u32) -> Result<MethodRefIdx> {
+    let mut items = Items::expect(expr, line, "methodref")?;
+    let class = items.class_ref(asm)?;
+    let name = asm.alloc_string(items.string()?);
+    let sig = items.sig(asm)?;
+    let kind = items.named(METHOD_KIND_NAMES)?;
+    let generics = items.generics(asm)?;
+    items.finish()?;
+    Ok(asm.alloc_methodref(MethodRef::new(class, name, sig, kind, generics)))
+}
+/// Reads a float, written either in decimal or as `#x` followed by its bits in hex.
+fn float<T: FromStr>(items: &mut Items, from_bits: impl Fn(u64) -> Option<T>) -> Result<T> {
+    let atom = items.atom()?;
+    let val = match atom.strip_prefix("#x") {
+        Some(bits) => u64::from_str_radix(bits, 16).ok().and_then(from_bits),
+        None => atom.parse().ok(),
+    };
+    val.ok_or_else(|| items.err(format!("invalid float `{atom}`")))
+}
+fn cst(asm: &mut Assembly, items: &mut Items) -> Result<Const> {
+    Ok(match items.atom()? {
+        "i8" => Const::I8(items.num()?),
+        "i16" => Const::I16(items.num()?),
+        "i32" => Const::I32(items.num()?),
+        "i64" => Const::I64(items.num()?),
+        "i128" => Const::I128(items.num()?),
+        "isize" => Const::ISize(items.num()?),
+        "u8" => Const::U8(items.num()?),
+        "u16" => Const::U16(items.num()?),
+        "u32" => Const::U32(items.num()?),
+        "u64" => Const::U64(items.num()?),
+        "u128" => Const::U128(items.num()?),
+        "usize" => Const::USize(items.num()?),
+        "bool" => Const::Bool(items.num()?),
+        "string" => Const::PlatformString(asm.alloc_string(items.string()?)),
+        "f32" => Const::F32(HashableF32(float(items, |bits| {
+            u32::try_from(bits).ok().map(f32::from_bits)
+        })?)),
+        "f64" => Const::F64(HashableF64(float(items, |bits| {
+            Some(f64::from_bits(bits))
+        })?)),
+        "null" => Const::Null(items.class_ref(asm)?),
+        other => return Err(items.err(format!("unknown constant type `{other}`"))),
+    })
+}
+fn node(asm: &mut Assembly, expr: &SExpr, line: u32) -> Result<NodeIdx> {
+    let mut items = Items::new(expr, line)?;
+    let node = match items.head {
+        "const" => CILNode::Const(Box::new(cst(asm, &mut items)?)),
+        "not" => CILNode::UnOp(items.node(asm)?, UnOp::Not),
+        "neg" => CILNode::UnOp(items.node(asm)?, UnOp::Neg),
+        "ldloc" => CILNode::LdLoc(items.num()?),
+        "ldloca" => CILNode::LdLocA(items.num()?),
+        "ldarg" => CILNode::LdArg(items.num()?),
+        "ldarga" => CILNode::LdArgA(items.num()?),
+        "call" => {
+            let mref = items.method_ref(asm)?;
+            CILNode::Call(Box::new((mref, items.nodes(asm)?)))
+        }
+        "intcast" => CILNode::IntCast {
+            input: items.node(asm)?,
+            target: items.named(INT_NAMES)?,
+            extend: match items.atom()? {
+                "zext" => ExtendKind::ZeroExtend,
+                "sext" => ExtendKind::SignExtend,
+                other => return Err(items.err(format!("expected `zext` or `sext`, got `{other}`"))),
+            },
+        },
+        "floatcast" => CILNode::FloatCast {
+            input: items.node(asm)?,
+            target: items.named(FLOAT_NAMES)?,
+            is_signed: match items.atom()? {
+                "signed" => true,
+                "unsigned" => false,
+                other => {
+                    return Err(items.err(format!("expected `signed` or `unsigned`, got `{other}`")))
+                }
+            },
+        },
+        "reftoptr" => CILNode::RefToPtr(items.node(asm)?),
+        "ptrcast" => {
+            let val = items.node(asm)?;
+            let res = match items.tpe(asm)? {
+                Type::Ptr(inner) => PtrCastRes::Ptr(inner),
+                Type::Ref(inner) => PtrCastRes::Ref(inner),
+                Type::FnPtr(sig) => PtrCastRes::FnPtr(sig),
+                Type::Int(crate::v2::Int::USize) => PtrCastRes::USize,
+                Type::Int(crate::v2::Int::ISize) => PtrCastRes::ISize,
+                _ => return Err(items.err("invalid pointer cast target")),
+            };
+            CILNode::PtrCast(val, Box::new(res))
+        }
+        "ldflda" => CILNode::LdFieldAdress {
+            addr: items.node(asm)?,
+            field: items.field(asm)?,
+        },
+        "ldfld" => CILNode::LdField {
+            addr: items.node(asm)?,
+            field: items.field(asm)?,
+        },
+        "ldind" => {
+            let addr = items.node(asm)?;
+            let tpe = items.tpe(asm)?;
+            CILNode::LdInd {
+                addr,
+                tpe: asm.alloc_type(tpe),
+                volatile: items.flag("volatile"),
+            }
+        }
+        "sizeof" => {
+            let tpe = items.tpe(asm)?;
+            CILNode::SizeOf(asm.alloc_type(tpe))
+        }
+        "get_exception" => CILNode::GetException,
+        "isinst" => {
+            let obj = items.node(asm)?;
+            let tpe = items.tpe(asm)?;
+            CILNode::IsInst(obj, asm.alloc_type(tpe))
+        }
+        "checked_cast" => {
+            let obj = items.node(asm)?;
+            let tpe = items.tpe(asm)?;
+            CILNode::CheckedCast(obj, asm.alloc_type(tpe))
+        }
+        "calli" => {
+            let ptr = items.node(asm)?;
+            let sig = items.sig(asm)?;
+            CILNode::CallI(Box::new((ptr, sig, items.nodes(asm)?)))
+        }
+        "localloc" => CILNode::LocAlloc {
+            size: items.node(asm)?,
+        },
+        "ldsfld" => CILNode::LdStaticField(items.static_field(asm)?),
+        "ldsflda" => CILNode::LdStaticFieldAdress(items.static_field(asm)?),
+        "ldftn" => CILNode::LdFtn(items.method_ref(asm)?),
+        "ldtypetoken" => {
+            let tpe = items.tpe(asm)?;
+            CILNode::LdTypeToken(asm.alloc_type(tpe))
+        }
+        "ldlen" => CILNode::LdLen(items.node(asm)?),
+        "localloc_aligned" => {
+            let tpe = items.tpe(asm)?;
+            CILNode::LocAllocAlgined {
+                tpe: asm.alloc_type(tpe),
+                align: items.num()?,
+            }
+        }
+        "ldelema" => CILNode::LdElelemRef {
+            array: items.node(asm)?,
+            index: items.node(asm)?,
+        },
+        "unbox_any" => {
+            let object = items.node(asm)?;
+            let tpe = items.tpe(asm)?;
+            CILNode::UnboxAny {
+                object,
+                tpe: asm.alloc_type(tpe),
+            }
+        }
+        "newarr" => {
+            let elem = items.tpe(asm)?;
+            CILNode::NewArr {
+                elem: asm.alloc_type(elem),
+                len: items.node(asm)?,
+            }
+        }
+        head => {
+            let Some(op) = value_of(BINOP_NAMES, head) else {
+                return Err(items.err(format!("unknown node `{head}`")));
+            };
+            CILNode::BinOp(items.node(asm)?, items.node(asm)?, op)
+        }
+    };
+    items.finish()?;
+    Ok(asm.alloc_node(node))
+}
+fn branch_cond(asm: &mut Assembly, expr: &SExpr, line: u32) -> Result<BranchCond> {
+    let mut items = Items::new(expr, line)?;
+    let cond = match items.head {
+        "true" => BranchCond::True(items.node(asm)?),
+        "false" => BranchCond::False(items.node(asm)?),
+        "eq" => BranchCond::Eq(items.node(asm)?, items.node(asm)?),
+        "ne" => BranchCond::Ne(items.node(asm)?, items.node(asm)?),
+        "lt" => BranchCond::Lt(
+            items.node(asm)?,
+            items.node(asm)?,
+            items.named(CMP_KIND_NAMES)?,
+        ),
+        "gt" => BranchCond::Gt(
+            items.node(asm)?,
+            items.node(asm)?,
+            items.named(CMP_KIND_NAMES)?,
+        ),
+        "le" => BranchCond::Le(
+            items.node(asm)?,
+            items.node(asm)?,
+            items.named(CMP_KIND_NAMES)?,
+        ),
+        "ge" => BranchCond::Ge(
+            items.node(asm)?,
+            items.node(asm)?,
+            items.named(CMP_KIND_NAMES)?,
+        ),
+        head => return Err(items.err(format!("unknown branch condition `{head}`"))),
+    };
+    items.finish()?;
+    Ok(cond)
+}
+fn root(asm: &mut Assembly, expr: &SExpr, line: u32) -> Result<RootIdx> {
+    let mut items = Items::new(expr, line)?;
+    let root = match items.head {
+        "stloc" => CILRoot::StLoc(items.num()?, items.node(asm)?),
+        "starg" => CILRoot::StArg(items.num()?, items.node(asm)?),
+        "ret" => CILRoot::Ret(items.node(asm)?),
+        "pop" => CILRoot::Pop(items.node(asm)?),
+        "throw" => CILRoot::Throw(items.node(asm)?),
+        "ret_void" => CILRoot::VoidRet,
+        "break" => CILRoot::Break,
+        "nop" => CILRoot::Nop,
+        "branch" => {
+            let target = items.num()?;
+            let sub_target = items.num()?;
+            let cond = match items.items.next() {
+                Some(cond) => Some(branch_cond(asm, cond, items.line)?),
+                None => None,
+            };
+            CILRoot::Branch(Box::new((target, sub_target, cond)))
+        }
+        "source_info" => CILRoot::SourceFileInfo {
+            file: asm.alloc_string(items.string()?),
+            line_start: items.num()?,
+            line_len: items.num()?,
+            col_start: items.num()?,
+            col_len: items.num()?,
+        },
+        "set_field" => CILRoot::SetField(Box::new((
+            items.field(asm)?,
+            items.node(asm)?,
+            items.node(asm)?,
+        ))),
+        "call" => {
+            let mref = items.method_ref(asm)?;
+            CILRoot::Call(Box::new((mref, items.nodes(asm)?)))
+        }
+        "stind" => CILRoot::StInd(Box::new((
+            items.node(asm)?,
+            items.node(asm)?,
+            items.tpe(asm)?,
+            items.flag("volatile"),
+        ))),
+        "initblk" => CILRoot::InitBlk(Box::new((
+            items.node(asm)?,
+            items.node(asm)?,
+            items.node(asm)?,
+        ))),
+        "cpblk" => CILRoot::CpBlk(Box::new((
+            items.node(asm)?,
+            items.node(asm)?,
+            items.node(asm)?,
+        ))),
+        "calli" => {
+            let ptr = items.node(asm)?;
+            let sig = items.sig(asm)?;
+            CILRoot::CallI(Box::new((ptr, sig, items.nodes(asm)?)))
+        }
+        "exit_special_region" => CILRoot::ExitSpecialRegion {
+            target: items.num()?,
+            source: items.num()?,
+        },
+        "rethrow" => CILRoot::ReThrow,
+        "set_static" => CILRoot::SetStaticField {
+            field: items.static_field(asm)?,
+            val: items.node(asm)?,
+        },
+        "cpobj" => {
+            let src = items.node(asm)?;
+            let dst = items.node(asm)?;
+            let tpe = items.tpe(asm)?;
+            CILRoot::CpObj {
+                src,
+                dst,
+                tpe: asm.alloc_type(tpe),
+            }
+        }
+        "unreachable" => CILRoot::Unreachable(asm.alloc_string(items.string()?)),
+        "initobj" => {
+            let addr = items.node(asm)?;
+            let tpe = items.tpe(asm)?;
+            CILRoot::InitObj(addr, asm.alloc_type(tpe))
+        }
+        head => return Err(items.err(format!("unknown root `{head}`"))),
+    };
+    items.finish()?;
+    Ok(asm.alloc_root(root))
+}
+fn block(asm: &mut Assembly, expr: &SExpr, line: u32) -> Result<BasicBlock> {
+    let mut items = Items::expect(expr, line, "block")?;
+    let block_id = items.num()?;
+    let mut roots = vec![];
+    let mut handler = None;
+    for expr in items.items.by_ref() {
+        if expr.head() == Some("handler") {
+            let handler_items = Items::new(expr, items.line)?;
+            let line = handler_items.line;
+            handler = Some(
+                handler_items
+                    .items
+                    .map(|block| self::block(asm, block, line))
+                    .collect::<Result<Vec<_>>>()?,
+            );
+            break;
+        }
+        roots.push(root(asm, expr, items.line)?);
+    }
+    items.finish()?;
+    Ok(BasicBlock::new(roots, block_id, handler))
+}
+fn local(asm: &mut Assembly, expr: &SExpr, line: u32) -> Result<LocalDef> {
+    let mut items = Items::expect(expr, line, "local")?;
+    let name = items.opt_string()?.map(|name| asm.alloc_string(name));
+    let tpe = items.tpe(asm)?;
+    items.finish()?;
+    Ok((name, asm.alloc_type(tpe)))
+}
+fn implementation(asm: &mut Assembly, expr: &SExpr, line: u32) -> Result<MethodImpl> {
+    let mut items = Items::new(expr, line)?;
+    let implementation = match items.head {
+        "body" => {
+            let locals = Items::expect(items.next()?, items.line, "locals")?;
+            let locals = locals
+                .items
+                .map(|loc| local(asm, loc, locals.line))
+                .collect::<Result<_>>()?;
+            let line = items.line;
+            let blocks = items
+                .items
+                .by_ref()
+                .map(|b| block(asm, b, line))
+                .collect::<Result<_>>()?;
+            MethodImpl::MethodBody { blocks, locals }
+        }
+        "extern" => MethodImpl::Extern {
+            lib: asm.alloc_string(items.string()?),
+            preserve_errno: items.flag("preserve_errno"),
+        },
+        "alias" => MethodImpl::AliasFor(items.method_ref(asm)?),
+        "missing" => MethodImpl::Missing,
+        head => return Err(items.err(format!("unknown method implementation `{head}`"))),
+    };
+    items.finish()?;
+    Ok(implementation)
+}
+fn class_def(asm: &mut Assembly, expr: &SExpr, line: u32) -> Result<()> {
+    let mut items = Items::expect(expr, line, "class")?;
+    let name = asm.alloc_string(items.string()?);
+    let access = items.named(ACCESS_NAMES)?;
+    let is_valuetype = items.flag("valuetype");
+    let mut generics = 0;
+    let mut extends = None;
+    let mut explict_size = None;
+    let mut align = None;
+    let mut fields = vec![];
+    let mut static_fields = vec![];
+    let mut methods = vec![];
+    for item in items.items.by_ref() {
+        let mut item = Items::new(item, items.line)?;
+        match item.head {
+            "generics" => generics = item.num()?,
+            "extends" => extends = Some(item.class_ref(asm)?),
+            "size" => explict_size = Some(item.num::<NonZeroU32>()?),
+            "align" => align = Some(item.num::<NonZeroU32>()?),
+            "field" => {
+                let tpe = item.tpe(asm)?;
+                let name = asm.alloc_string(item.string()?);
+                let offset = if item.peek().is_some() {
+                    Some(item.num()?)
+                } else {
+                    None
+                };
+                fields.push((tpe, name, offset));
+            }
+            "static" => {
+                let tpe = item.tpe(asm)?;
+                let name = asm.alloc_string(item.string()?);
+                static_fields.push((tpe, name, item.flag("thread_local")));
+            }
+            // Methods are added once the class exists.
+            "method" => {
+                methods.push(item);
+                continue;
+            }
+            head => return Err(item.err(format!("unknown class element `{head}`"))),
+        }
+        item.finish()?;
+    }
+    let class = asm.class_def(ClassDef::new(
+        name,
+        is_valuetype,
+        generics,
+        extends,
+        fields,
+        static_fields,
+        access,
+        explict_size,
+        align,
+    ));
+    for mut method in methods {
+        let access = method.named(ACCESS_NAMES)?;
+        let kind = method.named(METHOD_KIND_NAMES)?;
+        let name = asm.alloc_string(method.string()?);
+        let sig = method.sig(asm)?;
+        let mut args = Items::expect(method.next()?, method.line, "args")?;
+        let mut arg_names = vec![];
+        while args.peek().is_some() {
+            arg_names.push(args.opt_string()?.map(|name| asm.alloc_string(name)));
+        }
+        let line = method.line;
+        let implementation = implementation(asm, method.next()?, line)?;
+        method.finish()?;
+        asm.new_method(MethodDef::new(
+            access,
+            class,
+            name,
+            sig,
+            kind,
+            implementation,
+            arg_names,
+        ));
+    }
+    Ok(())
+}
+/// Parses an assembly from its textual cilly IR form.
+/// # Errors
+/// Returns an error if `src` is not valid cilly IR. The error contains the line the problem was found at.
+pub fn parse_assembly(src: &str) -> Result<Assembly> {
+    let exprs = parse_sexprs(src)?;
+    let mut exprs = exprs.iter();
+    let mut asm = Assembly::default();
+    let Some(version) = exprs.next() else {
+        return Err(ParseError::new(1, "missing `(cillyir version)` header"));
+    };
+    let mut version = Items::expect(version, 1, "cillyir")?;
+    let version_num: u32 = version.num()?;
+    if version_num != FORMAT_VERSION {
+        return Err(version.err(format!(
+            "unsupported cilly IR version {version_num}, expected {FORMAT_VERSION}"
+        )));
+    }
+    version.finish()?;
+    for expr in exprs {
+        class_def(&mut asm, expr, 0)?;
+    }
+    Ok(asm)
+}
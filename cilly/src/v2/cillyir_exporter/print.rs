@@ -0,0 +1,480 @@
+//! Turns an [`Assembly`] into its textual cilly IR form.
+use super::{
+    sexpr::SExpr, ACCESS_NAMES, BINOP_NAMES, CMP_KIND_NAMES, FLOAT_NAMES, FORMAT_VERSION,
+    GENERIC_KIND_NAMES, INT_NAMES, METHOD_KIND_NAMES,
+};
+use crate::v2::{
+    cilnode::{ExtendKind, UnOp},
+    cilroot::BranchCond,
+    hashable::{HashableF32, HashableF64},
+    method::LocalDef,
+    tpe::simd::SIMDElem,
+    Access, Assembly, BasicBlock, CILNode, CILRoot, ClassDef, ClassRefIdx, Const, FieldIdx,
+    MethodDef, MethodImpl, MethodRefIdx, NodeIdx, RootIdx, SigIdx, StaticFieldIdx, StringIdx, Type,
+};
+
+/// Looks up the name of `val` in one of the name tables.
+pub(super) fn name_of<T: PartialEq>(table: &[(T, &'static str)], val: &T) -> &'static str {
+    table
+        .iter()
+        .find(|(key, _)| key == val)
+        .map(|(_, name)| *name)
+        .expect("Value missing from a cilly IR name table")
+}
+fn num(val: impl ToString) -> SExpr {
+    SExpr::atom(val.to_string())
+}
+fn string(asm: &Assembly, idx: StringIdx) -> SExpr {
+    SExpr::Str(asm[idx].to_string())
+}
+fn access(access: Access) -> SExpr {
+    SExpr::atom(name_of(ACCESS_NAMES, &access))
+}
+/// Floats are printed in their shortest round-tripping decimal form, and non-finite floats as raw bits.
+fn float(val: impl std::fmt::Debug, is_finite: bool, bits: u64) -> SExpr {
+    if is_finite {
+        SExpr::atom(format!("{val:?}"))
+    } else {
+        SExpr::atom(format!("#x{bits:x}"))
+    }
+}
+pub(super) fn tpe(asm: &Assembly, tpe: Type) -> SExpr {
+    match tpe {
+        Type::Ptr(inner) => SExpr::list("ptr", [self::tpe(asm, asm[inner])]),
+        Type::Ref(inner) => SExpr::list("ref", [self::tpe(asm, asm[inner])]),
+        Type::Pinned(inner) => SExpr::list("pinned", [self::tpe(asm, asm[inner])]),
+        Type::Int(int) => SExpr::atom(name_of(INT_NAMES, &int)),
+        Type::ClassRef(cref) => class_ref(asm, cref),
+        Type::Float(float) => SExpr::atom(name_of(FLOAT_NAMES, &float)),
+        Type::PlatformString => SExpr::atom("string"),
+        Type::PlatformChar => SExpr::atom("char"),
+        Type::PlatformGeneric(idx, kind) => SExpr::list(
+            "generic",
+            [num(idx), SExpr::atom(name_of(GENERIC_KIND_NAMES, &kind))],
+        ),
+        Type::PlatformObject => SExpr::atom("object"),
+        Type::Bool => SExpr::atom("bool"),
+        Type::Void => SExpr::atom("void"),
+        Type::PlatformArray { elem, dims } => {
+            SExpr::list("array", [self::tpe(asm, asm[elem]), num(dims)])
+        }
+        Type::FnPtr(sig) => SExpr::list("fnptr", [self::sig(asm, sig)]),
+        Type::SIMDVector(vec) => {
+            let elem = match vec.elem() {
+                SIMDElem::Int(int) => name_of(INT_NAMES, &int),
+                SIMDElem::Float(float) => name_of(FLOAT_NAMES, &float),
+            };
+            SExpr::list("simd", [SExpr::atom(elem), num(vec.count())])
+        }
+    }
+}
+fn types(asm: &Assembly, types: &[Type]) -> SExpr {
+    SExpr::List(types.iter().map(|t| tpe(asm, *t)).collect(), 0)
+}
+pub(super) fn class_ref(asm: &Assembly, cref: ClassRefIdx) -> SExpr {
+    let cref = &asm[cref];
+    let mut items = vec![string(asm, cref.name())];
+    if cref.is_valuetype() {
+        items.push(SExpr::atom("valuetype"));
+    }
+    if let Some(cref_asm) = cref.asm() {
+        items.push(SExpr::list("asm", [string(asm, cref_asm)]));
+    }
+    if !cref.generics().is_empty() {
+        items.push(generics(asm, cref.generics()));
+    }
+    SExpr::list("cref", items)
+}
+fn generics(asm: &Assembly, generics: &[Type]) -> SExpr {
+    SExpr::list("generics", generics.iter().map(|t| tpe(asm, *t)))
+}
+pub(super) fn sig(asm: &Assembly, sig: SigIdx) -> SExpr {
+    let sig = &asm[sig];
+    SExpr::list("sig", [types(asm, sig.inputs()), tpe(asm, *sig.output())])
+}
+fn field(asm: &Assembly, field: FieldIdx) -> SExpr {
+    let field = asm[field];
+    SExpr::list(
+        "fieldref",
+        [
+            class_ref(asm, field.owner()),
+            string(asm, field.name()),
+            tpe(asm, field.tpe()),
+        ],
+    )
+}
+fn static_field(asm: &Assembly, field: StaticFieldIdx) -> SExpr {
+    let field = asm[field];
+    SExpr::list(
+        "staticref",
+        [
+            class_ref(asm, field.owner()),
+            string(asm, field.name()),
+            tpe(asm, field.tpe()),
+        ],
+    )
+}
+pub(super) fn method_ref(asm: &Assembly, mref: MethodRefIdx) -> SExpr {
+    let mref = &asm[mref];
+    let mut items = vec![
+        class_ref(asm, mref.class()),
+        string(asm, mref.name()),
+        sig(asm, mref.sig()),
+        SExpr::atom(name_of(METHOD_KIND_NAMES, &mref.kind())),
+    ];
+    if !mref.generics().is_empty() {
+        items.push(generics(asm, mref.generics()));
+    }
+    SExpr::list("methodref", items)
+}
+fn cst(asm: &Assembly, cst: &Const) -> SExpr {
+    let (tpe, val) = match cst {
+        Const::I8(val) => ("i8", num(val)),
+        Const::I16(val) => ("i16", num(val)),
+        Const::I32(val) => ("i32", num(val)),
+        Const::I64(val) => ("i64", num(val)),
+        Const::I128(val) => ("i128", num(val)),
+        Const::ISize(val) => ("isize", num(val)),
+        Const::U8(val) => ("u8", num(val)),
+        Const::U16(val) => ("u16", num(val)),
+        Const::U32(val) => ("u32", num(val)),
+        Const::U64(val) => ("u64", num(val)),
+        Const::U128(val) => ("u128", num(val)),
+        Const::USize(val) => ("usize", num(val)),
+        Const::PlatformString(val) => ("string", string(asm, *val)),
+        Const::Bool(val) => ("bool", num(val)),
+        Const::F32(HashableF32(val)) => {
+            ("f32", float(val, val.is_finite(), u64::from(val.to_bits())))
+        }
+        Const::F64(HashableF64(val)) => ("f64", float(val, val.is_finite(), val.to_bits())),
+        Const::Null(cref) => ("null", class_ref(asm, *cref)),
+    };
+    SExpr::list("const", [SExpr::atom(tpe), val])
+}
+fn call_like(
+    asm: &Assembly,
+    head: &str,
+    first: impl IntoIterator<Item = SExpr>,
+    args: &[NodeIdx],
+) -> SExpr {
+    SExpr::list(
+        head,
+        first
+            .into_iter()
+            .chain(args.iter().map(|arg| node(asm, *arg))),
+    )
+}
+pub(super) fn node(asm: &Assembly, node: NodeIdx) -> SExpr {
+    match &asm[node] {
+        CILNode::Const(val) => cst(asm, val),
+        CILNode::BinOp(lhs, rhs, op) => SExpr::list(
+            name_of(BINOP_NAMES, op),
+            [self::node(asm, *lhs), self::node(asm, *rhs)],
+        ),
+        CILNode::UnOp(val, op) => SExpr::list(
+            match op {
+                UnOp::Not => "not",
+                UnOp::Neg => "neg",
+            },
+            [self::node(asm, *val)],
+        ),
+        CILNode::LdLoc(loc) => SExpr::list("ldloc", [num(loc)]),
+        CILNode::LdLocA(loc) => SExpr::list("ldloca", [num(loc)]),
+        CILNode::LdArg(arg) => SExpr::list("ldarg", [num(arg)]),
+        CILNode::LdArgA(arg) => SExpr::list("ldarga", [num(arg)]),
+        CILNode::Call(info) => call_like(asm, "call", [method_ref(asm, info.0)], &info.1),
+        CILNode::IntCast {
+            input,
+            target,
+            extend,
+        } => SExpr::list(
+            "intcast",
+            [
+                self::node(asm, *input),
+                SExpr::atom(name_of(INT_NAMES, target)),
+                SExpr::atom(match extend {
+                    ExtendKind::ZeroExtend => "zext",
+                    ExtendKind::SignExtend => "sext",
+                }),
+            ],
+        ),
+        CILNode::FloatCast {
+            input,
+            target,
+            is_signed,
+        } => SExpr::list(
+            "floatcast",
+            [
+                self::node(asm, *input),
+                SExpr::atom(name_of(FLOAT_NAMES, target)),
+                SExpr::atom(if *is_signed { "signed" } else { "unsigned" }),
+            ],
+        ),
+        CILNode::RefToPtr(val) => SExpr::list("reftoptr", [self::node(asm, *val)]),
+        CILNode::PtrCast(val, res) => {
+            SExpr::list("ptrcast", [self::node(asm, *val), tpe(asm, res.as_type())])
+        }
+        CILNode::LdFieldAdress { addr, field } => {
+            SExpr::list("ldflda", [self::node(asm, *addr), self::field(asm, *field)])
+        }
+        CILNode::LdField { addr, field } => {
+            SExpr::list("ldfld", [self::node(asm, *addr), self::field(asm, *field)])
+        }
+        CILNode::LdInd {
+            addr,
+            tpe,
+            volatile,
+        } => SExpr::list(
+            "ldind",
+            [self::node(asm, *addr), self::tpe(asm, asm[*tpe])]
+                .into_iter()
+                .chain(volatile.then(|| SExpr::atom("volatile"))),
+        ),
+        CILNode::SizeOf(tpe) => SExpr::list("sizeof", [self::tpe(asm, asm[*tpe])]),
+        CILNode::GetException => SExpr::list("get_exception", []),
+        CILNode::IsInst(obj, tpe) => {
+            SExpr::list("isinst", [self::node(asm, *obj), self::tpe(asm, asm[*tpe])])
+        }
+        CILNode::CheckedCast(obj, tpe) => SExpr::list(
+            "checked_cast",
+            [self::node(asm, *obj), self::tpe(asm, asm[*tpe])],
+        ),
+        CILNode::CallI(info) => call_like(
+            asm,
+            "calli",
+            [self::node(asm, info.0), sig(asm, info.1)],
+            &info.2,
+        ),
+        CILNode::LocAlloc { size } => SExpr::list("localloc", [self::node(asm, *size)]),
+        CILNode::LdStaticField(field) => SExpr::list("ldsfld", [static_field(asm, *field)]),
+        CILNode::LdStaticFieldAdress(field) => SExpr::list("ldsflda", [static_field(asm, *field)]),
+        CILNode::LdFtn(mref) => SExpr::list("ldftn", [method_ref(asm, *mref)]),
+        CILNode::LdTypeToken(tpe) => SExpr::list("ldtypetoken", [self::tpe(asm, asm[*tpe])]),
+        CILNode::LdLen(arr) => SExpr::list("ldlen", [self::node(asm, *arr)]),
+        CILNode::LocAllocAlgined { tpe, align } => {
+            SExpr::list("localloc_aligned", [self::tpe(asm, asm[*tpe]), num(align)])
+        }
+        CILNode::LdElelemRef { array, index } => SExpr::list(
+            "ldelema",
+            [self::node(asm, *array), self::node(asm, *index)],
+        ),
+        CILNode::UnboxAny { object, tpe } => SExpr::list(
+            "unbox_any",
+            [self::node(asm, *object), self::tpe(asm, asm[*tpe])],
+        ),
+        CILNode::NewArr { elem, len } => SExpr::list(
+            "newarr",
+            [self::tpe(asm, asm[*elem]), self::node(asm, *len)],
+        ),
+    }
+}
+fn branch_cond(asm: &Assembly, cond: &BranchCond) -> SExpr {
+    let cmp = |head: &str, lhs: &NodeIdx, rhs: &NodeIdx, kind| {
+        SExpr::list(
+            head,
+            [
+                node(asm, *lhs),
+                node(asm, *rhs),
+                SExpr::atom(name_of(CMP_KIND_NAMES, kind)),
+            ],
+        )
+    };
+    match cond {
+        BranchCond::True(val) => SExpr::list("true", [node(asm, *val)]),
+        BranchCond::False(val) => SExpr::list("false", [node(asm, *val)]),
+        BranchCond::Eq(lhs, rhs) => SExpr::list("eq", [node(asm, *lhs), node(asm, *rhs)]),
+        BranchCond::Ne(lhs, rhs) => SExpr::list("ne", [node(asm, *lhs), node(asm, *rhs)]),
+        BranchCond::Lt(lhs, rhs, kind) => cmp("lt", lhs, rhs, kind),
+        BranchCond::Gt(lhs, rhs, kind) => cmp("gt", lhs, rhs, kind),
+        BranchCond::Le(lhs, rhs, kind) => cmp("le", lhs, rhs, kind),
+        BranchCond::Ge(lhs, rhs, kind) => cmp("ge", lhs, rhs, kind),
+    }
+}
+pub(super) fn root(asm: &Assembly, root: RootIdx) -> SExpr {
+    match &asm[root] {
+        CILRoot::StLoc(loc, val) => SExpr::list("stloc", [num(loc), node(asm, *val)]),
+        CILRoot::StArg(arg, val) => SExpr::list("starg", [num(arg), node(asm, *val)]),
+        CILRoot::Ret(val) => SExpr::list("ret", [node(asm, *val)]),
+        CILRoot::Pop(val) => SExpr::list("pop", [node(asm, *val)]),
+        CILRoot::Throw(val) => SExpr::list("throw", [node(asm, *val)]),
+        CILRoot::VoidRet => SExpr::list("ret_void", []),
+        CILRoot::Break => SExpr::list("break", []),
+        CILRoot::Nop => SExpr::list("nop", []),
+        CILRoot::Branch(info) => SExpr::list(
+            "branch",
+            [num(info.0), num(info.1)]
+                .into_iter()
+                .chain(info.2.as_ref().map(|cond| branch_cond(asm, cond))),
+        ),
+        CILRoot::SourceFileInfo {
+            line_start,
+            line_len,
+            col_start,
+            col_len,
+            file,
+        } => SExpr::list(
+            "source_info",
+            [
+                string(asm, *file),
+                num(line_start),
+                num(line_len),
+                num(col_start),
+                num(col_len),
+            ],
+        ),
+        CILRoot::SetField(info) => SExpr::list(
+            "set_field",
+            [field(asm, info.0), node(asm, info.1), node(asm, info.2)],
+        ),
+        CILRoot::Call(info) => call_like(asm, "call", [method_ref(asm, info.0)], &info.1),
+        CILRoot::StInd(info) => SExpr::list(
+            "stind",
+            [node(asm, info.0), node(asm, info.1), tpe(asm, info.2)]
+                .into_iter()
+                .chain(info.3.then(|| SExpr::atom("volatile"))),
+        ),
+        CILRoot::InitBlk(info) => SExpr::list(
+            "initblk",
+            [node(asm, info.0), node(asm, info.1), node(asm, info.2)],
+        ),
+        CILRoot::CpBlk(info) => SExpr::list(
+            "cpblk",
+            [node(asm, info.0), node(asm, info.1), node(asm, info.2)],
+        ),
+        CILRoot::CallI(info) => {
+            call_like(asm, "calli", [node(asm, info.0), sig(asm, info.1)], &info.2)
+        }
+        CILRoot::ExitSpecialRegion { target, source } => {
+            SExpr::list("exit_special_region", [num(target), num(source)])
+        }
+        CILRoot::ReThrow => SExpr::list("rethrow", []),
+        CILRoot::SetStaticField { field, val } => {
+            SExpr::list("set_static", [static_field(asm, *field), node(asm, *val)])
+        }
+        CILRoot::CpObj { src, dst, tpe } => SExpr::list(
+            "cpobj",
+            [node(asm, *src), node(asm, *dst), self::tpe(asm, asm[*tpe])],
+        ),
+        CILRoot::Unreachable(msg) => SExpr::list("unreachable", [string(asm, *msg)]),
+        CILRoot::InitObj(addr, tpe) => {
+            SExpr::list("initobj", [node(asm, *addr), self::tpe(asm, asm[*tpe])])
+        }
+    }
+}
+fn block(asm: &Assembly, block: &BasicBlock) -> SExpr {
+    SExpr::list(
+        "block",
+        std::iter::once(num(block.block_id()))
+            .chain(block.roots().iter().map(|root| self::root(asm, *root)))
+            .chain(block.handler().map(|handler| {
+                SExpr::list(
+                    "handler",
+                    handler.iter().map(|block| self::block(asm, block)),
+                )
+            })),
+    )
+}
+fn local(asm: &Assembly, (name, tpe): &LocalDef) -> SExpr {
+    let name = name.map_or_else(|| SExpr::atom("_"), |name| string(asm, name));
+    SExpr::list("local", [name, self::tpe(asm, asm[*tpe])])
+}
+fn method_def(asm: &Assembly, def: &MethodDef) -> SExpr {
+    let args = def
+        .arg_names()
+        .iter()
+        .map(|name| name.map_or_else(|| SExpr::atom("_"), |name| string(asm, name)));
+    let implementation = match def.implementation() {
+        MethodImpl::MethodBody { blocks, locals } => SExpr::list(
+            "body",
+            std::iter::once(SExpr::list(
+                "locals",
+                locals.iter().map(|loc| local(asm, loc)),
+            ))
+            .chain(blocks.iter().map(|b| block(asm, b))),
+        ),
+        MethodImpl::Extern {
+            lib,
+            preserve_errno,
+        } => SExpr::list(
+            "extern",
+            std::iter::once(string(asm, *lib))
+                .chain(preserve_errno.then(|| SExpr::atom("preserve_errno"))),
+        ),
+        MethodImpl::AliasFor(mref) => SExpr::list("alias", [method_ref(asm, *mref)]),
+        MethodImpl::Missing => SExpr::list("missing", []),
+    };
+    SExpr::list(
+        "method",
+        [
+            access(*def.access()),
+            SExpr::atom(name_of(METHOD_KIND_NAMES, &def.kind())),
+            string(asm, def.name()),
+            sig(asm, def.sig()),
+            SExpr::list("args", args),
+            implementation,
+        ],
+    )
+}
+pub(super) fn class_def(asm: &Assembly, def: &ClassDef) -> SExpr {
+    let mut items = vec![string(asm, def.name()), access(*def.access())];
+    if def.is_valuetype() {
+        items.push(SExpr::atom("valuetype"));
+    }
+    if def.generics() != 0 {
+        items.push(SExpr::list("generics", [num(def.generics())]));
+    }
+    if let Some(extends) = def.extends() {
+        items.push(SExpr::list("extends", [class_ref(asm, extends)]));
+    }
+    if let Some(size) = def.explict_size() {
+        items.push(SExpr::list("size", [num(size)]));
+    }
+    if let Some(align) = def.align() {
+        items.push(SExpr::list("align", [num(align)]));
+    }
+    items.extend(def.fields().iter().map(|(field_tpe, name, offset)| {
+        SExpr::list(
+            "field",
+            [tpe(asm, *field_tpe), string(asm, *name)]
+                .into_iter()
+                .chain(offset.map(num)),
+        )
+    }));
+    items.extend(
+        def.static_fields()
+            .iter()
+            .map(|(field_tpe, name, thread_local)| {
+                SExpr::list(
+                    "static",
+                    [tpe(asm, *field_tpe), string(asm, *name)]
+                        .into_iter()
+                        .chain(thread_local.then(|| SExpr::atom("thread_local"))),
+                )
+            }),
+    );
+    items.extend(
+        def.methods()
+            .iter()
+            .map(|method| method_def(asm, &asm[*method])),
+    );
+    SExpr::list("class", items)
+}
+/// Prints a single method definition.
+#[must_use]
+pub fn print_method(asm: &Assembly, def: &MethodDef) -> String {
+    let mut out = String::new();
+    method_def(asm, def).pretty(&mut out, 0);
+    out
+}
+/// Prints the whole assembly. Classes are sorted by name, so that the output does not depend on the order they were added in.
+#[must_use]
+pub fn print_assembly(asm: &Assembly) -> String {
+    let mut out = format!("; cilly IR\n(cillyir {FORMAT_VERSION})\n");
+    let mut defs: Vec<_> = asm.class_defs().values().collect();
+    defs.sort_by(|a, b| asm[a.name()].cmp(&asm[b.name()]));
+    for def in defs {
+        class_def(asm, def).pretty(&mut out, 0);
+        out.push('\n');
+    }
+    out
+}
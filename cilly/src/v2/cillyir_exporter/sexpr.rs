@@ -0,0 +1,236 @@
+//! The syntax layer of the textual cilly IR: a minimal S-expression reader and pretty printer.
+use std::fmt::{Display, Write};
+
+/// Lists with these heads are always broken into multiple lines, even if they would fit on one.
+const BLOCK_FORMS: &[&str] = &["class", "method", "body", "block", "handler"];
+/// Maximal width of a line, before the printer starts breaking lists up.
+const MAX_WIDTH: usize = 100;
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SExpr {
+    /// A bare word or number, like `i32`, `ldloc` or `-15`.
+    Atom(String),
+    /// A quoted string literal.
+    Str(String),
+    /// A parenthesised list, together with the line it started on(0 for lists which were not parsed).
+    List(Vec<SExpr>, u32),
+}
+/// An error encountered while reading the textual cilly IR.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    line: u32,
+    msg: String,
+}
+impl ParseError {
+    #[must_use]
+    pub fn new(line: u32, msg: impl Into<String>) -> Self {
+        Self {
+            line,
+            msg: msg.into(),
+        }
+    }
+    /// The line this error occurred at.
+    #[must_use]
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+    #[must_use]
+    pub fn msg(&self) -> &str {
+        &self.msg
+    }
+}
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.msg)
+    }
+}
+impl std::error::Error for ParseError {}
+impl SExpr {
+    #[must_use]
+    pub fn atom(atom: impl Into<String>) -> Self {
+        Self::Atom(atom.into())
+    }
+    /// Creates a list starting with the atom `head`.
+    #[must_use]
+    pub fn list(head: &str, items: impl IntoIterator<Item = SExpr>) -> Self {
+        Self::List(std::iter::once(Self::atom(head)).chain(items).collect(), 0)
+    }
+    /// Returns the head of this list, if it is a list starting with an atom.
+    #[must_use]
+    pub fn head(&self) -> Option<&str> {
+        match self {
+            Self::List(items, _) => match items.first() {
+                Some(Self::Atom(head)) => Some(head),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+    /// Writes this expression into `out`, breaking long lists into multiple, indented, lines.
+    pub fn pretty(&self, out: &mut String, indent: usize) {
+        let Self::List(items, _) = self else {
+            write!(out, "{self}").unwrap();
+            return;
+        };
+        let flat = self.to_string();
+        let forced = self.head().is_some_and(|head| BLOCK_FORMS.contains(&head));
+        if !forced && indent + flat.len() <= MAX_WIDTH {
+            out.push_str(&flat);
+            return;
+        }
+        // Leading atoms stay on the same line as the opening parenthesis.
+        let split = items
+            .iter()
+            .position(|item| matches!(item, Self::List(..)))
+            .unwrap_or(items.len());
+        out.push('(');
+        for (idx, item) in items[..split].iter().enumerate() {
+            if idx != 0 {
+                out.push(' ');
+            }
+            write!(out, "{item}").unwrap();
+        }
+        for item in &items[split..] {
+            out.push('\n');
+            out.extend(std::iter::repeat_n(' ', indent + 2));
+            item.pretty(out, indent + 2);
+        }
+        out.push(')');
+    }
+}
+impl Display for SExpr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Atom(atom) => write!(f, "{atom}"),
+            Self::Str(string) => {
+                f.write_char('"')?;
+                for c in string.chars() {
+                    match c {
+                        '"' => f.write_str("\\\"")?,
+                        '\\' => f.write_str("\\\\")?,
+                        '\n' => f.write_str("\\n")?,
+                        '\r' => f.write_str("\\r")?,
+                        '\t' => f.write_str("\\t")?,
+                        c if c.is_control() => write!(f, "\\u{{{:x}}}", c as u32)?,
+                        c => f.write_char(c)?,
+                    }
+                }
+                f.write_char('"')
+            }
+            Self::List(items, _) => {
+                f.write_char('(')?;
+                for (idx, item) in items.iter().enumerate() {
+                    if idx != 0 {
+                        f.write_char(' ')?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                f.write_char(')')
+            }
+        }
+    }
+}
+/// Reads all the top-level expressions in `src`. Comments start with `;` and end with the line.
+/// ```
+/// # use cilly::v2::cillyir_exporter::sexpr::*;
+/// let exprs = parse_sexprs("(ldloc 0) ; comment\n\"str\"").unwrap();
+/// assert_eq!(exprs[0].head(), Some("ldloc"));
+/// assert_eq!(exprs[1], SExpr::Str("str".into()));
+/// assert_eq!(parse_sexprs("\n(ldloc 0").unwrap_err().line(), 2);
+/// ```
+pub fn parse_sexprs(src: &str) -> Result<Vec<SExpr>, ParseError> {
+    let mut chars = src.chars().peekable();
+    let mut line = 1;
+    // Stack of the currently open lists, and the lines they were opened at.
+    let mut stack: Vec<(Vec<SExpr>, u32)> = vec![(vec![], 0)];
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            c if c.is_whitespace() => (),
+            ';' => while chars.next_if(|c| *c != '\n').is_some() {},
+            '(' => stack.push((vec![], line)),
+            ')' => {
+                if stack.len() == 1 {
+                    return Err(ParseError::new(line, "unexpected `)`"));
+                }
+                let (items, start) = stack.pop().unwrap();
+                stack.last_mut().unwrap().0.push(SExpr::List(items, start));
+            }
+            '"' => {
+                let start = line;
+                let mut string = String::new();
+                loop {
+                    match chars.next() {
+                        None => return Err(ParseError::new(start, "unterminated string")),
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('"') => string.push('"'),
+                            Some('\\') => string.push('\\'),
+                            Some('n') => string.push('\n'),
+                            Some('r') => string.push('\r'),
+                            Some('t') => string.push('\t'),
+                            Some('u') => {
+                                if chars.next() != Some('{') {
+                                    return Err(ParseError::new(line, "expected `{` after `\\u`"));
+                                }
+                                let mut hex = String::new();
+                                while let Some(c) = chars.next_if(|c| *c != '}') {
+                                    hex.push(c);
+                                }
+                                chars.next();
+                                let escaped = u32::from_str_radix(&hex, 16)
+                                    .ok()
+                                    .and_then(char::from_u32)
+                                    .ok_or_else(|| {
+                                        ParseError::new(line, format!("invalid escape {hex:?}"))
+                                    })?;
+                                string.push(escaped);
+                            }
+                            other => {
+                                return Err(ParseError::new(
+                                    line,
+                                    format!("invalid escape {other:?}"),
+                                ))
+                            }
+                        },
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            string.push(c);
+                        }
+                    }
+                }
+                stack.last_mut().unwrap().0.push(SExpr::Str(string));
+            }
+            c => {
+                let mut atom = String::from(c);
+                while let Some(c) =
+                    chars.next_if(|c| !c.is_whitespace() && !matches!(c, '(' | ')' | '"' | ';'))
+                {
+                    atom.push(c);
+                }
+                stack.last_mut().unwrap().0.push(SExpr::Atom(atom));
+            }
+        }
+    }
+    if stack.len() != 1 {
+        let (_, start) = stack.pop().unwrap();
+        return Err(ParseError::new(start, "unclosed `(`"));
+    }
+    Ok(stack.pop().unwrap().0)
+}
+#[test]
+fn pretty_roundtrip() {
+    let expr = SExpr::list(
+        "block",
+        [
+            SExpr::atom("0"),
+            SExpr::list("unreachable", [SExpr::Str("a\"b\\c\n\u{1}ż".into())]),
+        ],
+    );
+    let mut out = String::new();
+    expr.pretty(&mut out, 0);
+    assert_eq!(out, "(block 0\n  (unreachable \"a\\\"b\\\\c\\n\\u{1}ż\"))");
+    let parsed = parse_sexprs(&out).unwrap();
+    assert_eq!(parsed[0].to_string(), expr.to_string());
+}
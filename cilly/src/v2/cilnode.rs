@@ -97,6 +97,11 @@ pub enum CILNode {
         object: NodeIdx,
         tpe: TypeIdx,
     },
+    /// Allocates a new, zero-initialized, one-dimensional managed array(`newarr`), with `len` elements of type `elem`.
+    NewArr {
+        elem: TypeIdx,
+        len: NodeIdx,
+    },
 }
 #[derive(Hash, PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
 pub enum PtrCastRes {
@@ -297,7 +302,8 @@ impl CILNode {
             }
             | CILNode::UnboxAny {
                 object: node_idx, ..
-            } => vec![*node_idx],
+            }
+            | CILNode::NewArr { len: node_idx, .. } => vec![*node_idx],
             CILNode::BinOp(lhs, rhs, _) => vec![*lhs, *rhs],
             CILNode::Call(info) => {
                 let (_, args) = info.as_ref();
@@ -1017,6 +1023,14 @@ impl CILNode {
                 };
                 map(node, asm)
             }
+            CILNode::NewArr { elem, len } => {
+                let len = asm.get_node(len).clone().map(asm, map);
+                let node = CILNode::NewArr {
+                    elem,
+                    len: asm.alloc_node(len),
+                };
+                map(node, asm)
+            }
         }
     }
 }
@@ -47,49 +47,6 @@ impl Const {
             Const::Null(tpe) => Type::ClassRef(*tpe),
         }
     }
-
-    pub(crate) fn is_zero(&self) -> bool {
-        match self {
-            Const::I8(val) => *val == 0,
-            Const::I16(val) => *val == 0,
-            Const::I32(val) => *val == 0,
-            Const::I64(val) => *val == 0,
-            Const::I128(val) => *val == 0,
-            Const::ISize(val) => *val == 0,
-            Const::U8(val) => *val == 0,
-            Const::U16(val) => *val == 0,
-            Const::U32(val) => *val == 0,
-            Const::U64(val) => *val == 0,
-            Const::U128(val) => *val == 0,
-            Const::USize(val) => *val == 0,
-            Const::PlatformString(_) => false,
-            Const::Bool(_) => false,
-            Const::F32(val) => **val == 0.0,
-            Const::F64(val) => **val == 0.0,
-            Const::Null(_) => true,
-        }
-    }
-    pub(crate) fn is_one(&self) -> bool {
-        match self {
-            Const::I8(val) => *val == 1,
-            Const::I16(val) => *val == 1,
-            Const::I32(val) => *val == 1,
-            Const::I64(val) => *val == 1,
-            Const::I128(val) => *val == 1,
-            Const::ISize(val) => *val == 1,
-            Const::U8(val) => *val == 1,
-            Const::U16(val) => *val == 1,
-            Const::U32(val) => *val == 1,
-            Const::U64(val) => *val == 1,
-            Const::U128(val) => *val == 1,
-            Const::USize(val) => *val == 1,
-            Const::PlatformString(_) => false,
-            Const::Bool(_) => false,
-            Const::F32(val) => **val == 1.1,
-            Const::F64(val) => **val == 1.1,
-            Const::Null(_) => true,
-        }
-    }
 }
 
 impl From<Const> for CILNode {
@@ -124,3 +81,23 @@ const_impl! {i32, I32}
 const_impl! {i64, I64}
 const_impl! {i128, I128}
 const_impl! {isize, ISize}
+impl From<f32> for Const {
+    fn from(value: f32) -> Self {
+        Const::F32(HashableF32(value))
+    }
+}
+impl From<f32> for CILNode {
+    fn from(value: f32) -> Self {
+        Const::from(value).into()
+    }
+}
+impl From<f64> for Const {
+    fn from(value: f64) -> Self {
+        Const::F64(HashableF64(value))
+    }
+}
+impl From<f64> for CILNode {
+    fn from(value: f64) -> Self {
+        Const::from(value).into()
+    }
+}
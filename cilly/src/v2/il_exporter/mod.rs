@@ -140,14 +140,16 @@ impl ILExporter {
                     })
                     .intersperse(",".to_string())
                     .collect();
-                let preservesig = if method.implementation().is_extern() {
+                let impl_attrs = if method.implementation().is_extern() {
                     "preservesig"
+                } else if method.no_inlining() {
+                    "noinlining"
                 } else {
                     ""
                 };
                 writeln!(
                     out,
-                    ".method {vis} hidebysig {kind} {pinvoke} {ret} '{name}'({inputs}) cil managed {preservesig}{{// Method ID {method_id:?}"
+                    ".method {vis} hidebysig {kind} {pinvoke} {ret} '{name}'({inputs}) cil managed {impl_attrs}{{// Method ID {method_id:?}"
                 )?;
                 debug_assert!(ensure_unqiue.insert(*method_id));
                 let stack_size = match method.resolved_implementation(asm_mut) {
@@ -562,8 +564,11 @@ impl ILExporter {
                 volatile: volitale,
             } => {
                 self.export_node(asm, out, addr, sig, locals)?;
-                let tpe = asm[tpe];
-
+                let mut tpe = asm[tpe];
+                // Pinning only affects locals, so a pinned value is loaded like the value it pins.
+                while let Type::Pinned(inner) = tpe {
+                    tpe = asm[inner];
+                }
                 match (tpe, volitale) {
                     (Type::Ptr(_), true) => writeln!(out, "volatile. ldind.i"),
                     (Type::Ptr(_), false) => writeln!(out, "ldind.i"),
@@ -654,6 +659,7 @@ impl ILExporter {
                     (Type::SIMDVector(_), true) => {
                         writeln!(out, "volatile. ldobj {}", type_il(&tpe, asm))
                     }
+                    (Type::Pinned(_), _) => unreachable!("Pinned types are unwrapped above"),
                     (Type::SIMDVector(_), false) => {
                         writeln!(out, "ldobj {}", type_il(&tpe, asm))
                     }
@@ -759,6 +765,10 @@ impl ILExporter {
                 self.export_node(asm, out, object, sig, locals)?;
                 writeln!(out, "unbox.any {object}", object = type_il(&asm[tpe], asm))
             }
+            CILNode::NewArr { elem, len } => {
+                self.export_node(asm, out, len, sig, locals)?;
+                writeln!(out, "newarr {elem}", elem = type_il(&asm[elem], asm))
+            }
         }
     }
     #[allow(clippy::too_many_arguments)]
@@ -1091,7 +1101,11 @@ impl ILExporter {
                 self.export_node(asm, out, stind.0, sig, locals)?;
                 self.export_node(asm, out, stind.1, sig, locals)?;
 
-                let tpe = stind.2;
+                let mut tpe = stind.2;
+                // Pinning only affects locals, so a pinned value is stored like the value it pins.
+                while let Type::Pinned(inner) = tpe {
+                    tpe = asm[inner];
+                }
                 let is_volitale = if stind.3 { "volatile." } else { "" };
                 match tpe {
                     Type::Ptr(_) => writeln!(out, "{is_volitale} stind.i"),
@@ -1142,6 +1156,7 @@ impl ILExporter {
                     Type::PlatformArray { .. } => writeln!(out, "{is_volitale} stind.ref"),
                     Type::FnPtr(_) => writeln!(out, "{is_volitale} stind.i"),
                     Type::SIMDVector(_)=>writeln!(out, "stobj {}", type_il(&tpe, asm)),
+                    Type::Pinned(_) => unreachable!("Pinned types are unwrapped above"),
                 }
             }
             super::CILRoot::InitBlk(blk) => {
@@ -1373,6 +1388,7 @@ fn type_il(tpe: &Type, asm: &Assembly) -> String {
         }
         Type::Ptr(inner) => format!("{}*", type_il(&asm[*inner], asm)),
         Type::Ref(inner) => format!("{}&", type_il(&asm[*inner], asm)),
+        Type::Pinned(inner) => format!("{} pinned", type_il(&asm[*inner], asm)),
         Type::Int(int) => match int {
             super::Int::U8 => "uint8".into(),
             super::Int::U16 => "uint16".into(),
@@ -1464,3 +1480,98 @@ static RUNTIME_CONFIG: std::sync::LazyLock<String> = std::sync::LazyLock::new(||
       }}"
     )
 });
+#[test]
+fn extern_items_are_public() {
+    use super::{cilnode::MethodKind, Access, BasicBlock, ClassDef, Float, MethodDef};
+    // Exported items are `Access::Extern`, and must be visible to other .NET assemblies under their own names.
+    let mut asm = Assembly::default();
+    let class_name = asm.alloc_string("Acme.Geo.Mesh");
+    let class = asm.class_def(ClassDef::new(
+        class_name,
+        false,
+        0,
+        None,
+        vec![],
+        vec![],
+        Access::Extern,
+        None,
+        None,
+    ));
+    let sig = asm.sig(
+        [Type::Int(Int::U32), Type::Float(Float::F32)],
+        Type::Int(Int::U32),
+    );
+    let arg = asm.alloc_node(CILNode::LdArg(0));
+    let ret = asm.alloc_root(super::CILRoot::Ret(arg));
+    let name = asm.alloc_string("SimplifyMesh");
+    let arg_names = vec![
+        Some(asm.alloc_string("vertex_count")),
+        Some(asm.alloc_string("tolerance")),
+    ];
+    asm.new_method(MethodDef::new(
+        Access::Extern,
+        class,
+        name,
+        sig,
+        MethodKind::Static,
+        MethodImpl::MethodBody {
+            blocks: vec![BasicBlock::new(vec![ret], 0, None)],
+            locals: vec![],
+        },
+        arg_names,
+    ));
+    let mut out = Vec::new();
+    ILExporter::new(IlasmFlavour::Modern, true)
+        .export_to_write(&asm, &mut out)
+        .unwrap();
+    let il = String::from_utf8(out).unwrap();
+    assert!(
+        il.contains(".class public ansi  auto 'Acme.Geo.Mesh'"),
+        "{il}"
+    );
+    assert!(
+        il.contains(".method public hidebysig static  uint32 'SimplifyMesh'(uint32 'vertex_count',float32 'tolerance')"),
+        "{il}"
+    );
+}
+#[test]
+fn pinned_ind() {
+    use super::{cilnode::MethodKind, Access, BasicBlock, CILRoot, Const, MethodDef};
+    // Values behind pointers to pinned types are read and written like the values they pin.
+    let mut asm = Assembly::default();
+    let main_module = asm.main_module();
+    let i32_idx = asm.alloc_type(Type::Int(Int::I32));
+    let pinned = Type::Pinned(i32_idx);
+    let ptr = asm.nptr(pinned);
+    let sig = asm.sig([ptr], Type::Int(Int::I32));
+    let addr = asm.alloc_node(CILNode::LdArg(0));
+    let val = asm.alloc_node(Const::I32(7));
+    let st = asm.alloc_root(CILRoot::StInd(Box::new((addr, val, pinned, false))));
+    let pinned_idx = asm.alloc_type(pinned);
+    let ld = asm.alloc_node(CILNode::LdInd {
+        addr,
+        tpe: pinned_idx,
+        volatile: false,
+    });
+    let ret = asm.alloc_root(CILRoot::Ret(ld));
+    let name = asm.alloc_string("pinned_ind");
+    asm.new_method(MethodDef::new(
+        Access::Extern,
+        main_module,
+        name,
+        sig,
+        MethodKind::Static,
+        MethodImpl::MethodBody {
+            blocks: vec![BasicBlock::new(vec![st, ret], 0, None)],
+            locals: vec![],
+        },
+        vec![None],
+    ));
+    let mut out = Vec::new();
+    ILExporter::new(IlasmFlavour::Modern, true)
+        .export_to_write(&asm, &mut out)
+        .unwrap();
+    let il = String::from_utf8(out).unwrap();
+    assert!(il.contains("stind.i4"), "{il}");
+    assert!(il.contains("ldind.i4"), "{il}");
+}
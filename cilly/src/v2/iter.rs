@@ -175,7 +175,8 @@ impl Iterator for CILIter<'_> {
                     | CILNode::IsInst(val, _)
                     | CILNode::CheckedCast(val, _)
                     | CILNode::LocAlloc { size: val }
-                    | CILNode::UnboxAny { object: val, .. },
+                    | CILNode::UnboxAny { object: val, .. }
+                    | CILNode::NewArr { len: val, .. },
                 )
                 | CILIterElem::Root(
                     CILRoot::StLoc(_, val)
@@ -492,6 +493,7 @@ impl<'this, T: Iterator<Item = CILIterElem> + 'this> TpeIter<'this> for T {
                     | CILNode::CheckedCast(_, tpe)
                     | CILNode::LdTypeToken(tpe)
                     | CILNode::UnboxAny { tpe, .. }
+                    | CILNode::NewArr { elem: tpe, .. }
                     | CILNode::LocAllocAlgined { tpe, .. } => {
                         Some(Box::new(std::iter::once(asm[tpe])))
                     }
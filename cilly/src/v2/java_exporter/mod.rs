@@ -182,6 +182,7 @@ fn type_string(tpe: Type, asm: &Assembly) -> String {
         ),
         Type::FnPtr(_) => "J".into(),
         Type::SIMDVector(_) => panic!("SMID is not supported in Java"),
+        Type::Pinned(inner) => type_string(asm[inner], asm),
     }
 }
 /*
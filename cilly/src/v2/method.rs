@@ -146,6 +146,7 @@ pub struct MethodDef {
     arg_names: Vec<Option<StringIdx>>,
     kind: MethodKind,
     implementation: MethodImpl,
+    no_inlining: bool,
 }
 
 impl MethodDef {
@@ -214,6 +215,7 @@ impl MethodDef {
             arg_names,
             kind,
             implementation,
+            no_inlining: false,
         }
     }
 
@@ -353,6 +355,17 @@ impl MethodDef {
         self.access = access;
     }
 
+    /// Returns true if this method must never be inlined (`NoInlining` in .NET, `#[inline(never)]` in Rust).
+    #[must_use]
+    pub fn no_inlining(&self) -> bool {
+        self.no_inlining
+    }
+
+    /// Marks this method as `NoInlining`, which forbids both cilly and the .NET JIT from inlining it.
+    pub fn set_no_inlining(&mut self, no_inlining: bool) {
+        self.no_inlining = no_inlining;
+    }
+
     pub fn stack_inputs(&self, asm: &mut Assembly) -> Vec<(Type, Option<StringIdx>)> {
         let mut arg_names = self.arg_names().to_vec();
         let sig = asm[self.sig()].clone();
@@ -47,6 +47,7 @@ pub mod macros;
 pub mod method;
 pub mod method_builder;
 pub mod opt;
+pub mod reduce;
 pub mod strings;
 pub mod tpe;
 pub mod typecheck;
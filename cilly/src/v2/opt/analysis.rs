@@ -0,0 +1,913 @@
+//! Control-flow and dataflow analyses over the blocks of a method body.
+//!
+//! The control flow graph contains every block of a method, including the blocks of exception handlers.
+//! Normal edges come from branches, jumps out of protected regions and fall-through into the next block of the same list.
+//! A protected block also has an exceptional edge to the first block of its handler: that edge may be taken before any
+//! root of the block has been executed, and the dataflow analyses account for that.
+//!
+//! Analyses are cheap to request repeatedly through an [`AnalysisCache`], which recomputes them only after the blocks
+//! it is asked about have changed.
+use std::{
+    hash::{Hash, Hasher},
+    rc::Rc,
+};
+
+use fxhash::{FxHashMap, FxHashSet, FxHasher};
+
+use crate::v2::{
+    basic_block::BlockId, Assembly, BasicBlock, CILIter, CILIterElem, CILNode, CILRoot,
+};
+
+/// Dense index of a block in a [`Cfg`]. The entry block is always `0`.
+pub type CfgNode = usize;
+/// Identifies a block of a method body, the same way a branch does.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct BlockKey {
+    /// The protected block whose handler contains this block, or `None` for top-level blocks.
+    pub owner: Option<BlockId>,
+    pub id: BlockId,
+}
+impl BlockKey {
+    /// Key of a top-level block.
+    #[must_use]
+    pub fn top(id: BlockId) -> Self {
+        Self { owner: None, id }
+    }
+    /// Key of the block targeted by a branch to `target`, `sub_target`.
+    /// ```
+    /// # use cilly::v2::opt::analysis::BlockKey;
+    /// assert_eq!(BlockKey::from_jump(3, 0), BlockKey::top(3));
+    /// assert_eq!(BlockKey::from_jump(3, 5).owner, Some(3));
+    /// ```
+    #[must_use]
+    pub fn from_jump(target: BlockId, sub_target: BlockId) -> Self {
+        if sub_target == 0 {
+            Self::top(target)
+        } else {
+            Self {
+                owner: Some(target),
+                id: sub_target,
+            }
+        }
+    }
+}
+/// Where a root transfers control to, as far as the analyses are concerned.
+enum Flow {
+    /// Control continues with the next root.
+    Next,
+    /// Control may continue at the target, or with the next root.
+    Cond(BlockKey),
+    /// Control always continues at the target.
+    Jump(BlockKey),
+    /// Control leaves the method.
+    Exit,
+}
+fn root_flow(root: &CILRoot) -> Flow {
+    match root {
+        CILRoot::Branch(info) => {
+            let (target, sub_target, cond) = info.as_ref();
+            let key = BlockKey::from_jump(*target, *sub_target);
+            if cond.is_some() {
+                Flow::Cond(key)
+            } else {
+                Flow::Jump(key)
+            }
+        }
+        CILRoot::ExitSpecialRegion { target, .. } => Flow::Jump(BlockKey::top(*target)),
+        CILRoot::Ret(_)
+        | CILRoot::VoidRet
+        | CILRoot::Throw(_)
+        | CILRoot::ReThrow
+        | CILRoot::Unreachable(_) => Flow::Exit,
+        _ => Flow::Next,
+    }
+}
+/// The control flow graph of a method body.
+#[derive(Debug)]
+pub struct Cfg {
+    keys: Vec<BlockKey>,
+    index: FxHashMap<BlockKey, CfgNode>,
+    roots: Vec<Box<[crate::v2::RootIdx]>>,
+    succs: Vec<Vec<CfgNode>>,
+    preds: Vec<Vec<CfgNode>>,
+    handlers: Vec<Option<CfgNode>>,
+    fallthrough: Vec<Option<CfgNode>>,
+    rpo: Vec<CfgNode>,
+}
+impl Cfg {
+    /// Builds the control flow graph of `blocks`. The first block is the entry.
+    #[must_use]
+    pub fn new(blocks: &[BasicBlock], asm: &Assembly) -> Self {
+        let mut cfg = Self {
+            keys: vec![],
+            index: FxHashMap::default(),
+            roots: vec![],
+            succs: vec![],
+            preds: vec![],
+            handlers: vec![],
+            fallthrough: vec![],
+            rpo: vec![],
+        };
+        let top = cfg.add_blocks(blocks, None);
+        for (block, node) in blocks.iter().zip(top) {
+            if let Some(handler) = block.handler() {
+                let handler = cfg.add_blocks(handler, Some(block.block_id()));
+                cfg.handlers[node] = handler.first().copied();
+            }
+        }
+        for node in 0..cfg.len() {
+            let mut succs = vec![];
+            let mut falls_through = true;
+            for root in cfg.roots[node].iter() {
+                match root_flow(&asm[*root]) {
+                    Flow::Next => continue,
+                    Flow::Cond(key) => succs.extend(cfg.index.get(&key).copied()),
+                    Flow::Jump(key) => {
+                        succs.extend(cfg.index.get(&key).copied());
+                        falls_through = false;
+                        break;
+                    }
+                    Flow::Exit => {
+                        falls_through = false;
+                        break;
+                    }
+                }
+            }
+            if falls_through {
+                succs.extend(cfg.fallthrough[node]);
+            }
+            let mut unique = Vec::with_capacity(succs.len());
+            for succ in succs {
+                if !unique.contains(&succ) {
+                    unique.push(succ);
+                }
+            }
+            cfg.succs[node] = unique;
+        }
+        for node in 0..cfg.len() {
+            for succ in cfg.all_succs(node).collect::<Vec<_>>() {
+                if !cfg.preds[succ].contains(&node) {
+                    cfg.preds[succ].push(node);
+                }
+            }
+        }
+        cfg.rpo = cfg.compute_rpo();
+        cfg
+    }
+    fn add_blocks(&mut self, blocks: &[BasicBlock], owner: Option<BlockId>) -> Vec<CfgNode> {
+        let first = self.len();
+        for (idx, block) in blocks.iter().enumerate() {
+            let key = BlockKey {
+                owner,
+                id: block.block_id(),
+            };
+            let node = self.keys.len();
+            self.keys.push(key);
+            self.index.insert(key, node);
+            self.roots.push(block.roots().into());
+            self.succs.push(vec![]);
+            self.preds.push(vec![]);
+            self.handlers.push(None);
+            self.fallthrough
+                .push((idx + 1 < blocks.len()).then_some(node + 1));
+        }
+        (first..self.len()).collect()
+    }
+    fn compute_rpo(&self) -> Vec<CfgNode> {
+        if self.is_empty() {
+            return vec![];
+        }
+        let mut visited = vec![false; self.len()];
+        let mut postorder = Vec::with_capacity(self.len());
+        let mut stack = vec![(0, self.all_succs(0).collect::<Vec<_>>())];
+        visited[0] = true;
+        while let Some((node, succs)) = stack.last_mut() {
+            if let Some(succ) = succs.pop() {
+                if !visited[succ] {
+                    visited[succ] = true;
+                    let succs = self.all_succs(succ).collect();
+                    stack.push((succ, succs));
+                }
+            } else {
+                postorder.push(*node);
+                stack.pop();
+            }
+        }
+        postorder.reverse();
+        postorder
+    }
+    /// The number of blocks in this graph.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+    /// Checks if this graph has no blocks.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+    /// The key of the block `node`.
+    #[must_use]
+    pub fn key(&self, node: CfgNode) -> BlockKey {
+        self.keys[node]
+    }
+    /// Looks up the node of the block identified by `key`.
+    #[must_use]
+    pub fn node(&self, key: BlockKey) -> Option<CfgNode> {
+        self.index.get(&key).copied()
+    }
+    /// The roots of the block `node`.
+    #[must_use]
+    pub fn roots(&self, node: CfgNode) -> &[crate::v2::RootIdx] {
+        &self.roots[node]
+    }
+    /// The blocks control may normally flow to from `node`.
+    #[must_use]
+    pub fn succs(&self, node: CfgNode) -> &[CfgNode] {
+        &self.succs[node]
+    }
+    /// The first block of the handler protecting `node`, if any.
+    #[must_use]
+    pub fn handler(&self, node: CfgNode) -> Option<CfgNode> {
+        self.handlers[node]
+    }
+    /// Normal and exceptional successors of `node`.
+    pub fn all_succs(&self, node: CfgNode) -> impl Iterator<Item = CfgNode> + '_ {
+        self.succs[node]
+            .iter()
+            .copied()
+            .chain(self.handlers[node].filter(|handler| !self.succs[node].contains(handler)))
+    }
+    /// The blocks control may flow to `node` from, including through exceptional edges.
+    #[must_use]
+    pub fn preds(&self, node: CfgNode) -> &[CfgNode] {
+        &self.preds[node]
+    }
+    /// The blocks reachable from the entry, in reverse postorder.
+    #[must_use]
+    pub fn reverse_postorder(&self) -> &[CfgNode] {
+        &self.rpo
+    }
+    /// Checks if `node` can be reached from the entry block.
+    #[must_use]
+    pub fn is_reachable(&self, node: CfgNode) -> bool {
+        self.rpo.contains(&node)
+    }
+}
+/// The dominator tree of a [`Cfg`].
+#[derive(Debug)]
+pub struct Dominators {
+    idom: Vec<Option<CfgNode>>,
+    rpo_pos: Vec<usize>,
+}
+impl Dominators {
+    /// Computes the dominators using the iterative algorithm of Cooper, Harvey and Kennedy.
+    #[must_use]
+    pub fn new(cfg: &Cfg) -> Self {
+        let mut rpo_pos = vec![usize::MAX; cfg.len()];
+        for (pos, node) in cfg.reverse_postorder().iter().enumerate() {
+            rpo_pos[*node] = pos;
+        }
+        let mut idom = vec![None; cfg.len()];
+        if cfg.is_empty() {
+            return Self { idom, rpo_pos };
+        }
+        idom[0] = Some(0);
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for node in cfg.reverse_postorder().iter().skip(1).copied() {
+                let mut new_idom = None;
+                for pred in cfg.preds(node).iter().copied() {
+                    if idom[pred].is_none() {
+                        continue;
+                    }
+                    new_idom = Some(match new_idom {
+                        None => pred,
+                        Some(other) => Self::intersect(&idom, &rpo_pos, pred, other),
+                    });
+                }
+                if new_idom != idom[node] {
+                    idom[node] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+        Self { idom, rpo_pos }
+    }
+    fn intersect(
+        idom: &[Option<CfgNode>],
+        rpo_pos: &[usize],
+        mut a: CfgNode,
+        mut b: CfgNode,
+    ) -> CfgNode {
+        while a != b {
+            while rpo_pos[a] > rpo_pos[b] {
+                a = idom[a].unwrap();
+            }
+            while rpo_pos[b] > rpo_pos[a] {
+                b = idom[b].unwrap();
+            }
+        }
+        a
+    }
+    /// The immediate dominator of `node`. `None` for the entry block and unreachable blocks.
+    #[must_use]
+    pub fn idom(&self, node: CfgNode) -> Option<CfgNode> {
+        self.idom[node].filter(|idom| *idom != node)
+    }
+    /// Checks if every path from the entry to `node` goes through `dominator`.
+    /// Every reachable block dominates itself. Unreachable blocks neither dominate nor are dominated.
+    #[must_use]
+    pub fn dominates(&self, dominator: CfgNode, mut node: CfgNode) -> bool {
+        if self.idom[dominator].is_none() || self.idom[node].is_none() {
+            return false;
+        }
+        while self.rpo_pos[node] > self.rpo_pos[dominator] {
+            node = self.idom[node].unwrap();
+        }
+        node == dominator
+    }
+}
+/// A natural loop: a header and all the blocks which can reach one of its back edges without passing through it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Loop {
+    header: CfgNode,
+    latches: Vec<CfgNode>,
+    body: Vec<CfgNode>,
+}
+impl Loop {
+    /// Finds the natural loops of `cfg`, ordered by their header. Back edges sharing a header form one loop.
+    /// Irreducible cycles, which have no dominating header, are not reported.
+    #[must_use]
+    pub fn find_all(cfg: &Cfg, dominators: &Dominators) -> Vec<Self> {
+        let mut loops: Vec<Self> = vec![];
+        for node in cfg.reverse_postorder().iter().copied() {
+            for header in cfg.all_succs(node) {
+                if !dominators.dominates(header, node) {
+                    continue;
+                }
+                let mut body = FxHashSet::default();
+                body.insert(header);
+                let mut stack = vec![node];
+                while let Some(block) = stack.pop() {
+                    if body.insert(block) {
+                        stack.extend(cfg.preds(block).iter().copied());
+                    }
+                }
+                match loops.iter_mut().find(|lp| lp.header == header) {
+                    Some(lp) => {
+                        lp.latches.push(node);
+                        body.extend(lp.body.iter().copied());
+                        lp.body = body.into_iter().collect();
+                        lp.body.sort_unstable();
+                    }
+                    None => {
+                        let mut body: Vec<_> = body.into_iter().collect();
+                        body.sort_unstable();
+                        loops.push(Self {
+                            header,
+                            latches: vec![node],
+                            body,
+                        });
+                    }
+                }
+            }
+        }
+        loops.sort_by_key(|lp| lp.header);
+        loops
+    }
+    /// The block every iteration of the loop starts at.
+    #[must_use]
+    pub fn header(&self) -> CfgNode {
+        self.header
+    }
+    /// The sources of the back edges to the header.
+    #[must_use]
+    pub fn latches(&self) -> &[CfgNode] {
+        &self.latches
+    }
+    /// All the blocks of this loop, sorted.
+    #[must_use]
+    pub fn body(&self) -> &[CfgNode] {
+        &self.body
+    }
+    /// Checks if `node` is a part of this loop.
+    #[must_use]
+    pub fn contains(&self, node: CfgNode) -> bool {
+        self.body.binary_search(&node).is_ok()
+    }
+}
+/// Locals read by `root`. Taking the address of a local counts as reading it.
+fn local_uses<'asm>(root: &CILRoot, asm: &'asm Assembly) -> impl Iterator<Item = u32> + 'asm {
+    CILIter::new(root.clone(), asm).filter_map(|elem| match elem {
+        CILIterElem::Node(CILNode::LdLoc(loc) | CILNode::LdLocA(loc)) => Some(loc),
+        _ => None,
+    })
+}
+fn address_taken(cfg: &Cfg, asm: &Assembly) -> FxHashSet<u32> {
+    (0..cfg.len())
+        .flat_map(|node| cfg.roots(node).iter())
+        .flat_map(|root| CILIter::new(asm[*root].clone(), asm))
+        .filter_map(|elem| match elem {
+            CILIterElem::Node(CILNode::LdLocA(loc)) => Some(loc),
+            _ => None,
+        })
+        .collect()
+}
+/// Where a local gets its value.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum DefSite {
+    /// The value the local has on method entry.
+    Entry,
+    /// The `StLoc` at position `root` of block `node`.
+    Root { node: CfgNode, root: usize },
+}
+/// A definition of a local.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Def {
+    pub local: u32,
+    pub site: DefSite,
+}
+/// Index of a [`Def`] in [`ReachingDefs`].
+pub type DefId = usize;
+/// The definitions of locals which may reach each block.
+///
+/// Only `StLoc` is considered a definition. Locals whose address is taken may also be written indirectly,
+/// so clients must check [`ReachingDefs::is_address_taken`] before relying on their definitions.
+#[derive(Debug)]
+pub struct ReachingDefs {
+    defs: Vec<Def>,
+    by_local: FxHashMap<u32, Vec<DefId>>,
+    by_site: FxHashMap<(CfgNode, usize), DefId>,
+    reach_in: Vec<FxHashSet<DefId>>,
+    address_taken: FxHashSet<u32>,
+}
+impl ReachingDefs {
+    #[must_use]
+    pub fn new(cfg: &Cfg, asm: &Assembly) -> Self {
+        let mut this = Self {
+            defs: vec![],
+            by_local: FxHashMap::default(),
+            by_site: FxHashMap::default(),
+            reach_in: vec![FxHashSet::default(); cfg.len()],
+            address_taken: address_taken(cfg, asm),
+        };
+        for node in 0..cfg.len() {
+            for (idx, root) in cfg.roots(node).iter().enumerate() {
+                let root = &asm[*root];
+                for local in local_uses(root, asm) {
+                    this.add_def(local, DefSite::Entry);
+                }
+                if let CILRoot::StLoc(local, _) = root {
+                    this.add_def(*local, DefSite::Entry);
+                    let def = this.add_def(*local, DefSite::Root { node, root: idx });
+                    this.by_site.insert((node, idx), def);
+                }
+            }
+        }
+        if cfg.is_empty() {
+            return this;
+        }
+        this.reach_in[0] = this
+            .defs
+            .iter()
+            .enumerate()
+            .filter(|(_, def)| def.site == DefSite::Entry)
+            .map(|(id, _)| id)
+            .collect();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for node in cfg.reverse_postorder().iter().copied() {
+                for (target, defs) in this.transfer(cfg, asm, node) {
+                    let len = this.reach_in[target].len();
+                    this.reach_in[target].extend(defs);
+                    changed |= this.reach_in[target].len() != len;
+                }
+            }
+        }
+        this
+    }
+    fn add_def(&mut self, local: u32, site: DefSite) -> DefId {
+        let ids = self.by_local.entry(local).or_default();
+        if let Some(id) = ids.iter().find(|id| self.defs[**id].site == site) {
+            return *id;
+        }
+        ids.push(self.defs.len());
+        self.defs.push(Def { local, site });
+        self.defs.len() - 1
+    }
+    /// Runs `node` forward, collecting the definitions flowing out of it along every edge.
+    fn transfer(
+        &self,
+        cfg: &Cfg,
+        asm: &Assembly,
+        node: CfgNode,
+    ) -> Vec<(CfgNode, FxHashSet<DefId>)> {
+        let mut out = vec![];
+        let mut current = self.reach_in[node].clone();
+        if let Some(handler) = cfg.handler(node) {
+            // The handler may start running before or after any definition in this block.
+            let mut defs = current.clone();
+            defs.extend(
+                (0..cfg.roots(node).len()).filter_map(|idx| self.by_site.get(&(node, idx))),
+            );
+            out.push((handler, defs));
+        }
+        for (idx, root) in cfg.roots(node).iter().enumerate() {
+            let root = &asm[*root];
+            if let CILRoot::StLoc(local, _) = root {
+                for def in &self.by_local[local] {
+                    current.remove(def);
+                }
+                current.insert(self.by_site[&(node, idx)]);
+            }
+            match root_flow(root) {
+                Flow::Next => (),
+                Flow::Cond(key) => {
+                    out.extend(cfg.node(key).map(|target| (target, current.clone())))
+                }
+                Flow::Jump(key) => {
+                    out.extend(cfg.node(key).map(|target| (target, current)));
+                    return out;
+                }
+                Flow::Exit => return out,
+            }
+        }
+        if let Some(next) = cfg.fallthrough[node] {
+            out.push((next, current));
+        }
+        out
+    }
+    /// The definition with id `def`.
+    #[must_use]
+    pub fn def(&self, def: DefId) -> Def {
+        self.defs[def]
+    }
+    /// All the definitions of `local`, including its entry definition.
+    #[must_use]
+    pub fn defs_of(&self, local: u32) -> &[DefId] {
+        self.by_local.get(&local).map_or(&[], Vec::as_slice)
+    }
+    /// The definition made by the `StLoc` at position `root` of `node`, if it is one.
+    #[must_use]
+    pub fn def_at(&self, node: CfgNode, root: usize) -> Option<DefId> {
+        self.by_site.get(&(node, root)).copied()
+    }
+    /// The definitions which may reach the start of `node`.
+    #[must_use]
+    pub fn reaching_in(&self, node: CfgNode) -> &FxHashSet<DefId> {
+        &self.reach_in[node]
+    }
+    /// The definitions of `local` which may reach the root at position `root` of `node`, before it is executed.
+    #[must_use]
+    pub fn reaching_at(
+        &self,
+        cfg: &Cfg,
+        asm: &Assembly,
+        node: CfgNode,
+        root: usize,
+        local: u32,
+    ) -> Vec<DefId> {
+        let last_def = cfg.roots(node)[..root]
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, root)| matches!(asm[**root], CILRoot::StLoc(loc, _) if loc == local));
+        if let Some((idx, _)) = last_def {
+            return vec![self.by_site[&(node, idx)]];
+        }
+        let mut defs: Vec<_> = self
+            .defs_of(local)
+            .iter()
+            .filter(|def| self.reach_in[node].contains(def))
+            .copied()
+            .collect();
+        defs.sort_unstable();
+        defs
+    }
+    /// Checks if the address of `local` is taken anywhere in the method.
+    #[must_use]
+    pub fn is_address_taken(&self, local: u32) -> bool {
+        self.address_taken.contains(&local)
+    }
+}
+/// The locals which are live at the start of each block.
+///
+/// A local is live if its current value may be read later. Locals whose address is taken are live everywhere.
+#[derive(Debug)]
+pub struct Liveness {
+    live_in: Vec<FxHashSet<u32>>,
+    address_taken: FxHashSet<u32>,
+}
+impl Liveness {
+    #[must_use]
+    pub fn new(cfg: &Cfg, asm: &Assembly) -> Self {
+        let mut this = Self {
+            live_in: vec![FxHashSet::default(); cfg.len()],
+            address_taken: address_taken(cfg, asm),
+        };
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for node in cfg.reverse_postorder().iter().rev().copied() {
+                let live = this.live_before(cfg, asm, node, 0);
+                if live != this.live_in[node] {
+                    this.live_in[node] = live;
+                    changed = true;
+                }
+            }
+        }
+        this
+    }
+    /// The locals live just before the root at position `root` of `node` is executed.
+    /// Passing the number of roots in the block gives the locals live at its end.
+    #[must_use]
+    pub fn live_before(
+        &self,
+        cfg: &Cfg,
+        asm: &Assembly,
+        node: CfgNode,
+        root: usize,
+    ) -> FxHashSet<u32> {
+        let roots = cfg.roots(node);
+        // Roots after the first unconditional transfer of control are never executed.
+        let terminator = roots
+            .iter()
+            .position(|root| matches!(root_flow(&asm[*root]), Flow::Jump(_) | Flow::Exit));
+        let (end, mut live) = match terminator {
+            Some(idx) => (idx + 1, FxHashSet::default()),
+            None => (
+                roots.len(),
+                cfg.fallthrough[node]
+                    .map(|next| self.live_in[next].clone())
+                    .unwrap_or_default(),
+            ),
+        };
+        let live_in = |key| {
+            cfg.node(key)
+                .map(|target| self.live_in[target].clone())
+                .unwrap_or_default()
+        };
+        for idx in (root..end).rev() {
+            let root = &asm[roots[idx]];
+            match root_flow(root) {
+                Flow::Next => (),
+                Flow::Cond(key) => live.extend(live_in(key)),
+                Flow::Jump(key) => live = live_in(key),
+                Flow::Exit => live.clear(),
+            }
+            if let CILRoot::StLoc(local, _) = root {
+                live.remove(local);
+            }
+            live.extend(local_uses(root, asm));
+        }
+        if let Some(handler) = cfg.handler(node) {
+            live.extend(self.live_in[handler].iter().copied());
+        }
+        live.extend(self.address_taken.iter().copied());
+        live
+    }
+    /// The locals live at the start of `node`.
+    #[must_use]
+    pub fn live_in(&self, node: CfgNode) -> &FxHashSet<u32> {
+        &self.live_in[node]
+    }
+    /// The locals live at the start of any successor of `node`.
+    #[must_use]
+    pub fn live_out(&self, cfg: &Cfg, node: CfgNode) -> FxHashSet<u32> {
+        cfg.all_succs(node)
+            .flat_map(|succ| self.live_in[succ].iter().copied())
+            .chain(self.address_taken.iter().copied())
+            .collect()
+    }
+    /// Checks if `local` is live just after the root at position `root` of `node` is executed.
+    #[must_use]
+    pub fn is_live_after(
+        &self,
+        cfg: &Cfg,
+        asm: &Assembly,
+        node: CfgNode,
+        root: usize,
+        local: u32,
+    ) -> bool {
+        self.live_before(cfg, asm, node, root + 1).contains(&local)
+    }
+}
+/// Lazily computed analyses of a method body, recomputed when the body changes.
+///
+/// The cache remembers a fingerprint of the blocks it was last asked about. Roots are interned, so any change to the
+/// body - a new root, a removed block or a changed handler - changes the fingerprint and drops all cached results.
+#[derive(Default)]
+pub struct AnalysisCache {
+    fingerprint: Option<u64>,
+    cfg: Option<Rc<Cfg>>,
+    dominators: Option<Rc<Dominators>>,
+    loops: Option<Rc<[Loop]>>,
+    reaching_defs: Option<Rc<ReachingDefs>>,
+    liveness: Option<Rc<Liveness>>,
+}
+impl AnalysisCache {
+    /// Drops all cached analyses.
+    pub fn invalidate(&mut self) {
+        *self = Self::default();
+    }
+    fn sync(&mut self, blocks: &[BasicBlock]) {
+        let mut hasher = FxHasher::default();
+        blocks.hash(&mut hasher);
+        let fingerprint = hasher.finish();
+        if self.fingerprint != Some(fingerprint) {
+            *self = Self {
+                fingerprint: Some(fingerprint),
+                ..Self::default()
+            };
+        }
+    }
+    /// The control flow graph of `blocks`.
+    pub fn cfg(&mut self, blocks: &[BasicBlock], asm: &Assembly) -> Rc<Cfg> {
+        self.sync(blocks);
+        self.cfg
+            .get_or_insert_with(|| Rc::new(Cfg::new(blocks, asm)))
+            .clone()
+    }
+    /// The dominator tree of `blocks`.
+    pub fn dominators(&mut self, blocks: &[BasicBlock], asm: &Assembly) -> Rc<Dominators> {
+        let cfg = self.cfg(blocks, asm);
+        self.dominators
+            .get_or_insert_with(|| Rc::new(Dominators::new(&cfg)))
+            .clone()
+    }
+    /// The natural loops of `blocks`.
+    pub fn loops(&mut self, blocks: &[BasicBlock], asm: &Assembly) -> Rc<[Loop]> {
+        let dominators = self.dominators(blocks, asm);
+        let cfg = self.cfg(blocks, asm);
+        self.loops
+            .get_or_insert_with(|| Loop::find_all(&cfg, &dominators).into())
+            .clone()
+    }
+    /// The reaching definitions of `blocks`.
+    pub fn reaching_defs(&mut self, blocks: &[BasicBlock], asm: &Assembly) -> Rc<ReachingDefs> {
+        let cfg = self.cfg(blocks, asm);
+        self.reaching_defs
+            .get_or_insert_with(|| Rc::new(ReachingDefs::new(&cfg, asm)))
+            .clone()
+    }
+    /// The live locals of `blocks`.
+    pub fn liveness(&mut self, blocks: &[BasicBlock], asm: &Assembly) -> Rc<Liveness> {
+        let cfg = self.cfg(blocks, asm);
+        self.liveness
+            .get_or_insert_with(|| Rc::new(Liveness::new(&cfg, asm)))
+            .clone()
+    }
+}
+#[cfg(test)]
+fn branch(asm: &mut Assembly, target: BlockId, cond: Option<u32>) -> crate::v2::RootIdx {
+    use crate::v2::cilroot::BranchCond;
+    let cond = cond.map(|local| BranchCond::True(asm.alloc_node(CILNode::LdLoc(local))));
+    asm.alloc_root(CILRoot::Branch(Box::new((target, 0, cond))))
+}
+#[cfg(test)]
+fn store(asm: &mut Assembly, local: u32, val: i32) -> crate::v2::RootIdx {
+    let val = asm.alloc_node(val);
+    asm.alloc_root(CILRoot::StLoc(local, val))
+}
+#[cfg(test)]
+fn ret_local(asm: &mut Assembly, local: u32) -> crate::v2::RootIdx {
+    let val = asm.alloc_node(CILNode::LdLoc(local));
+    asm.alloc_root(CILRoot::Ret(val))
+}
+#[test]
+fn diamond() {
+    let mut asm = Assembly::default();
+    // 0: if loc0 goto 2 else fall through to 1; 1: loc1 = 1, goto 3; 2: loc1 = 2; 3: ret loc1
+    let blocks = vec![
+        BasicBlock::new(vec![branch(&mut asm, 2, Some(0))], 0, None),
+        BasicBlock::new(
+            vec![store(&mut asm, 1, 1), branch(&mut asm, 3, None)],
+            1,
+            None,
+        ),
+        BasicBlock::new(vec![store(&mut asm, 1, 2)], 2, None),
+        BasicBlock::new(vec![ret_local(&mut asm, 1)], 3, None),
+    ];
+    let cfg = Cfg::new(&blocks, &asm);
+    assert_eq!(cfg.succs(0), &[2, 1]);
+    assert_eq!(cfg.succs(1), &[3]);
+    assert_eq!(cfg.succs(2), &[3]);
+    assert!(cfg.succs(3).is_empty());
+    assert_eq!(cfg.preds(3), &[1, 2]);
+    let dom = Dominators::new(&cfg);
+    assert_eq!(dom.idom(0), None);
+    assert_eq!(dom.idom(1), Some(0));
+    assert_eq!(dom.idom(3), Some(0));
+    assert!(dom.dominates(0, 3));
+    assert!(!dom.dominates(1, 3));
+    assert!(Loop::find_all(&cfg, &dom).is_empty());
+    let live = Liveness::new(&cfg, &asm);
+    assert_eq!(live.live_in(0), &[0].into_iter().collect());
+    assert_eq!(live.live_in(3), &[1].into_iter().collect());
+    assert!(live.live_in(1).is_empty());
+    assert!(!live.is_live_after(&cfg, &asm, 0, 0, 0));
+    let defs = ReachingDefs::new(&cfg, &asm);
+    let reaching: Vec<_> = defs
+        .reaching_at(&cfg, &asm, 3, 0, 1)
+        .into_iter()
+        .map(|def| defs.def(def).site)
+        .collect();
+    assert_eq!(
+        reaching,
+        [
+            DefSite::Root { node: 1, root: 0 },
+            DefSite::Root { node: 2, root: 0 }
+        ]
+    );
+    // Before the branch, only the entry value of loc1 is visible.
+    assert_eq!(
+        defs.reaching_at(&cfg, &asm, 0, 0, 1)
+            .into_iter()
+            .map(|def| defs.def(def).site)
+            .collect::<Vec<_>>(),
+        [DefSite::Entry]
+    );
+}
+#[test]
+fn loops() {
+    let mut asm = Assembly::default();
+    // 0: loc0 = 0; 1: if loc1 goto 3; 2: loc0 = 1, goto 1; 3: ret loc0
+    let blocks = vec![
+        BasicBlock::new(vec![store(&mut asm, 0, 0)], 0, None),
+        BasicBlock::new(vec![branch(&mut asm, 3, Some(1))], 1, None),
+        BasicBlock::new(
+            vec![store(&mut asm, 0, 1), branch(&mut asm, 1, None)],
+            2,
+            None,
+        ),
+        BasicBlock::new(vec![ret_local(&mut asm, 0)], 3, None),
+    ];
+    let mut cache = AnalysisCache::default();
+    let cfg = cache.cfg(&blocks, &asm);
+    let loops = cache.loops(&blocks, &asm);
+    assert_eq!(loops.len(), 1);
+    assert_eq!(loops[0].header(), 1);
+    assert_eq!(loops[0].latches(), &[2]);
+    assert_eq!(loops[0].body(), &[1, 2]);
+    assert!(!loops[0].contains(3));
+    let live = cache.liveness(&blocks, &asm);
+    // loc0 is live around the loop, loc1 is read in the header.
+    assert_eq!(live.live_in(1), &[0, 1].into_iter().collect());
+    assert_eq!(live.live_in(2), &[1].into_iter().collect());
+    let defs = cache.reaching_defs(&blocks, &asm);
+    assert_eq!(defs.reaching_at(&cfg, &asm, 3, 0, 0).len(), 2);
+    // Cached results are reused until the body changes.
+    assert!(Rc::ptr_eq(&cfg, &cache.cfg(&blocks, &asm)));
+    let mut changed = blocks.clone();
+    changed[2].roots_mut().remove(0);
+    let new_cfg = cache.cfg(&changed, &asm);
+    assert!(!Rc::ptr_eq(&cfg, &new_cfg));
+    assert_eq!(
+        cache
+            .reaching_defs(&changed, &asm)
+            .reaching_at(&new_cfg, &asm, 3, 0, 0)
+            .len(),
+        1
+    );
+}
+#[test]
+fn handlers() {
+    let mut asm = Assembly::default();
+    // 0 is protected: loc0 = 1, loc0 = 2, fall through to 1. The handler reads loc0 and leaves to 1.
+    let handler_read = asm.alloc_node(CILNode::LdLoc(0));
+    let handler_read = asm.alloc_root(CILRoot::Pop(handler_read));
+    let leave = asm.alloc_root(CILRoot::ExitSpecialRegion {
+        target: 1,
+        source: 0,
+    });
+    let handler = vec![BasicBlock::new(vec![handler_read, leave], 5, None)];
+    let blocks = vec![
+        BasicBlock::new(
+            vec![store(&mut asm, 0, 1), store(&mut asm, 0, 2)],
+            0,
+            Some(handler),
+        ),
+        BasicBlock::new(vec![asm.alloc_root(CILRoot::VoidRet)], 1, None),
+    ];
+    let cfg = Cfg::new(&blocks, &asm);
+    let handler = cfg
+        .node(BlockKey {
+            owner: Some(0),
+            id: 5,
+        })
+        .unwrap();
+    assert_eq!(cfg.handler(0), Some(handler));
+    assert_eq!(cfg.succs(handler), &[1]);
+    assert_eq!(cfg.preds(1), &[0, handler]);
+    assert!(Dominators::new(&cfg).dominates(0, handler));
+    // The handler may observe any of the stores, and the entry value.
+    let defs = ReachingDefs::new(&cfg, &asm);
+    assert_eq!(defs.reaching_at(&cfg, &asm, handler, 0, 0).len(), 3);
+    // Both stores are live, since an exception may be thrown right after either.
+    let live = Liveness::new(&cfg, &asm);
+    assert!(live.is_live_after(&cfg, &asm, 0, 0, 0));
+    assert!(live.live_in(0).contains(&0));
+}
@@ -1,10 +1,12 @@
+use fxhash::FxHashMap;
+
 use super::{OptFuel, SideEffectInfoCache};
+use crate::config;
 use crate::v2::{
-    cilnode::MethodKind, Assembly, CILIter, CILIterElem, CILNode, CILRoot, MethodDef, MethodImpl,
-    MethodRefIdx, NodeIdx, RootIdx,
+    cilnode::MethodKind, method::LocalDef, Assembly, BasicBlock, CILIter, CILIterElem, CILNode,
+    CILRoot, ClassDefIdx, MethodDef, MethodDefIdx, MethodImpl, MethodRefIdx, NodeIdx, RootIdx,
+    Type,
 };
-#[cfg(test)]
-use crate::v2::{BasicBlock, Type};
 fn trivial_inline_block<'def, 'asm: 'def>(
     def: &'def MethodDef,
     asm: &'asm mut Assembly,
@@ -81,8 +83,8 @@ pub fn trivial_inline_call(
     let Some(def) = asm.method_def_from_ref(calle).cloned() else {
         return CILNode::Call(Box::new((calle, call_args.into())));
     };
-    // Check if the calle is a static method
-    let MethodKind::Static = def.kind() else {
+    // Check if the calle is a static method, which may be inlined.
+    let (MethodKind::Static, false) = (def.kind(), def.no_inlining()) else {
         return CILNode::Call(Box::new((calle, call_args.into())));
     };
     // TODO: only inline methods within the same class, to get around access.
@@ -139,8 +141,8 @@ pub fn inline_trivial_call_root(
     let Some(def) = asm.method_def_from_ref(calle).cloned() else {
         return CILRoot::Call(Box::new((calle, call_args.into())));
     };
-    // Check if the calle is a static method
-    let MethodKind::Static = def.kind() else {
+    // Check if the calle is a static method, which may be inlined.
+    let (MethodKind::Static, false) = (def.kind(), def.no_inlining()) else {
         return CILRoot::Call(Box::new((calle, call_args.into())));
     };
     match trivial_inline_root(&def, call_args, fuel, asm) {
@@ -148,6 +150,396 @@ pub fn inline_trivial_call_root(
         None => CILRoot::Call(Box::new((calle, call_args.into()))),
     }
 }
+config!(
+    INLINE_METHODS,
+    bool,
+    true,
+    "Enables the general inliner, which inlines whole method bodies(with locals, multiple blocks and handlers) into their callers. Set to 0 to disable it."
+);
+config!(INLINE_MAX_SIZE, u32, 64);
+config!(INLINE_MAX_SINGLE_CALL_SIZE, u32, 512);
+config!(INLINE_MAX_CALLER_SIZE, u32, 16384);
+/// Where the value returned by an inlined call ends up.
+#[derive(Clone, Copy)]
+enum CallDst {
+    /// The value is discarded.
+    Discard,
+    /// The value is stored in a local of the caller.
+    Local(u32),
+}
+/// The size of a method, measured in roots and nodes.
+fn impl_size(implementation: &MethodImpl, asm: &Assembly) -> u32 {
+    let Some(blocks) = implementation.blocks() else {
+        return 0;
+    };
+    let size = blocks
+        .iter()
+        .flat_map(BasicBlock::iter_roots)
+        .map(|root| CILIter::new(asm[root].clone(), asm).count())
+        .sum::<usize>();
+    u32::try_from(size).unwrap_or(u32::MAX)
+}
+/// Counts how many times each method is called(or has its address taken) in the whole assembly.
+fn call_counts(asm: &Assembly) -> FxHashMap<MethodRefIdx, u32> {
+    let mut counts: FxHashMap<MethodRefIdx, u32> = FxHashMap::default();
+    for (_, def) in asm.methods_with(|_, _, _| true) {
+        let Some(blocks) = def.implementation().blocks() else {
+            continue;
+        };
+        for elem in blocks
+            .iter()
+            .flat_map(BasicBlock::iter_roots)
+            .flat_map(|root| CILIter::new(asm[root].clone(), asm))
+        {
+            match elem {
+                CILIterElem::Node(CILNode::Call(info)) | CILIterElem::Root(CILRoot::Call(info)) => {
+                    *counts.entry(info.0).or_default() += 1;
+                }
+                // A method whose address is taken will stay alive no matter how many calls get inlined.
+                CILIterElem::Node(CILNode::LdFtn(mref)) => *counts.entry(mref).or_default() += 2,
+                _ => (),
+            }
+        }
+    }
+    counts
+}
+/// The cost model: small methods are always inlined, and methods with a single call site are inlined as long as they are not huge.
+fn should_inline(size: u32, calls: u32) -> bool {
+    size <= *INLINE_MAX_SIZE || (calls <= 1 && size <= *INLINE_MAX_SINGLE_CALL_SIZE)
+}
+/// Checks if `callee` can be inlined into `caller`, and returns its size if so.
+fn inline_candidate(
+    callee: MethodRefIdx,
+    caller: MethodRefIdx,
+    caller_class: ClassDefIdx,
+    counts: &FxHashMap<MethodRefIdx, u32>,
+    asm: &Assembly,
+) -> Option<u32> {
+    // The caller is borrowed from the assembly, so it will not be found here - so direct recursion is never inlined.
+    let def = asm.method_def_from_ref(callee)?;
+    // Only static methods within the same class are inlined, to not break access rules.
+    if def.kind() != MethodKind::Static || def.class() != caller_class || def.no_inlining() {
+        return None;
+    }
+    let MethodImpl::MethodBody { blocks, locals } = def.implementation() else {
+        return None;
+    };
+    // Pinned locals of the callee would stay pinned until the caller returns.
+    if (0..locals.len()).any(|loc| super::is_pinned(u32::try_from(loc).unwrap(), locals, asm)) {
+        return None;
+    }
+    let size = impl_size(def.implementation(), asm);
+    if !should_inline(size, counts.get(&callee).copied().unwrap_or(0)) {
+        return None;
+    }
+    for block in blocks {
+        for elem in block
+            .iter_roots()
+            .flat_map(|root| CILIter::new(asm[root].clone(), asm))
+        {
+            match elem {
+                // Recursion guard: methods which call themselves, or the caller, are not inlined.
+                CILIterElem::Node(CILNode::Call(info)) | CILIterElem::Root(CILRoot::Call(info))
+                    if info.0 == callee || info.0 == caller =>
+                {
+                    return None
+                }
+                // Local allocations would grow the stack of the caller with each loop iteration.
+                CILIterElem::Node(CILNode::LocAlloc { .. } | CILNode::LocAllocAlgined { .. }) => {
+                    return None
+                }
+                // Returns from protected regions or handlers can't be turned into jumps.
+                CILIterElem::Root(CILRoot::Ret(_) | CILRoot::VoidRet)
+                    if block.handler().is_some() =>
+                {
+                    return None
+                }
+                _ => (),
+            }
+        }
+        if let Some(handler) = block.handler() {
+            if handler
+                .iter()
+                .flat_map(BasicBlock::roots)
+                .any(|root| matches!(asm[*root], CILRoot::Ret(_) | CILRoot::VoidRet))
+            {
+                return None;
+            }
+        }
+    }
+    Some(size)
+}
+/// Returns the callee, arguments and destination of a root, if it is a call which could be inlined.
+fn call_site(root: &CILRoot, asm: &Assembly) -> Option<(MethodRefIdx, Box<[NodeIdx]>, CallDst)> {
+    match root {
+        CILRoot::Call(info) => Some((info.0, info.1.clone(), CallDst::Discard)),
+        CILRoot::Pop(val) => match &asm[*val] {
+            CILNode::Call(info) => Some((info.0, info.1.clone(), CallDst::Discard)),
+            _ => None,
+        },
+        CILRoot::StLoc(loc, val) => match &asm[*val] {
+            CILNode::Call(info) => Some((info.0, info.1.clone(), CallDst::Local(*loc))),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+/// Maps the block ids of the callee to fresh ids in the caller.
+struct InlineMap {
+    arg_base: u32,
+    loc_base: u32,
+    block_ids: FxHashMap<u32, u32>,
+    cont: u32,
+    dst: CallDst,
+}
+impl InlineMap {
+    fn block(&self, id: u32) -> u32 {
+        self.block_ids.get(&id).copied().unwrap_or(id)
+    }
+    fn node(&self, node: CILNode) -> CILNode {
+        match node {
+            CILNode::LdArg(arg) => CILNode::LdLoc(self.arg_base + arg),
+            CILNode::LdArgA(arg) => CILNode::LdLocA(self.arg_base + arg),
+            CILNode::LdLoc(loc) => CILNode::LdLoc(self.loc_base + loc),
+            CILNode::LdLocA(loc) => CILNode::LdLocA(self.loc_base + loc),
+            _ => node,
+        }
+    }
+    fn root(&self, root: CILRoot) -> CILRoot {
+        match root {
+            CILRoot::StArg(arg, val) => CILRoot::StLoc(self.arg_base + arg, val),
+            CILRoot::StLoc(loc, val) => CILRoot::StLoc(self.loc_base + loc, val),
+            CILRoot::Branch(info) => {
+                let (target, sub_target, cond) = *info;
+                let sub_target = if sub_target == 0 {
+                    0
+                } else {
+                    self.block(sub_target)
+                };
+                CILRoot::Branch(Box::new((self.block(target), sub_target, cond)))
+            }
+            CILRoot::ExitSpecialRegion { target, source } => CILRoot::ExitSpecialRegion {
+                target: self.block(target),
+                source: self.block(source),
+            },
+            _ => root,
+        }
+    }
+    /// Copies a block of the callee into the caller, turning returns into jumps to the continuation block.
+    fn copy_block(&self, block: &BasicBlock, asm: &mut Assembly) -> BasicBlock {
+        let mut roots = Vec::with_capacity(block.roots().len());
+        for root in block.roots() {
+            let root =
+                asm[*root]
+                    .clone()
+                    .map(asm, &mut |root, _| self.root(root), &mut |node, _| {
+                        self.node(node)
+                    });
+            match (root, self.dst) {
+                (CILRoot::Ret(val), CallDst::Local(loc)) => {
+                    roots.push(asm.alloc_root(CILRoot::StLoc(loc, val)));
+                }
+                (CILRoot::Ret(val), CallDst::Discard) => {
+                    roots.push(asm.alloc_root(CILRoot::Pop(val)));
+                }
+                (CILRoot::VoidRet, _) => (),
+                (root, _) => {
+                    roots.push(asm.alloc_root(root));
+                    continue;
+                }
+            }
+            roots.push(asm.alloc_root(CILRoot::Branch(Box::new((self.cont, 0, None)))));
+        }
+        let handler = block.handler().map(|handler| {
+            handler
+                .iter()
+                .map(|block| self.copy_block(block, asm))
+                .collect()
+        });
+        BasicBlock::new(roots, self.block(block.block_id()), handler)
+    }
+}
+/// Inlines `callee` at the root `root_idx` of the block `block_idx`. Returns the index of the block containing the rest of the original block.
+#[allow(clippy::too_many_arguments)]
+fn inline_call_site(
+    blocks: &mut Vec<BasicBlock>,
+    locals: &mut Vec<LocalDef>,
+    block_idx: usize,
+    root_idx: usize,
+    callee: &MethodDef,
+    args: &[NodeIdx],
+    dst: CallDst,
+    asm: &mut Assembly,
+) -> usize {
+    let MethodImpl::MethodBody {
+        blocks: callee_blocks,
+        locals: callee_locals,
+    } = callee.implementation()
+    else {
+        panic!("Only methods with bodies can be inlined.")
+    };
+    // Arguments of the callee become locals of the caller.
+    let arg_base = u32::try_from(locals.len()).unwrap();
+    let inputs = asm[callee.sig()].inputs().to_vec();
+    for (arg, input) in inputs.iter().enumerate() {
+        let name = callee.arg_names().get(arg).copied().flatten();
+        locals.push((name, asm.alloc_type(*input)));
+    }
+    let loc_base = u32::try_from(locals.len()).unwrap();
+    locals.extend(callee_locals.iter().copied());
+    // Give every block of the callee(including handler blocks) a fresh id.
+    let mut next_id = blocks
+        .iter()
+        .flat_map(|block| {
+            std::iter::once(block.block_id()).chain(
+                block
+                    .handler()
+                    .into_iter()
+                    .flatten()
+                    .map(BasicBlock::block_id),
+            )
+        })
+        .max()
+        .unwrap_or(0)
+        + 1;
+    let mut block_ids = FxHashMap::default();
+    for block in callee_blocks {
+        for id in std::iter::once(block.block_id()).chain(
+            block
+                .handler()
+                .into_iter()
+                .flatten()
+                .map(BasicBlock::block_id),
+        ) {
+            block_ids.entry(id).or_insert_with(|| {
+                next_id += 1;
+                next_id - 1
+            });
+        }
+    }
+    let map = InlineMap {
+        arg_base,
+        loc_base,
+        block_ids,
+        cont: next_id,
+        dst,
+    };
+    // Split the block at the call site: the arguments are stored, and control jumps into the callee.
+    let block = &mut blocks[block_idx];
+    let mut rest = block.roots_mut().split_off(root_idx);
+    rest.remove(0);
+    for (arg, val) in args.iter().enumerate() {
+        let arg = arg_base + u32::try_from(arg).unwrap();
+        let store = asm.alloc_root(CILRoot::StLoc(arg, *val));
+        blocks[block_idx].roots_mut().push(store);
+    }
+    // The call site may be executed more than once(e.g. in a loop), so the locals of the callee must be zeroed on each entry, like on each call.
+    for (loc, (_, tpe)) in callee_locals.iter().enumerate() {
+        let loc = loc_base + u32::try_from(loc).unwrap();
+        let local_tpe = asm[*tpe];
+        let init = match local_tpe {
+            Type::Int(int) => CILRoot::StLoc(loc, asm.alloc_node(int.zero())),
+            Type::Float(float) => CILRoot::StLoc(loc, asm.alloc_node(float.zero())),
+            Type::Bool => CILRoot::StLoc(loc, asm.alloc_node(false)),
+            _ => CILRoot::InitObj(asm.alloc_node(CILNode::LdLocA(loc)), *tpe),
+        };
+        let init = asm.alloc_root(init);
+        blocks[block_idx].roots_mut().push(init);
+    }
+    let entry = map.block(callee_blocks[0].block_id());
+    let jump = asm.alloc_root(CILRoot::Branch(Box::new((entry, 0, None))));
+    blocks[block_idx].roots_mut().push(jump);
+    let inlined: Vec<_> = callee_blocks
+        .iter()
+        .map(|block| map.copy_block(block, asm))
+        .chain(std::iter::once(BasicBlock::new(rest, map.cont, None)))
+        .collect();
+    let cont_idx = block_idx + inlined.len();
+    blocks.splice(block_idx + 1..block_idx + 1, inlined);
+    cont_idx
+}
+impl MethodDef {
+    /// Inlines calls to small static methods of the same class. Returns the number of inlined calls.
+    fn inline_calls(
+        &mut self,
+        this: MethodRefIdx,
+        counts: &FxHashMap<MethodRefIdx, u32>,
+        fuel: &mut OptFuel,
+        asm: &mut Assembly,
+    ) -> usize {
+        let class = self.class();
+        let mut size = impl_size(self.implementation(), asm);
+        let MethodImpl::MethodBody { blocks, locals } = self.implementation_mut() else {
+            return 0;
+        };
+        let mut inlined = 0;
+        let mut block_idx = 0;
+        'blocks: while block_idx < blocks.len() {
+            // Calls within protected regions are not inlined, since the callee would need to be protected too.
+            if blocks[block_idx].handler().is_some() {
+                block_idx += 1;
+                continue;
+            }
+            for root_idx in 0..blocks[block_idx].roots().len() {
+                let root = blocks[block_idx].roots()[root_idx];
+                let Some((callee, args, dst)) = call_site(&asm[root], asm) else {
+                    continue;
+                };
+                // A call must not be the last root of a block, since the continuation can't be empty.
+                if root_idx + 1 == blocks[block_idx].roots().len() {
+                    continue;
+                }
+                let Some(callee_size) = inline_candidate(callee, this, class, counts, asm) else {
+                    continue;
+                };
+                if size.saturating_add(callee_size) > *INLINE_MAX_CALLER_SIZE
+                    || !fuel.consume(4 + callee_size / 8)
+                {
+                    continue;
+                }
+                let callee = asm.method_def_from_ref(callee).unwrap().clone();
+                // The inlined blocks are skipped, so that mutualy recursive methods do not get expanded forever.
+                block_idx = inline_call_site(
+                    blocks, locals, block_idx, root_idx, &callee, &args, dst, asm,
+                );
+                size += callee_size;
+                inlined += 1;
+                continue 'blocks;
+            }
+            block_idx += 1;
+        }
+        inlined
+    }
+}
+impl Assembly {
+    /// Inlines calls in all the methods of this assembly, according to the cost model. Each inlined call consumes some fuel.
+    /// Returns the number of inlined calls.
+    pub fn inline_calls(&mut self, fuel: &mut OptFuel) -> usize {
+        self.inline_calls_in(fuel, |_| true)
+    }
+    /// Like [`Assembly::inline_calls`], but only inlines calls in the methods accepted by `filter`.
+    pub fn inline_calls_in(
+        &mut self,
+        fuel: &mut OptFuel,
+        filter: impl Fn(MethodDefIdx) -> bool,
+    ) -> usize {
+        let counts = call_counts(self);
+        let defs: Vec<_> = self
+            .methods_with(|_, id, _| filter(id))
+            .map(|(id, _)| *id)
+            .collect();
+        let mut inlined = 0;
+        for def_idx in defs {
+            if fuel.exchausted() {
+                break;
+            }
+            let mut def = self.borrow_methoddef(def_idx);
+            inlined += def.inline_calls(*def_idx, &counts, fuel, self);
+            self.return_methoddef(def_idx, def);
+        }
+        inlined
+    }
+}
 #[test]
 fn test_inline() {
     let mut asm = Assembly::default();
@@ -192,3 +584,363 @@ fn test_inline() {
     )
     .is_some());
 }
+#[cfg(test)]
+fn calls_in(asm: &Assembly, def: crate::v2::MethodDefIdx) -> usize {
+    asm[def]
+        .implementation()
+        .blocks()
+        .unwrap()
+        .iter()
+        .flat_map(BasicBlock::iter_roots)
+        .flat_map(|root| CILIter::new(asm[root].clone(), asm))
+        .filter(|elem| {
+            matches!(
+                elem,
+                CILIterElem::Node(CILNode::Call(_)) | CILIterElem::Root(CILRoot::Call(_))
+            )
+        })
+        .count()
+}
+#[test]
+#[cfg(not(miri))]
+fn inline_multi_block() {
+    use crate::v2::{
+        c_exporter::CExporter,
+        cilroot::{BranchCond, CmpKind},
+        Access, BinOp, Const, Int,
+    };
+    let mut asm = Assembly::default();
+    let main_module = asm.main_module();
+    let i32_idx = asm.alloc_type(Type::Int(Int::I32));
+    // clamp_add(a, b): the sum of a and b, saturated at 10.
+    let a = asm.alloc_node(CILNode::LdArg(0));
+    let b = asm.alloc_node(CILNode::LdArg(1));
+    let sum = asm.alloc_node(CILNode::BinOp(a, b, BinOp::Add));
+    let set_sum = asm.alloc_root(CILRoot::StLoc(0, sum));
+    let sum = asm.alloc_node(CILNode::LdLoc(0));
+    let ten = asm.alloc_node(Const::I32(10));
+    let is_small = asm.alloc_root(CILRoot::Branch(Box::new((
+        1,
+        0,
+        Some(BranchCond::Lt(sum, ten, CmpKind::Signed)),
+    ))));
+    let set_arg = asm.alloc_root(CILRoot::StArg(0, ten));
+    let ret_arg = asm.alloc_root(CILRoot::Ret(a));
+    let ret_sum = asm.alloc_root(CILRoot::Ret(sum));
+    let name = asm.alloc_string("clamp_add");
+    let sig = asm.sig(
+        [Type::Int(Int::I32), Type::Int(Int::I32)],
+        Type::Int(Int::I32),
+    );
+    let clamp_add = asm.new_method(MethodDef::new(
+        Access::Extern,
+        main_module,
+        name,
+        sig,
+        MethodKind::Static,
+        MethodImpl::MethodBody {
+            blocks: vec![
+                BasicBlock::new(vec![set_sum, is_small, set_arg, ret_arg], 0, None),
+                BasicBlock::new(vec![ret_sum], 1, None),
+            ],
+            locals: vec![(None, i32_idx)],
+        },
+        vec![None, None],
+    ));
+    let check = |asm: &mut Assembly, lhs: i32, rhs: i32, expected: i32| {
+        let lhs = asm.alloc_node(Const::I32(lhs));
+        let rhs = asm.alloc_node(Const::I32(rhs));
+        let call = asm.alloc_node(CILNode::Call(Box::new((*clamp_add, [lhs, rhs].into()))));
+        let set = asm.alloc_root(CILRoot::StLoc(0, call));
+        let res = asm.alloc_node(CILNode::LdLoc(0));
+        let expected = asm.alloc_node(Const::I32(expected));
+        let branch = asm.alloc_root(CILRoot::Branch(Box::new((
+            1,
+            0,
+            Some(BranchCond::Ne(res, expected)),
+        ))));
+        [set, branch]
+    };
+    let mut roots = vec![];
+    roots.extend(check(&mut asm, 2, 3, 5));
+    roots.extend(check(&mut asm, 7, 8, 10));
+    roots.push(asm.alloc_root(CILRoot::VoidRet));
+    let msg = asm.alloc_string("Wrong result");
+    let unreachable = asm.alloc_root(CILRoot::Unreachable(msg));
+    let u8_ptr = asm.nptr(Type::Int(Int::U8));
+    let sig = asm.sig([u8_ptr], Type::Void);
+    let name = asm.alloc_string("entrypoint");
+    let entrypoint = asm.new_method(MethodDef::new(
+        Access::Extern,
+        main_module,
+        name,
+        sig,
+        MethodKind::Static,
+        MethodImpl::MethodBody {
+            blocks: vec![
+                BasicBlock::new(roots, 0, None),
+                BasicBlock::new(vec![unreachable], 1, None),
+            ],
+            locals: vec![(None, i32_idx)],
+        },
+        vec![None],
+    ));
+    assert_eq!(asm.inline_calls(&mut OptFuel::new(1000)), 2);
+    assert_eq!(calls_in(&asm, entrypoint), 0);
+    let exe = std::env::temp_dir().join("cilly_inline_multi_block");
+    asm.export(&exe, CExporter::new(false));
+    let out = std::process::Command::new(&exe).output().unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+}
+#[test]
+fn inline_recursive() {
+    use crate::v2::{cilroot::BranchCond, Access, BinOp, Const, Int};
+    let mut asm = Assembly::default();
+    let main_module = asm.main_module();
+    let sig = asm.sig([Type::Int(Int::I32)], Type::Int(Int::I32));
+    let name = asm.alloc_string("countdown");
+    let countdown = asm.new_methodref(*main_module, "countdown", sig, MethodKind::Static, vec![]);
+    // countdown(n): if n == 0 {0} else {countdown(n - 1)}
+    let arg = asm.alloc_node(CILNode::LdArg(0));
+    let zero = asm.alloc_node(Const::I32(0));
+    let one = asm.alloc_node(Const::I32(1));
+    let is_zero = asm.alloc_root(CILRoot::Branch(Box::new((
+        1,
+        0,
+        Some(BranchCond::Eq(arg, zero)),
+    ))));
+    let dec = asm.alloc_node(CILNode::BinOp(arg, one, BinOp::Sub));
+    let call = asm.alloc_node(CILNode::Call(Box::new((countdown, [dec].into()))));
+    let ret_call = asm.alloc_root(CILRoot::Ret(call));
+    let ret_zero = asm.alloc_root(CILRoot::Ret(zero));
+    let countdown = asm.new_method(MethodDef::new(
+        Access::Extern,
+        main_module,
+        name,
+        sig,
+        MethodKind::Static,
+        MethodImpl::MethodBody {
+            blocks: vec![
+                BasicBlock::new(vec![is_zero, ret_call], 0, None),
+                BasicBlock::new(vec![ret_zero], 1, None),
+            ],
+            locals: vec![],
+        },
+        vec![None],
+    ));
+    let five = asm.alloc_node(Const::I32(5));
+    let call = asm.alloc_node(CILNode::Call(Box::new((*countdown, [five].into()))));
+    let pop = asm.alloc_root(CILRoot::Pop(call));
+    let ret = asm.alloc_root(CILRoot::VoidRet);
+    let name = asm.alloc_string("caller");
+    let void_sig = asm.sig([], Type::Void);
+    let caller = asm.new_method(MethodDef::new(
+        Access::Extern,
+        main_module,
+        name,
+        void_sig,
+        MethodKind::Static,
+        MethodImpl::MethodBody {
+            blocks: vec![BasicBlock::new(vec![pop, ret], 0, None)],
+            locals: vec![],
+        },
+        vec![],
+    ));
+    assert_eq!(asm.inline_calls(&mut OptFuel::new(1000)), 0);
+    assert_eq!(calls_in(&asm, caller), 1);
+    assert_eq!(calls_in(&asm, countdown), 1);
+}
+#[test]
+#[cfg(not(miri))]
+fn inline_in_loop() {
+    use crate::v2::{
+        c_exporter::CExporter,
+        cilroot::{BranchCond, CmpKind},
+        Access, BinOp, Const, Int,
+    };
+    let mut asm = Assembly::default();
+    let main_module = asm.main_module();
+    let i32_idx = asm.alloc_type(Type::Int(Int::I32));
+    // add_to_zero(n): adds n to a local, which starts out zeroed.
+    let acc = asm.alloc_node(CILNode::LdLoc(0));
+    let n = asm.alloc_node(CILNode::LdArg(0));
+    let sum = asm.alloc_node(CILNode::BinOp(acc, n, BinOp::Add));
+    let set_acc = asm.alloc_root(CILRoot::StLoc(0, sum));
+    let ret_acc = asm.alloc_root(CILRoot::Ret(acc));
+    let name = asm.alloc_string("add_to_zero");
+    let sig = asm.sig([Type::Int(Int::I32)], Type::Int(Int::I32));
+    let add_to_zero = asm.new_method(MethodDef::new(
+        Access::Extern,
+        main_module,
+        name,
+        sig,
+        MethodKind::Static,
+        MethodImpl::MethodBody {
+            blocks: vec![BasicBlock::new(vec![set_acc, ret_acc], 0, None)],
+            locals: vec![(None, i32_idx)],
+        },
+        vec![None],
+    ));
+    // for i in 0..3 { assert_eq!(add_to_zero(2), 2) }
+    let zero = asm.alloc_node(Const::I32(0));
+    let one = asm.alloc_node(Const::I32(1));
+    let two = asm.alloc_node(Const::I32(2));
+    let three = asm.alloc_node(Const::I32(3));
+    let i = asm.alloc_node(CILNode::LdLoc(0));
+    let res = asm.alloc_node(CILNode::LdLoc(1));
+    let init_i = asm.alloc_root(CILRoot::StLoc(0, zero));
+    let enter_loop = asm.alloc_root(CILRoot::Branch(Box::new((1, 0, None))));
+    let call = asm.alloc_node(CILNode::Call(Box::new((*add_to_zero, [two].into()))));
+    let set_res = asm.alloc_root(CILRoot::StLoc(1, call));
+    let check_res = asm.alloc_root(CILRoot::Branch(Box::new((
+        2,
+        0,
+        Some(BranchCond::Ne(res, two)),
+    ))));
+    let inc = asm.alloc_node(CILNode::BinOp(i, one, BinOp::Add));
+    let inc_i = asm.alloc_root(CILRoot::StLoc(0, inc));
+    let repeat = asm.alloc_root(CILRoot::Branch(Box::new((
+        1,
+        0,
+        Some(BranchCond::Lt(i, three, CmpKind::Signed)),
+    ))));
+    let ret = asm.alloc_root(CILRoot::VoidRet);
+    let msg = asm.alloc_string("Inlined local was not re-initialized");
+    let unreachable = asm.alloc_root(CILRoot::Unreachable(msg));
+    let u8_ptr = asm.nptr(Type::Int(Int::U8));
+    let sig = asm.sig([u8_ptr], Type::Void);
+    let name = asm.alloc_string("entrypoint");
+    let entrypoint = asm.new_method(MethodDef::new(
+        Access::Extern,
+        main_module,
+        name,
+        sig,
+        MethodKind::Static,
+        MethodImpl::MethodBody {
+            blocks: vec![
+                BasicBlock::new(vec![init_i, enter_loop], 0, None),
+                BasicBlock::new(vec![set_res, check_res, inc_i, repeat, ret], 1, None),
+                BasicBlock::new(vec![unreachable], 2, None),
+            ],
+            locals: vec![(None, i32_idx), (None, i32_idx)],
+        },
+        vec![None],
+    ));
+    assert_eq!(asm.inline_calls(&mut OptFuel::new(1000)), 1);
+    assert_eq!(calls_in(&asm, entrypoint), 0);
+    // The inlined local(placed after the caller locals and the callee argument) is zeroed before entering the callee body.
+    let loop_block = &asm[entrypoint].implementation().blocks().unwrap()[1];
+    assert!(loop_block
+        .roots()
+        .iter()
+        .any(|root| matches!(asm[*root], CILRoot::StLoc(3, val) if asm[val] == CILNode::Const(Box::new(Const::I32(0))))));
+    let exe = std::env::temp_dir().join("cilly_inline_in_loop");
+    asm.export(&exe, CExporter::new(false));
+    let out = std::process::Command::new(&exe).output().unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+}
+#[test]
+fn inline_no_inlining() {
+    use crate::v2::{Access, Const, Int};
+    let mut asm = Assembly::default();
+    let main_module = asm.main_module();
+    let sig = asm.sig([], Type::Int(Int::I32));
+    let name = asm.alloc_string("opaque");
+    let five = asm.alloc_node(Const::I32(5));
+    let ret_five = asm.alloc_root(CILRoot::Ret(five));
+    let mut opaque = MethodDef::new(
+        Access::Extern,
+        main_module,
+        name,
+        sig,
+        MethodKind::Static,
+        MethodImpl::MethodBody {
+            blocks: vec![BasicBlock::new(vec![ret_five], 0, None)],
+            locals: vec![],
+        },
+        vec![],
+    );
+    opaque.set_no_inlining(true);
+    let opaque = asm.new_method(opaque);
+    let call = asm.alloc_node(CILNode::Call(Box::new((*opaque, [].into()))));
+    let pop = asm.alloc_root(CILRoot::Pop(call));
+    let ret = asm.alloc_root(CILRoot::VoidRet);
+    let name = asm.alloc_string("caller");
+    let void_sig = asm.sig([], Type::Void);
+    let caller = asm.new_method(MethodDef::new(
+        Access::Extern,
+        main_module,
+        name,
+        void_sig,
+        MethodKind::Static,
+        MethodImpl::MethodBody {
+            blocks: vec![BasicBlock::new(vec![pop, ret], 0, None)],
+            locals: vec![],
+        },
+        vec![],
+    ));
+    assert_eq!(asm.inline_calls(&mut OptFuel::new(1000)), 0);
+    assert_eq!(calls_in(&asm, caller), 1);
+    let mut fuel = OptFuel::new(1000);
+    assert!(matches!(
+        trivial_inline_call(*opaque, &[], &mut fuel, &mut asm),
+        CILNode::Call(_)
+    ));
+}
+#[test]
+fn inline_by_default() {
+    use super::pipeline::{Pass, Pipeline};
+    use crate::v2::{Access, Int};
+    // The general inliner is a part of the default pipeline, and inlines bodies the trivial inliner can't handle.
+    assert!(Pipeline::default().passes().contains(&Pass::Inline));
+    let mut asm = Assembly::default();
+    let main_module = asm.main_module();
+    let i32_idx = asm.alloc_type(Type::Int(Int::I32));
+    let sig = asm.sig([Type::Int(Int::I32)], Type::Int(Int::I32));
+    let jump = asm.alloc_root(CILRoot::Branch(Box::new((1, 0, None))));
+    let arg = asm.alloc_node(CILNode::LdArg(0));
+    let ret_arg = asm.alloc_root(CILRoot::Ret(arg));
+    let name = asm.alloc_string("two_blocks");
+    let two_blocks = asm.new_method(MethodDef::new(
+        Access::Extern,
+        main_module,
+        name,
+        sig,
+        MethodKind::Static,
+        MethodImpl::MethodBody {
+            blocks: vec![
+                BasicBlock::new(vec![jump], 0, None),
+                BasicBlock::new(vec![ret_arg], 1, None),
+            ],
+            locals: vec![],
+        },
+        vec![None],
+    ));
+    let call = asm.alloc_node(CILNode::Call(Box::new((*two_blocks, [arg].into()))));
+    let set = asm.alloc_root(CILRoot::StLoc(0, call));
+    let res = asm.alloc_node(CILNode::LdLoc(0));
+    let ret = asm.alloc_root(CILRoot::Ret(res));
+    let name = asm.alloc_string("caller");
+    let caller = asm.new_method(MethodDef::new(
+        Access::Extern,
+        main_module,
+        name,
+        sig,
+        MethodKind::Static,
+        MethodImpl::MethodBody {
+            blocks: vec![BasicBlock::new(vec![set, ret], 0, None)],
+            locals: vec![(None, i32_idx)],
+        },
+        vec![None],
+    ));
+    asm.opt_with(&mut OptFuel::new(1000), &mut Pipeline::default());
+    assert_eq!(calls_in(&asm, caller), 0);
+}
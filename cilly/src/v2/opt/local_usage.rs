@@ -1,4 +1,9 @@
-#[derive(Default)]
+use std::ops::{Index, IndexMut};
+
+/// Counts the different ways a local is used in a method.
+/// Direct uses are uses of the local as a whole, which can't be split into accesses to its fields. Indirect uses are accesses to one of its fields,
+/// or whole-value copies / initializations which can be performed field by field.
+#[derive(Default, Clone, Debug)]
 pub struct LocalUsage {
     byval_direct: usize,
     byval_indirect: usize,
@@ -7,29 +12,88 @@ pub struct LocalUsage {
     assign: usize,
 }
 impl LocalUsage {
+    /// Checks if the whole local is assigned a value which can't be split.
+    #[must_use]
     pub fn is_assigned(&self) -> bool {
         self.assign != 0
     }
+    /// Checks if the address of this local escapes.
+    #[must_use]
     pub fn is_address_taken(&self) -> bool {
-        self.byaddres_direct != 0 && self.byaddres_indirect != 0
+        self.byaddres_direct != 0
     }
-    pub fn is_val_taken(&self) {
-        self.byval_direct != 0 && self.byaddres_indirect != 0
+    /// Checks if the value of this local is used as a whole.
+    #[must_use]
+    pub fn is_val_taken(&self) -> bool {
+        self.byval_direct != 0
     }
-    pub fn is_only_indirect(&self) {
-        self.byaddres_direct == 0 && self.byval_direct == 0
+    /// Checks if this local is only ever accessed field by field.
+    /// ```
+    /// # use cilly::v2::opt::local_usage::LocalUsage;
+    /// let mut access = LocalUsage::default();
+    /// access.byaddr_indirect();
+    /// access.byval_indirect();
+    /// assert!(access.is_only_indirect());
+    /// access.byaddr_direct();
+    /// assert!(!access.is_only_indirect());
+    /// ```
+    #[must_use]
+    pub fn is_only_indirect(&self) -> bool {
+        self.byaddres_direct == 0 && self.byval_direct == 0 && self.assign == 0
+    }
+    /// Checks if this local is never used.
+    #[must_use]
+    pub fn is_unused(&self) -> bool {
+        self.is_only_indirect() && self.byval_indirect == 0 && self.byaddres_indirect == 0
     }
     /// Increments the interal direct byval access counter.
     pub fn byval_direct(&mut self) {
         self.byval_direct += 1;
     }
-    /// Increments the interal direct byval access counter.
+    /// Increments the interal indirect byval access counter.
+    pub fn byval_indirect(&mut self) {
+        self.byval_indirect += 1;
+    }
+    /// Increments the interal direct address access counter.
     /// ```
     /// # use cilly::v2::opt::local_usage::LocalUsage;
-    /// let mut access =
+    /// let mut access = LocalUsage::default();
+    /// access.byaddr_direct();
+    /// assert!(access.is_address_taken());
     /// ```
     pub fn byaddr_direct(&mut self) {
         self.byaddres_direct += 1;
     }
+    /// Increments the interal indirect address access counter.
+    pub fn byaddr_indirect(&mut self) {
+        self.byaddres_indirect += 1;
+    }
+    /// Increments the interal assigement counter.
+    pub fn assign(&mut self) {
+        self.assign += 1;
+    }
+}
+/// The usages of all the locals of a method.
+pub struct MethodLocalUsages(Box<[LocalUsage]>);
+impl MethodLocalUsages {
+    /// Creates the usage info for `count` unused locals.
+    #[must_use]
+    pub fn new(count: usize) -> Self {
+        Self(vec![LocalUsage::default(); count].into())
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &LocalUsage> {
+        self.0.iter()
+    }
+}
+impl Index<u32> for MethodLocalUsages {
+    type Output = LocalUsage;
+
+    fn index(&self, index: u32) -> &Self::Output {
+        &self.0[index as usize]
+    }
+}
+impl IndexMut<u32> for MethodLocalUsages {
+    fn index_mut(&mut self, index: u32) -> &mut Self::Output {
+        &mut self.0[index as usize]
+    }
 }
-struct MethodLocalUsages(Box<[LocalUsage]>);
@@ -12,12 +12,16 @@ use super::{
 use crate::v2::{Assembly, MethodDef};
 pub use opt_fuel::OptFuel;
 pub use side_effect::*;
+pub mod analysis;
 mod inline;
+pub mod local_usage;
 mod opt_fuel;
 mod opt_node;
+pub mod pipeline;
 mod root;
 mod side_effect;
 mod simplify_handlers;
+mod split_structs;
 mod test;
 pub fn opt_if_fuel<T>(new: T, original: T, fuel: &mut OptFuel) -> T {
     if fuel.consume(1) {
@@ -283,6 +287,12 @@ impl CILNode {
                     tpe: *unboxtpe,
                 }
             }
+            CILNode::NewArr { elem, len } => {
+                let len = asm.get_node(*len).clone();
+                let len = len.propagate_locals(asm, idx, tpe, new_node, fuel);
+                let len = asm.alloc_node(len);
+                CILNode::NewArr { elem: *elem, len }
+            }
         }
     }
 }
@@ -358,6 +368,10 @@ impl BasicBlock {
         }
     }
 }
+/// Checks if the local `loc` is pinned. A store to a pinned local keeps its value pinned, so it is observable, and must never be propagated or removed.
+pub(crate) fn is_pinned(loc: u32, locals: &[LocalDef], asm: &Assembly) -> bool {
+    matches!(asm[locals[loc as usize].1], Type::Pinned(_))
+}
 fn propagate_roots(
     asm: &mut Assembly,
     root: &mut RootIdx,
@@ -369,6 +383,10 @@ fn propagate_roots(
 ) -> bool {
     match prev_root {
         CILRoot::StLoc(loc, tree) => {
+            // Propagating the value would unpin it.
+            if is_pinned(loc, locals, asm) {
+                return true;
+            }
             // 1 st. check if the previous node is a candiate for propagation.
             if cache.has_side_effects(tree, asm) {
                 return true;
@@ -537,7 +555,10 @@ impl MethodImpl {
             return;
         };
         // Check if each local is ever read or its address is taken
-        let mut local_reads = vec![false; locals.len()];
+        // Writes to pinned locals are always observable.
+        let mut local_reads: Vec<_> = (0..locals.len())
+            .map(|loc| is_pinned(u32::try_from(loc).unwrap(), locals, asm))
+            .collect();
         let mut local_address_of = vec![0_i32; locals.len()];
 
         if !fuel.consume(8) {
@@ -664,35 +685,14 @@ impl MethodDef {
         cache: &mut SideEffectInfoCache,
         fuel: &mut OptFuel,
     ) {
-        let sig = self.sig();
-        self.implementation_mut()
-            .propagate_locals(asm, cache, fuel, sig);
-        self.implementation_mut()
-            .remove_dead_writes(asm, cache, fuel);
-        if fuel.consume(1) {
-            self.implementation_mut().realloc_locals(asm);
-        }
-
-        if fuel.consume(15) {
-            self.dedup_roots(asm);
-        }
-        if fuel.consume(1) {
-            self.opt_roots(fuel, cache, asm);
-        }
-        if fuel.consume(1) {
-            self.implementation_mut().remove_duplicate_sfi(asm);
-        }
-        if let MethodImpl::MethodBody { blocks, .. } = self.implementation_mut() {
-            if let Some(block) = linearize_blocks(blocks, asm){
-                *blocks = vec![block];
+        // The pipeline is configured like the assembly-wide one, so `OPT_PASSES`, `OPT_DISABLE` and `OPT_PASS_FUEL` apply here too.
+        let mut pipeline = pipeline::Pipeline::from_env();
+        // Assembly-wide passes and dead block removal are run by the caller.
+        for pass in pipeline.passes().to_vec() {
+            if !pass.is_assembly_wide() && pass != pipeline::Pass::RemoveDeadBlocks {
+                pipeline.run_method_pass(pass, self, asm, cache, fuel);
             }
-            // Better, not yet done OPT.
-            // linearilze_best_span(blocks, asm);
-            // Linear, so supports some additional opts.
-            if blocks.len() == 1 {}
         }
-
-        self.remove_useless_handlers(asm, fuel, cache);
     }
     fn remove_useless_handlers(
         &mut self,
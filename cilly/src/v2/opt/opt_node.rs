@@ -1,5 +1,10 @@
 use crate::{
-    v2::{cilnode::ExtendKind, Assembly, CILNode, Const, Int, NodeIdx, Type},
+    v2::{
+        cilnode::{ExtendKind, UnOp},
+        cilroot::{BranchCond, CmpKind},
+        hashable::{HashableF32, HashableF64},
+        Assembly, CILNode, Const, Float, Int, NodeIdx, Type,
+    },
     BinOp,
 };
 
@@ -17,26 +22,9 @@ fn opt_int_cast(
         CILNode::LdField { addr, field } if asm[*field].tpe() == Type::Int(target) => {
             asm.get_node(input).clone()
         }
-        CILNode::Const(cst) => match (cst.as_ref(), target) {
-            (Const::U64(val), Int::USize) => opt_if_fuel(Const::USize(*val).into(), original, fuel),
-            (Const::I64(val), Int::ISize) => opt_if_fuel(Const::ISize(*val).into(), original, fuel),
-            (Const::U64(val), Int::U64) => opt_if_fuel(Const::U64(*val).into(), original, fuel),
-            (Const::I64(val), Int::I64) => opt_if_fuel(Const::I64(*val).into(), original, fuel),
-            (Const::U32(val), Int::U32) => opt_if_fuel(Const::U32(*val).into(), original, fuel),
-            (Const::I32(val), Int::I32) => opt_if_fuel(Const::I32(*val).into(), original, fuel),
-            (Const::I32(val), Int::U32) => {
-                opt_if_fuel(Const::U32(*val as u32).into(), original, fuel)
-            }
-            (Const::U64(val), Int::U8) => opt_if_fuel(Const::U8(*val as u8).into(), original, fuel),
-            (Const::I32(val), Int::USize) => match extend {
-                ExtendKind::SignExtend => {
-                    opt_if_fuel(Const::USize(*val as i64 as u64).into(), original, fuel)
-                }
-                ExtendKind::ZeroExtend => {
-                    opt_if_fuel(Const::USize(*val as u32 as u64).into(), original, fuel)
-                }
-            },
-            _ => original,
+        CILNode::Const(cst) => match fold_int_cast(cst, target, extend) {
+            Some(folded) => opt_if_fuel(folded.into(), original, fuel),
+            None => original,
         },
         CILNode::IntCast {
             input: input2,
@@ -119,6 +107,17 @@ pub fn opt_node(
             Type::Float(float) => {
                 opt_if_fuel(Const::I32(float.size() as i32).into(), original, fuel)
             }
+            Type::Bool => opt_if_fuel(Const::I32(1).into(), original, fuel),
+            // Valuetypes with an explicit size always have that size.
+            Type::ClassRef(cref) if asm[cref].is_valuetype() && asm[cref].generics().is_empty() => {
+                let size = asm
+                    .class_ref_to_def(cref)
+                    .and_then(|def| asm[def].explict_size());
+                match size.and_then(|size| i32::try_from(size.get()).ok()) {
+                    Some(size) => opt_if_fuel(Const::I32(size).into(), original, fuel),
+                    None => original,
+                }
+            }
             _ => original,
         },
         CILNode::IntCast {
@@ -126,6 +125,29 @@ pub fn opt_node(
             target,
             extend,
         } => opt_int_cast(original, asm, fuel, input, target, extend),
+        CILNode::FloatCast {
+            input,
+            target,
+            is_signed,
+        } => match asm.get_node(input) {
+            CILNode::Const(cst) => match fold_float_cast(cst, target, is_signed) {
+                Some(folded) => opt_if_fuel(folded.into(), original, fuel),
+                None => original,
+            },
+            _ => original,
+        },
+        CILNode::UnOp(input, ref op) => match asm.get_node(input) {
+            CILNode::Const(cst) => match fold_unop(op, cst) {
+                Some(folded) => opt_if_fuel(folded.into(), original, fuel),
+                None => original,
+            },
+            // -(-x) == x, !(!x) == x
+            CILNode::UnOp(inner, inner_op) if inner_op == op => {
+                let inner = asm.get_node(*inner).clone();
+                opt_if_fuel(inner, original, fuel)
+            }
+            _ => original,
+        },
         CILNode::Call(info) => super::inline::trivial_inline_call(info.0, &info.1, fuel, asm),
         CILNode::LdInd {
             addr,
@@ -167,63 +189,7 @@ pub fn opt_node(
             })),
             _ => original,
         },
-        CILNode::BinOp(lhs, rhs, op @ (BinOp::Add | BinOp::Sub)) => {
-            match (asm.get_node(lhs), asm.get_node(rhs)) {
-                (CILNode::Const(cst), rhs) if cst.as_ref().is_zero() && op != BinOp::Sub => {
-                    rhs.clone()
-                }
-                (lhs, CILNode::Const(cst)) if cst.as_ref().is_zero() => lhs.clone(),
-                _ => original,
-            }
-        }
-        CILNode::BinOp(lhs, rhs, BinOp::Rem | BinOp::RemUn) => {
-            match (asm.get_node(lhs), asm.get_node(rhs)) {
-                (CILNode::Const(a), CILNode::Const(b)) if a.get_type() == b.get_type() => {
-                    match (a.as_ref(), b.as_ref()) {
-                        (Const::U8(a), Const::U8(b)) => Const::U8(a.wrapping_rem(*b)).into(),
-                        (Const::U16(a), Const::U16(b)) => Const::U16(a.wrapping_rem(*b)).into(),
-                        (Const::U32(a), Const::U32(b)) => Const::U32(a.wrapping_rem(*b)).into(),
-                        (Const::U64(a), Const::U64(b)) => Const::U64(a.wrapping_rem(*b)).into(),
-                        (Const::U128(a), Const::U128(b)) => Const::U128(a.wrapping_rem(*b)).into(),
-                        (Const::USize(a), Const::USize(b)) => {
-                            Const::USize(a.wrapping_rem(*b)).into()
-                        }
-                        (Const::I8(a), Const::I8(b)) => Const::I8(a.wrapping_rem(*b)).into(),
-                        (Const::I16(a), Const::I16(b)) => Const::I16(a.wrapping_rem(*b)).into(),
-                        (Const::I32(a), Const::I32(b)) => Const::I32(a.wrapping_rem(*b)).into(),
-                        (Const::I64(a), Const::I64(b)) => Const::I64(a.wrapping_rem(*b)).into(),
-                        (Const::I128(a), Const::I128(b)) => Const::I128(a.wrapping_rem(*b)).into(),
-                        (Const::ISize(a), Const::ISize(b)) => {
-                            Const::ISize(a.wrapping_rem(*b)).into()
-                        }
-                        _ => original,
-                    }
-                }
-                _ => original,
-            }
-        }
-        CILNode::BinOp(lhs, rhs, BinOp::Mul) => match (asm.get_node(lhs), asm.get_node(rhs)) {
-            (CILNode::Const(a), CILNode::Const(b)) if a.get_type() == b.get_type() => {
-                match (a.as_ref(), b.as_ref()) {
-                    (Const::U8(a), Const::U8(b)) => Const::U8(a.wrapping_mul(*b)).into(),
-                    (Const::U16(a), Const::U16(b)) => Const::U16(a.wrapping_mul(*b)).into(),
-                    (Const::U32(a), Const::U32(b)) => Const::U32(a.wrapping_mul(*b)).into(),
-                    (Const::U64(a), Const::U64(b)) => Const::U64(a.wrapping_mul(*b)).into(),
-                    (Const::U128(a), Const::U128(b)) => Const::U128(a.wrapping_mul(*b)).into(),
-                    (Const::USize(a), Const::USize(b)) => Const::USize(a.wrapping_mul(*b)).into(),
-                    (Const::I8(a), Const::I8(b)) => Const::I8(a.wrapping_mul(*b)).into(),
-                    (Const::I16(a), Const::I16(b)) => Const::I16(a.wrapping_mul(*b)).into(),
-                    (Const::I32(a), Const::I32(b)) => Const::I32(a.wrapping_mul(*b)).into(),
-                    (Const::I64(a), Const::I64(b)) => Const::I64(a.wrapping_mul(*b)).into(),
-                    (Const::I128(a), Const::I128(b)) => Const::I128(a.wrapping_mul(*b)).into(),
-                    (Const::ISize(a), Const::ISize(b)) => Const::ISize(a.wrapping_mul(*b)).into(),
-                    _ => original,
-                }
-            }
-            (CILNode::Const(a), b) if a.is_one() => b.clone(),
-            (a, CILNode::Const(b)) if b.is_one() => a.clone(),
-            _ => original,
-        },
+        CILNode::BinOp(lhs, rhs, op) => opt_binop(original, lhs, rhs, op, asm, fuel, cache),
         CILNode::LdField { addr, field } => match asm.get_node(addr) {
             CILNode::RefToPtr(addr) => {
                 opt_if_fuel(CILNode::LdField { addr: *addr, field }, original, fuel)
@@ -278,3 +244,549 @@ pub fn opt_node(
         _ => original,
     }
 }
+/// Simplifies a binary operation: folds constants, removes identities and reduces the strength of some operations.
+fn opt_binop(
+    original: CILNode,
+    lhs: NodeIdx,
+    rhs: NodeIdx,
+    op: BinOp,
+    asm: &mut Assembly,
+    fuel: &mut OptFuel,
+    cache: &mut SideEffectInfoCache,
+) -> CILNode {
+    let lhs_cst = match asm.get_node(lhs) {
+        CILNode::Const(cst) => Some(*cst.as_ref()),
+        _ => None,
+    };
+    let rhs_cst = match asm.get_node(rhs) {
+        CILNode::Const(cst) => Some(*cst.as_ref()),
+        _ => None,
+    };
+    if let (Some(lhs_cst), Some(rhs_cst)) = (lhs_cst, rhs_cst) {
+        return match fold_binop(op, &lhs_cst, &rhs_cst) {
+            Some(folded) => opt_if_fuel(folded.into(), original, fuel),
+            None => original,
+        };
+    }
+    let lhs_int = lhs_cst.as_ref().and_then(int_bits);
+    let rhs_int = rhs_cst.as_ref().and_then(int_bits);
+    let new = match (op, lhs_int, rhs_int) {
+        // x + 0, x - 0, x | 0, x ^ 0, x << 0, x >> 0
+        (
+            BinOp::Add
+            | BinOp::Sub
+            | BinOp::Or
+            | BinOp::XOr
+            | BinOp::Shl
+            | BinOp::Shr
+            | BinOp::ShrUn,
+            _,
+            Some((_, 0)),
+        ) => Some(asm.get_node(lhs).clone()),
+        // 0 + x, 0 | x, 0 ^ x
+        (BinOp::Add | BinOp::Or | BinOp::XOr, Some((_, 0)), _) => Some(asm.get_node(rhs).clone()),
+        // x * 1, x / 1
+        (BinOp::Mul | BinOp::Div | BinOp::DivUn, _, Some((_, 1))) => {
+            Some(asm.get_node(lhs).clone())
+        }
+        (BinOp::Mul, Some((_, 1)), _) => Some(asm.get_node(rhs).clone()),
+        // x * 0, x & 0
+        (BinOp::Mul | BinOp::And, _, Some((int, 0))) if !cache.has_side_effects(lhs, asm) => {
+            Some(int.zero().into())
+        }
+        (BinOp::Mul | BinOp::And, Some((int, 0)), _) if !cache.has_side_effects(rhs, asm) => {
+            Some(int.zero().into())
+        }
+        // x & -1
+        (BinOp::And, _, Some((int, bits))) if bits == int_mask(int) => {
+            Some(asm.get_node(lhs).clone())
+        }
+        (BinOp::And, Some((int, bits)), _) if bits == int_mask(int) => {
+            Some(asm.get_node(rhs).clone())
+        }
+        // x | -1
+        (BinOp::Or, _, Some((int, bits)))
+            if bits == int_mask(int) && !cache.has_side_effects(lhs, asm) =>
+        {
+            Some(from_bits(int, bits).into())
+        }
+        // (x & a) & b == x & (a & b)
+        (BinOp::And, _, Some((int, bits))) => match asm.get_node(lhs) {
+            CILNode::BinOp(inner, mask, BinOp::And) => {
+                let inner = *inner;
+                match asm.get_node(*mask) {
+                    CILNode::Const(cst) => match int_bits(cst.as_ref()) {
+                        Some((mask_int, mask)) if mask_int == int => {
+                            let mask = asm.alloc_node(from_bits(int, mask & bits));
+                            Some(CILNode::BinOp(inner, mask, BinOp::And))
+                        }
+                        _ => None,
+                    },
+                    _ => None,
+                }
+            }
+            _ => None,
+        },
+        // x * 2^n == x << n
+        (BinOp::Mul, _, Some((int, bits))) => {
+            power_of_two(int, bits).map(|shift| shift_by(lhs, shift, BinOp::Shl, asm))
+        }
+        (BinOp::Mul, Some((int, bits)), _) => {
+            power_of_two(int, bits).map(|shift| shift_by(rhs, shift, BinOp::Shl, asm))
+        }
+        // x / 2^n == x >> n, for unsigned x
+        (BinOp::DivUn, _, Some((int, bits))) if !int.is_signed() => {
+            power_of_two(int, bits).map(|shift| shift_by(lhs, shift, BinOp::ShrUn, asm))
+        }
+        // x % 2^n == x & (2^n - 1), for unsigned x
+        (BinOp::RemUn, _, Some((int, bits))) if !int.is_signed() => {
+            power_of_two(int, bits).map(|_| {
+                let mask = asm.alloc_node(from_bits(int, bits - 1));
+                CILNode::BinOp(lhs, mask, BinOp::And)
+            })
+        }
+        _ => None,
+    };
+    if let Some(new) = new {
+        return opt_if_fuel(new, original, fuel);
+    }
+    if lhs == rhs && !cache.has_side_effects(lhs, asm) {
+        match op {
+            // x & x == x, x | x == x
+            BinOp::And | BinOp::Or => {
+                return opt_if_fuel(asm.get_node(lhs).clone(), original, fuel)
+            }
+            // x < x and x > x are always false, even for NaNs.
+            BinOp::Lt | BinOp::Gt => return opt_if_fuel(Const::Bool(false).into(), original, fuel),
+            _ => (),
+        }
+    }
+    original
+}
+/// Returns `n`, if `bits` is `2^n` and shifting by `n` behaves the same on all platforms.
+fn power_of_two(int: Int, bits: u128) -> Option<u32> {
+    if !bits.is_power_of_two() {
+        return None;
+    }
+    let shift = bits.trailing_zeros();
+    // Small intigers are extended on the evaluation stack, so `i8::MIN` is not a power of two there.
+    if int_width(int) < 32 && stack_value(int, bits) != 1 << shift {
+        return None;
+    }
+    (shift < shift_limit(int)).then_some(shift)
+}
+fn shift_by(val: NodeIdx, shift: u32, op: BinOp, asm: &mut Assembly) -> CILNode {
+    let shift = asm.alloc_node(Const::I32(shift as i32));
+    CILNode::BinOp(val, shift, op)
+}
+/// Returns the type and bits(zero-extended to 128 bits) of an intiger constant.
+fn int_bits(cst: &Const) -> Option<(Int, u128)> {
+    Some(match *cst {
+        Const::I8(val) => (Int::I8, u128::from(val as u8)),
+        Const::I16(val) => (Int::I16, u128::from(val as u16)),
+        Const::I32(val) => (Int::I32, u128::from(val as u32)),
+        Const::I64(val) => (Int::I64, u128::from(val as u64)),
+        Const::I128(val) => (Int::I128, val as u128),
+        Const::ISize(val) => (Int::ISize, u128::from(val as u64)),
+        Const::U8(val) => (Int::U8, u128::from(val)),
+        Const::U16(val) => (Int::U16, u128::from(val)),
+        Const::U32(val) => (Int::U32, u128::from(val)),
+        Const::U64(val) => (Int::U64, u128::from(val)),
+        Const::U128(val) => (Int::U128, val),
+        Const::USize(val) => (Int::USize, u128::from(val)),
+        _ => return None,
+    })
+}
+/// Creates an intiger constant of type `int`, truncating `bits` to its width.
+fn from_bits(int: Int, bits: u128) -> Const {
+    match int {
+        Int::I8 => Const::I8(bits as u8 as i8),
+        Int::I16 => Const::I16(bits as u16 as i16),
+        Int::I32 => Const::I32(bits as u32 as i32),
+        Int::I64 => Const::I64(bits as u64 as i64),
+        Int::I128 => Const::I128(bits as i128),
+        Int::ISize => Const::ISize(bits as u64 as i64),
+        Int::U8 => Const::U8(bits as u8),
+        Int::U16 => Const::U16(bits as u16),
+        Int::U32 => Const::U32(bits as u32),
+        Int::U64 => Const::U64(bits as u64),
+        Int::U128 => Const::U128(bits),
+        Int::USize => Const::USize(bits as u64),
+    }
+}
+/// The width of `int` in bits. `usize` and `isize` constants are 64 bit wide, and get truncated on 32 bit platforms.
+fn int_width(int: Int) -> u32 {
+    int.bits().map_or(64, u32::from)
+}
+fn int_mask(int: Int) -> u128 {
+    u128::MAX >> (128 - int_width(int))
+}
+fn is_size(int: Int) -> bool {
+    matches!(int, Int::USize | Int::ISize)
+}
+/// Shifts by this many bits or more are not portable.
+fn shift_limit(int: Int) -> u32 {
+    if is_size(int) {
+        32
+    } else {
+        int_width(int)
+    }
+}
+fn sign_extend(int: Int, bits: u128) -> i128 {
+    let shift = 128 - int_width(int);
+    ((bits << shift) as i128) >> shift
+}
+/// The value of a constant, as seen on the evaluation stack. Not valid for 128 bit intigers.
+fn stack_value(int: Int, bits: u128) -> i128 {
+    if int.is_signed() {
+        sign_extend(int, bits)
+    } else {
+        bits as i128
+    }
+}
+/// Checks if a `usize` / `isize` constant has the same value on 32 and 64 bit platforms.
+fn is_portable(int: Int, bits: u128) -> bool {
+    match int {
+        Int::USize => bits <= u128::from(u32::MAX),
+        Int::ISize => i32::try_from(sign_extend(int, bits)).is_ok(),
+        _ => true,
+    }
+}
+/// Checks if an operation with this signedness gives the same result as one with the signedness of `int`.
+/// Small unsigned intigers are zero-extended on the evaluation stack, so they compare the same way as signed ones.
+fn sign_matches(int: Int, signed: bool) -> bool {
+    int.is_signed() == signed || matches!(int, Int::U8 | Int::U16)
+}
+fn float_value(cst: &Const) -> Option<(Float, f64)> {
+    match cst {
+        Const::F32(val) => Some((Float::F32, f64::from(val.0))),
+        Const::F64(val) => Some((Float::F64, val.0)),
+        _ => None,
+    }
+}
+fn float_const(float: Float, val: f64) -> Option<Const> {
+    match float {
+        Float::F32 => Some(Const::F32(HashableF32(val as f32))),
+        Float::F64 => Some(Const::F64(HashableF64(val))),
+        Float::F16 | Float::F128 => None,
+    }
+}
+/// A comparison between two values.
+#[derive(Clone, Copy)]
+enum Cmp {
+    Lt,
+    Gt,
+    Le,
+    Ge,
+}
+/// Compares two constants. `un` selects an unsigned(for intigers) or unordered(for floats) comparison.
+fn fold_cmp(lhs: &Const, rhs: &Const, cmp: Cmp, un: bool) -> Option<bool> {
+    let ord = match (
+        int_bits(lhs),
+        int_bits(rhs),
+        float_value(lhs),
+        float_value(rhs),
+    ) {
+        (Some((int, a)), Some((rhs_int, b)), _, _) => {
+            if int != rhs_int
+                || !sign_matches(int, !un)
+                || !is_portable(int, a)
+                || !is_portable(int, b)
+            {
+                return None;
+            }
+            if un {
+                Some(a.cmp(&b))
+            } else {
+                Some(stack_value(int, a).cmp(&stack_value(int, b)))
+            }
+        }
+        (_, _, Some((float, a)), Some((rhs_float, b))) if float == rhs_float => a.partial_cmp(&b),
+        _ => return None,
+    };
+    // Unordered comparisons are true if any of the values is a NaN, ordered ones are false.
+    let Some(ord) = ord else {
+        return Some(un);
+    };
+    Some(match cmp {
+        Cmp::Lt => ord.is_lt(),
+        Cmp::Gt => ord.is_gt(),
+        Cmp::Le => ord.is_le(),
+        Cmp::Ge => ord.is_ge(),
+    })
+}
+/// Checks if two constants are equal, if this can be known at compile time.
+fn fold_eq(lhs: &Const, rhs: &Const) -> Option<bool> {
+    match (lhs, rhs) {
+        (Const::Bool(a), Const::Bool(b)) => Some(a == b),
+        _ => match (int_bits(lhs), int_bits(rhs)) {
+            (Some((int, a)), Some((rhs_int, b))) if int == rhs_int => {
+                (is_portable(int, a) && is_portable(int, b)).then_some(a == b)
+            }
+            _ => match (float_value(lhs), float_value(rhs)) {
+                (Some((float, a)), Some((rhs_float, b))) if float == rhs_float => Some(a == b),
+                _ => None,
+            },
+        },
+    }
+}
+/// Folds a binary operation on two constants, with the exact semantics of the operation on the evaluation stack.
+pub(super) fn fold_binop(op: BinOp, lhs: &Const, rhs: &Const) -> Option<Const> {
+    match op {
+        BinOp::Eq => return fold_eq(lhs, rhs).map(Const::Bool),
+        BinOp::Lt => return fold_cmp(lhs, rhs, Cmp::Lt, false).map(Const::Bool),
+        BinOp::LtUn => return fold_cmp(lhs, rhs, Cmp::Lt, true).map(Const::Bool),
+        BinOp::Gt => return fold_cmp(lhs, rhs, Cmp::Gt, false).map(Const::Bool),
+        BinOp::GtUn => return fold_cmp(lhs, rhs, Cmp::Gt, true).map(Const::Bool),
+        _ => (),
+    }
+    if let (Const::Bool(a), Const::Bool(b)) = (lhs, rhs) {
+        return match op {
+            BinOp::And => Some(Const::Bool(a & b)),
+            BinOp::Or => Some(Const::Bool(a | b)),
+            BinOp::XOr => Some(Const::Bool(a ^ b)),
+            _ => None,
+        };
+    }
+    if let (Some(lhs), Some(rhs)) = (int_bits(lhs), int_bits(rhs)) {
+        return fold_int_binop(op, lhs, rhs);
+    }
+    let ((float, a), (rhs_float, b)) = (float_value(lhs)?, float_value(rhs)?);
+    if float != rhs_float {
+        return None;
+    }
+    // f32 operations are computed exactly in f64, and then rounded back.
+    let res = match op {
+        BinOp::Add => a + b,
+        BinOp::Sub => a - b,
+        BinOp::Mul => a * b,
+        BinOp::Div => a / b,
+        BinOp::Rem => a % b,
+        _ => return None,
+    };
+    float_const(float, res)
+}
+fn fold_int_binop(op: BinOp, (int, a): (Int, u128), (rhs_int, b): (Int, u128)) -> Option<Const> {
+    // Shift amounts can have any type, all other operations require both sides to have the same type.
+    let is_shift = matches!(op, BinOp::Shl | BinOp::Shr | BinOp::ShrUn);
+    if !is_shift && int != rhs_int {
+        return None;
+    }
+    // 128 bit intigers are not stack values, but all the operations on them wrap just like on the other intigers.
+    let is_small = int_width(int) < 32;
+    let res = match op {
+        BinOp::Add => a.wrapping_add(b),
+        BinOp::Sub => a.wrapping_sub(b),
+        BinOp::Mul => a.wrapping_mul(b),
+        BinOp::And => a & b,
+        BinOp::Or => a | b,
+        BinOp::XOr => a ^ b,
+        BinOp::Shl | BinOp::Shr | BinOp::ShrUn => {
+            if !is_portable(rhs_int, b) {
+                return None;
+            }
+            let amount = u32::try_from(stack_value(rhs_int, b)).ok()?;
+            if amount >= shift_limit(int) {
+                return None;
+            }
+            match op {
+                BinOp::Shl => a << amount,
+                BinOp::Shr if sign_matches(int, true) && is_portable(int, a) => {
+                    (stack_value(int, a) >> amount) as u128
+                }
+                BinOp::ShrUn if sign_matches(int, false) && is_portable(int, a) => a >> amount,
+                _ => return None,
+            }
+        }
+        BinOp::Div | BinOp::Rem | BinOp::DivUn | BinOp::RemUn => {
+            let signed = matches!(op, BinOp::Div | BinOp::Rem);
+            // Division by zero throws.
+            if b == 0 || !sign_matches(int, signed) || !is_portable(int, a) || !is_portable(int, b)
+            {
+                return None;
+            }
+            if signed {
+                let (a, b) = (stack_value(int, a), stack_value(int, b));
+                // Overflowing divisions throw too.
+                let min = if is_size(int) {
+                    i128::from(i32::MIN)
+                } else {
+                    sign_extend(int, 1 << (int_width(int) - 1))
+                };
+                if a == min && b == -1 {
+                    return None;
+                }
+                if op == BinOp::Div {
+                    (a / b) as u128
+                } else {
+                    (a % b) as u128
+                }
+            } else if op == BinOp::DivUn {
+                a / b
+            } else {
+                a % b
+            }
+        }
+        _ => return None,
+    };
+    let res = res & int_mask(int);
+    // Small intigers are not truncated on the evaluation stack, so results which do not fit in their type can't be folded.
+    if is_small {
+        let (a, b) = (stack_value(int, a), stack_value(rhs_int, b));
+        let exact = match op {
+            BinOp::Add => a + b,
+            BinOp::Sub => a - b,
+            BinOp::Mul => a * b,
+            BinOp::Shl => a << b,
+            _ => stack_value(int, res),
+        };
+        if exact != stack_value(int, res) {
+            return None;
+        }
+    }
+    Some(from_bits(int, res))
+}
+/// Folds an unary operation on a constant.
+pub(super) fn fold_unop(op: &UnOp, cst: &Const) -> Option<Const> {
+    if let Some((float, val)) = float_value(cst) {
+        return match op {
+            UnOp::Neg => float_const(float, -val),
+            UnOp::Not => None,
+        };
+    }
+    let (int, bits) = int_bits(cst)?;
+    let res = match op {
+        UnOp::Not => !bits,
+        UnOp::Neg => bits.wrapping_neg(),
+    } & int_mask(int);
+    if int_width(int) < 32 {
+        let exact = match op {
+            UnOp::Not => !stack_value(int, bits),
+            UnOp::Neg => -stack_value(int, bits),
+        };
+        if exact != stack_value(int, res) {
+            return None;
+        }
+    }
+    Some(from_bits(int, res))
+}
+/// Folds an intiger cast of a constant.
+pub(super) fn fold_int_cast(cst: &Const, target: Int, extend: ExtendKind) -> Option<Const> {
+    if let Some((_, val)) = float_value(cst) {
+        // Out of range float to int conversions are not portable.
+        let val = val.trunc();
+        let (min, max) = match target {
+            Int::USize => (0, i128::from(u32::MAX)),
+            Int::ISize => (i128::from(i32::MIN), i128::from(i32::MAX)),
+            Int::I128 | Int::U128 => return None,
+            _ => (
+                stack_value(target, int_mask(target) ^ (int_mask(target) >> 1)).min(0),
+                stack_value(target, int_mask(target) >> u32::from(target.is_signed())),
+            ),
+        };
+        if !val.is_finite() || val < min as f64 || val >= (max + 1) as f64 {
+            return None;
+        }
+        return Some(from_bits(target, val as i128 as u128));
+    }
+    let (src, bits) = int_bits(cst)?;
+    // On 32 bit platforms, native intigers are 32 bits wide.
+    let widens = int_width(target) > 32 && !is_size(target);
+    let value = if int_width(src) < 32 {
+        // Small intigers are extended to 32 bits according to their own sign, and then to the target size according to `extend`.
+        let value = stack_value(src, bits);
+        match extend {
+            ExtendKind::SignExtend => value,
+            ExtendKind::ZeroExtend => i128::from(value as u32),
+        }
+    } else if is_size(src) {
+        if !is_portable(src, bits) {
+            return None;
+        }
+        let value = if src == Int::USize {
+            bits as i128
+        } else {
+            sign_extend(src, bits)
+        };
+        // A native intiger gets extended from 32 bits on 32 bit platforms, and from 64 bits on 64 bit ones.
+        let is_small = (0..=i128::from(i32::MAX)).contains(&value);
+        if widens && !is_small && !(value < 0 && extend == ExtendKind::SignExtend) {
+            return None;
+        }
+        value
+    } else {
+        match extend {
+            ExtendKind::SignExtend => sign_extend(src, bits),
+            ExtendKind::ZeroExtend => bits as i128,
+        }
+    };
+    // 128 bit casts are performed by calls, so only values which don't depend on the extension kind get folded.
+    if matches!(target, Int::I128 | Int::U128) && src != target && sign_extend(src, bits) < 0 {
+        return None;
+    }
+    Some(from_bits(target, value as u128))
+}
+/// Folds a conversion of a constant to a float.
+pub(super) fn fold_float_cast(cst: &Const, target: Float, is_signed: bool) -> Option<Const> {
+    if let Some((_, val)) = float_value(cst) {
+        return float_const(target, val);
+    }
+    let (src, bits) = int_bits(cst)?;
+    let value = match src {
+        Int::I128 | Int::U128 => return None,
+        Int::USize if !is_portable(src, bits) || (is_signed && bits > i32::MAX as u128) => {
+            return None
+        }
+        Int::ISize if !is_portable(src, bits) || (!is_signed && sign_extend(src, bits) < 0) => {
+            return None
+        }
+        Int::USize => bits as i128,
+        Int::ISize => sign_extend(src, bits),
+        // Small intigers are extended according to their own sign.
+        _ if int_width(src) < 32 => {
+            let value = stack_value(src, bits);
+            if value < 0 && !is_signed {
+                return None;
+            }
+            value
+        }
+        _ if is_signed => sign_extend(src, bits),
+        _ => bits as i128,
+    };
+    // Only exact conversions are folded, since rounding of big intigers differs between runtimes.
+    let exact = match target {
+        Float::F32 => (value as f32) as i128 == value,
+        Float::F64 => (value as f64) as i128 == value,
+        Float::F16 | Float::F128 => false,
+    };
+    if !exact {
+        return None;
+    }
+    float_const(target, value as f64)
+}
+/// Evaluates a branch condition, if it only depends on constants.
+pub(super) fn fold_branch_cond(cond: &BranchCond, asm: &Assembly) -> Option<bool> {
+    let cst = |node: &NodeIdx| match asm.get_node(*node) {
+        CILNode::Const(cst) => Some(*cst.as_ref()),
+        _ => None,
+    };
+    let truthy = |cst: Const| match cst {
+        Const::Bool(val) => Some(val),
+        cst => {
+            let (int, bits) = int_bits(&cst)?;
+            is_portable(int, bits).then_some(bits != 0)
+        }
+    };
+    let un = |kind: &CmpKind| matches!(kind, CmpKind::Unordered | CmpKind::Unsigned);
+    match cond {
+        BranchCond::True(val) => truthy(cst(val)?),
+        BranchCond::False(val) => truthy(cst(val)?).map(|val| !val),
+        BranchCond::Eq(lhs, rhs) => fold_eq(&cst(lhs)?, &cst(rhs)?),
+        // Not equal is an unordered comparison, so it is true for NaNs.
+        BranchCond::Ne(lhs, rhs) => fold_eq(&cst(lhs)?, &cst(rhs)?).map(|eq| !eq),
+        BranchCond::Lt(lhs, rhs, kind) => fold_cmp(&cst(lhs)?, &cst(rhs)?, Cmp::Lt, un(kind)),
+        BranchCond::Gt(lhs, rhs, kind) => fold_cmp(&cst(lhs)?, &cst(rhs)?, Cmp::Gt, un(kind)),
+        BranchCond::Le(lhs, rhs, kind) => fold_cmp(&cst(lhs)?, &cst(rhs)?, Cmp::Le, un(kind)),
+        BranchCond::Ge(lhs, rhs, kind) => fold_cmp(&cst(lhs)?, &cst(rhs)?, Cmp::Ge, un(kind)),
+    }
+}
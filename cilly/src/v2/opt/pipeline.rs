@@ -0,0 +1,544 @@
+//! A configurable sequence of named optimization passes.
+//!
+//! The pipeline can be configured using the following enviroment variables, or the `-Z` style flags of the same name
+//! (`-Zopt-passes=inline,opt_roots`):
+//! - `OPT_PASSES` - comma separated list of passes to run, in order. Defaults to [`Pass::DEFAULT`].
+//! - `OPT_DISABLE` - comma separated list of passes to skip.
+//! - `OPT_PASS_FUEL` - comma separated list of `pass=fuel` pairs, limiting how much fuel a pass may use in total.
+//! - `OPT_REPORT` - path to write the per-pass statistics to, after optimization finishes.
+use std::{fmt::Display, str::FromStr, time::Duration};
+
+use fxhash::{FxHashMap, FxHashSet};
+
+use super::{inline, linearize_blocks, split_structs, OptFuel, SideEffectInfoCache};
+use crate::v2::{Assembly, CILIter, MethodDef, MethodDefIdx, MethodImpl};
+
+/// A single, named optimization.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Pass {
+    /// Inlines calls to small methods. Runs on the whole assembly.
+    Inline,
+    /// Splits local structs into one local per field.
+    SplitStructs,
+    PropagateLocals,
+    RemoveDeadWrites,
+    /// Removes unused locals.
+    ReallocLocals,
+    DedupRoots,
+    /// Simplifies and folds individual roots and nodes.
+    OptRoots,
+    RemoveDuplicateSfi,
+    /// Merges chains of blocks into a single block.
+    LinearizeBlocks,
+    RemoveUselessHandlers,
+    RemoveDeadBlocks,
+}
+impl Pass {
+    /// All the passes, in their default order.
+    pub const ALL: [Self; 11] = [
+        Self::Inline,
+        Self::SplitStructs,
+        Self::PropagateLocals,
+        Self::RemoveDeadWrites,
+        Self::ReallocLocals,
+        Self::DedupRoots,
+        Self::OptRoots,
+        Self::RemoveDuplicateSfi,
+        Self::LinearizeBlocks,
+        Self::RemoveUselessHandlers,
+        Self::RemoveDeadBlocks,
+    ];
+    /// The passes run by default. `inline` can be disabled with `INLINE_METHODS=0`, and `split_structs` is only enabled by `SPLIT_LOCAL_STRUCTS`.
+    pub const DEFAULT: [Self; 10] = [
+        Self::Inline,
+        Self::PropagateLocals,
+        Self::RemoveDeadWrites,
+        Self::ReallocLocals,
+        Self::DedupRoots,
+        Self::OptRoots,
+        Self::RemoveDuplicateSfi,
+        Self::LinearizeBlocks,
+        Self::RemoveUselessHandlers,
+        Self::RemoveDeadBlocks,
+    ];
+    /// The name of this pass, as used in the pipeline configuration.
+    /// ```
+    /// # use cilly::v2::opt::pipeline::Pass;
+    /// assert_eq!(Pass::OptRoots.name(), "opt_roots");
+    /// assert_eq!("opt_roots".parse::<Pass>(), Ok(Pass::OptRoots));
+    /// ```
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Inline => "inline",
+            Self::SplitStructs => "split_structs",
+            Self::PropagateLocals => "propagate_locals",
+            Self::RemoveDeadWrites => "remove_dead_writes",
+            Self::ReallocLocals => "realloc_locals",
+            Self::DedupRoots => "dedup_roots",
+            Self::OptRoots => "opt_roots",
+            Self::RemoveDuplicateSfi => "remove_duplicate_sfi",
+            Self::LinearizeBlocks => "linearize_blocks",
+            Self::RemoveUselessHandlers => "remove_useless_handlers",
+            Self::RemoveDeadBlocks => "remove_dead_blocks",
+        }
+    }
+    /// Checks if this pass operates on the whole assembly, instead of a single method.
+    #[must_use]
+    pub fn is_assembly_wide(self) -> bool {
+        matches!(self, Self::Inline)
+    }
+}
+impl FromStr for Pass {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let name = name.trim().replace('-', "_");
+        Self::ALL
+            .into_iter()
+            .find(|pass| pass.name() == name)
+            .ok_or_else(|| format!("Unknown optimization pass {name:?}"))
+    }
+}
+impl Display for Pass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+/// What a pass did, summed over all of its runs.
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+pub struct PassStats {
+    pub runs: u64,
+    pub time: Duration,
+    pub fuel: u64,
+    /// Net number of roots removed. Negative if the pass added roots.
+    pub roots_removed: i64,
+    /// Net number of nodes removed from method bodies. Only measured if [`Pipeline::measure_nodes`] is set.
+    pub nodes_removed: i64,
+}
+/// Size of a method body.
+#[derive(Clone, Copy, Default)]
+struct BodySize {
+    roots: i64,
+    nodes: i64,
+}
+impl BodySize {
+    fn of(method: &MethodDef, asm: &Assembly, measure_nodes: bool) -> Self {
+        let Some(blocks) = method.implementation().blocks() else {
+            return Self::default();
+        };
+        let roots = blocks.iter().flat_map(|block| block.iter_roots());
+        if measure_nodes {
+            roots.fold(Self::default(), |size, root| Self {
+                roots: size.roots + 1,
+                nodes: size.nodes + CILIter::new(asm[root].clone(), asm).count() as i64,
+            })
+        } else {
+            Self {
+                roots: roots.count() as i64,
+                nodes: 0,
+            }
+        }
+    }
+}
+/// An ordered list of passes, along with their fuel limits and statistics.
+#[derive(Clone, Debug)]
+pub struct Pipeline {
+    passes: Vec<Pass>,
+    limits: FxHashMap<Pass, OptFuel>,
+    stats: FxHashMap<Pass, PassStats>,
+    measure_nodes: bool,
+    methods: Option<FxHashSet<MethodDefIdx>>,
+}
+impl Default for Pipeline {
+    fn default() -> Self {
+        let mut passes = Pass::DEFAULT.to_vec();
+        if *split_structs::SPLIT_LOCAL_STRUCTS {
+            passes.insert(1, Pass::SplitStructs);
+        }
+        if !*inline::INLINE_METHODS {
+            passes.retain(|pass| *pass != Pass::Inline);
+        }
+        Self::new(passes)
+    }
+}
+impl Pipeline {
+    /// Creates a pipeline running `passes` in order, with no fuel limits.
+    #[must_use]
+    pub fn new(passes: Vec<Pass>) -> Self {
+        Self {
+            passes,
+            limits: FxHashMap::default(),
+            stats: FxHashMap::default(),
+            measure_nodes: false,
+            methods: None,
+        }
+    }
+    /// Creates the default pipeline, adjusted by the `OPT_*` enviroment variables.
+    /// # Panics
+    /// Panics if any of the variables is malformed.
+    #[must_use]
+    pub fn from_env() -> Self {
+        let mut pipeline = Self::default();
+        for (var, flag) in [
+            ("OPT_PASSES", "opt-passes"),
+            ("OPT_DISABLE", "opt-disable"),
+            ("OPT_PASS_FUEL", "opt-pass-fuel"),
+        ] {
+            if let Ok(value) = std::env::var(var) {
+                pipeline
+                    .apply_flag(&format!("{flag}={value}"))
+                    .unwrap_or_else(|err| panic!("Invalid value of {var}: {err}"));
+            }
+        }
+        pipeline.measure_nodes = std::env::var("OPT_REPORT").is_ok();
+        pipeline
+    }
+    /// Applies a `-Z` style flag to this pipeline. Returns `Ok(false)` if the flag is not a pipeline flag.
+    /// ```
+    /// # use cilly::v2::opt::pipeline::{Pass, Pipeline};
+    /// let mut pipeline = Pipeline::default();
+    /// assert_eq!(pipeline.apply_flag("-Zopt-passes=inline,opt_roots,dedup_roots"), Ok(true));
+    /// assert_eq!(pipeline.apply_flag("-Zopt-disable=inline"), Ok(true));
+    /// assert_eq!(pipeline.passes(), &[Pass::OptRoots, Pass::DedupRoots]);
+    /// assert_eq!(pipeline.apply_flag("-Zopt-pass-fuel=opt_roots=100"), Ok(true));
+    /// assert_eq!(pipeline.apply_flag("-Zthreads=8"), Ok(false));
+    /// assert!(pipeline.apply_flag("-Zopt-passes=not_a_pass").is_err());
+    /// ```
+    pub fn apply_flag(&mut self, flag: &str) -> Result<bool, String> {
+        let flag = flag.strip_prefix("-Z").unwrap_or(flag);
+        let Some((name, value)) = flag.split_once('=') else {
+            return Ok(false);
+        };
+        let list = || value.split(',').filter(|item| !item.trim().is_empty());
+        match name {
+            "opt-passes" => {
+                self.passes = list().map(str::parse).collect::<Result<_, _>>()?;
+            }
+            "opt-disable" => {
+                let disabled: Vec<Pass> = list().map(str::parse).collect::<Result<_, _>>()?;
+                self.passes.retain(|pass| !disabled.contains(pass));
+            }
+            "opt-pass-fuel" => {
+                for item in list() {
+                    let (pass, fuel) = item
+                        .split_once('=')
+                        .ok_or_else(|| format!("Expected `pass=fuel`, got {item:?}"))?;
+                    let fuel = fuel
+                        .trim()
+                        .parse::<u32>()
+                        .map_err(|err| format!("Invalid fuel {fuel:?}: {err}"))?;
+                    self.limits.insert(pass.parse()?, OptFuel::new(fuel));
+                }
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+    /// The passes this pipeline runs, in order.
+    #[must_use]
+    pub fn passes(&self) -> &[Pass] {
+        &self.passes
+    }
+    /// Only keeps the first `count` passes of this pipeline. Used to bisect miscompilations by pass.
+    pub fn truncate(&mut self, count: usize) {
+        self.passes.truncate(count);
+    }
+    /// Only optimizes `methods`, leaving all the other methods untouched. Used to bisect miscompilations by method.
+    pub fn restrict_to(&mut self, methods: impl IntoIterator<Item = MethodDefIdx>) {
+        self.methods = Some(methods.into_iter().collect());
+    }
+    fn is_optimized(&self, method: MethodDefIdx) -> bool {
+        self.methods
+            .as_ref()
+            .is_none_or(|methods| methods.contains(&method))
+    }
+    /// Enables counting the nodes removed by each pass. This requires walking every method body after each pass.
+    pub fn measure_nodes(&mut self, measure_nodes: bool) {
+        self.measure_nodes = measure_nodes;
+    }
+    /// The statistics of `pass`, if it ran.
+    #[must_use]
+    pub fn stats(&self, pass: Pass) -> Option<&PassStats> {
+        self.stats.get(&pass)
+    }
+    /// Gives a pass its share of `fuel`, runs it, and charges the fuel used against both budgets.
+    fn with_fuel(&mut self, pass: Pass, fuel: &mut OptFuel, run: impl FnOnce(&mut OptFuel)) {
+        let mut pass_fuel = match self.limits.get(&pass) {
+            Some(limit) if limit.exchausted() => return,
+            Some(limit) => OptFuel::new(limit.raw().min(fuel.raw())),
+            None => fuel.clone(),
+        };
+        let start = std::time::Instant::now();
+        let before = pass_fuel.raw();
+        run(&mut pass_fuel);
+        let used = before - pass_fuel.raw();
+        fuel.consume(used);
+        if let Some(limit) = self.limits.get_mut(&pass) {
+            limit.consume(used);
+        }
+        let stats = self.stats.entry(pass).or_default();
+        stats.runs += 1;
+        stats.time += start.elapsed();
+        stats.fuel += u64::from(used);
+    }
+    /// Runs every pass of the pipeline once over the whole assembly.
+    pub fn run_once(
+        &mut self,
+        asm: &mut Assembly,
+        fuel: &mut OptFuel,
+        cache: &mut SideEffectInfoCache,
+    ) {
+        let passes = self.passes.clone();
+        for group in passes.chunk_by(|a, b| !a.is_assembly_wide() && !b.is_assembly_wide()) {
+            if let [pass] = group {
+                if pass.is_assembly_wide() {
+                    self.run_assembly_pass(*pass, asm, fuel);
+                    continue;
+                }
+            }
+            let method_def_idxs: Box<[_]> = asm
+                .method_defs()
+                .keys()
+                .copied()
+                .filter(|method| self.is_optimized(*method))
+                .collect();
+            for method in method_def_idxs {
+                let mut tmp_method = asm.borrow_methoddef(method);
+                for pass in group {
+                    self.run_method_pass(*pass, &mut tmp_method, asm, cache, fuel);
+                }
+                asm.return_methoddef(method, tmp_method);
+                if fuel.exchausted() {
+                    break;
+                }
+            }
+        }
+    }
+    fn run_assembly_pass(&mut self, pass: Pass, asm: &mut Assembly, fuel: &mut OptFuel) {
+        let measure_nodes = self.measure_nodes;
+        let size = |asm: &Assembly| {
+            asm.method_defs()
+                .values()
+                .map(|method| BodySize::of(method, asm, measure_nodes))
+                .fold(BodySize::default(), |a, b| BodySize {
+                    roots: a.roots + b.roots,
+                    nodes: a.nodes + b.nodes,
+                })
+        };
+        let before = size(asm);
+        let methods = self.methods.clone();
+        self.with_fuel(pass, fuel, |fuel| match pass {
+            Pass::Inline => {
+                asm.inline_calls_in(fuel, |method| {
+                    methods
+                        .as_ref()
+                        .is_none_or(|methods| methods.contains(&method))
+                });
+            }
+            _ => unreachable!("{pass} is not an assembly-wide pass"),
+        });
+        self.record_size(pass, before, size(asm));
+    }
+    /// Runs a single pass on `method`.
+    pub fn run_method_pass(
+        &mut self,
+        pass: Pass,
+        method: &mut MethodDef,
+        asm: &mut Assembly,
+        cache: &mut SideEffectInfoCache,
+        fuel: &mut OptFuel,
+    ) {
+        let before = BodySize::of(method, asm, self.measure_nodes);
+        self.with_fuel(pass, fuel, |fuel| method.run_pass(pass, asm, cache, fuel));
+        let after = BodySize::of(method, asm, self.measure_nodes);
+        self.record_size(pass, before, after);
+    }
+    fn record_size(&mut self, pass: Pass, before: BodySize, after: BodySize) {
+        if let Some(stats) = self.stats.get_mut(&pass) {
+            stats.roots_removed += before.roots - after.roots;
+            stats.nodes_removed += before.nodes - after.nodes;
+        }
+    }
+    /// Writes the statistics to the path in `OPT_REPORT`, if it is set.
+    /// # Panics
+    /// Panics if the report could not be written.
+    pub fn write_report_from_env(&self) {
+        if let Ok(path) = std::env::var("OPT_REPORT") {
+            std::fs::write(&path, self.to_string()).unwrap_or_else(|err| {
+                panic!("Could not write the optimization report to {path:?}: {err}")
+            });
+        }
+    }
+}
+impl Display for Pipeline {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{:<24} {:>8} {:>10} {:>10} {:>14} {:>14}",
+            "pass", "runs", "time(ms)", "fuel", "roots removed", "nodes removed"
+        )?;
+        for pass in &self.passes {
+            let stats = self.stats.get(pass).cloned().unwrap_or_default();
+            write!(
+                f,
+                "{:<24} {:>8} {:>10} {:>10} {:>14} ",
+                pass.name(),
+                stats.runs,
+                stats.time.as_millis(),
+                stats.fuel,
+                stats.roots_removed,
+            )?;
+            if self.measure_nodes {
+                writeln!(f, "{:>14}", stats.nodes_removed)?;
+            } else {
+                writeln!(f, "{:>14}", "-")?;
+            }
+        }
+        Ok(())
+    }
+}
+impl MethodDef {
+    /// Runs a single method-level optimization pass.
+    /// # Panics
+    /// Panics if `pass` is an assembly-wide pass.
+    pub fn run_pass(
+        &mut self,
+        pass: Pass,
+        asm: &mut Assembly,
+        cache: &mut SideEffectInfoCache,
+        fuel: &mut OptFuel,
+    ) {
+        match pass {
+            Pass::Inline => panic!("{pass} is not a method-level pass"),
+            Pass::SplitStructs => self.split_local_structs(asm, fuel),
+            Pass::PropagateLocals => {
+                let sig = self.sig();
+                self.implementation_mut()
+                    .propagate_locals(asm, cache, fuel, sig);
+            }
+            Pass::RemoveDeadWrites => self
+                .implementation_mut()
+                .remove_dead_writes(asm, cache, fuel),
+            Pass::ReallocLocals => {
+                if fuel.consume(1) {
+                    self.implementation_mut().realloc_locals(asm);
+                }
+            }
+            Pass::DedupRoots => {
+                if fuel.consume(15) {
+                    self.dedup_roots(asm);
+                }
+            }
+            Pass::OptRoots => {
+                if fuel.consume(1) {
+                    self.opt_roots(fuel, cache, asm);
+                }
+            }
+            Pass::RemoveDuplicateSfi => {
+                if fuel.consume(1) {
+                    self.implementation_mut().remove_duplicate_sfi(asm);
+                }
+            }
+            Pass::LinearizeBlocks => {
+                if let MethodImpl::MethodBody { blocks, .. } = self.implementation_mut() {
+                    if let Some(block) = linearize_blocks(blocks, asm) {
+                        *blocks = vec![block];
+                    }
+                }
+            }
+            Pass::RemoveUselessHandlers => self.remove_useless_handlers(asm, fuel, cache),
+            Pass::RemoveDeadBlocks => self.remove_dead_blocks(asm),
+        }
+    }
+}
+#[test]
+fn pipeline_config() {
+    let mut pipeline = Pipeline::new(Pass::ALL.to_vec());
+    assert_eq!(
+        pipeline.apply_flag("opt-disable=inline,remove-dead-blocks"),
+        Ok(true)
+    );
+    assert_eq!(pipeline.passes().len(), Pass::ALL.len() - 2);
+    assert!(!pipeline.passes().contains(&Pass::Inline));
+    assert!(pipeline.apply_flag("opt-pass-fuel=opt_roots").is_err());
+    assert!(pipeline.apply_flag("opt-pass-fuel=opt_roots=many").is_err());
+    pipeline.truncate(2);
+    assert_eq!(pipeline.passes(), &Pass::ALL[1..3]);
+    for pass in Pass::ALL {
+        assert_eq!(pass.name().parse::<Pass>(), Ok(pass));
+    }
+}
+#[cfg(test)]
+fn folded_asm() -> (Assembly, MethodDefIdx) {
+    use crate::v2::{cilnode::MethodKind, Access, BasicBlock, CILNode, CILRoot, Type};
+    let mut asm = Assembly::default();
+    let main_module = asm.main_module();
+    let sig = asm.sig([], Type::Int(crate::v2::Int::I32));
+    let name = asm.alloc_string("folded");
+    let two = asm.alloc_node(2_i32);
+    let sum = asm.alloc_node(CILNode::BinOp(two, two, crate::v2::BinOp::Add));
+    let ret = asm.alloc_root(CILRoot::Ret(sum));
+    let nop = asm.alloc_root(CILRoot::Nop);
+    let method = asm.new_method(MethodDef::new(
+        Access::Public,
+        main_module,
+        name,
+        sig,
+        MethodKind::Static,
+        MethodImpl::MethodBody {
+            blocks: vec![BasicBlock::new(vec![nop, nop, ret], 0, None)],
+            locals: vec![],
+        },
+        vec![],
+    ));
+    (asm, method)
+}
+#[test]
+fn pipeline_restrict() {
+    let (mut asm, method) = folded_asm();
+    let original = asm[method].clone();
+    let mut pipeline = Pipeline::default();
+    pipeline.restrict_to([]);
+    asm.opt_with(&mut OptFuel::new(1000), &mut pipeline);
+    assert_eq!(asm[method], original);
+    pipeline.restrict_to([method]);
+    asm.opt_with(&mut OptFuel::new(1000), &mut pipeline);
+    assert_ne!(asm[method], original);
+}
+#[test]
+fn pipeline_stats() {
+    let (mut asm, _) = folded_asm();
+    let mut pipeline = Pipeline::new(vec![
+        Pass::DedupRoots,
+        Pass::RemoveDeadWrites,
+        Pass::OptRoots,
+    ]);
+    pipeline.measure_nodes(true);
+    // Dedup roots may only run once.
+    pipeline.apply_flag("opt-pass-fuel=dedup_roots=15").unwrap();
+    let mut fuel = OptFuel::new(1000);
+    let mut cache = SideEffectInfoCache::default();
+    pipeline.run_once(&mut asm, &mut fuel, &mut cache);
+    pipeline.run_once(&mut asm, &mut fuel, &mut cache);
+    let dedup = pipeline.stats(Pass::DedupRoots).unwrap();
+    assert_eq!(dedup.runs, 1);
+    assert_eq!(dedup.fuel, 15);
+    // Nops are removed along with dead writes.
+    assert_eq!(
+        pipeline
+            .stats(Pass::RemoveDeadWrites)
+            .unwrap()
+            .roots_removed,
+        2
+    );
+    let opt = pipeline.stats(Pass::OptRoots).unwrap();
+    assert_eq!(opt.runs, 2);
+    assert_eq!(opt.nodes_removed, 2);
+    assert!(pipeline.to_string().contains("dedup_roots"));
+    let total: u64 = [Pass::DedupRoots, Pass::RemoveDeadWrites, Pass::OptRoots]
+        .into_iter()
+        .map(|pass| pipeline.stats(pass).unwrap().fuel)
+        .sum();
+    assert_eq!(u64::from(1000 - fuel.raw()), total);
+}
@@ -48,6 +48,21 @@ pub fn root_opt(
         CILRoot::InitObj(addr, tpe) => opt_init_obj(addr, tpe, asm, root_fuel),
         CILRoot::Branch(ref info) => {
             let (target, sub_target, cond) = info.as_ref();
+            // Branches on constant conditions are either always or never taken.
+            match cond
+                .as_ref()
+                .and_then(|cond| super::opt_node::fold_branch_cond(cond, asm))
+            {
+                Some(true) => {
+                    return opt_if_fuel(
+                        CILRoot::Branch(Box::new((*target, *sub_target, None))),
+                        root,
+                        root_fuel,
+                    )
+                }
+                Some(false) => return opt_if_fuel(CILRoot::Nop, root, root_fuel),
+                None => (),
+            }
             match cond {
                 Some(BranchCond::False(cond)) => {
                     match asm.get_node(*cond) {
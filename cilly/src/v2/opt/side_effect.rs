@@ -39,7 +39,9 @@ impl SideEffectInfoCache {
                 self.has_side_effects(*object, asm) // Class checks / casts / unboxes have no side effects.
             }
             CILNode::CallI(_) => true, // Indidrect calls may have side effects
-            CILNode::LocAllocAlgined { .. } | CILNode::LocAlloc { .. } => true, // Allocation has side effects
+            CILNode::LocAllocAlgined { .. } | CILNode::LocAlloc { .. } | CILNode::NewArr { .. } => {
+                true // Allocation has side effects
+            }
             CILNode::LdStaticField(_) | CILNode::LdStaticFieldAdress(_) => false, // Loading static fields has no side effects.
             CILNode::LdLen(arr) => self.has_side_effects(*arr, asm), // Loading a length only has side effects if the index has array.
             CILNode::LdElelemRef { array, index } => {
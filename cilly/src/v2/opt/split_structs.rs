@@ -0,0 +1,634 @@
+use fxhash::FxHashMap;
+
+use super::{local_usage::MethodLocalUsages, OptFuel};
+use crate::{
+    config,
+    v2::{
+        method::LocalDef, Assembly, BasicBlock, CILNode, CILRoot, Const, FieldDesc, FieldIdx,
+        Float, MethodDef, MethodImpl, NodeIdx, RootIdx, SigIdx, Type,
+    },
+};
+config!(
+    SPLIT_LOCAL_STRUCTS,
+    bool,
+    false,
+    "Turns on the struct spliting optimzation: valuetype locals, which are only ever accessed field by field, get replaced with one local per field."
+);
+/// Structs with more fields than this are never split.
+const MAX_SPLIT_FIELDS: usize = 16;
+/// The biggest size a value of this type could have, if known.
+fn max_size(tpe: Type, asm: &Assembly) -> Option<u32> {
+    match tpe {
+        Type::Int(int) => Some(int.size().map_or(8, u32::from)),
+        Type::Float(float) => Some(u32::from(float.size())),
+        Type::Bool => Some(1),
+        Type::Ptr(_) | Type::Ref(_) | Type::FnPtr(_) => Some(8),
+        Type::ClassRef(cref) if !asm[cref].is_valuetype() => Some(8),
+        _ => None,
+    }
+}
+/// Returns the fields of `tpe`, if a local of this type could be split.
+fn split_fields(tpe: Type, asm: &mut Assembly) -> Option<Box<[(Type, FieldIdx)]>> {
+    let Type::ClassRef(cref) = tpe else {
+        return None;
+    };
+    if !asm[cref].is_valuetype() || !asm[cref].generics().is_empty() {
+        return None;
+    }
+    let def = asm.get_class_def(asm.class_ref_to_def(cref)?);
+    let fields = def.fields().to_vec();
+    if fields.is_empty() || fields.len() > MAX_SPLIT_FIELDS {
+        return None;
+    }
+    // Fields must not overlap, like the fields of unions and enums do.
+    let mut offsets: Vec<_> = fields
+        .iter()
+        .filter_map(|(tpe, _, offset)| Some(((*offset)?, *tpe)))
+        .collect();
+    if !offsets.is_empty() && offsets.len() != fields.len() {
+        return None;
+    }
+    offsets.sort_by_key(|(offset, _)| *offset);
+    for pair in offsets.windows(2) {
+        if pair[0].0 + max_size(pair[0].1, asm)? > pair[1].0 {
+            return None;
+        }
+    }
+    Some(
+        fields
+            .into_iter()
+            .map(|(tpe, name, _)| (tpe, asm.alloc_field(FieldDesc::new(cref, name, tpe))))
+            .collect(),
+    )
+}
+/// A place a struct is copied to or from.
+#[derive(Clone, Copy)]
+enum Place {
+    /// A local which gets split.
+    Split(u32),
+    /// An address of a struct in memory.
+    Addr(NodeIdx),
+}
+/// Splits the valuetype locals of a single method.
+struct StructSplitter {
+    /// Fields of the locals which could be split.
+    candidates: FxHashMap<u32, Box<[(Type, FieldIdx)]>>,
+    /// The first new local of each split local.
+    split: FxHashMap<u32, u32>,
+}
+impl StructSplitter {
+    /// The local, whose address this node is, if it is a candidate for splitting.
+    fn candidate_addr(&self, addr: NodeIdx, asm: &Assembly) -> Option<u32> {
+        match asm[addr] {
+            CILNode::LdLocA(loc) if self.candidates.contains_key(&loc) => Some(loc),
+            _ => None,
+        }
+    }
+    fn field_index(&self, loc: u32, field: FieldIdx) -> Option<usize> {
+        self.candidates[&loc]
+            .iter()
+            .position(|(_, candidate)| *candidate == field)
+    }
+    /// Checks if `tpe` is the type of the candidate local `loc`.
+    fn is_local_type(loc: u32, tpe: Type, locals: &[LocalDef], asm: &Assembly) -> bool {
+        asm[locals[loc as usize].1] == tpe
+    }
+    fn count_node(&self, node: NodeIdx, usage: &mut MethodLocalUsages, asm: &Assembly) {
+        match &asm[node] {
+            CILNode::LdField { addr, field } => {
+                if let Some(loc) = self.candidate_addr(*addr, asm) {
+                    if self.field_index(loc, *field).is_some() {
+                        usage[loc].byval_indirect();
+                    } else {
+                        usage[loc].byaddr_direct();
+                    }
+                } else {
+                    self.count_node(*addr, usage, asm);
+                }
+            }
+            CILNode::LdLoc(loc) => usage[*loc].byval_direct(),
+            CILNode::LdLocA(loc) => usage[*loc].byaddr_direct(),
+            node => {
+                for child in node.child_nodes() {
+                    self.count_node(child, usage, asm);
+                }
+            }
+        }
+    }
+    /// Counts the uses of a struct address, which is copied to or from as a whole.
+    #[allow(clippy::too_many_arguments)]
+    fn count_copy_addr(
+        &self,
+        addr: NodeIdx,
+        tpe: Type,
+        usage: &mut MethodLocalUsages,
+        sig: SigIdx,
+        locals: &[LocalDef],
+        asm: &mut Assembly,
+    ) -> bool {
+        match self.candidate_addr(addr, asm) {
+            Some(loc) if Self::is_local_type(loc, tpe, locals, asm) => {
+                usage[loc].byaddr_indirect();
+                true
+            }
+            _ => {
+                self.count_node(addr, usage, asm);
+                // The address will be saved in a new local, so its type must be known.
+                asm[addr].clone().typecheck(sig, locals, asm).is_ok()
+            }
+        }
+    }
+    fn count_root(
+        &self,
+        root: RootIdx,
+        usage: &mut MethodLocalUsages,
+        sig: SigIdx,
+        locals: &[LocalDef],
+        asm: &mut Assembly,
+    ) {
+        match asm[root].clone() {
+            CILRoot::SetField(info) if self.candidate_addr(info.1, asm).is_some() => {
+                let (field, addr, val) = *info;
+                let loc = self.candidate_addr(addr, asm).unwrap();
+                if self.field_index(loc, field).is_some() {
+                    usage[loc].byaddr_indirect();
+                } else {
+                    usage[loc].byaddr_direct();
+                }
+                self.count_node(val, usage, asm);
+            }
+            CILRoot::InitObj(addr, tpe) if self.candidate_addr(addr, asm).is_some() => {
+                let loc = self.candidate_addr(addr, asm).unwrap();
+                if Self::is_local_type(loc, asm[tpe], locals, asm) {
+                    usage[loc].byaddr_indirect();
+                } else {
+                    usage[loc].byaddr_direct();
+                }
+            }
+            CILRoot::CpObj { src, dst, tpe }
+                if self.candidate_addr(src, asm).is_some()
+                    || self.candidate_addr(dst, asm).is_some() =>
+            {
+                let tpe = asm[tpe];
+                let src_ok = self.count_copy_addr(src, tpe, usage, sig, locals, asm);
+                let dst_ok = self.count_copy_addr(dst, tpe, usage, sig, locals, asm);
+                if !src_ok || !dst_ok {
+                    self.escape(&[src, dst], usage, asm);
+                }
+            }
+            CILRoot::StLoc(loc, val) if self.candidates.contains_key(&loc) => {
+                let tpe = asm[locals[loc as usize].1];
+                match asm[val].clone() {
+                    CILNode::LdLoc(src) if Self::is_local_type(src, tpe, locals, asm) => {
+                        usage[loc].byaddr_indirect();
+                        usage[src].byval_indirect();
+                    }
+                    CILNode::LdInd {
+                        addr,
+                        tpe: ind_tpe,
+                        volatile: false,
+                    } if asm[ind_tpe] == tpe => {
+                        if self.count_copy_addr(addr, tpe, usage, sig, locals, asm) {
+                            usage[loc].byaddr_indirect();
+                        } else {
+                            usage[loc].assign();
+                        }
+                    }
+                    _ => {
+                        usage[loc].assign();
+                        self.count_node(val, usage, asm);
+                    }
+                }
+            }
+            CILRoot::StLoc(loc, val) => match asm[val] {
+                CILNode::LdLoc(src)
+                    if self.candidates.contains_key(&src)
+                        && Self::is_local_type(src, asm[locals[loc as usize].1], locals, asm) =>
+                {
+                    usage[src].byval_indirect();
+                    usage[loc].byaddr_direct();
+                }
+                _ => self.count_node(val, usage, asm),
+            },
+            CILRoot::StInd(info) => {
+                let (addr, val, tpe, volatile) = *info;
+                match asm[val] {
+                    CILNode::LdLoc(src)
+                        if !volatile
+                            && self.candidates.contains_key(&src)
+                            && Self::is_local_type(src, tpe, locals, asm) =>
+                    {
+                        if self.count_copy_addr(addr, tpe, usage, sig, locals, asm) {
+                            usage[src].byval_indirect();
+                        } else {
+                            usage[src].byval_direct();
+                        }
+                    }
+                    _ => {
+                        self.count_node(addr, usage, asm);
+                        self.count_node(val, usage, asm);
+                    }
+                }
+            }
+            root => {
+                for node in root.nodes() {
+                    self.count_node(*node, usage, asm);
+                }
+            }
+        }
+    }
+    /// Marks all the candidates used by `nodes` as escaping.
+    fn escape(&self, nodes: &[NodeIdx], usage: &mut MethodLocalUsages, asm: &Assembly) {
+        for node in nodes {
+            if let Some(loc) = self.candidate_addr(*node, asm) {
+                usage[loc].byaddr_direct();
+            }
+        }
+    }
+    fn split_addr(&self, addr: NodeIdx, asm: &Assembly) -> Option<u32> {
+        match asm[addr] {
+            CILNode::LdLocA(loc) if self.split.contains_key(&loc) => Some(loc),
+            _ => None,
+        }
+    }
+    fn place(&self, addr: NodeIdx, asm: &Assembly) -> Place {
+        self.split_addr(addr, asm)
+            .map_or(Place::Addr(addr), Place::Split)
+    }
+    /// Copies a struct field by field, from `src` to `dst`.
+    #[allow(clippy::too_many_arguments)]
+    fn copy(
+        &self,
+        src: Place,
+        dst: Place,
+        sig: SigIdx,
+        locals: &mut Vec<LocalDef>,
+        roots: &mut Vec<RootIdx>,
+        asm: &mut Assembly,
+    ) {
+        let split = match (src, dst) {
+            (Place::Split(loc), _) | (_, Place::Split(loc)) => loc,
+            (Place::Addr(_), Place::Addr(_)) => panic!("Copy between two unsplit structs"),
+        };
+        // Addresses other than the ones of locals may have side effects, so they are evaluated only once.
+        let mut spill = |place: Place, locals: &mut Vec<LocalDef>, asm: &mut Assembly| match place {
+            Place::Addr(addr) if !matches!(asm[addr], CILNode::LdLocA(_)) => {
+                let tpe = asm[addr]
+                    .clone()
+                    .typecheck(sig, locals, asm)
+                    .expect("Struct address was checked to typecheck");
+                let tmp = u32::try_from(locals.len()).unwrap();
+                locals.push((None, asm.alloc_type(tpe)));
+                roots.push(asm.alloc_root(CILRoot::StLoc(tmp, addr)));
+                Place::Addr(asm.alloc_node(CILNode::LdLoc(tmp)))
+            }
+            place => place,
+        };
+        let dst = spill(dst, locals, asm);
+        let src = spill(src, locals, asm);
+        for (idx, (_, field)) in self.candidates[&split].iter().enumerate() {
+            let idx = u32::try_from(idx).unwrap();
+            let val = match src {
+                Place::Split(loc) => CILNode::LdLoc(self.split[&loc] + idx),
+                Place::Addr(addr) => CILNode::LdField {
+                    addr,
+                    field: *field,
+                },
+            };
+            let val = asm.alloc_node(val);
+            let root = match dst {
+                Place::Split(loc) => CILRoot::StLoc(self.split[&loc] + idx, val),
+                Place::Addr(addr) => CILRoot::SetField(Box::new((*field, addr, val))),
+            };
+            roots.push(asm.alloc_root(root));
+        }
+    }
+    /// Rewrites a root, replacing all accesses to split locals with accesses to their field locals.
+    fn rewrite_root(
+        &self,
+        root: RootIdx,
+        sig: SigIdx,
+        locals: &mut Vec<LocalDef>,
+        roots: &mut Vec<RootIdx>,
+        asm: &mut Assembly,
+    ) {
+        let root = asm[root]
+            .clone()
+            .map(asm, &mut |root, _| root, &mut |node, asm| match node {
+                CILNode::LdField { addr, field } => match self.split_addr(addr, asm) {
+                    Some(loc) => {
+                        let idx = self.field_index(loc, field).unwrap();
+                        CILNode::LdLoc(self.split[&loc] + u32::try_from(idx).unwrap())
+                    }
+                    None => node,
+                },
+                _ => node,
+            });
+        match root {
+            CILRoot::SetField(info) if self.split_addr(info.1, asm).is_some() => {
+                let (field, addr, val) = *info;
+                let loc = self.split_addr(addr, asm).unwrap();
+                let idx = u32::try_from(self.field_index(loc, field).unwrap()).unwrap();
+                roots.push(asm.alloc_root(CILRoot::StLoc(self.split[&loc] + idx, val)));
+            }
+            CILRoot::InitObj(addr, _) if self.split_addr(addr, asm).is_some() => {
+                let loc = self.split_addr(addr, asm).unwrap();
+                for (idx, (tpe, _)) in self.candidates[&loc].iter().enumerate() {
+                    let field_loc = self.split[&loc] + u32::try_from(idx).unwrap();
+                    let zero = match tpe {
+                        Type::Int(int) => Some(int.zero()),
+                        Type::Float(float @ (Float::F32 | Float::F64)) => Some(float.zero()),
+                        Type::Bool => Some(Const::Bool(false)),
+                        _ => None,
+                    };
+                    let root = if let Some(zero) = zero {
+                        CILRoot::StLoc(field_loc, asm.alloc_node(zero))
+                    } else {
+                        let addr = asm.alloc_node(CILNode::LdLocA(field_loc));
+                        CILRoot::InitObj(addr, asm.alloc_type(*tpe))
+                    };
+                    roots.push(asm.alloc_root(root));
+                }
+            }
+            CILRoot::CpObj { src, dst, .. }
+                if self.split_addr(src, asm).is_some() || self.split_addr(dst, asm).is_some() =>
+            {
+                let src = self.place(src, asm);
+                let dst = self.place(dst, asm);
+                self.copy(src, dst, sig, locals, roots, asm);
+            }
+            CILRoot::StLoc(loc, val)
+                if self.split.contains_key(&loc)
+                    || matches!(asm[val], CILNode::LdLoc(src) if self.split.contains_key(&src)) =>
+            {
+                let dst = if self.split.contains_key(&loc) {
+                    Place::Split(loc)
+                } else {
+                    Place::Addr(asm.alloc_node(CILNode::LdLocA(loc)))
+                };
+                let src = match asm[val] {
+                    CILNode::LdLoc(src) if self.split.contains_key(&src) => Place::Split(src),
+                    CILNode::LdLoc(src) => Place::Addr(asm.alloc_node(CILNode::LdLocA(src))),
+                    CILNode::LdInd { addr, .. } => Place::Addr(addr),
+                    _ => panic!("Split local assigned an unsplitable value"),
+                };
+                self.copy(src, dst, sig, locals, roots, asm);
+            }
+            CILRoot::StInd(info) if matches!(asm[info.1], CILNode::LdLoc(src) if self.split.contains_key(&src)) =>
+            {
+                let CILNode::LdLoc(src) = asm[info.1] else {
+                    unreachable!()
+                };
+                self.copy(
+                    Place::Split(src),
+                    Place::Addr(info.0),
+                    sig,
+                    locals,
+                    roots,
+                    asm,
+                );
+            }
+            root => roots.push(asm.alloc_root(root)),
+        }
+    }
+    fn rewrite_block(
+        &self,
+        block: &mut BasicBlock,
+        sig: SigIdx,
+        locals: &mut Vec<LocalDef>,
+        asm: &mut Assembly,
+    ) {
+        let mut roots = Vec::with_capacity(block.roots().len());
+        for root in block.roots().to_vec() {
+            self.rewrite_root(root, sig, locals, &mut roots, asm);
+        }
+        *block.roots_mut() = roots;
+        if let Some(handler) = block.handler_mut() {
+            for block in handler {
+                self.rewrite_block(block, sig, locals, asm);
+            }
+        }
+    }
+}
+impl MethodDef {
+    /// Replaces valuetype locals, whose address never escapes, with one local per field.
+    /// This allows the runtime to keep those fields in registers.
+    pub fn split_local_structs(&mut self, asm: &mut Assembly, fuel: &mut OptFuel) {
+        let sig = self.sig();
+        let MethodImpl::MethodBody { blocks, locals } = self.implementation_mut() else {
+            return;
+        };
+        let mut splitter = StructSplitter {
+            candidates: FxHashMap::default(),
+            split: FxHashMap::default(),
+        };
+        for (loc, (_, tpe)) in locals.iter().enumerate() {
+            // Pinned locals must stay whole, since splitting them would unpin their value.
+            if super::is_pinned(u32::try_from(loc).unwrap(), locals, asm) {
+                continue;
+            }
+            if let Some(fields) = split_fields(asm[*tpe], asm) {
+                splitter
+                    .candidates
+                    .insert(u32::try_from(loc).unwrap(), fields);
+            }
+        }
+        if splitter.candidates.is_empty() {
+            return;
+        }
+        let mut usage = MethodLocalUsages::new(locals.len());
+        for root in blocks.iter().flat_map(BasicBlock::iter_roots) {
+            splitter.count_root(root, &mut usage, sig, locals, asm);
+        }
+        let mut candidates: Vec<_> = splitter.candidates.keys().copied().collect();
+        candidates.sort_unstable();
+        for loc in candidates {
+            let fields = &splitter.candidates[&loc];
+            if !usage[loc].is_only_indirect()
+                || usage[loc].is_unused()
+                || !fuel.consume(u32::try_from(fields.len()).unwrap())
+            {
+                continue;
+            }
+            let name = locals[loc as usize].0;
+            splitter
+                .split
+                .insert(loc, u32::try_from(locals.len()).unwrap());
+            for (tpe, field) in fields.iter() {
+                let name = name.map(|name| {
+                    let field_name = asm[*field].name();
+                    let split_name = format!("{}_{}", &asm[name], &asm[field_name]);
+                    asm.alloc_string(split_name)
+                });
+                locals.push((name, asm.alloc_type(*tpe)));
+            }
+        }
+        if splitter.split.is_empty() {
+            return;
+        }
+        for block in blocks.iter_mut() {
+            splitter.rewrite_block(block, sig, locals, asm);
+        }
+    }
+}
+#[cfg(test)]
+fn pair_asm() -> (Assembly, Type, FieldIdx, FieldIdx) {
+    use crate::v2::{Access, ClassDef, Int};
+    let mut asm = Assembly::default();
+    let name = asm.alloc_string("Pair");
+    let a = asm.alloc_string("a");
+    let b = asm.alloc_string("b");
+    let pair = asm.class_def(ClassDef::new(
+        name,
+        true,
+        0,
+        None,
+        vec![
+            (Type::Int(Int::I32), a, Some(0)),
+            (Type::Int(Int::I64), b, Some(8)),
+        ],
+        vec![],
+        Access::Public,
+        None,
+        None,
+    ));
+    let a = asm.alloc_field(FieldDesc::new(*pair, a, Type::Int(Int::I32)));
+    let b = asm.alloc_field(FieldDesc::new(*pair, b, Type::Int(Int::I64)));
+    (asm, Type::ClassRef(*pair), a, b)
+}
+#[test]
+#[cfg(not(miri))]
+fn split_pair() {
+    use crate::v2::{
+        c_exporter::CExporter, cilnode::ExtendKind, cilnode::MethodKind, Access, BinOp, BranchCond,
+        CILIter, CILIterElem, Int,
+    };
+    let (mut asm, pair, a, b) = pair_asm();
+    let main_module = asm.main_module();
+    let pair_idx = asm.alloc_type(pair);
+    // sum_pair(x): let mut p = Pair::default(); p.a = x; let mut q = p; q.b = 5; q.a + q.b + p.b
+    let p = asm.alloc_node(CILNode::LdLocA(0));
+    let q = asm.alloc_node(CILNode::LdLocA(1));
+    let arg = asm.alloc_node(CILNode::LdArg(0));
+    let five = asm.alloc_node(Const::I64(5));
+    let roots = [
+        CILRoot::InitObj(p, pair_idx),
+        CILRoot::SetField(Box::new((a, p, arg))),
+        CILRoot::CpObj {
+            src: p,
+            dst: q,
+            tpe: pair_idx,
+        },
+        CILRoot::SetField(Box::new((b, q, five))),
+    ];
+    let mut roots: Vec<_> = roots.into_iter().map(|root| asm.alloc_root(root)).collect();
+    let qa = asm.alloc_node(CILNode::LdField { addr: q, field: a });
+    let qa = asm.alloc_node(CILNode::IntCast {
+        input: qa,
+        target: Int::I64,
+        extend: ExtendKind::SignExtend,
+    });
+    let qb = asm.alloc_node(CILNode::LdField { addr: q, field: b });
+    let pb = asm.alloc_node(CILNode::LdField { addr: p, field: b });
+    let sum = asm.alloc_node(CILNode::BinOp(qa, qb, BinOp::Add));
+    let sum = asm.alloc_node(CILNode::BinOp(sum, pb, BinOp::Add));
+    roots.push(asm.alloc_root(CILRoot::Ret(sum)));
+    let sig = asm.sig([Type::Int(Int::I32)], Type::Int(Int::I64));
+    let name = asm.alloc_string("sum_pair");
+    let mut def = MethodDef::new(
+        Access::Extern,
+        main_module,
+        name,
+        sig,
+        MethodKind::Static,
+        MethodImpl::MethodBody {
+            blocks: vec![BasicBlock::new(roots, 0, None)],
+            locals: vec![(None, pair_idx), (None, pair_idx)],
+        },
+        vec![None],
+    );
+    def.split_local_structs(&mut asm, &mut OptFuel::new(100));
+    let MethodImpl::MethodBody { blocks, locals } = def.implementation() else {
+        panic!()
+    };
+    assert_eq!(locals.len(), 6);
+    assert!(!blocks
+        .iter()
+        .flat_map(BasicBlock::iter_roots)
+        .flat_map(|root| CILIter::new(asm[root].clone(), &asm))
+        .any(|elem| matches!(elem, CILIterElem::Node(CILNode::LdLocA(_)))));
+    let sum_pair = asm.new_method(def);
+    // Check that the split method still computes the same value.
+    let three = asm.alloc_node(Const::I32(3));
+    let call = asm.alloc_node(CILNode::Call(Box::new((*sum_pair, [three].into()))));
+    let eight = asm.alloc_node(Const::I64(8));
+    let branch = asm.alloc_root(CILRoot::Branch(Box::new((
+        1,
+        0,
+        Some(BranchCond::Ne(call, eight)),
+    ))));
+    let ret = asm.alloc_root(CILRoot::VoidRet);
+    let msg = asm.alloc_string("Wrong result");
+    let unreachable = asm.alloc_root(CILRoot::Unreachable(msg));
+    let u8_ptr = asm.nptr(Type::Int(Int::U8));
+    let sig = asm.sig([u8_ptr], Type::Void);
+    let name = asm.alloc_string("entrypoint");
+    asm.new_method(MethodDef::new(
+        Access::Extern,
+        main_module,
+        name,
+        sig,
+        MethodKind::Static,
+        MethodImpl::MethodBody {
+            blocks: vec![
+                BasicBlock::new(vec![branch, ret], 0, None),
+                BasicBlock::new(vec![unreachable], 1, None),
+            ],
+            locals: vec![],
+        },
+        vec![None],
+    ));
+    let exe = std::env::temp_dir().join("cilly_split_pair");
+    asm.export(&exe, CExporter::new(false));
+    let out = std::process::Command::new(&exe).output().unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+}
+#[test]
+fn split_escaping() {
+    use crate::v2::{cilnode::MethodKind, Access, Int};
+    let (mut asm, pair, a, _) = pair_asm();
+    let main_module = asm.main_module();
+    let pair_idx = asm.alloc_type(pair);
+    let p = asm.alloc_node(CILNode::LdLocA(0));
+    let one = asm.alloc_node(Const::I32(1));
+    let set = asm.alloc_root(CILRoot::SetField(Box::new((a, p, one))));
+    // The address of the struct is returned, so it can't be split.
+    let ret = asm.alloc_root(CILRoot::Ret(p));
+    let ptr = asm.nptr(pair);
+    let sig = asm.sig([], ptr);
+    let name = asm.alloc_string("escape");
+    let mut def = MethodDef::new(
+        Access::Extern,
+        main_module,
+        name,
+        sig,
+        MethodKind::Static,
+        MethodImpl::MethodBody {
+            blocks: vec![BasicBlock::new(vec![set, ret], 0, None)],
+            locals: vec![
+                (None, pair_idx),
+                (None, asm.alloc_type(Type::Int(Int::I32))),
+            ],
+        },
+        vec![],
+    );
+    def.split_local_structs(&mut asm, &mut OptFuel::new(100));
+    let MethodImpl::MethodBody { locals, .. } = def.implementation() else {
+        panic!()
+    };
+    assert_eq!(locals.len(), 2);
+}
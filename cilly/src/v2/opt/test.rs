@@ -33,3 +33,442 @@ fn sfi_dedup() {
     bb.remove_duplicate_sfi(&mut asm);
     assert_eq!(bb.roots().len(), 2);
 }
+#[cfg(test)]
+fn fold(asm: &mut Assembly, node: crate::v2::CILNode) -> crate::v2::CILNode {
+    super::opt_node::opt_node(
+        node,
+        asm,
+        &mut super::OptFuel::new(100),
+        &mut super::SideEffectInfoCache::default(),
+    )
+}
+#[cfg(test)]
+fn fold_binop(
+    lhs: impl Into<crate::v2::CILNode>,
+    rhs: impl Into<crate::v2::CILNode>,
+    op: crate::v2::BinOp,
+) -> crate::v2::CILNode {
+    let mut asm = Assembly::default();
+    let lhs = asm.alloc_node(lhs);
+    let rhs = asm.alloc_node(rhs);
+    fold(&mut asm, crate::v2::CILNode::BinOp(lhs, rhs, op))
+}
+#[test]
+fn fold_int_arith() {
+    use crate::v2::{BinOp, CILNode};
+    // 32 and 64 bit intigers wrap.
+    assert_eq!(fold_binop(i32::MAX, 1_i32, BinOp::Add), i32::MIN.into());
+    assert_eq!(fold_binop(3_u64, 5_u64, BinOp::Sub), (u64::MAX - 1).into());
+    assert_eq!(
+        fold_binop(u64::MAX, 2_u64, BinOp::Mul),
+        (u64::MAX - 1).into()
+    );
+    assert_eq!(
+        fold_binop(0b1100_u32, 0b1010_u32, BinOp::And),
+        0b1000_u32.into()
+    );
+    assert_eq!(
+        fold_binop(0b1100_u32, 0b1010_u32, BinOp::Or),
+        0b1110_u32.into()
+    );
+    assert_eq!(
+        fold_binop(0b1100_u32, 0b1010_u32, BinOp::XOr),
+        0b0110_u32.into()
+    );
+    assert_eq!(fold_binop(u128::MAX, 1_u128, BinOp::Add), 0_u128.into());
+    // Small intigers are not truncated on the evaluation stack, so overflowing results are not folded.
+    assert_eq!(fold_binop(100_u8, 27_u8, BinOp::Add), 127_u8.into());
+    assert!(matches!(
+        fold_binop(200_u8, 100_u8, BinOp::Add),
+        CILNode::BinOp(..)
+    ));
+    assert!(matches!(
+        fold_binop(3_u8, 5_u8, BinOp::Sub),
+        CILNode::BinOp(..)
+    ));
+    assert_eq!(fold_binop(-3_i8, 5_i8, BinOp::Sub), (-8_i8).into());
+    // Native intigers wrap the same way on all platforms.
+    assert_eq!(fold_binop(0_usize, 1_usize, BinOp::Sub), usize::MAX.into());
+    // Operands of different types are never folded.
+    assert!(matches!(
+        fold_binop(1_u32, 1_i32, BinOp::Add),
+        CILNode::BinOp(..)
+    ));
+}
+#[test]
+fn fold_int_div() {
+    use crate::v2::{BinOp, CILNode};
+    assert_eq!(fold_binop(-7_i32, 2_i32, BinOp::Div), (-3_i32).into());
+    assert_eq!(fold_binop(-7_i32, 2_i32, BinOp::Rem), (-1_i32).into());
+    assert_eq!(fold_binop(7_u32, 2_u32, BinOp::DivUn), 3_u32.into());
+    assert_eq!(fold_binop(7_u64, 4_u64, BinOp::RemUn), 3_u64.into());
+    // Division by zero and overflowing divisions throw.
+    assert!(matches!(
+        fold_binop(7_i32, 0_i32, BinOp::Div),
+        CILNode::BinOp(..)
+    ));
+    assert!(matches!(
+        fold_binop(i32::MIN, -1_i32, BinOp::Div),
+        CILNode::BinOp(..)
+    ));
+    // Signed division of unsigned values depends on the evaluation stack.
+    assert!(matches!(
+        fold_binop(7_u32, 2_u32, BinOp::Div),
+        CILNode::BinOp(..)
+    ));
+    assert_eq!(fold_binop(7_u8, 2_u8, BinOp::Div), 3_u8.into());
+    // `usize` values bigger than `u32::MAX` are truncated on 32 bit platforms.
+    assert!(matches!(
+        fold_binop(1_usize << 40, 2_usize, BinOp::DivUn),
+        CILNode::BinOp(..)
+    ));
+}
+#[test]
+fn fold_shifts() {
+    use crate::v2::{BinOp, CILNode};
+    assert_eq!(fold_binop(1_u32, 4_i32, BinOp::Shl), 16_u32.into());
+    assert_eq!(fold_binop(-16_i32, 2_i32, BinOp::Shr), (-4_i32).into());
+    assert_eq!(
+        fold_binop(0x8000_0000_u32, 31_i32, BinOp::ShrUn),
+        1_u32.into()
+    );
+    assert_eq!(fold_binop(-1_i64, 63_u8, BinOp::Shl), i64::MIN.into());
+    // Shifts by the width of the type or more are not portable.
+    assert!(matches!(
+        fold_binop(1_u32, 32_i32, BinOp::Shl),
+        CILNode::BinOp(..)
+    ));
+    assert!(matches!(
+        fold_binop(1_usize, 32_i32, BinOp::Shl),
+        CILNode::BinOp(..)
+    ));
+    // An arithmetic shift of an unsigned value depends on the evaluation stack.
+    assert!(matches!(
+        fold_binop(0x8000_0000_u32, 1_i32, BinOp::Shr),
+        CILNode::BinOp(..)
+    ));
+}
+#[test]
+fn fold_comparisons() {
+    use crate::v2::{BinOp, CILNode};
+    assert_eq!(fold_binop(-1_i32, 1_i32, BinOp::Lt), true.into());
+    assert_eq!(fold_binop(u32::MAX, 1_u32, BinOp::GtUn), true.into());
+    assert_eq!(fold_binop(5_u64, 5_u64, BinOp::Eq), true.into());
+    assert_eq!(fold_binop(200_u8, 100_u8, BinOp::Gt), true.into());
+    assert_eq!(fold_binop(true, false, BinOp::Eq), false.into());
+    // A signed comparison of `u32::MAX` sees -1.
+    assert!(matches!(
+        fold_binop(u32::MAX, 1_u32, BinOp::Gt),
+        CILNode::BinOp(..)
+    ));
+    // Ordered float comparisons are false for NaNs, unordered ones are true.
+    assert_eq!(fold_binop(f64::NAN, 1.0_f64, BinOp::Lt), false.into());
+    assert_eq!(fold_binop(f64::NAN, 1.0_f64, BinOp::LtUn), true.into());
+    assert_eq!(fold_binop(f64::NAN, f64::NAN, BinOp::Eq), false.into());
+    assert_eq!(fold_binop(0.5_f32, 1.5_f32, BinOp::Gt), false.into());
+}
+#[test]
+fn fold_floats() {
+    use crate::v2::{cilnode::UnOp, BinOp, CILNode};
+    assert_eq!(
+        fold_binop(0.1_f32, 0.2_f32, BinOp::Add),
+        (0.1_f32 + 0.2_f32).into()
+    );
+    assert_eq!(
+        fold_binop(0.1_f64, 0.2_f64, BinOp::Mul),
+        (0.1_f64 * 0.2_f64).into()
+    );
+    assert_eq!(fold_binop(7.5_f64, 2.0_f64, BinOp::Rem), 1.5_f64.into());
+    assert_eq!(
+        fold_binop(1.0_f64, 0.0_f64, BinOp::Div),
+        f64::INFINITY.into()
+    );
+    let mut asm = Assembly::default();
+    let val = asm.alloc_node(0.0_f64);
+    assert_eq!(
+        fold(&mut asm, CILNode::UnOp(val, UnOp::Neg)),
+        (-0.0_f64).into()
+    );
+    // x + 0.0 is not x for x == -0.0.
+    let x = asm.alloc_node(CILNode::LdArg(0));
+    let node = CILNode::BinOp(x, val, BinOp::Add);
+    assert_eq!(fold(&mut asm, node.clone()), node);
+}
+#[test]
+fn fold_unops() {
+    use crate::v2::{cilnode::UnOp, CILNode};
+    let mut asm = Assembly::default();
+    let unop = |val: CILNode, op: UnOp, asm: &mut Assembly| {
+        let val = asm.alloc_node(val);
+        fold(asm, CILNode::UnOp(val, op))
+    };
+    assert_eq!(unop(0_u32.into(), UnOp::Not, &mut asm), u32::MAX.into());
+    assert_eq!(unop(i64::MIN.into(), UnOp::Neg, &mut asm), i64::MIN.into());
+    assert_eq!(unop(5_i8.into(), UnOp::Neg, &mut asm), (-5_i8).into());
+    assert_eq!(unop(5_i8.into(), UnOp::Not, &mut asm), (-6_i8).into());
+    // `!0_u8` is 0xFFFF_FFFF on the evaluation stack.
+    assert!(matches!(
+        unop(0_u8.into(), UnOp::Not, &mut asm),
+        CILNode::UnOp(..)
+    ));
+    // -(-x) == x
+    let x = asm.alloc_node(CILNode::LdArg(0));
+    let neg = asm.alloc_node(CILNode::UnOp(x, UnOp::Neg));
+    assert_eq!(
+        unop(CILNode::UnOp(neg, UnOp::Neg), UnOp::Neg, &mut asm),
+        CILNode::UnOp(x, UnOp::Neg)
+    );
+}
+#[test]
+fn fold_casts() {
+    use crate::v2::{cilnode::ExtendKind, CILNode, Float, Int};
+    let mut asm = Assembly::default();
+    let mut int_cast = |val: CILNode, target: Int, extend: ExtendKind| {
+        let input = asm.alloc_node(val);
+        fold(
+            &mut asm,
+            CILNode::IntCast {
+                input,
+                target,
+                extend,
+            },
+        )
+    };
+    assert_eq!(
+        int_cast((-1_i32).into(), Int::I64, ExtendKind::SignExtend),
+        (-1_i64).into()
+    );
+    assert_eq!(
+        int_cast((-1_i32).into(), Int::U64, ExtendKind::ZeroExtend),
+        u64::from(u32::MAX).into()
+    );
+    assert_eq!(
+        int_cast(0x1234_u64.into(), Int::U8, ExtendKind::ZeroExtend),
+        0x34_u8.into()
+    );
+    assert_eq!(
+        int_cast((-1_i8).into(), Int::U32, ExtendKind::ZeroExtend),
+        u32::MAX.into()
+    );
+    // The 32 bit stack value of a small intiger is extended according to `extend`.
+    assert_eq!(
+        int_cast((-1_i8).into(), Int::U64, ExtendKind::ZeroExtend),
+        0x0000_0000_FFFF_FFFF_u64.into()
+    );
+    assert_eq!(
+        int_cast((-1_i16).into(), Int::I64, ExtendKind::ZeroExtend),
+        0x0000_0000_FFFF_FFFF_i64.into()
+    );
+    assert_eq!(
+        int_cast((-1_i8).into(), Int::I64, ExtendKind::SignExtend),
+        (-1_i64).into()
+    );
+    assert_eq!(
+        int_cast((-1_i16).into(), Int::USize, ExtendKind::ZeroExtend),
+        (u32::MAX as usize).into()
+    );
+    assert_eq!(
+        int_cast((-1_i32).into(), Int::USize, ExtendKind::SignExtend),
+        usize::MAX.into()
+    );
+    assert_eq!(
+        int_cast(3.7_f64.into(), Int::I32, ExtendKind::SignExtend),
+        3_i32.into()
+    );
+    // Out of range float to int conversions are not portable.
+    assert!(matches!(
+        int_cast(f64::NAN.into(), Int::I32, ExtendKind::SignExtend),
+        CILNode::IntCast { .. }
+    ));
+    assert!(matches!(
+        int_cast(300.0_f32.into(), Int::U8, ExtendKind::ZeroExtend),
+        CILNode::IntCast { .. }
+    ));
+    // A zero-extended negative native intiger depends on the platform.
+    assert!(matches!(
+        int_cast((-1_isize).into(), Int::U64, ExtendKind::ZeroExtend),
+        CILNode::IntCast { .. }
+    ));
+    let mut float_cast = |val: CILNode, target: Float, is_signed: bool| {
+        let input = asm.alloc_node(val);
+        fold(
+            &mut asm,
+            CILNode::FloatCast {
+                input,
+                target,
+                is_signed,
+            },
+        )
+    };
+    assert_eq!(
+        float_cast((-3_i32).into(), Float::F64, true),
+        (-3.0_f64).into()
+    );
+    assert_eq!(
+        float_cast(u32::MAX.into(), Float::F64, false),
+        4_294_967_295.0_f64.into()
+    );
+    assert_eq!(float_cast(0.1_f64.into(), Float::F32, true), 0.1_f32.into());
+    // Inexact conversions are not folded.
+    assert!(matches!(
+        float_cast(u64::MAX.into(), Float::F64, false),
+        CILNode::FloatCast { .. }
+    ));
+}
+#[test]
+fn simplify_binops() {
+    use crate::v2::{BinOp, CILNode};
+    let mut asm = Assembly::default();
+    let x = asm.alloc_node(CILNode::LdArg(0));
+    let binop = |rhs: CILNode, op: BinOp, asm: &mut Assembly| {
+        let rhs = asm.alloc_node(rhs);
+        fold(asm, CILNode::BinOp(x, rhs, op))
+    };
+    assert_eq!(binop(0_u32.into(), BinOp::Add, &mut asm), CILNode::LdArg(0));
+    assert_eq!(binop(0_u32.into(), BinOp::Sub, &mut asm), CILNode::LdArg(0));
+    assert_eq!(binop(0_u32.into(), BinOp::Shl, &mut asm), CILNode::LdArg(0));
+    assert_eq!(binop(1_u32.into(), BinOp::Mul, &mut asm), CILNode::LdArg(0));
+    assert_eq!(binop(1_i32.into(), BinOp::Div, &mut asm), CILNode::LdArg(0));
+    assert_eq!(binop(0_u32.into(), BinOp::Mul, &mut asm), 0_u32.into());
+    assert_eq!(binop(0_u32.into(), BinOp::And, &mut asm), 0_u32.into());
+    assert_eq!(
+        binop(u32::MAX.into(), BinOp::And, &mut asm),
+        CILNode::LdArg(0)
+    );
+    assert_eq!(binop(u32::MAX.into(), BinOp::Or, &mut asm), u32::MAX.into());
+    // x * 8 == x << 3
+    let shl_3 = CILNode::BinOp(x, asm.alloc_node(3_i32), BinOp::Shl);
+    assert_eq!(binop(8_u32.into(), BinOp::Mul, &mut asm), shl_3);
+    // x / 4 == x >> 2, x % 8 == x & 7 for unsigned x
+    let shr_2 = CILNode::BinOp(x, asm.alloc_node(2_i32), BinOp::ShrUn);
+    assert_eq!(binop(4_u64.into(), BinOp::DivUn, &mut asm), shr_2);
+    let and_7 = CILNode::BinOp(x, asm.alloc_node(7_usize), BinOp::And);
+    assert_eq!(binop(8_usize.into(), BinOp::RemUn, &mut asm), and_7);
+    // Signed division by a power of two rounds towards 0, so it is not a shift.
+    assert!(matches!(
+        binop(4_i32.into(), BinOp::Div, &mut asm),
+        CILNode::BinOp(_, _, BinOp::Div)
+    ));
+    // `i8::MIN` is a negative number on the evaluation stack.
+    assert!(matches!(
+        binop(i8::MIN.into(), BinOp::Mul, &mut asm),
+        CILNode::BinOp(_, _, BinOp::Mul)
+    ));
+    // (x & 0xF0) & 0x3C == x & 0x30
+    let mask = asm.alloc_node(0xF0_u32);
+    let masked = asm.alloc_node(CILNode::BinOp(x, mask, BinOp::And));
+    let mask = asm.alloc_node(0x3C_u32);
+    let merged = CILNode::BinOp(x, asm.alloc_node(0x30_u32), BinOp::And);
+    assert_eq!(
+        fold(&mut asm, CILNode::BinOp(masked, mask, BinOp::And)),
+        merged
+    );
+    // x < x is always false, x | x is x.
+    assert_eq!(
+        fold(&mut asm, CILNode::BinOp(x, x, BinOp::Lt)),
+        false.into()
+    );
+    assert_eq!(
+        fold(&mut asm, CILNode::BinOp(x, x, BinOp::Or)),
+        CILNode::LdArg(0)
+    );
+}
+#[test]
+fn fold_branches() {
+    use super::root::root_opt;
+    use crate::v2::{cilroot::CmpKind, BranchCond};
+    let mut asm = Assembly::default();
+    let one = asm.alloc_node(1_i32);
+    let two = asm.alloc_node(2_i32);
+    let nan = asm.alloc_node(f32::NAN);
+    let mut branch = |cond: BranchCond| {
+        root_opt(
+            CILRoot::Branch(Box::new((1, 0, Some(cond)))),
+            &mut asm,
+            &mut super::OptFuel::new(100),
+            &mut super::SideEffectInfoCache::default(),
+            &[],
+        )
+    };
+    let taken = CILRoot::Branch(Box::new((1, 0, None)));
+    assert_eq!(branch(BranchCond::Lt(one, two, CmpKind::Signed)), taken);
+    assert_eq!(
+        branch(BranchCond::Ge(one, two, CmpKind::Signed)),
+        CILRoot::Nop
+    );
+    assert_eq!(branch(BranchCond::Eq(one, one)), taken);
+    assert_eq!(branch(BranchCond::Ne(one, one)), CILRoot::Nop);
+    assert_eq!(branch(BranchCond::True(two)), taken);
+    assert_eq!(branch(BranchCond::False(two)), CILRoot::Nop);
+    assert_eq!(
+        branch(BranchCond::Le(nan, nan, CmpKind::Ordered)),
+        CILRoot::Nop
+    );
+    assert_eq!(branch(BranchCond::Le(nan, nan, CmpKind::Unordered)), taken);
+    assert_eq!(branch(BranchCond::Ne(nan, nan)), taken);
+}
+#[test]
+fn fold_sizeof() {
+    use crate::v2::{Access, CILNode, ClassDef, Type};
+    let mut asm = Assembly::default();
+    let bool_idx = asm.alloc_type(Type::Bool);
+    assert_eq!(fold(&mut asm, CILNode::SizeOf(bool_idx)), 1_i32.into());
+    let name = asm.alloc_string("Sized");
+    let sized = asm.class_def(ClassDef::new(
+        name,
+        true,
+        0,
+        None,
+        vec![],
+        vec![],
+        Access::Public,
+        Some(24.try_into().unwrap()),
+        None,
+    ));
+    let sized = asm.alloc_type(Type::ClassRef(*sized));
+    assert_eq!(fold(&mut asm, CILNode::SizeOf(sized)), 24_i32.into());
+    // The size of pointers depends on the platform.
+    let usize_idx = asm.alloc_type(Type::Int(crate::v2::Int::USize));
+    assert_eq!(
+        fold(&mut asm, CILNode::SizeOf(usize_idx)),
+        CILNode::SizeOf(usize_idx)
+    );
+}
+#[test]
+fn pinned_locals() {
+    use super::{OptFuel, SideEffectInfoCache};
+    use crate::v2::{CILNode, Int, MethodImpl, Type};
+    let mut asm = Assembly::default();
+    let elem = asm.alloc_type(Type::Int(Int::I32));
+    let arr = asm.alloc_type(Type::PlatformArray {
+        elem,
+        dims: 1.try_into().unwrap(),
+    });
+    let pinned = asm.alloc_type(Type::Pinned(arr));
+    let sig = asm.sig(
+        [Type::PlatformArray {
+            elem,
+            dims: 1.try_into().unwrap(),
+        }],
+        Type::Void,
+    );
+    let arg0 = asm.alloc_node(CILNode::LdArg(0));
+    let pin = asm.alloc_root(CILRoot::StLoc(0, arg0));
+    let loc0 = asm.alloc_node(CILNode::LdLoc(0));
+    let use_pinned = asm.alloc_root(CILRoot::Pop(loc0));
+    let ret = asm.alloc_root(CILRoot::VoidRet);
+    // The pinned local is read, so the store to it must not be propagated into that read.
+    // The pinned local is never read, but the store still keeps the array pinned.
+    for roots in [vec![pin, use_pinned, ret], vec![pin, ret]] {
+        let mut mimpl = MethodImpl::MethodBody {
+            blocks: vec![BasicBlock::new(roots.clone(), 0, None)],
+            locals: vec![(None, pinned)],
+        };
+        let mut cache = SideEffectInfoCache::default();
+        let mut fuel = OptFuel::new(1000);
+        mimpl.propagate_locals(&mut asm, &mut cache, &mut fuel, sig);
+        mimpl.remove_dead_writes(&mut asm, &mut cache, &mut fuel);
+        mimpl.remove_nops(&mut asm);
+        assert_eq!(mimpl.blocks_mut().unwrap()[0].roots(), roots);
+    }
+}
@@ -0,0 +1,359 @@
+//! Automatic test case reduction.
+//!
+//! Shrinks an assembly while a user-provided predicate still considers it "interesting"(e.g. it still miscompiles).
+//! The reducer repeatedly tries smaller versions of the assembly, and keeps each one the predicate accepts:
+//! 1. Removes dead methods and types.
+//! 2. Stubs out whole methods, by replacing their bodies with [`MethodImpl::Missing`].
+//! 3. Removes blocks, and then individual roots.
+//! 4. Replaces nodes of primitive types with zero constants.
+//!
+//! Candidates are tried in chunks, which get smaller when no chunk can be removed(like in delta debugging).
+//! All of the steps are repeated until none of them makes progress.
+use super::{
+    basic_block::BlockId, Assembly, BasicBlock, CILIter, CILIterElem, CILNode, CILRoot, Const,
+    MethodDefIdx, MethodImpl, RootIdx, Type,
+};
+
+/// Shrinks `asm` for as long as `interesting` holds. `interesting` must hold for `asm` itself.
+/// # Example
+/// ```
+/// # use cilly::v2::{Assembly, reduce::reduce};
+/// let asm = Assembly::default();
+/// let reduced = reduce(asm, |_| true);
+/// ```
+pub fn reduce(asm: Assembly, interesting: impl FnMut(&Assembly) -> bool) -> Assembly {
+    let mut reducer = Reducer {
+        asm,
+        interesting,
+        tests: 0,
+    };
+    while reducer.pass() {}
+    reducer.asm
+}
+/// Identifies a root of a method body: the block id, the id of the handler block(if the root is in a handler), and its position.
+type RootPos = (BlockId, Option<BlockId>, usize);
+struct Reducer<F> {
+    asm: Assembly,
+    interesting: F,
+    tests: usize,
+}
+impl<F: FnMut(&Assembly) -> bool> Reducer<F> {
+    /// Runs all the reduction steps once. Returns true if any of them made progress.
+    fn pass(&mut self) -> bool {
+        let mut progress = self.remove_dead_code();
+        progress |= self.reduce_chunks(self.methods(), |asm, methods| {
+            for method in methods {
+                asm.modify_methodef(
+                    |_, def| *def.implementation_mut() = MethodImpl::Missing,
+                    *method,
+                );
+            }
+        });
+        for method in self.methods() {
+            progress |= self.reduce_blocks(method);
+            progress |= self.reduce_roots(method);
+            progress |= self.simplify_nodes(method);
+        }
+        eprintln!("Reduction pass done after {} tests.", self.tests);
+        progress
+    }
+    /// Accepts `candidate` if it is still interesting.
+    fn try_candidate(&mut self, candidate: Assembly) -> bool {
+        self.tests += 1;
+        if (self.interesting)(&candidate) {
+            self.asm = candidate;
+            true
+        } else {
+            false
+        }
+    }
+    /// Tries to apply `edit` to chunks of `items`, starting with big chunks, and splitting them when no chunk can be edited.
+    fn reduce_chunks<T>(&mut self, mut items: Vec<T>, edit: impl Fn(&mut Assembly, &[T])) -> bool {
+        let mut progress = false;
+        let mut chunk = items.len().div_ceil(2).max(1);
+        while !items.is_empty() {
+            let mut start = 0;
+            while start < items.len() {
+                let end = (start + chunk).min(items.len());
+                let mut candidate = self.asm.clone();
+                edit(&mut candidate, &items[start..end]);
+                if self.try_candidate(candidate) {
+                    items.drain(start..end);
+                    progress = true;
+                } else {
+                    start = end;
+                }
+            }
+            if chunk == 1 {
+                break;
+            }
+            chunk = chunk.div_ceil(2);
+        }
+        progress
+    }
+    /// All the methods with a body, in a deterministic order.
+    fn methods(&self) -> Vec<MethodDefIdx> {
+        let asm = &self.asm;
+        let mut methods: Vec<_> = asm
+            .methods_with(|_, _, def| def.implementation().blocks().is_some())
+            .map(|(idx, _)| *idx)
+            .collect();
+        methods.sort_by_cached_key(|method| {
+            let def = &asm[*method];
+            (
+                asm[asm[def.class()].name()].to_owned(),
+                asm[def.name()].to_owned(),
+                format!("{:?}", asm[def.sig()]),
+            )
+        });
+        methods
+    }
+    fn remove_dead_code(&mut self) -> bool {
+        let mut candidate = self.asm.clone();
+        candidate.eliminate_dead_code();
+        let size = |asm: &Assembly| (asm.method_defs().len(), asm.class_defs().len());
+        size(&candidate) != size(&self.asm) && self.try_candidate(candidate)
+    }
+    /// Removes the blocks of `method`, besides its entry block.
+    fn reduce_blocks(&mut self, method: MethodDefIdx) -> bool {
+        let Some(blocks) = self.asm[method].implementation().blocks() else {
+            return false;
+        };
+        let ids: Vec<_> = blocks.iter().skip(1).map(BasicBlock::block_id).collect();
+        self.reduce_chunks(ids, |asm, ids| {
+            asm.modify_methodef(
+                |_, def| {
+                    if let Some(blocks) = def.implementation_mut().blocks_mut() {
+                        blocks.retain(|block| !ids.contains(&block.block_id()));
+                    }
+                },
+                method,
+            );
+        })
+    }
+    /// Removes the roots of `method`. Roots are first replaced with `Nop`s, so that the positions of the other roots don't change.
+    fn reduce_roots(&mut self, method: MethodDefIdx) -> bool {
+        let Some(blocks) = self.asm[method].implementation().blocks() else {
+            return false;
+        };
+        let mut roots = root_positions(blocks);
+        let asm = &self.asm;
+        roots.retain(|pos| {
+            !matches!(
+                asm[root_at(blocks, *pos)],
+                CILRoot::Nop | CILRoot::SourceFileInfo { .. }
+            )
+        });
+        let mut progress = self.reduce_chunks(roots, |asm, roots| {
+            let nop = asm.alloc_root(CILRoot::Nop);
+            asm.modify_methodef(
+                |_, def| {
+                    let blocks = def.implementation_mut().blocks_mut().unwrap();
+                    for pos in roots {
+                        *root_at_mut(blocks, *pos) = nop;
+                    }
+                },
+                method,
+            );
+        });
+        let mut candidate = self.asm.clone();
+        candidate.modify_methodef(|asm, def| def.implementation_mut().remove_nops(asm), method);
+        if candidate[method] != self.asm[method] {
+            progress |= self.try_candidate(candidate);
+        }
+        progress
+    }
+    /// Replaces nodes of `method` with zero constants of the same type, one at a time.
+    fn simplify_nodes(&mut self, method: MethodDefIdx) -> bool {
+        let mut progress = false;
+        let Some(blocks) = self.asm[method].implementation().blocks() else {
+            return false;
+        };
+        let roots = root_positions(blocks);
+        for pos in roots {
+            let mut node = 0;
+            while let Some((candidate, replaced, size)) = self.zero_nth_node(method, pos, node) {
+                if self.try_candidate(candidate) {
+                    // The whole subtree of the replaced node got turned into a single constant.
+                    node = replaced + 2 - size;
+                    progress = true;
+                } else {
+                    node = replaced + 1;
+                }
+            }
+        }
+        progress
+    }
+    /// Creates a copy of the assembly, with the first primitive node of a root at or after position `n`(in postorder)
+    /// replaced with a zero constant. Returns the copy, the position of the replaced node and the size of its subtree,
+    /// or `None` if there are no more nodes to replace.
+    fn zero_nth_node(
+        &self,
+        method: MethodDefIdx,
+        pos: RootPos,
+        n: usize,
+    ) -> Option<(Assembly, usize, usize)> {
+        let mut candidate = self.asm.clone();
+        let def = &candidate[method];
+        let sig = def.sig();
+        let MethodImpl::MethodBody { blocks, locals } = def.implementation() else {
+            return None;
+        };
+        let locals = locals.clone();
+        let root = candidate[root_at(blocks, pos)].clone();
+        let mut visited = 0;
+        let mut replaced = None;
+        let root = root.map(&mut candidate, &mut |root, _| root, &mut |node, asm| {
+            visited += 1;
+            if replaced.is_some() || visited <= n || matches!(node, CILNode::Const(_)) {
+                return node;
+            }
+            let Some(zero) = node.typecheck(sig, &locals, asm).ok().and_then(zero_of) else {
+                return node;
+            };
+            let size = CILIter::new(node, asm)
+                .filter(|elem| matches!(elem, CILIterElem::Node(_)))
+                .count();
+            replaced = Some((visited - 1, size));
+            zero.into()
+        });
+        let (replaced, size) = replaced?;
+        let root = candidate.alloc_root(root);
+        candidate.modify_methodef(
+            |_, def| *root_at_mut(def.implementation_mut().blocks_mut().unwrap(), pos) = root,
+            method,
+        );
+        Some((candidate, replaced, size))
+    }
+}
+/// The zero constant of a primitive type.
+fn zero_of(tpe: Type) -> Option<Const> {
+    match tpe {
+        Type::Int(int) => Some(int.zero()),
+        Type::Float(float) => Some(float.zero()),
+        Type::Bool => Some(Const::Bool(false)),
+        _ => None,
+    }
+}
+fn root_positions(blocks: &[BasicBlock]) -> Vec<RootPos> {
+    let mut roots = vec![];
+    for block in blocks {
+        roots.extend((0..block.roots().len()).map(|pos| (block.block_id(), None, pos)));
+        for handler in block.handler().into_iter().flatten() {
+            roots.extend(
+                (0..handler.roots().len())
+                    .map(|pos| (block.block_id(), Some(handler.block_id()), pos)),
+            );
+        }
+    }
+    roots
+}
+fn root_at(blocks: &[BasicBlock], (block, handler, pos): RootPos) -> RootIdx {
+    let block = blocks.iter().find(|b| b.block_id() == block).unwrap();
+    match handler {
+        None => block.roots()[pos],
+        Some(handler) => {
+            let handler = block
+                .handler()
+                .unwrap()
+                .iter()
+                .find(|b| b.block_id() == handler)
+                .unwrap();
+            handler.roots()[pos]
+        }
+    }
+}
+fn root_at_mut(blocks: &mut [BasicBlock], (block, handler, pos): RootPos) -> &mut RootIdx {
+    let block = blocks.iter_mut().find(|b| b.block_id() == block).unwrap();
+    match handler {
+        None => &mut block.roots_mut()[pos],
+        Some(handler) => {
+            let handler = block
+                .handler_mut()
+                .unwrap()
+                .iter_mut()
+                .find(|b| b.block_id() == handler)
+                .unwrap();
+            &mut handler.roots_mut()[pos]
+        }
+    }
+}
+#[cfg(test)]
+fn method_with_roots(asm: &mut Assembly, name: &str, roots: Vec<RootIdx>) -> MethodDefIdx {
+    use super::{cilnode::MethodKind, Access, Int, MethodDef};
+    let main_module = asm.main_module();
+    let sig = asm.sig([], Type::Void);
+    let name = asm.alloc_string(name);
+    let i32_idx = asm.alloc_type(Type::Int(Int::I32));
+    asm.new_method(MethodDef::new(
+        Access::Public,
+        main_module,
+        name,
+        sig,
+        MethodKind::Static,
+        MethodImpl::MethodBody {
+            blocks: vec![BasicBlock::new(roots, 0, None)],
+            locals: vec![(None, i32_idx), (None, i32_idx)],
+        },
+        vec![],
+    ))
+}
+#[cfg(test)]
+fn body_roots(asm: &Assembly) -> Vec<CILRoot> {
+    asm.methods_with(|_, _, _| true)
+        .filter_map(|(_, def)| def.implementation().blocks())
+        .flatten()
+        .flat_map(BasicBlock::iter_roots)
+        .map(|root| asm[root].clone())
+        .collect()
+}
+#[test]
+fn reduce_roots_and_methods() {
+    use super::BinOp;
+    let mut asm = Assembly::default();
+    let void_ret = asm.alloc_root(CILRoot::VoidRet);
+    let helper = method_with_roots(&mut asm, "helper", vec![void_ret]);
+    let one = asm.alloc_node(1_i32);
+    let sum = asm.alloc_node(CILNode::BinOp(one, one, BinOp::Add));
+    let set_sum = asm.alloc_root(CILRoot::StLoc(0, sum));
+    let answer = asm.alloc_node(42_i32);
+    let set_answer = asm.alloc_root(CILRoot::StLoc(1, answer));
+    let call = asm.alloc_root(CILRoot::Call(Box::new((*helper, [].into()))));
+    method_with_roots(
+        &mut asm,
+        "main",
+        vec![set_sum, set_answer, call, set_sum, void_ret],
+    );
+    let reduced = reduce(asm, |asm| {
+        body_roots(asm).iter().any(
+            |root| matches!(root, CILRoot::StLoc(1, val) if asm[*val] == CILNode::from(42_i32)),
+        )
+    });
+    // Everything besides the interesting root is gone.
+    assert_eq!(body_roots(&reduced).len(), 1);
+}
+#[test]
+fn reduce_nodes() {
+    use super::BinOp;
+    let mut asm = Assembly::default();
+    let three = asm.alloc_node(3_i32);
+    let four = asm.alloc_node(4_i32);
+    let five = asm.alloc_node(5_i32);
+    let mul = asm.alloc_node(CILNode::BinOp(three, four, BinOp::Mul));
+    let add = asm.alloc_node(CILNode::BinOp(mul, five, BinOp::Add));
+    let set = asm.alloc_root(CILRoot::StLoc(0, add));
+    method_with_roots(&mut asm, "main", vec![set]);
+    let reduced = reduce(asm, |asm| {
+        body_roots(asm).iter().any(|root| {
+            matches!(root, CILRoot::StLoc(0, val) if matches!(asm[*val], CILNode::BinOp(..)))
+        })
+    });
+    let [CILRoot::StLoc(0, val)] = body_roots(&reduced)[..] else {
+        panic!("Unexpected roots {:?}", body_roots(&reduced));
+    };
+    let CILNode::BinOp(lhs, rhs, BinOp::Add) = reduced[val] else {
+        panic!("Unexpected node {:?}", reduced[val]);
+    };
+    assert_eq!(reduced[lhs], CILNode::from(0_i32));
+    assert_eq!(reduced[rhs], CILNode::from(5_i32));
+}
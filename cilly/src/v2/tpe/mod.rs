@@ -38,6 +38,9 @@ pub enum Type {
     PlatformArray { elem: TypeIdx, dims: NonZeroU8 },
     FnPtr(SigIdx),
     SIMDVector(SIMDVector),
+    /// A local variable, which pins the object it refers to(like a C# `fixed` statement) for as long as it holds it.
+    /// Only valid as the type of a local.
+    Pinned(TypeIdx),
 }
 #[derive(Hash, PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum GenericKind {
@@ -51,7 +54,10 @@ impl Type {
         asm: &'asm Assembly,
     ) -> impl Iterator<Item = ClassRefIdx> + 'a {
         let tmp: Box<dyn Iterator<Item = ClassRefIdx>> = match self {
-            Type::PlatformArray { elem: inner, .. } | Type::Ptr(inner) | Type::Ref(inner) => {
+            Type::PlatformArray { elem: inner, .. }
+            | Type::Ptr(inner)
+            | Type::Ref(inner)
+            | Type::Pinned(inner) => {
                 asm[*inner].iter_class_refs::<'a, 'asm>(asm)
             }
             Type::Int(_)
@@ -93,6 +99,7 @@ impl Type {
             Type::SIMDVector(val) => val.name(),
             Type::Ptr(inner) => format!("p{}", asm[*inner].mangle(asm)),
             Type::Ref(inner) => format!("r{}", asm[*inner].mangle(asm)),
+            Type::Pinned(inner) => format!("P{}", asm[*inner].mangle(asm)),
             Type::Int(int) => int.name().to_owned(),
             Type::ClassRef(cref) => {
                 let cref = asm.class_ref(*cref);
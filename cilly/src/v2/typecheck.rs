@@ -5,7 +5,9 @@ use crate::{v2::bimap::IntoBiMapIndex, IString};
 use super::{
     cilnode::{PtrCastRes, UnOp},
     method::LocalDef,
-    Assembly, BinOp, CILNode, CILRoot, ClassRef, FieldIdx, Int, NodeIdx, SigIdx, Type,
+    opt::analysis::BlockKey,
+    Assembly, BasicBlock, BinOp, CILNode, CILRoot, ClassRef, FieldIdx, Int, MethodDefIdx,
+    MethodImpl, NodeIdx, RootIdx, SigIdx, Type,
 };
 #[derive(Debug)]
 pub enum TypeCheckError {
@@ -576,7 +578,11 @@ impl CILNode {
                     }),
                 }
             }
-            CILNode::LdLoc(loc) => Ok(asm[locals[*loc as usize].1]),
+            CILNode::LdLoc(loc) => match asm[locals[*loc as usize].1] {
+                // Pinning only affects the GC, so a pinned local holds a value of its inner type.
+                Type::Pinned(inner) => Ok(asm[inner]),
+                tpe => Ok(tpe),
+            },
             CILNode::LdLocA(loc) => Ok(asm.nref(asm[locals[*loc as usize].1])),
             CILNode::LdArg(arg) => Ok(asm[sig].inputs()[*arg as usize]),
             CILNode::LdArgA(arg) => Ok(asm.nref(asm[sig].inputs()[*arg as usize])),
@@ -887,6 +893,18 @@ impl CILNode {
                 };
                 Ok(asm[*tpe])
             }
+            CILNode::NewArr { elem, len } => {
+                let len = asm.get_node(*len).clone();
+                let len_tpe = len.typecheck(sig, locals, asm)?;
+                match len_tpe {
+                    Type::Int(Int::I32 | Int::U32 | Int::USize | Int::ISize) => (),
+                    _ => return Err(TypeCheckError::ArrIndexInvalidType { index_tpe: len_tpe }),
+                }
+                Ok(Type::PlatformArray {
+                    elem: *elem,
+                    dims: std::num::NonZeroU8::new(1).unwrap(),
+                })
+            }
         }
     }
 }
@@ -983,8 +1001,13 @@ impl CILRoot {
                         val,
                     });
                 }
-                let Some(pointed_tpe) = addr.pointed_to().map(|tpe| asm[tpe]) else {
-                    return Err(TypeCheckError::TypeNotPtr { tpe: addr });
+                // Fields of objects can be set directly, without taking their address.
+                let pointed_tpe = match addr {
+                    Type::ClassRef(cref) if !asm[cref].is_valuetype() => addr,
+                    _ => match addr.pointed_to() {
+                        Some(tpe) => asm[tpe],
+                        None => return Err(TypeCheckError::TypeNotPtr { tpe: addr }),
+                    },
                 };
                 let Type::ClassRef(pointed_owner) = pointed_tpe else {
                     return Err(TypeCheckError::FieldAccessInvalidType {
@@ -1049,6 +1072,382 @@ impl CILRoot {
         }
     }
 }
+/// Location of the Rust source code a root was compiled from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub file: IString,
+    pub line: u32,
+    pub column: u16,
+}
+impl std::fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+/// A single line of the rendered tree of an invalid root.
+#[derive(Debug, Clone)]
+struct TreeLine {
+    depth: usize,
+    label: String,
+    /// The type of this node, or the reason it is invalid.
+    tpe: Result<String, String>,
+    /// Is this the innermost invalid part of the root?
+    is_bad: bool,
+}
+/// A typecheck error, together with the place in the assembly it was found in.
+#[derive(Debug)]
+pub struct LocatedTypeCheckError {
+    pub method: MethodDefIdx,
+    pub method_name: String,
+    pub block: BlockKey,
+    /// Index of the invalid root within its block.
+    pub root_idx: usize,
+    pub root: RootIdx,
+    /// The innermost node which fails to typecheck, or `None` if the root itself is invalid.
+    pub bad_node: Option<NodeIdx>,
+    /// The closest source location preceding the invalid root.
+    pub source: Option<SourceLocation>,
+    pub error: TypeCheckError,
+    tree: Vec<TreeLine>,
+}
+impl LocatedTypeCheckError {
+    fn location(&self) -> String {
+        let block = match self.block.owner {
+            Some(owner) => format!("handler block {} of block {owner}", self.block.id),
+            None => format!("block {}", self.block.id),
+        };
+        format!("in `{}`, {block}, root {}", self.method_name, self.root_idx)
+    }
+}
+/// All the typecheck errors found in an assembly.
+#[derive(Debug, Default)]
+pub struct TypeCheckReport {
+    errors: Vec<LocatedTypeCheckError>,
+}
+impl TypeCheckReport {
+    #[must_use]
+    pub fn errors(&self) -> &[LocatedTypeCheckError] {
+        &self.errors
+    }
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+    /// The methods containing at least one invalid root.
+    #[must_use]
+    pub fn methods(&self) -> FxHashSet<MethodDefIdx> {
+        self.errors.iter().map(|err| err.method).collect()
+    }
+    /// Renders this report as plain text, with the innermost invalid node of each root marked with `<--`.
+    #[must_use]
+    pub fn to_text(&self) -> String {
+        use std::fmt::Write;
+        let mut out = String::new();
+        for err in &self.errors {
+            let _ = writeln!(out, "error: {:?}", err.error);
+            if let Some(source) = &err.source {
+                let _ = writeln!(out, " --> {source}");
+            }
+            let _ = writeln!(out, " {}", err.location());
+            for line in &err.tree {
+                let indent = "  ".repeat(line.depth + 1);
+                let _ = match (&line.tpe, line.is_bad) {
+                    (Ok(tpe), _) => writeln!(out, "{indent}{} : {tpe}", line.label),
+                    (Err(msg), true) => writeln!(out, "{indent}{} <-- {msg}", line.label),
+                    (Err(_), false) => writeln!(out, "{indent}{} : <invalid>", line.label),
+                };
+            }
+            out.push('\n');
+        }
+        let _ = writeln!(
+            out,
+            "{} typecheck errors in {} methods",
+            self.errors.len(),
+            self.methods().len()
+        );
+        out
+    }
+    /// Renders this report as a HTML page, with the innermost invalid node of each root highlighted.
+    #[must_use]
+    pub fn to_html(&self) -> String {
+        use std::fmt::Write;
+        let mut out = String::from(
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Typecheck report</title>\n<style>.bad{background:#fcc;font-weight:bold}.invalid{color:#a50}.tpe{color:#666}</style></head><body>\n",
+        );
+        let _ = writeln!(
+            out,
+            "<h1>{} typecheck errors in {} methods</h1>",
+            self.errors.len(),
+            self.methods().len()
+        );
+        for err in &self.errors {
+            let _ = writeln!(out, "<h2>{}</h2>", html_escape(&format!("{:?}", err.error)));
+            let _ = write!(out, "<p>{}", html_escape(&err.location()));
+            if let Some(source) = &err.source {
+                let _ = write!(out, "<br>{}", html_escape(&source.to_string()));
+            }
+            out.push_str("</p>\n<pre>\n");
+            for line in &err.tree {
+                let indent = "  ".repeat(line.depth);
+                let label = html_escape(&line.label);
+                let _ = match (&line.tpe, line.is_bad) {
+                    (Ok(tpe), _) => writeln!(
+                        out,
+                        "{indent}{label} <span class=\"tpe\">: {}</span>",
+                        html_escape(tpe)
+                    ),
+                    (Err(msg), true) => writeln!(
+                        out,
+                        "{indent}<span class=\"bad\" title=\"{msg}\">{label}</span> <span class=\"tpe\">&lt;-- {msg}</span>",
+                        msg = html_escape(msg)
+                    ),
+                    (Err(_), false) => {
+                        writeln!(out, "{indent}<span class=\"invalid\">{label}</span>")
+                    }
+                };
+            }
+            out.push_str("</pre>\n");
+        }
+        out.push_str("</body></html>\n");
+        out
+    }
+    /// Saves this report to the file `TYPECHECK_REPORT` points to, if it is set. Files with the `html` extension get a
+    /// HTML report, and all other files get a text one.
+    pub fn save_from_env(&self) {
+        let Ok(path) = std::env::var("TYPECHECK_REPORT") else {
+            return;
+        };
+        let path = std::path::Path::new(&path);
+        let report = if path.extension().is_some_and(|ext| ext == "html") {
+            self.to_html()
+        } else {
+            self.to_text()
+        };
+        std::fs::write(path, report)
+            .unwrap_or_else(|err| panic!("Could not save the typecheck report {path:?}: {err}"));
+    }
+    /// Replaces every method containing an invalid root with one throwing an exception describing the first error.
+    pub fn replace_with_throw(&self, asm: &mut Assembly) {
+        let mut replaced = FxHashSet::default();
+        for err in &self.errors {
+            if !replaced.insert(err.method) {
+                continue;
+            }
+            let msg = format!(
+                "Method {} failed to typecheck: {:?}",
+                err.method_name, err.error
+            );
+            let throw = crate::cil_root::CILRoot::throw(&msg, asm);
+            let throw = CILRoot::from_v1(&throw, asm);
+            let throw = asm.alloc_root(throw);
+            asm.modify_methodef(
+                |_, def| {
+                    *def.implementation_mut() = MethodImpl::MethodBody {
+                        blocks: vec![BasicBlock::new(vec![throw], 0, None)],
+                        locals: vec![],
+                    }
+                },
+                err.method,
+            );
+        }
+    }
+}
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+/// What the linker does with methods which fail to typecheck.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TypeCheckPolicy {
+    /// Print the report, and stop.
+    Fail,
+    /// Print the report, and carry on.
+    Warn,
+    /// Print the report, and replace the invalid methods with ones throwing an exception.
+    Throw,
+}
+impl std::str::FromStr for TypeCheckPolicy {
+    type Err = String;
+
+    fn from_str(policy: &str) -> Result<Self, Self::Err> {
+        match policy {
+            "fail" => Ok(Self::Fail),
+            "warn" => Ok(Self::Warn),
+            "throw" => Ok(Self::Throw),
+            _ => Err(format!(
+                "Unknown typecheck policy {policy:?}, expected `fail`, `warn` or `throw`"
+            )),
+        }
+    }
+}
+impl TypeCheckPolicy {
+    /// Reads the policy from the `TYPECHECK_POLICY` enviroment variable. Returns `None` if it is not set.
+    #[must_use]
+    pub fn from_env() -> Option<Self> {
+        let policy = std::env::var("TYPECHECK_POLICY").ok()?;
+        Some(policy.parse().unwrap_or_else(|err: String| panic!("{err}")))
+    }
+}
+/// Returns the innermost node of `node` which fails to typecheck, if any.
+fn innermost_invalid(
+    node: NodeIdx,
+    sig: SigIdx,
+    locals: &[LocalDef],
+    asm: &mut Assembly,
+) -> Option<NodeIdx> {
+    let node_val = asm[node].clone();
+    if node_val.typecheck(sig, locals, asm).is_ok() {
+        return None;
+    }
+    let child = node_val
+        .child_nodes()
+        .into_iter()
+        .find_map(|child| innermost_invalid(child, sig, locals, asm));
+    Some(child.unwrap_or(node))
+}
+fn node_label(node: &CILNode) -> String {
+    match node {
+        CILNode::BinOp(_, _, op) => format!("BinOp({op:?})"),
+        CILNode::UnOp(_, op) => format!("UnOp({op:?})"),
+        _ if node.child_nodes().is_empty() => format!("{node:?}"),
+        _ => variant_name(&format!("{node:?}")),
+    }
+}
+fn root_label(root: &CILRoot) -> String {
+    match root {
+        CILRoot::StLoc(loc, _) => format!("StLoc({loc})"),
+        _ if root.nodes().is_empty() => format!("{root:?}"),
+        _ => variant_name(&format!("{root:?}")),
+    }
+}
+/// Strips the fields from the debug representation of an enum variant.
+fn variant_name(debug: &str) -> String {
+    debug
+        .split(['(', '{'])
+        .next()
+        .unwrap_or(debug)
+        .trim()
+        .to_owned()
+}
+fn node_tree(
+    node: NodeIdx,
+    depth: usize,
+    bad_node: Option<NodeIdx>,
+    sig: SigIdx,
+    locals: &[LocalDef],
+    asm: &mut Assembly,
+    out: &mut Vec<TreeLine>,
+) {
+    let node_val = asm[node].clone();
+    let tpe = node_val
+        .typecheck(sig, locals, asm)
+        .map(|tpe| tpe.mangle(asm))
+        .map_err(|err| format!("{err:?}"));
+    out.push(TreeLine {
+        depth,
+        label: node_label(&node_val),
+        tpe,
+        is_bad: bad_node == Some(node),
+    });
+    for child in node_val.child_nodes() {
+        node_tree(child, depth + 1, bad_node, sig, locals, asm, out);
+    }
+}
+impl Assembly {
+    /// Typechecks all methods of this assembly, and collects every invalid root, together with its location.
+    pub fn typecheck_report(&mut self) -> TypeCheckReport {
+        let mut methods: Vec<_> = self
+            .methods_with(|_, _, def| def.implementation().blocks().is_some())
+            .map(|(idx, def)| {
+                let class = self[def.class()].name();
+                (*idx, format!("{}::{}", &self[class], &self[def.name()]))
+            })
+            .collect();
+        methods.sort_by(|(_, a), (_, b)| a.cmp(b));
+        let mut errors = vec![];
+        for (method, method_name) in methods {
+            let def = self[method].clone();
+            let MethodImpl::MethodBody { blocks, locals } = def.implementation() else {
+                continue;
+            };
+            let sig = def.sig();
+            let mut source = None;
+            let all_blocks = blocks.iter().flat_map(|block| {
+                let handlers = block.handler().unwrap_or_default().iter().map(|handler| {
+                    let key = BlockKey::from_jump(block.block_id(), handler.block_id());
+                    (key, handler)
+                });
+                std::iter::once((BlockKey::top(block.block_id()), block)).chain(handlers)
+            });
+            for (block, block_def) in all_blocks {
+                for (root_idx, root) in block_def.roots().iter().enumerate() {
+                    let root_val = self[*root].clone();
+                    if let CILRoot::SourceFileInfo {
+                        line_start,
+                        col_start,
+                        file,
+                        ..
+                    } = root_val
+                    {
+                        source = Some(SourceLocation {
+                            file: self[file].into(),
+                            line: line_start,
+                            column: col_start,
+                        });
+                        continue;
+                    }
+                    let Err(error) = root_val.typecheck(sig, locals, self) else {
+                        continue;
+                    };
+                    let bad_node = root_val
+                        .nodes()
+                        .iter()
+                        .find_map(|node| innermost_invalid(**node, sig, locals, self));
+                    let mut tree = vec![TreeLine {
+                        depth: 0,
+                        label: root_label(&root_val),
+                        tpe: Err(format!("{error:?}")),
+                        is_bad: bad_node.is_none(),
+                    }];
+                    for node in root_val.nodes() {
+                        node_tree(*node, 1, bad_node, sig, locals, self, &mut tree);
+                    }
+                    errors.push(LocatedTypeCheckError {
+                        method,
+                        method_name: method_name.clone(),
+                        block,
+                        root_idx,
+                        root: *root,
+                        bad_node,
+                        source: source.clone(),
+                        error,
+                        tree,
+                    });
+                }
+            }
+        }
+        TypeCheckReport { errors }
+    }
+    /// Typechecks this assembly, and handles the invalid methods according to `policy`. The report is also saved to
+    /// `TYPECHECK_REPORT`, if set.
+    pub fn enforce_typecheck_policy(&mut self, policy: TypeCheckPolicy) {
+        let report = self.typecheck_report();
+        report.save_from_env();
+        if report.is_empty() {
+            return;
+        }
+        match policy {
+            TypeCheckPolicy::Fail => panic!("{}", report.to_text()),
+            TypeCheckPolicy::Warn => eprintln!("{}", report.to_text()),
+            TypeCheckPolicy::Throw => {
+                eprintln!("{}", report.to_text());
+                report.replace_with_throw(self);
+            }
+        }
+    }
+}
 #[test]
 fn test() {
     let mut asm = Assembly::default();
@@ -1058,3 +1457,59 @@ fn test() {
     let _sum = asm.alloc_node(sum);
     let _sig = asm.sig([], Type::Void);
 }
+#[test]
+fn typecheck_report() {
+    use super::{cilnode::MethodKind, Access, Const, MethodDef};
+    let mut asm = Assembly::default();
+    let main_module = asm.main_module();
+    let sig = asm.sig([], Type::Void);
+    let name = asm.alloc_string("bad");
+    let file = asm.alloc_string("main.rs");
+    let i64_idx = asm.alloc_type(Type::Int(Int::I64));
+    let sfi = asm.alloc_root(CILRoot::SourceFileInfo {
+        line_start: 7,
+        line_len: 1,
+        col_start: 3,
+        col_len: 10,
+        file,
+    });
+    let int = asm.alloc_node(Const::I64(1));
+    let float = asm.alloc_node(Const::F64(super::hashable::HashableF64(1.0)));
+    let sum = asm.alloc_node(CILNode::BinOp(int, float, BinOp::Add));
+    let neg = asm.alloc_node(CILNode::UnOp(sum, UnOp::Neg));
+    let set = asm.alloc_root(CILRoot::StLoc(0, neg));
+    let ret = asm.alloc_root(CILRoot::VoidRet);
+    let method = asm.new_method(MethodDef::new(
+        Access::Public,
+        main_module,
+        name,
+        sig,
+        MethodKind::Static,
+        MethodImpl::MethodBody {
+            blocks: vec![BasicBlock::new(vec![sfi, set, ret], 0, None)],
+            locals: vec![(None, i64_idx)],
+        },
+        vec![],
+    ));
+    let report = asm.typecheck_report();
+    let [err] = report.errors() else {
+        panic!("Expected a single error, got {report:?}");
+    };
+    assert_eq!(err.method, method);
+    assert_eq!((err.block, err.root_idx), (BlockKey::top(0), 1));
+    // The error is reported at the addition, not at the negation which uses its result.
+    assert_eq!(err.bad_node, Some(sum));
+    let source = err.source.as_ref().unwrap();
+    assert_eq!(
+        (&*source.file, source.line, source.column),
+        ("main.rs", 7, 3)
+    );
+    let text = report.to_text();
+    assert!(text.contains("--> main.rs:7:3"), "{text}");
+    assert!(text.contains("BinOp(Add) <-- WrongBinopArgs"), "{text}");
+    assert!(report.to_html().contains("class=\"bad\""));
+    report.replace_with_throw(&mut asm);
+    assert!(asm.typecheck_report().is_empty());
+    assert!("panic".parse::<TypeCheckPolicy>().is_err());
+    assert_eq!("throw".parse(), Ok(TypeCheckPolicy::Throw));
+}
@@ -1,12 +1,36 @@
+//! GC handles, which let Rust code own managed objects.
+//!
+//! The GC only knows about naked managed references(`RustcCLRInteropManagedClass`) stored on the stack, or inside
+//! managed objects. References stored in Rust aggregates, or on the Rust heap, are invisible to it: the backend warns
+//! about them. Instead, Rust code holds objects through GC handles:
+//! 1. [`Class`] is a strong handle. It keeps the object alive, for as long as the handle exists.
+//! 2. [`Weak`] does not keep the object alive, and can be upgraded to a [`Class`] while the object is alive.
+//! 3. [`Pinned`] keeps the object alive, and prevents the GC from moving it, so its address can be used.
+//!
+//! Every handle frees its underlying `GCHandle` when dropped.
 use crate::{
-    intrinsics::{RustcCLRInteropManagedClass, RustcCLRInteropManagedStruct},
+    intrinsics::{
+        rustc_clr_interop_managed_checked_cast, ManagedArgs, RustcCLRInteropManagedClass,
+    },
+    system::runtime::interop_services::{GCHandle, GCHandleType},
     FromManagedSafe, IntoManagedSafe, ManagedSafe,
 };
-type GCHandle = RustcCLRInteropManagedStruct<
-    "System.Runtime",
-    "System.Runtime.InteropServices.GCHandle",
-    { size_of::<usize>() },
->;
+type Object = RustcCLRInteropManagedClass<"System.Runtime", "System.Object">;
+/// Allocates a GC handle of type `kind`, referencing `naked`.
+fn alloc_handle<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str>(
+    naked: RustcCLRInteropManagedClass<ASSEMBLY, CLASS_PATH>,
+    kind: GCHandleType,
+) -> GCHandle {
+    let object: Object = rustc_clr_interop_managed_checked_cast(naked);
+    GCHandle::static2::<"Alloc", Object, GCHandleType, GCHandle>(object, kind)
+}
+/// The object referenced by `handle`, or null if it got collected.
+fn handle_target<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str>(
+    handle: &GCHandle,
+) -> RustcCLRInteropManagedClass<ASSEMBLY, CLASS_PATH> {
+    rustc_clr_interop_managed_checked_cast(handle.instance0::<"get_Target", Object>())
+}
+/// A strong handle to a managed object, which keeps it alive.
 pub struct Class<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str> {
     handle: GCHandle,
 }
@@ -31,13 +55,20 @@ impl<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str> Class<ASSEMBL
     /// The type returned by this function is extermaly unsafe, and dealing with it in a sound way is hard.
     /// See [`StackOnly`] trait documentation for more.
     pub unsafe fn get_naked_ref(&self) -> RustcCLRInteropManagedClass<ASSEMBLY, CLASS_PATH> {
-        self.handle.instance0::<"get_Target", Self::NakedRef>()
+        handle_target(&self.handle)
     }
     pub fn from_naked_ref(naked: RustcCLRInteropManagedClass<ASSEMBLY, CLASS_PATH>) -> Self {
-        let object: RustcCLRInteropManagedClass<"System.Runtime", "System.Objetc"> =
-            crate::intrinsics::rustc_clr_interop_managed_checked_cast(naked);
-        let handle = GCHandle::static1::<"Alloc", _, _>(object);
-        Self { handle }
+        Self {
+            handle: alloc_handle(naked, GCHandleType::normal()),
+        }
+    }
+    /// Creates a weak handle to this object.
+    pub fn downgrade(&self) -> Weak<ASSEMBLY, CLASS_PATH> {
+        Weak::new(unsafe { self.get_naked_ref() })
+    }
+    /// Pins this object. See [`Pinned`] for the objects which can be pinned.
+    pub fn pin(&self) -> Pinned<ASSEMBLY, CLASS_PATH> {
+        Pinned::new(unsafe { self.get_naked_ref() })
     }
     pub fn instance0<
         const NAME: &'static str,
@@ -86,6 +117,37 @@ impl<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str> Class<ASSEMBL
                 .instance2::<NAME, Arg, Arg2, SigRet>(arg.into_managed(), arg2.into_managed()),
         )
     }
+    /// Constructs a new object, using a constructor with any number of arguments.
+    /// Unlike [`Self::ctor1`], the arguments are not converted, and must already be managed-safe.
+    pub fn ctor_n<Args: ManagedArgs>(args: Args) -> Self {
+        Self::from_naked_ref(Self::NakedRef::ctor_n(args))
+    }
+    /// Calls an instance method with any number of arguments, passed in a tuple.
+    pub fn instance_n<
+        const NAME: &'static str,
+        Args: ManagedArgs,
+        SigRet: ManagedSafe,
+        RealRet: FromManagedSafe<SigRet>,
+    >(
+        &mut self,
+        args: Args,
+    ) -> RealRet {
+        RealRet::from_managed(
+            unsafe { self.get_naked_ref() }.instance_n::<NAME, Args, SigRet>(args),
+        )
+    }
+    /// Calls a virtual method with any number of arguments, passed in a tuple.
+    pub fn virt_n<
+        const NAME: &'static str,
+        Args: ManagedArgs,
+        SigRet: ManagedSafe,
+        RealRet: FromManagedSafe<SigRet>,
+    >(
+        &mut self,
+        args: Args,
+    ) -> RealRet {
+        RealRet::from_managed(unsafe { self.get_naked_ref() }.virt_n::<NAME, Args, SigRet>(args))
+    }
     //pub fn to_mstring(&self)->
 }
 impl<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str> Drop
@@ -102,3 +164,73 @@ impl<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str> Clone
         Self::from_naked_ref(unsafe { self.get_naked_ref() })
     }
 }
+/// Another name for [`Class`], the strong handle.
+pub type Strong<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str> =
+    Class<ASSEMBLY, CLASS_PATH>;
+/// A weak handle to a managed object. It does not keep the object alive, and [`Self::upgrade`] returns `None` once the
+/// object got collected. Like a `WeakReference`, it does not track resurrection.
+pub struct Weak<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str> {
+    handle: GCHandle,
+}
+impl<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str> Weak<ASSEMBLY, CLASS_PATH> {
+    pub fn new(naked: RustcCLRInteropManagedClass<ASSEMBLY, CLASS_PATH>) -> Self {
+        Self {
+            handle: alloc_handle(naked, GCHandleType::weak()),
+        }
+    }
+    /// Returns a strong handle to the object, or `None` if it got collected.
+    pub fn upgrade(&self) -> Option<Class<ASSEMBLY, CLASS_PATH>> {
+        let target = handle_target::<ASSEMBLY, CLASS_PATH>(&self.handle);
+        (!target.is_null()).then(|| Class::from_naked_ref(target))
+    }
+    /// Checks if the object is still alive. The object may get collected right after this returns `true`: use
+    /// [`Self::upgrade`] to access it.
+    pub fn is_alive(&self) -> bool {
+        !handle_target::<ASSEMBLY, CLASS_PATH>(&self.handle).is_null()
+    }
+}
+impl<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str> Drop
+    for Weak<ASSEMBLY, CLASS_PATH>
+{
+    fn drop(&mut self) {
+        self.handle.instance0::<"Free", ()>()
+    }
+}
+impl<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str> Clone
+    for Weak<ASSEMBLY, CLASS_PATH>
+{
+    fn clone(&self) -> Self {
+        // A weak handle to a collected object is a weak handle to null.
+        Self::new(handle_target(&self.handle))
+    }
+}
+/// A pinned handle to a managed object. While it exists, the GC neither collects nor moves the object, so its
+/// address stays valid. Only objects with blittable contents(e.g. strings, or arrays of primitives) can be pinned:
+/// pinning any other object throws an `ArgumentException`.
+pub struct Pinned<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str> {
+    handle: GCHandle,
+}
+impl<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str> Pinned<ASSEMBLY, CLASS_PATH> {
+    pub fn new(naked: RustcCLRInteropManagedClass<ASSEMBLY, CLASS_PATH>) -> Self {
+        Self {
+            handle: alloc_handle(naked, GCHandleType::pinned()),
+        }
+    }
+    /// The address of the data of the pinned object: the first element of an array, or the first character of a
+    /// string. It is valid until this handle is dropped.
+    pub fn addr(&self) -> *mut u8 {
+        self.handle.instance0::<"AddrOfPinnedObject", isize>() as *mut u8
+    }
+    /// Creates a strong handle to the pinned object. The object stays alive as long as the strong handle exists, but
+    /// may get moved once this handle is dropped.
+    pub fn to_class(&self) -> Class<ASSEMBLY, CLASS_PATH> {
+        Class::from_naked_ref(handle_target(&self.handle))
+    }
+}
+impl<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str> Drop
+    for Pinned<ASSEMBLY, CLASS_PATH>
+{
+    fn drop(&mut self) {
+        self.handle.instance0::<"Free", ()>()
+    }
+}
@@ -98,6 +98,223 @@ impl<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str>
             Arg2,
         >(self, arg1, arg2)
     }
+    /// Calls a constructor with any number of arguments, passed in a tuple.
+    #[inline(always)]
+    pub fn ctor_n<Args: ManagedArgs>(args: Args) -> Self {
+        rustc_clr_interop_managed_ctor_tuple_::<ASSEMBLY, CLASS_PATH, false, Args>(args)
+    }
+    /// Calls a static method with any number of arguments, passed in a tuple.
+    #[inline(always)]
+    pub fn static_n<const METHOD: &'static str, Args: ManagedArgs, Ret>(args: Args) -> Ret {
+        rustc_clr_interop_managed_call_tuple_::<ASSEMBLY, CLASS_PATH, false, METHOD, true, Ret, Args>(
+            args,
+        )
+    }
+    /// Calls an instance method with any number of arguments, passed in a tuple.
+    #[inline(always)]
+    pub fn instance_n<const METHOD: &'static str, Args: ManagedArgs, Ret>(self, args: Args) -> Ret {
+        rustc_clr_interop_managed_call_tuple_::<
+            ASSEMBLY,
+            CLASS_PATH,
+            false,
+            METHOD,
+            false,
+            Ret,
+            Args::Prepend<Self>,
+        >(args.prepend(self))
+    }
+    /// Calls a virtual method with any number of arguments, passed in a tuple.
+    #[inline(always)]
+    pub fn virt_n<const METHOD: &'static str, Args: ManagedArgs, Ret>(self, args: Args) -> Ret {
+        rustc_clr_interop_managed_call_virt_tuple_::<
+            ASSEMBLY,
+            CLASS_PATH,
+            false,
+            METHOD,
+            false,
+            Ret,
+            Args::Prepend<Self>,
+        >(args.prepend(self))
+    }
+    /// Like [`Self::static_n`], but catches any exception thrown by the method.
+    #[inline(always)]
+    pub fn try_static_n<const METHOD: &'static str, Args: ManagedArgs, Ret>(
+        args: Args,
+    ) -> Result<Ret, crate::system::exception::Exception> {
+        let mut ret = core::mem::MaybeUninit::uninit();
+        let exception = rustc_clr_interop_managed_try_call_tuple_::<
+            ASSEMBLY,
+            CLASS_PATH,
+            false,
+            METHOD,
+            true,
+            Ret,
+            Args,
+        >(args, ret.as_mut_ptr());
+        crate::system::exception::Exception::check(exception)?;
+        Ok(unsafe { ret.assume_init() })
+    }
+    /// Like [`Self::instance_n`], but catches any exception thrown by the method.
+    #[inline(always)]
+    pub fn try_instance_n<const METHOD: &'static str, Args: ManagedArgs, Ret>(
+        self,
+        args: Args,
+    ) -> Result<Ret, crate::system::exception::Exception> {
+        let mut ret = core::mem::MaybeUninit::uninit();
+        let exception = rustc_clr_interop_managed_try_call_tuple_::<
+            ASSEMBLY,
+            CLASS_PATH,
+            false,
+            METHOD,
+            false,
+            Ret,
+            Args::Prepend<Self>,
+        >(args.prepend(self), ret.as_mut_ptr());
+        crate::system::exception::Exception::check(exception)?;
+        Ok(unsafe { ret.assume_init() })
+    }
+    /// Like [`Self::virt_n`], but catches any exception thrown by the method.
+    #[inline(always)]
+    pub fn try_virt_n<const METHOD: &'static str, Args: ManagedArgs, Ret>(
+        self,
+        args: Args,
+    ) -> Result<Ret, crate::system::exception::Exception> {
+        let mut ret = core::mem::MaybeUninit::uninit();
+        let exception = rustc_clr_interop_managed_try_call_virt_tuple_::<
+            ASSEMBLY,
+            CLASS_PATH,
+            false,
+            METHOD,
+            false,
+            Ret,
+            Args::Prepend<Self>,
+        >(args.prepend(self), ret.as_mut_ptr());
+        crate::system::exception::Exception::check(exception)?;
+        Ok(unsafe { ret.assume_init() })
+    }
+    /// Loads the value of the instance field `FIELD`.
+    #[inline(always)]
+    pub fn field<const FIELD: &'static str, T>(self) -> T {
+        rustc_clr_interop_managed_ld_fld::<ASSEMBLY, CLASS_PATH, false, FIELD, T, Self>(self)
+    }
+    /// Stores `value` in the instance field `FIELD`.
+    #[inline(always)]
+    pub fn set_field<const FIELD: &'static str, T>(self, value: T) {
+        rustc_clr_interop_managed_st_fld::<ASSEMBLY, CLASS_PATH, false, FIELD, T, Self>(self, value)
+    }
+    /// Loads the value of the static field `FIELD`.
+    #[inline(always)]
+    pub fn static_field<const FIELD: &'static str, T>() -> T {
+        rustc_clr_interop_managed_ld_static_fld::<ASSEMBLY, CLASS_PATH, false, FIELD, T>()
+    }
+    /// Stores `value` in the static field `FIELD`.
+    #[inline(always)]
+    pub fn set_static_field<const FIELD: &'static str, T>(value: T) {
+        rustc_clr_interop_managed_st_static_fld::<ASSEMBLY, CLASS_PATH, false, FIELD, T>(value)
+    }
+    /// Gets the value of the property `PROPERTY`, by calling its `get_PROPERTY` accessor.
+    /// ```no_run
+    /// # use mycorrhiza::system::MString;
+    /// let len = MString::from_str("Hi").property::<"Length", i32>();
+    /// ```
+    #[inline(always)]
+    pub fn property<const PROPERTY: &'static str, T>(self) -> T {
+        rustc_clr_interop_managed_call_accessor::<
+            ASSEMBLY,
+            CLASS_PATH,
+            false,
+            "get",
+            PROPERTY,
+            false,
+            T,
+            (Self,),
+        >((self,))
+    }
+    /// Sets the value of the property `PROPERTY`, by calling its `set_PROPERTY` accessor.
+    #[inline(always)]
+    pub fn set_property<const PROPERTY: &'static str, T>(self, value: T) {
+        rustc_clr_interop_managed_call_accessor::<
+            ASSEMBLY,
+            CLASS_PATH,
+            false,
+            "set",
+            PROPERTY,
+            false,
+            (),
+            (Self, T),
+        >((self, value))
+    }
+    /// Gets the value of the static property `PROPERTY`.
+    #[inline(always)]
+    pub fn static_property<const PROPERTY: &'static str, T>() -> T {
+        rustc_clr_interop_managed_call_accessor::<
+            ASSEMBLY,
+            CLASS_PATH,
+            false,
+            "get",
+            PROPERTY,
+            true,
+            T,
+            (),
+        >(())
+    }
+    /// Sets the value of the static property `PROPERTY`.
+    #[inline(always)]
+    pub fn set_static_property<const PROPERTY: &'static str, T>(value: T) {
+        rustc_clr_interop_managed_call_accessor::<
+            ASSEMBLY,
+            CLASS_PATH,
+            false,
+            "set",
+            PROPERTY,
+            true,
+            (),
+            (T,),
+        >((value,))
+    }
+    /// Subscribes `handler` to the event `EVENT`, by calling its `add_EVENT` accessor. See
+    /// [`crate::system::delegate`] for turning closures into delegates.
+    #[inline(always)]
+    pub fn add_event<
+        const EVENT: &'static str,
+        const DELEGATE_ASSEMBLY: &'static str,
+        const DELEGATE: &'static str,
+    >(
+        self,
+        handler: RustcCLRInteropManagedClass<DELEGATE_ASSEMBLY, DELEGATE>,
+    ) {
+        rustc_clr_interop_managed_call_accessor::<
+            ASSEMBLY,
+            CLASS_PATH,
+            false,
+            "add",
+            EVENT,
+            false,
+            (),
+            _,
+        >((self, handler))
+    }
+    /// Unsubscribes `handler` from the event `EVENT`, by calling its `remove_EVENT` accessor.
+    #[inline(always)]
+    pub fn remove_event<
+        const EVENT: &'static str,
+        const DELEGATE_ASSEMBLY: &'static str,
+        const DELEGATE: &'static str,
+    >(
+        self,
+        handler: RustcCLRInteropManagedClass<DELEGATE_ASSEMBLY, DELEGATE>,
+    ) {
+        rustc_clr_interop_managed_call_accessor::<
+            ASSEMBLY,
+            CLASS_PATH,
+            false,
+            "remove",
+            EVENT,
+            false,
+            (),
+            _,
+        >((self, handler))
+    }
     #[inline(always)]
     pub fn to_mstring(self) -> crate::system::MString {
         self.instance0::<"ToString", crate::system::MString>()
@@ -110,8 +327,12 @@ impl<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str>
     pub fn null() -> Self {
         rustc_clr_interop_managed_ld_null::<Self>()
     }
+    /// Checks if this is a null reference. Unlike [`Self::equality`], this does not depend on the type overloading
+    /// `==`.
     pub fn is_null(self) -> bool {
-        self.equality(Self::null())
+        type Object = RustcCLRInteropManagedClass<"System.Runtime", "System.Object">;
+        let object: Object = rustc_clr_interop_managed_checked_cast(self);
+        Object::static2::<"ReferenceEquals", Object, Object, bool>(object, Object::null())
     }
 }
 
@@ -249,6 +470,241 @@ pub fn rustc_clr_interop_managed_call_virt2_<
 ) -> Ret {
     core::intrinsics::abort();
 }
+/// The arguments of a N-ary managed call, passed as a tuple. Implemented for tuples of up to 15 elements.
+pub trait ManagedArgs {
+    /// This tuple, with an additional first element of type `T`.
+    type Prepend<T>;
+    /// Inserts `first` before all the other elements of this tuple. Used to pass `this` to instance methods.
+    fn prepend<T>(self, first: T) -> Self::Prepend<T>;
+}
+macro_rules! managed_args {
+    ($($arg:ident),*) => {
+        impl<$($arg),*> ManagedArgs for ($($arg,)*) {
+            type Prepend<T> = (T, $($arg,)*);
+            #[allow(non_snake_case)]
+            #[inline(always)]
+            fn prepend<T>(self, first: T) -> Self::Prepend<T> {
+                let ($($arg,)*) = self;
+                (first, $($arg,)*)
+            }
+        }
+    };
+}
+managed_args! {}
+managed_args! {A1}
+managed_args! {A1, A2}
+managed_args! {A1, A2, A3}
+managed_args! {A1, A2, A3, A4}
+managed_args! {A1, A2, A3, A4, A5}
+managed_args! {A1, A2, A3, A4, A5, A6}
+managed_args! {A1, A2, A3, A4, A5, A6, A7}
+managed_args! {A1, A2, A3, A4, A5, A6, A7, A8}
+managed_args! {A1, A2, A3, A4, A5, A6, A7, A8, A9}
+managed_args! {A1, A2, A3, A4, A5, A6, A7, A8, A9, A10}
+managed_args! {A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11}
+managed_args! {A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12}
+managed_args! {A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13}
+managed_args! {A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14}
+managed_args! {A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15}
+//N-ary calls
+/// Calls a managed method, passing the elements of the tuple `args` as its arguments. For instance methods, the first
+/// element is `this`. The backend supports tuples of any size.
+#[allow(unused_variables)]
+#[inline(never)]
+pub fn rustc_clr_interop_managed_call_tuple_<
+    const ASSEMBLY: &'static str,
+    const CLASS_PATH: &'static str,
+    const IS_VALUETYPE: bool,
+    const METHOD: &'static str,
+    const IS_STATIC: bool,
+    Ret,
+    Args,
+>(
+    args: Args,
+) -> Ret {
+    core::intrinsics::abort();
+}
+/// Like [`rustc_clr_interop_managed_call_tuple_`], but calls the method virtually.
+#[allow(unused_variables)]
+#[inline(never)]
+pub fn rustc_clr_interop_managed_call_virt_tuple_<
+    const ASSEMBLY: &'static str,
+    const CLASS_PATH: &'static str,
+    const IS_VALUETYPE: bool,
+    const METHOD: &'static str,
+    const IS_STATIC: bool,
+    Ret,
+    Args,
+>(
+    args: Args,
+) -> Ret {
+    core::intrinsics::abort();
+}
+/// Like [`rustc_clr_interop_managed_call_tuple_`], but the call is wrapped in a `try` block. The result is written to `ret`,
+/// and the exception thrown by the method(or null, if it returned normally) is returned.
+#[allow(unused_variables)]
+#[inline(never)]
+pub fn rustc_clr_interop_managed_try_call_tuple_<
+    const ASSEMBLY: &'static str,
+    const CLASS_PATH: &'static str,
+    const IS_VALUETYPE: bool,
+    const METHOD: &'static str,
+    const IS_STATIC: bool,
+    Ret,
+    Args,
+>(
+    args: Args,
+    ret: *mut Ret,
+) -> crate::system::exception::SystemException {
+    core::intrinsics::abort();
+}
+/// Like [`rustc_clr_interop_managed_try_call_tuple_`], but calls the method virtually.
+#[allow(unused_variables)]
+#[inline(never)]
+pub fn rustc_clr_interop_managed_try_call_virt_tuple_<
+    const ASSEMBLY: &'static str,
+    const CLASS_PATH: &'static str,
+    const IS_VALUETYPE: bool,
+    const METHOD: &'static str,
+    const IS_STATIC: bool,
+    Ret,
+    Args,
+>(
+    args: Args,
+    ret: *mut Ret,
+) -> crate::system::exception::SystemException {
+    core::intrinsics::abort();
+}
+/// Calls the accessor `{ACCESSOR}_{MEMBER}` of a property or event(e.g. `get_Length` or `add_Exited`), passing the
+/// elements of the tuple `args` as its arguments. Instance accessors of classes are called virtually, while those of
+/// value types take a pointer to the value as their first argument, and are called directly.
+#[allow(unused_variables)]
+#[inline(never)]
+pub fn rustc_clr_interop_managed_call_accessor<
+    const ASSEMBLY: &'static str,
+    const CLASS_PATH: &'static str,
+    const IS_VALUETYPE: bool,
+    const ACCESSOR: &'static str,
+    const MEMBER: &'static str,
+    const IS_STATIC: bool,
+    Ret,
+    Args,
+>(
+    args: Args,
+) -> Ret {
+    core::intrinsics::abort();
+}
+//Fields
+/// Loads the instance field `FIELD` of type `T`. `obj` is the object, or a pointer to a value type.
+#[allow(unused_variables)]
+#[inline(never)]
+pub fn rustc_clr_interop_managed_ld_fld<
+    const ASSEMBLY: &'static str,
+    const CLASS_PATH: &'static str,
+    const IS_VALUETYPE: bool,
+    const FIELD: &'static str,
+    T,
+    Obj,
+>(
+    obj: Obj,
+) -> T {
+    core::intrinsics::abort();
+}
+/// Stores `value` in the instance field `FIELD` of type `T`. `obj` is the object, or a pointer to a value type.
+#[allow(unused_variables)]
+#[inline(never)]
+pub fn rustc_clr_interop_managed_st_fld<
+    const ASSEMBLY: &'static str,
+    const CLASS_PATH: &'static str,
+    const IS_VALUETYPE: bool,
+    const FIELD: &'static str,
+    T,
+    Obj,
+>(
+    obj: Obj,
+    value: T,
+) {
+    core::intrinsics::abort();
+}
+/// Loads the static field `FIELD` of type `T`.
+#[allow(unused_variables)]
+#[inline(never)]
+pub fn rustc_clr_interop_managed_ld_static_fld<
+    const ASSEMBLY: &'static str,
+    const CLASS_PATH: &'static str,
+    const IS_VALUETYPE: bool,
+    const FIELD: &'static str,
+    T,
+>() -> T {
+    core::intrinsics::abort();
+}
+/// Stores `value` in the static field `FIELD` of type `T`.
+#[allow(unused_variables)]
+#[inline(never)]
+pub fn rustc_clr_interop_managed_st_static_fld<
+    const ASSEMBLY: &'static str,
+    const CLASS_PATH: &'static str,
+    const IS_VALUETYPE: bool,
+    const FIELD: &'static str,
+    T,
+>(
+    value: T,
+) {
+    core::intrinsics::abort();
+}
+/// Allocates a new, zero-initialized, one-dimensional managed array of length `len`.
+#[allow(unused_variables)]
+#[inline(never)]
+pub fn rustc_clr_interop_managed_new_arr<T>(len: i32) -> RustcCLRInteropManagedArray<T, 1> {
+    core::intrinsics::abort();
+}
+/// Pins `arr` in a pinned local of the calling method(like a C# `fixed` statement), and returns the address of its first
+/// element. The array stays pinned until the calling method returns.
+#[allow(unused_variables)]
+#[inline(never)]
+pub fn rustc_clr_interop_managed_arr_pin<T, const DIMENSIONS: usize>(
+    arr: RustcCLRInteropManagedArray<T, DIMENSIONS>,
+) -> *mut T {
+    core::intrinsics::abort();
+}
+/// Allocates a new, zero-initialized, multidimensional managed array. The length of each dimension is stored in `lengths`.
+#[allow(unused_variables)]
+#[inline(never)]
+pub fn rustc_clr_interop_managed_new_md_arr<T, const DIMENSIONS: usize>(
+    lengths: RustcCLRInteropManagedArray<i32, 1>,
+) -> RustcCLRInteropManagedArray<T, DIMENSIONS> {
+    core::intrinsics::abort();
+}
+/// Creates a delegate of type `CLASS_PATH`, which calls `invoke(env, args...)`, where `args` are the elements of the
+/// tuple type `Args`. `drop(env)` gets called by the finalizer of the delegate target, once the delegate is collected.
+#[allow(unused_variables)]
+#[inline(never)]
+pub fn rustc_clr_interop_delegate_from_fn<
+    const ASSEMBLY: &'static str,
+    const CLASS_PATH: &'static str,
+    Ret,
+    Args,
+    Invoke,
+>(
+    invoke: Invoke,
+    env: *mut (),
+    drop: fn(*mut ()),
+) -> RustcCLRInteropManagedClass<ASSEMBLY, CLASS_PATH> {
+    core::intrinsics::abort();
+}
+/// Creates a new managed object, passing the elements of the tuple `args` to its constructor.
+#[allow(unused_variables)]
+#[inline(never)]
+pub fn rustc_clr_interop_managed_ctor_tuple_<
+    const ASSEMBLY: &'static str,
+    const CLASS_PATH: &'static str,
+    const IS_VALUETYPE: bool,
+    Args,
+>(
+    args: Args,
+) -> RustcCLRInteropManagedClass<ASSEMBLY, CLASS_PATH> {
+    core::intrinsics::abort();
+}
 //Ctors
 #[allow(unused_variables)]
 #[inline(never)]
@@ -325,6 +781,11 @@ impl From<u16> for RustcCLRInteropManagedChar {
         }
     }
 }
+impl From<RustcCLRInteropManagedChar> for u16 {
+    fn from(managed_char: RustcCLRInteropManagedChar) -> u16 {
+        managed_char.utf16_char
+    }
+}
 
 impl RustcCLRInteropManagedChar {
     pub fn single_codepoint_unchecked(value: char) -> Self {
@@ -392,4 +853,149 @@ impl<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str, const SIZE: u
             arg1,
         )
     }
+    #[inline(always)]
+    pub fn instance1<const METHOD: &'static str, Arg1, Ret>(self, arg1: Arg1) -> Ret {
+        rustc_clr_interop_managed_call2_::<
+            ASSEMBLY,
+            CLASS_PATH,
+            false,
+            METHOD,
+            false,
+            Ret,
+            &Self,
+            Arg1,
+        >(&self, arg1)
+    }
+    #[inline(always)]
+    pub fn static2<const METHOD: &'static str, Arg1, Arg2, Ret>(arg1: Arg1, arg2: Arg2) -> Ret {
+        rustc_clr_interop_managed_call2_::<ASSEMBLY, CLASS_PATH, false, METHOD, true, Ret, Arg1, Arg2>(
+            arg1, arg2,
+        )
+    }
+    /// Loads the value of the instance field `FIELD` of this value type.
+    #[inline(always)]
+    pub fn field<const FIELD: &'static str, T>(&self) -> T {
+        rustc_clr_interop_managed_ld_fld::<ASSEMBLY, CLASS_PATH, true, FIELD, T, &Self>(self)
+    }
+    /// Stores `value` in the instance field `FIELD` of this value type.
+    #[inline(always)]
+    pub fn set_field<const FIELD: &'static str, T>(&mut self, value: T) {
+        rustc_clr_interop_managed_st_fld::<ASSEMBLY, CLASS_PATH, true, FIELD, T, &mut Self>(
+            self, value,
+        )
+    }
+    /// Loads the value of the static field `FIELD`.
+    #[inline(always)]
+    pub fn static_field<const FIELD: &'static str, T>() -> T {
+        rustc_clr_interop_managed_ld_static_fld::<ASSEMBLY, CLASS_PATH, true, FIELD, T>()
+    }
+    /// Stores `value` in the static field `FIELD`.
+    #[inline(always)]
+    pub fn set_static_field<const FIELD: &'static str, T>(value: T) {
+        rustc_clr_interop_managed_st_static_fld::<ASSEMBLY, CLASS_PATH, true, FIELD, T>(value)
+    }
+    /// Gets the value of the property `PROPERTY` of this value type, by calling its `get_PROPERTY` accessor.
+    /// ```no_run
+    /// # #![feature(adt_const_params, unsized_const_params)]
+    /// # use mycorrhiza::intrinsics::RustcCLRInteropManagedStruct;
+    /// type DateTime = RustcCLRInteropManagedStruct<"System.Runtime", "System.DateTime", 8>;
+    /// let year = DateTime::static_property::<"Now", DateTime>().property::<"Year", i32>();
+    /// ```
+    #[inline(always)]
+    pub fn property<const PROPERTY: &'static str, T>(&self) -> T {
+        rustc_clr_interop_managed_call_accessor::<
+            ASSEMBLY,
+            CLASS_PATH,
+            true,
+            "get",
+            PROPERTY,
+            false,
+            T,
+            (&Self,),
+        >((self,))
+    }
+    /// Sets the value of the property `PROPERTY` of this value type, by calling its `set_PROPERTY` accessor.
+    #[inline(always)]
+    pub fn set_property<const PROPERTY: &'static str, T>(&mut self, value: T) {
+        rustc_clr_interop_managed_call_accessor::<
+            ASSEMBLY,
+            CLASS_PATH,
+            true,
+            "set",
+            PROPERTY,
+            false,
+            (),
+            (&mut Self, T),
+        >((self, value))
+    }
+    /// Gets the value of the static property `PROPERTY`.
+    #[inline(always)]
+    pub fn static_property<const PROPERTY: &'static str, T>() -> T {
+        rustc_clr_interop_managed_call_accessor::<
+            ASSEMBLY,
+            CLASS_PATH,
+            true,
+            "get",
+            PROPERTY,
+            true,
+            T,
+            (),
+        >(())
+    }
+    /// Sets the value of the static property `PROPERTY`.
+    #[inline(always)]
+    pub fn set_static_property<const PROPERTY: &'static str, T>(value: T) {
+        rustc_clr_interop_managed_call_accessor::<
+            ASSEMBLY,
+            CLASS_PATH,
+            true,
+            "set",
+            PROPERTY,
+            true,
+            (),
+            (T,),
+        >((value,))
+    }
+    /// Subscribes `handler` to the event `EVENT` of this value type, by calling its `add_EVENT` accessor.
+    #[inline(always)]
+    pub fn add_event<
+        const EVENT: &'static str,
+        const DELEGATE_ASSEMBLY: &'static str,
+        const DELEGATE: &'static str,
+    >(
+        &mut self,
+        handler: RustcCLRInteropManagedClass<DELEGATE_ASSEMBLY, DELEGATE>,
+    ) {
+        rustc_clr_interop_managed_call_accessor::<
+            ASSEMBLY,
+            CLASS_PATH,
+            true,
+            "add",
+            EVENT,
+            false,
+            (),
+            _,
+        >((self, handler))
+    }
+    /// Unsubscribes `handler` from the event `EVENT` of this value type, by calling its `remove_EVENT` accessor.
+    #[inline(always)]
+    pub fn remove_event<
+        const EVENT: &'static str,
+        const DELEGATE_ASSEMBLY: &'static str,
+        const DELEGATE: &'static str,
+    >(
+        &mut self,
+        handler: RustcCLRInteropManagedClass<DELEGATE_ASSEMBLY, DELEGATE>,
+    ) {
+        rustc_clr_interop_managed_call_accessor::<
+            ASSEMBLY,
+            CLASS_PATH,
+            true,
+            "remove",
+            EVENT,
+            false,
+            (),
+            _,
+        >((self, handler))
+    }
 }
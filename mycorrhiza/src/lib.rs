@@ -76,7 +76,7 @@ pub trait FromManagedSafe<From: ManagedSafe> {
 /// **Don't use types marked with this trait** unless you know exactly what you are doing.
 /// # Safety
 /// This kind of type can be:
-/// 1. Stored directly on the stack - *not inside any other type*. You could, in theory, store it safely in some types, but the rustc_codegen_clr is not able to check the safety of that, and may raise false alarms, so just don't dop
+/// 1. Stored directly on the stack - *not inside any other type*. You could, in theory, store it safely in some types, but the rustc_codegen_clr is not able to check the safety of that, and warns about any naked managed reference stored in a Rust aggregate or on the heap. Use the handles from [`class`] instead.
 /// 2. Stored inside a object .NET type.
 /// 3. Stored inside a .NET value type.
 pub unsafe trait StackOnly {}
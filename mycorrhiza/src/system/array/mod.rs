@@ -0,0 +1,111 @@
+//! Bridging between managed arrays and Rust slices.
+//!
+//! Managed arrays are freely copyable references, and .NET code may access them at any time, so borrowing their
+//! elements as Rust slices is `unsafe`. The safe APIs only ever copy elements in or out of an array, or hand out raw
+//! pointers.
+use crate::intrinsics::{
+    rustc_clr_interop_managed_arr_pin, rustc_clr_interop_managed_checked_cast,
+    rustc_clr_interop_managed_new_arr, rustc_clr_interop_managed_new_md_arr,
+    RustcCLRInteropManagedArray, RustcCLRInteropManagedClass,
+};
+/// `System.Array`
+pub type SystemArray = RustcCLRInteropManagedClass<"System.Runtime", "System.Array">;
+/// Marker trait for types with the same layout in Rust and .NET, which can be pinned.
+/// # Safety
+/// The type must be a primitive .NET type, which contains no managed references.
+pub unsafe trait Blittable: Copy {}
+macro_rules! blittable {
+    ($($t:ty),+) => {
+        $(unsafe impl Blittable for $t {})+
+    };
+}
+blittable! {u8,i8,u16,i16,u32,i32,u64,i64,usize,isize,f32,f64}
+impl<T: Blittable> RustcCLRInteropManagedArray<T, 1> {
+    /// Allocates a new array(`newarr`), with `len` zero-initialized elements.
+    pub fn new(len: usize) -> Self {
+        rustc_clr_interop_managed_new_arr(i32::try_from(len).expect("Array too long for .NET"))
+    }
+    /// Allocates a new array, and copies `slice` into it.
+    pub fn from_slice(slice: &[T]) -> Self {
+        let arr = Self::new(slice.len());
+        arr.copy_from_slice(slice);
+        arr
+    }
+}
+impl<T: Blittable, const DIMENSIONS: usize> RustcCLRInteropManagedArray<T, DIMENSIONS> {
+    /// Allocates a new multidimensional array(`T[,]` for 2 dimensions), with the given length of each dimension.
+    pub fn new_md(lengths: [usize; DIMENSIONS]) -> Self {
+        let lengths = lengths.map(|len| i32::try_from(len).expect("Array too long for .NET"));
+        rustc_clr_interop_managed_new_md_arr(RustcCLRInteropManagedArray::<i32, 1>::from_slice(
+            &lengths,
+        ))
+    }
+    fn as_system_array(self) -> SystemArray {
+        rustc_clr_interop_managed_checked_cast(self)
+    }
+    /// The total number of elements in this array, in all dimensions.
+    pub fn total_len(self) -> usize {
+        self.as_system_array().virt0::<"get_Length", i32>() as usize
+    }
+    /// The number of elements in the dimension `dim`.
+    pub fn dim_len(self, dim: usize) -> usize {
+        self.as_system_array()
+            .instance1::<"GetLength", i32, i32>(dim as i32) as usize
+    }
+    /// Pins this array for the duration of `f`, and passes it the address and the total number of its elements.
+    /// The elements of multidimensional arrays are stored in row-major order.
+    pub fn with_ptr<R>(self, f: impl FnOnce(*mut T, usize) -> R) -> R {
+        let len = self.total_len();
+        // The array is pinned by a local of this function, so it stays pinned until `f` returns.
+        let elements = rustc_clr_interop_managed_arr_pin(self);
+        f(elements, len)
+    }
+    /// Pins this array for the duration of `f`, and lends it its elements without copying.
+    /// # Safety
+    /// Nothing may write to the elements of this array while `f` runs: neither Rust code using another copy of this
+    /// array reference, nor .NET code.
+    pub unsafe fn with_slice<R>(self, f: impl FnOnce(&[T]) -> R) -> R {
+        self.with_ptr(|elements, len| {
+            if len == 0 {
+                return f(&[]);
+            }
+            f(unsafe { core::slice::from_raw_parts(elements, len) })
+        })
+    }
+    /// Pins this array for the duration of `f`, and mutably lends it its elements without copying.
+    /// # Safety
+    /// Nothing but `f` may access the elements of this array while it runs: neither Rust code using another copy of
+    /// this array reference(including nested calls to `with_slice` or `with_mut_slice`), nor .NET code.
+    pub unsafe fn with_mut_slice<R>(self, f: impl FnOnce(&mut [T]) -> R) -> R {
+        self.with_ptr(|elements, len| {
+            if len == 0 {
+                return f(&mut []);
+            }
+            f(unsafe { core::slice::from_raw_parts_mut(elements, len) })
+        })
+    }
+    /// Copies `src` into this array. Panics if their lengths differ.
+    pub fn copy_from_slice(self, src: &[T]) {
+        self.with_ptr(|elements, len| {
+            assert_eq!(
+                len,
+                src.len(),
+                "Source slice length does not match the array"
+            );
+            // No other code runs while the elements are being copied.
+            unsafe { core::ptr::copy_nonoverlapping(src.as_ptr(), elements, len) };
+        })
+    }
+    /// Copies the elements of this array into a new `Vec`.
+    pub fn to_vec(self) -> Vec<T> {
+        self.with_ptr(|elements, len| {
+            let mut vec = Vec::with_capacity(len);
+            // No other code runs while the elements are being copied.
+            unsafe {
+                core::ptr::copy_nonoverlapping(elements, vec.as_mut_ptr(), len);
+                vec.set_len(len);
+            }
+            vec
+        })
+    }
+}
@@ -0,0 +1,73 @@
+//! Passing Rust closures to .NET, as delegates.
+//!
+//! The closure is boxed, and stored in an object created by the backend. The delegate calls the `Invoke` method of that
+//! object, which calls the closure. The closure gets dropped by the finalizer of that object, once the delegate is
+//! no longer referenced by .NET. Since the finalizer runs on a separate thread, the closure must be `Send`. .NET may
+//! invoke the same delegate from many threads at once, so the closure is only ever borrowed immutably, and must be `Fn`
+//! and `Sync`. Closures which need mutable state should keep it behind a `Mutex` or an atomic.
+//!
+//! Unwinding out of a delegate into .NET frames is not supported, so a panic inside the closure aborts the process.
+use crate::intrinsics::{rustc_clr_interop_delegate_from_fn, RustcCLRInteropManagedClass};
+/// A .NET delegate type. Only non-generic delegate types are supported.
+pub type Delegate<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str> =
+    RustcCLRInteropManagedClass<ASSEMBLY, CLASS_PATH>;
+/// `System.Action`
+pub type Action = Delegate<"System.Runtime", "System.Action">;
+/// `System.EventHandler`, called with the sender of an event(a `System.Object`) and its `System.EventArgs`.
+pub type EventHandler = Delegate<"System.Runtime", "System.EventHandler">;
+/// A Rust closure, which can be turned into a delegate taking `Args`, and returning `Ret`.
+pub trait IntoDelegate<Args, Ret> {
+    fn into_delegate<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str>(
+        self,
+    ) -> Delegate<ASSEMBLY, CLASS_PATH>;
+}
+fn drop_env<F>(env: *mut ()) {
+    drop(unsafe { Box::from_raw(env as *mut F) });
+}
+macro_rules! into_delegate {
+    ($($arg:ident),*) => {
+        impl<F: Fn($($arg),*) -> Ret + Send + Sync + 'static, Ret, $($arg),*> IntoDelegate<($($arg,)*), Ret> for F {
+            fn into_delegate<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str>(
+                self,
+            ) -> Delegate<ASSEMBLY, CLASS_PATH> {
+                #[allow(non_snake_case)]
+                fn invoke<F: Fn($($arg),*) -> Ret, Ret, $($arg),*>(env: *mut (), $($arg: $arg),*) -> Ret {
+                    let closure = unsafe { &*(env as *const F) };
+                    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| closure($($arg),*))) {
+                        Ok(ret) => ret,
+                        Err(_) => std::process::abort(),
+                    }
+                }
+                let env = Box::into_raw(Box::new(self)) as *mut ();
+                rustc_clr_interop_delegate_from_fn::<ASSEMBLY, CLASS_PATH, Ret, ($($arg,)*), _>(
+                    invoke::<F, Ret, $($arg),*> as fn(*mut (), $($arg),*) -> Ret,
+                    env,
+                    drop_env::<F>,
+                )
+            }
+        }
+    };
+}
+into_delegate! {}
+into_delegate! {A1}
+into_delegate! {A1, A2}
+into_delegate! {A1, A2, A3}
+into_delegate! {A1, A2, A3, A4}
+into_delegate! {A1, A2, A3, A4, A5}
+into_delegate! {A1, A2, A3, A4, A5, A6}
+into_delegate! {A1, A2, A3, A4, A5, A6, A7}
+into_delegate! {A1, A2, A3, A4, A5, A6, A7, A8}
+impl<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str>
+    RustcCLRInteropManagedClass<ASSEMBLY, CLASS_PATH>
+{
+    /// Creates a delegate of this type, which calls `f`. The arguments and the return type of `f` must match the
+    /// signature of the delegate.
+    /// ```no_run
+    /// # use mycorrhiza::system::delegate::Action;
+    /// let action = Action::from_fn(|| println!("Hello from .NET!"));
+    /// action.virt0::<"Invoke", ()>();
+    /// ```
+    pub fn from_fn<Args, Ret>(f: impl IntoDelegate<Args, Ret>) -> Self {
+        f.into_delegate()
+    }
+}
@@ -28,3 +28,64 @@ impl Stopwatch {
         Self::virt0::<"get_ElapsedMilliseconds", i64>(self)
     }
 }
+/// The options used to start a [`Process`].
+pub type ProcessStartInfo = crate::intrinsics::RustcCLRInteropManagedClass<
+    "System.Diagnostics.Process",
+    "System.Diagnostics.ProcessStartInfo",
+>;
+impl ProcessStartInfo {
+    /// Creates the options for starting the executable `file_name`.
+    #[inline(always)]
+    pub fn new(file_name: &str) -> Self {
+        Self::ctor1(crate::system::MString::from_str(file_name))
+    }
+    #[inline(always)]
+    pub fn set_arguments(self, arguments: &str) {
+        self.set_property::<"Arguments", _>(crate::system::MString::from_str(arguments))
+    }
+    #[inline(always)]
+    pub fn set_working_directory(self, directory: &str) {
+        self.set_property::<"WorkingDirectory", _>(crate::system::MString::from_str(directory))
+    }
+    #[inline(always)]
+    pub fn set_use_shell_execute(self, use_shell_execute: bool) {
+        self.set_property::<"UseShellExecute", _>(use_shell_execute)
+    }
+    #[inline(always)]
+    pub fn set_redirect_standard_output(self, redirect: bool) {
+        self.set_property::<"RedirectStandardOutput", _>(redirect)
+    }
+    #[inline(always)]
+    pub fn set_create_no_window(self, create_no_window: bool) {
+        self.set_property::<"CreateNoWindow", _>(create_no_window)
+    }
+}
+pub type Process = crate::intrinsics::RustcCLRInteropManagedClass<
+    "System.Diagnostics.Process",
+    "System.Diagnostics.Process",
+>;
+impl Process {
+    /// Starts a new process, configured by `info`.
+    #[inline(always)]
+    pub fn start(info: ProcessStartInfo) -> Self {
+        Self::static1::<"Start", ProcessStartInfo, Self>(info)
+    }
+    #[inline(always)]
+    pub fn wait_for_exit(self) {
+        self.virt0::<"WaitForExit", ()>()
+    }
+    #[inline(always)]
+    pub fn exit_code(self) -> i32 {
+        self.property::<"ExitCode", i32>()
+    }
+    /// Makes the process raise its `Exited` event, once it exits.
+    #[inline(always)]
+    pub fn set_enable_raising_events(self, enable: bool) {
+        self.set_property::<"EnableRaisingEvents", _>(enable)
+    }
+    /// Calls `handler` once the process exits. Requires [`Self::set_enable_raising_events`].
+    #[inline(always)]
+    pub fn on_exited(self, handler: crate::system::delegate::EventHandler) {
+        self.add_event::<"Exited", _, _>(handler)
+    }
+}
@@ -0,0 +1,56 @@
+use crate::{class::Class, system::MString};
+/// A naked reference to a `System.Exception`.
+pub type SystemException =
+    crate::intrinsics::RustcCLRInteropManagedClass<"System.Runtime", "System.Exception">;
+/// A .NET exception, caught by one of the `try_*` managed calls.
+pub struct Exception {
+    handle: Class<"System.Runtime", "System.Exception">,
+    message: String,
+    stack_trace: Option<String>,
+}
+impl Exception {
+    /// Returns `Err` if `exception` is not null.
+    pub(crate) fn check(exception: SystemException) -> Result<(), Self> {
+        if exception.is_null() {
+            Ok(())
+        } else {
+            Err(Self::from_naked_ref(exception))
+        }
+    }
+    pub fn from_naked_ref(exception: SystemException) -> Self {
+        let message = exception.virt0::<"get_Message", MString>().into();
+        let stack_trace = exception.virt0::<"get_StackTrace", MString>();
+        let stack_trace = (!stack_trace.is_null()).then(|| stack_trace.into());
+        Self {
+            handle: Class::from_naked_ref(exception),
+            message,
+            stack_trace,
+        }
+    }
+    /// The handle to the managed exception object.
+    pub fn handle(&self) -> &Class<"System.Runtime", "System.Exception"> {
+        &self.handle
+    }
+    /// The message of the exception(`Exception.Message`).
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+    /// The stack trace of the exception(`Exception.StackTrace`), if it is available.
+    pub fn stack_trace(&self) -> Option<&str> {
+        self.stack_trace.as_deref()
+    }
+}
+impl core::fmt::Debug for Exception {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Exception")
+            .field("message", &self.message)
+            .field("stack_trace", &self.stack_trace)
+            .finish()
+    }
+}
+impl core::fmt::Display for Exception {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+impl std::error::Error for Exception {}
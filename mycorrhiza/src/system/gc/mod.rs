@@ -0,0 +1,32 @@
+/// `System.GC`, which controls the garbage collector.
+pub type GC = crate::intrinsics::RustcCLRInteropManagedClass<"System.Runtime", "System.GC">;
+impl GC {
+    /// Forces an immediate collection of all generations.
+    #[inline(always)]
+    pub fn collect() {
+        Self::static_n::<"Collect", (), ()>(())
+    }
+    /// Blocks until the finalizers of all collected objects have run.
+    #[inline(always)]
+    pub fn wait_for_pending_finalizers() {
+        Self::static_n::<"WaitForPendingFinalizers", (), ()>(())
+    }
+    /// Collects all unreachable objects, including the ones only kept alive by their finalizers.
+    pub fn collect_all() {
+        Self::collect();
+        Self::wait_for_pending_finalizers();
+        Self::collect();
+    }
+    /// Keeps `object` alive until this call. Like `GC.KeepAlive`, this has no effect besides marking the object as
+    /// referenced.
+    #[inline(always)]
+    pub fn keep_alive<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str>(
+        object: crate::intrinsics::RustcCLRInteropManagedClass<ASSEMBLY, CLASS_PATH>,
+    ) {
+        let object: crate::intrinsics::RustcCLRInteropManagedClass<
+            "System.Runtime",
+            "System.Object",
+        > = crate::intrinsics::rustc_clr_interop_managed_checked_cast(object);
+        Self::static1::<"KeepAlive", _, ()>(object)
+    }
+}
@@ -1,17 +1,79 @@
-use runtime::interop_services::Marshal;
+use runtime::interop_services::{GCHandle, GCHandleType};
 
+pub mod array;
 pub mod console;
+pub mod delegate;
 pub mod diagnostics;
+pub mod exception;
+pub mod gc;
 pub mod runtime;
 pub mod text;
+pub mod threading;
 pub type MString =
     crate::intrinsics::RustcCLRInteropManagedClass<"System.Runtime", "System.String">;
 
+impl MString {
+    /// Creates a new .NET string from a Rust one. The UTF-8 data is decoded directly from `string`, without any
+    /// intermediate copies.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(string: &str) -> Self {
+        let len = i32::try_from(string.len()).expect("String too long for .NET");
+        unsafe { text::Encoding::utf8().get_string(string.as_ptr(), len) }
+    }
+    /// The length of this string, in UTF-16 code units.
+    pub fn len(self) -> usize {
+        self.instance0::<"get_Length", i32>() as usize
+    }
+    pub fn is_empty(self) -> bool {
+        self.len() == 0
+    }
+    /// Pins this string, allowing its UTF-16 data to be borrowed.
+    pub fn pin(self) -> PinnedMString {
+        let object: crate::intrinsics::RustcCLRInteropManagedClass<
+            "System.Runtime",
+            "System.Object",
+        > = crate::intrinsics::rustc_clr_interop_managed_checked_cast(self);
+        let handle = GCHandle::static2::<"Alloc", _, _, GCHandle>(object, GCHandleType::pinned());
+        let chars = handle.instance0::<"AddrOfPinnedObject", isize>() as *const u16;
+        PinnedMString {
+            handle,
+            chars,
+            len: self.len(),
+        }
+    }
+    /// Converts this string into a Rust string. Invalid UTF-16 is replaced with `U+FFFD`.
+    #[allow(clippy::inherent_to_string)]
+    pub fn to_string(self) -> String {
+        String::from_utf16_lossy(self.pin().as_slice())
+    }
+}
+/// A string, pinned in memory(like by a C# `fixed` statement). The string gets unpinned when this is dropped.
+pub struct PinnedMString {
+    handle: GCHandle,
+    chars: *const u16,
+    len: usize,
+}
+impl PinnedMString {
+    /// The UTF-16 data of the pinned string, borrowed without copying.
+    pub fn as_slice(&self) -> &[u16] {
+        if self.len == 0 {
+            return &[];
+        }
+        unsafe { core::slice::from_raw_parts(self.chars, self.len) }
+    }
+}
+impl Drop for PinnedMString {
+    fn drop(&mut self) {
+        self.handle.instance0::<"Free", ()>()
+    }
+}
+impl From<MString> for String {
+    fn from(val: MString) -> Self {
+        val.to_string()
+    }
+}
 impl From<&str> for MString {
     fn from(val: &str) -> Self {
-        Marshal::static2::<"PtrToStringUTF8", isize, i32, MString>(
-            val.as_ptr() as isize,
-            val.len() as i32,
-        )
+        Self::from_str(val)
     }
 }
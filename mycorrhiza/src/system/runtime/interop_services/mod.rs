@@ -4,3 +4,32 @@ pub type Marshal = RustcCLRInteropManagedClass<
     "System.Runtime.InteropServices",
     "System.Runtime.InteropServices.Marshal",
 >;
+pub type GCHandle = crate::intrinsics::RustcCLRInteropManagedStruct<
+    "System.Runtime",
+    "System.Runtime.InteropServices.GCHandle",
+    { size_of::<usize>() },
+>;
+/// `System.Runtime.InteropServices.GCHandleType`
+pub type GCHandleType = crate::intrinsics::RustcCLRInteropManagedStruct<
+    "System.Runtime",
+    "System.Runtime.InteropServices.GCHandleType",
+    4,
+>;
+impl GCHandleType {
+    pub fn weak() -> Self {
+        Self::from_raw(0)
+    }
+    pub fn weak_track_resurrection() -> Self {
+        Self::from_raw(1)
+    }
+    pub fn normal() -> Self {
+        Self::from_raw(2)
+    }
+    pub fn pinned() -> Self {
+        Self::from_raw(3)
+    }
+    fn from_raw(value: i32) -> Self {
+        // `black_box` prevents this from being turned into a constant of a managed type.
+        unsafe { core::mem::transmute::<i32, Self>(core::intrinsics::black_box(value)) }
+    }
+}
@@ -14,3 +14,19 @@ impl StringBuilder {
         self.append_mchar(crate::DotNetChar::single_codepoint_unchecked(chr))
     }
 }
+pub type Encoding =
+    crate::intrinsics::RustcCLRInteropManagedClass<"System.Runtime", "System.Text.Encoding">;
+impl Encoding {
+    /// `Encoding.UTF8`
+    #[inline(always)]
+    pub fn utf8() -> Self {
+        Self::static0::<"get_UTF8", Self>()
+    }
+    /// Decodes `len` bytes at `bytes` into a new string.
+    /// # Safety
+    /// `bytes` must point to `len` readable bytes.
+    #[inline(always)]
+    pub unsafe fn get_string(self, bytes: *const u8, len: i32) -> crate::system::MString {
+        self.virt_n::<"GetString", _, crate::system::MString>((bytes, len))
+    }
+}
@@ -0,0 +1,2 @@
+/// Wrappers around types from the `System.Threading.Tasks` namespace, and bridging between .NET tasks and Rust futures.
+pub mod tasks;
@@ -0,0 +1,438 @@
+//! Bridging between .NET `Task`s and Rust `Future`s.
+//!
+//! A [`TaskFuture`] awaits a .NET task from Rust: it registers a continuation on the task, which wakes the Rust waker.
+//! [`future_to_task`] exposes a Rust future to .NET as a `Task`, completed through a `TaskCompletionSource`. The
+//! future is polled on the .NET thread pool, each time it gets woken.
+//!
+//! The result of a task is a [`TaskResult`]: `()` for a non-generic `Task`, or the `T` of a `Task<T>`. Mycorrhiza can't
+//! yet name generic .NET types, so generic tasks are handled through reflection, and always have the static type
+//! `Task`. C# code awaiting a `Task<T>` returned by Rust must cast it first: `await (Task<int>)Service.Fetch(id)`.
+//!
+//! Exported `async fn`s can't be awaited from C#, since their futures are Rust types. Instead, export a function
+//! returning the `Task` created by [`future_to_task`]:
+//! ```no_run
+//! # #![feature(register_tool)]
+//! # #![register_tool(dotnet)]
+//! # use mycorrhiza::system::threading::tasks::{future_to_task, Task};
+//! async fn fetch(id: u32) -> i32 {
+//!     id as i32 * 2
+//! }
+//! #[dotnet::export(class = "Service")]
+//! pub fn fetch_async(id: u32) -> Task {
+//!     future_to_task(fetch(id))
+//! }
+//! ```
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll, Wake, Waker},
+};
+
+use crate::{
+    class::Class,
+    intrinsics::{
+        rustc_clr_interop_managed_checked_cast, rustc_clr_interop_managed_new_arr,
+        RustcCLRInteropManagedArray, RustcCLRInteropManagedClass, RustcCLRInteropManagedStruct,
+    },
+    system::{
+        array::{Blittable, SystemArray},
+        delegate::Action,
+        exception::{Exception, SystemException},
+        MString,
+    },
+};
+type Object = RustcCLRInteropManagedClass<"System.Runtime", "System.Object">;
+pub type Task = RustcCLRInteropManagedClass<"System.Runtime", "System.Threading.Tasks.Task">;
+type TaskAwaiter = RustcCLRInteropManagedStruct<
+    "System.Runtime",
+    "System.Runtime.CompilerServices.TaskAwaiter",
+    { size_of::<usize>() },
+>;
+pub type SystemType = RustcCLRInteropManagedClass<"System.Runtime", "System.Type">;
+type Activator = RustcCLRInteropManagedClass<"System.Runtime", "System.Activator">;
+type PropertyInfo = RustcCLRInteropManagedClass<"System.Runtime", "System.Reflection.PropertyInfo">;
+type MethodInfo = RustcCLRInteropManagedClass<"System.Runtime", "System.Reflection.MethodInfo">;
+type MethodBase = RustcCLRInteropManagedClass<"System.Runtime", "System.Reflection.MethodBase">;
+/// Looks up a .NET type by its assembly-qualified name.
+fn get_type(name: &str) -> SystemType {
+    SystemType::static2::<"GetType", MString, bool, SystemType>(MString::from_str(name), true)
+}
+/// Creates an array of managed references, for passing to reflection APIs.
+fn managed_array<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str>(
+    elements: &[RustcCLRInteropManagedClass<ASSEMBLY, CLASS_PATH>],
+) -> RustcCLRInteropManagedArray<RustcCLRInteropManagedClass<ASSEMBLY, CLASS_PATH>, 1> {
+    let arr = rustc_clr_interop_managed_new_arr(
+        i32::try_from(elements.len()).expect("Array too long for .NET"),
+    );
+    let system_arr: SystemArray = rustc_clr_interop_managed_checked_cast(arr);
+    for (index, element) in elements.iter().enumerate() {
+        let element: Object = rustc_clr_interop_managed_checked_cast(*element);
+        system_arr.instance2::<"SetValue", Object, i32, ()>(element, index as i32);
+    }
+    arr
+}
+/// The result of a .NET task, which can be passed between Rust and .NET.
+pub trait TaskResult: Sized + 'static {
+    /// The .NET type `T`, if the tasks with this result are `Task<T>`s, or `None` if they are non-generic `Task`s.
+    fn dotnet_type() -> Option<SystemType>;
+    /// Unboxes the result of a `Task<T>`. Throws an `InvalidCastException` if it has a different type.
+    fn from_object(object: Object) -> Self;
+    /// Boxes this value, as the result of a `Task<T>`.
+    fn into_object(self) -> Object;
+}
+impl TaskResult for () {
+    fn dotnet_type() -> Option<SystemType> {
+        None
+    }
+    fn from_object(_: Object) -> Self {}
+    fn into_object(self) -> Object {
+        Object::null()
+    }
+}
+impl<const ASSEMBLY: &'static str, const CLASS_PATH: &'static str> TaskResult
+    for RustcCLRInteropManagedClass<ASSEMBLY, CLASS_PATH>
+{
+    fn dotnet_type() -> Option<SystemType> {
+        Some(get_type(&format!("{CLASS_PATH}, {ASSEMBLY}")))
+    }
+    fn from_object(object: Object) -> Self {
+        rustc_clr_interop_managed_checked_cast(object)
+    }
+    fn into_object(self) -> Object {
+        rustc_clr_interop_managed_checked_cast(self)
+    }
+}
+/// Primitive results are boxed and unboxed through a single element array, since `System.Array` converts between
+/// elements and objects.
+macro_rules! primitive_result {
+    ($($tpe:ty => $name:literal),+) => {
+        $(impl TaskResult for $tpe {
+            fn dotnet_type() -> Option<SystemType> {
+                Some(get_type(concat!($name, ", System.Runtime")))
+            }
+            fn from_object(object: Object) -> Self {
+                unbox_primitive(object)
+            }
+            fn into_object(self) -> Object {
+                box_primitive(self)
+            }
+        })+
+    };
+}
+primitive_result! {
+    u8 => "System.Byte", i8 => "System.SByte", u16 => "System.UInt16", i16 => "System.Int16",
+    u32 => "System.UInt32", i32 => "System.Int32", u64 => "System.UInt64", i64 => "System.Int64",
+    usize => "System.UIntPtr", isize => "System.IntPtr", f32 => "System.Single", f64 => "System.Double"
+}
+fn unbox_primitive<T: Blittable>(object: Object) -> T {
+    let arr = RustcCLRInteropManagedArray::<T, 1>::new(1);
+    let system_arr: SystemArray = rustc_clr_interop_managed_checked_cast(arr);
+    system_arr.instance2::<"SetValue", Object, i32, ()>(object, 0);
+    arr.to_vec()[0]
+}
+fn box_primitive<T: Blittable>(value: T) -> Object {
+    let arr = RustcCLRInteropManagedArray::<T, 1>::from_slice(&[value]);
+    let system_arr: SystemArray = rustc_clr_interop_managed_checked_cast(arr);
+    system_arr.instance1::<"GetValue", i32, Object>(0)
+}
+impl Task {
+    /// `Task.Run(Action)`
+    pub fn run(action: Action) -> Self {
+        Self::static1::<"Run", Action, Self>(action)
+    }
+    pub fn is_completed(self) -> bool {
+        self.virt0::<"get_IsCompleted", bool>()
+    }
+    pub fn is_faulted(self) -> bool {
+        self.virt0::<"get_IsFaulted", bool>()
+    }
+    pub fn is_canceled(self) -> bool {
+        self.virt0::<"get_IsCanceled", bool>()
+    }
+    /// The result of a completed task, or the exception which caused it to fail. `T` must be the result type of this
+    /// task: `()` for a `Task`, or `T` for a `Task<T>`.
+    pub fn result<T: TaskResult>(self) -> Result<T, Exception> {
+        if self.is_faulted() {
+            let aggregate: SystemException = rustc_clr_interop_managed_checked_cast(
+                self.virt0::<"get_Exception", RustcCLRInteropManagedClass<
+                    "System.Runtime",
+                    "System.AggregateException",
+                >>(),
+            );
+            return Err(Exception::from_naked_ref(
+                aggregate.virt0::<"GetBaseException", SystemException>(),
+            ));
+        }
+        if self.is_canceled() {
+            let canceled = RustcCLRInteropManagedClass::<
+                "System.Runtime",
+                "System.Threading.Tasks.TaskCanceledException",
+            >::ctor1(self);
+            return Err(Exception::from_naked_ref(
+                rustc_clr_interop_managed_checked_cast(canceled),
+            ));
+        }
+        if T::dotnet_type().is_none() {
+            // Non-generic tasks may still be `Task<VoidTaskResult>`s at runtime, whose result is meaningless.
+            return Ok(T::from_object(Object::null()));
+        }
+        let object: Object = rustc_clr_interop_managed_checked_cast(self);
+        let property = object
+            .instance0::<"GetType", SystemType>()
+            .instance1::<"GetProperty", MString, PropertyInfo>(MString::from_str("Result"));
+        assert!(
+            !property.is_null(),
+            "Awaited a non-generic task, expecting a result"
+        );
+        Ok(T::from_object(
+            property.instance1::<"GetValue", Object, Object>(object),
+        ))
+    }
+    /// Returns a future, which awaits this task. `T` must be the result type of this task: `()` for a `Task`, or `T`
+    /// for a `Task<T>`.
+    pub fn into_future<T: TaskResult>(self) -> TaskFuture<T> {
+        TaskFuture::new(self)
+    }
+}
+/// The waker of the Rust task awaiting a .NET task.
+type WakerSlot = Arc<Mutex<Option<Waker>>>;
+/// A future, which completes once a .NET task does. Its output is the result of the task, or the exception which
+/// caused it to fail.
+pub struct TaskFuture<T: TaskResult = ()> {
+    task: Class<"System.Runtime", "System.Threading.Tasks.Task">,
+    waker: Option<WakerSlot>,
+    result: core::marker::PhantomData<fn() -> T>,
+}
+impl<T: TaskResult> TaskFuture<T> {
+    pub fn new(task: Task) -> Self {
+        Self {
+            task: Class::from_naked_ref(task),
+            waker: None,
+            result: core::marker::PhantomData,
+        }
+    }
+}
+impl<T: TaskResult> Future for TaskFuture<T> {
+    type Output = Result<T, Exception>;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let task = unsafe { self.task.get_naked_ref() };
+        if task.is_completed() {
+            return Poll::Ready(task.result());
+        }
+        if let Some(slot) = &self.waker {
+            *slot.lock().unwrap() = Some(cx.waker().clone());
+            // The continuation may have taken the previous waker before the new one got stored.
+            if task.is_completed() {
+                return Poll::Ready(task.result());
+            }
+            return Poll::Pending;
+        }
+        // First poll: register a continuation, which wakes the current waker. If the task completed in the meantime,
+        // the continuation runs right away.
+        let slot: WakerSlot = Arc::new(Mutex::new(Some(cx.waker().clone())));
+        let continuation_slot = slot.clone();
+        let continuation = Action::from_fn(move || {
+            if let Some(waker) = continuation_slot.lock().unwrap().take() {
+                waker.wake();
+            }
+        });
+        task.instance0::<"GetAwaiter", TaskAwaiter>()
+            .instance1::<"OnCompleted", Action, ()>(continuation);
+        self.waker = Some(slot);
+        Poll::Pending
+    }
+}
+/// The output of a Rust future, which can complete a .NET task.
+pub trait IntoTaskResult {
+    type Result: TaskResult;
+    fn into_task_result(self) -> Result<Self::Result, Exception>;
+}
+impl<T: TaskResult> IntoTaskResult for T {
+    type Result = T;
+    fn into_task_result(self) -> Result<T, Exception> {
+        Ok(self)
+    }
+}
+impl<T: TaskResult> IntoTaskResult for Result<T, Exception> {
+    type Result = T;
+    fn into_task_result(self) -> Result<T, Exception> {
+        self
+    }
+}
+/// A `TaskCompletionSource`, or a `TaskCompletionSource<T>` for tasks with a result. Since the generic variant can't be
+/// named, both are created and completed through reflection.
+struct Completion {
+    source: Class<"System.Runtime", "System.Object">,
+}
+impl Completion {
+    fn new<T: TaskResult>() -> Self {
+        let tpe = match T::dotnet_type() {
+            None => get_type("System.Threading.Tasks.TaskCompletionSource, System.Runtime"),
+            Some(result) => get_type(
+                "System.Threading.Tasks.TaskCompletionSource`1, System.Runtime",
+            )
+            .instance1::<"MakeGenericType", RustcCLRInteropManagedArray<SystemType, 1>, SystemType>(
+                managed_array(&[result]),
+            ),
+        };
+        Self {
+            source: Class::from_naked_ref(
+                Activator::static1::<"CreateInstance", SystemType, Object>(tpe),
+            ),
+        }
+    }
+    /// The task completed by this source.
+    fn task(&self) -> Task {
+        let source = unsafe { self.source.get_naked_ref() };
+        let property = source
+            .instance0::<"GetType", SystemType>()
+            .instance1::<"GetProperty", MString, PropertyInfo>(MString::from_str("Task"));
+        rustc_clr_interop_managed_checked_cast(
+            property.instance1::<"GetValue", Object, Object>(source),
+        )
+    }
+    /// Calls the method `name` of this source, with parameters of type `params`.
+    fn invoke(&self, name: &str, params: &[SystemType], args: &[Object]) {
+        let source = unsafe { self.source.get_naked_ref() };
+        let method = source
+            .instance0::<"GetType", SystemType>()
+            .instance2::<"GetMethod", MString, RustcCLRInteropManagedArray<SystemType, 1>, MethodInfo>(
+                MString::from_str(name),
+                managed_array(params),
+            );
+        let method: MethodBase = rustc_clr_interop_managed_checked_cast(method);
+        method.virt_n::<"Invoke", (Object, RustcCLRInteropManagedArray<Object, 1>), Object>((
+            source,
+            managed_array(args),
+        ));
+    }
+    fn set_result<T: TaskResult>(&self, result: T) {
+        match T::dotnet_type() {
+            None => self.invoke("SetResult", &[], &[]),
+            Some(tpe) => self.invoke("SetResult", &[tpe], &[result.into_object()]),
+        }
+    }
+    fn set_exception(&self, exception: SystemException) {
+        self.invoke(
+            "SetException",
+            &[get_type("System.Exception, System.Runtime")],
+            &[rustc_clr_interop_managed_checked_cast(exception)],
+        );
+    }
+}
+type BoxedFuture<T> = Pin<Box<dyn Future<Output = Result<T, Exception>> + Send>>;
+/// Nothing is polling the future, and it was not woken.
+const IDLE: u8 = 0;
+/// A poll of the future is queued on the thread pool.
+const SCHEDULED: u8 = 1;
+/// The future is being polled.
+const RUNNING: u8 = 2;
+/// The future got woken while being polled, so it must be polled again.
+const NOTIFIED: u8 = 3;
+/// The future completed.
+const DONE: u8 = 4;
+/// A Rust future, driven by the .NET thread pool.
+///
+/// .NET may wake the future from many threads at once. The state ensures only one poll is queued or running at any
+/// time, so concurrent wakes neither poll the future in parallel, nor block thread pool threads on its lock. A wake
+/// during a poll makes the polling thread poll again, so no wake gets lost.
+struct FutureTask<T: TaskResult> {
+    state: AtomicU8,
+    future: Mutex<Option<BoxedFuture<T>>>,
+    completion: Completion,
+}
+impl<T: TaskResult> FutureTask<T> {
+    /// Polls the future until it is pending and not woken, completing the .NET task if the future is ready.
+    fn run(self: &Arc<Self>) {
+        self.state.store(RUNNING, Ordering::Release);
+        let waker = Waker::from(self.clone());
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            // Only one thread ever runs the future, so the lock is never contended.
+            let mut future = self.future.lock().unwrap();
+            let Some(inner) = future.as_mut() else {
+                // Already completed
+                return;
+            };
+            let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                inner.as_mut().poll(&mut cx)
+            }));
+            let res = match res {
+                Ok(Poll::Pending) => {
+                    drop(future);
+                    match self.state.compare_exchange(
+                        RUNNING,
+                        IDLE,
+                        Ordering::AcqRel,
+                        Ordering::Acquire,
+                    ) {
+                        Ok(_) => return,
+                        // Woken during the poll.
+                        Err(_) => {
+                            self.state.store(RUNNING, Ordering::Release);
+                            continue;
+                        }
+                    }
+                }
+                Ok(Poll::Ready(res)) => res,
+                Err(_) => Err(Exception::from_naked_ref(SystemException::ctor1(
+                    MString::from_str("A Rust future panicked"),
+                ))),
+            };
+            *future = None;
+            self.state.store(DONE, Ordering::Release);
+            match res {
+                Ok(result) => self.completion.set_result(result),
+                Err(exception) => self
+                    .completion
+                    .set_exception(unsafe { exception.handle().get_naked_ref() }),
+            }
+            return;
+        }
+    }
+}
+impl<T: TaskResult> Wake for FutureTask<T> {
+    fn wake(self: Arc<Self>) {
+        let mut state = self.state.load(Ordering::Acquire);
+        loop {
+            let next = match state {
+                IDLE => SCHEDULED,
+                RUNNING => NOTIFIED,
+                // Already going to be polled, or done.
+                _ => return,
+            };
+            match self
+                .state
+                .compare_exchange_weak(state, next, Ordering::AcqRel, Ordering::Acquire)
+            {
+                Ok(_) if next == SCHEDULED => break,
+                Ok(_) => return,
+                Err(actual) => state = actual,
+            }
+        }
+        Task::run(Action::from_fn(move || self.run()));
+    }
+}
+/// Exposes a Rust future to .NET, as a `Task`, or a `Task<T>` if the future has a result. The future is first polled
+/// on the .NET thread pool.
+pub fn future_to_task<F>(future: F) -> Task
+where
+    F: Future + Send + 'static,
+    F::Output: IntoTaskResult,
+{
+    let completion = Completion::new::<<F::Output as IntoTaskResult>::Result>();
+    let task = completion.task();
+    let future_task = Arc::new(FutureTask {
+        state: AtomicU8::new(IDLE),
+        future: Mutex::new(Some(Box::pin(
+            async move { future.await.into_task_result() },
+        ))),
+        completion,
+    });
+    future_task.wake();
+    task
+}
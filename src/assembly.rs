@@ -3,6 +3,7 @@ use crate::{
     basic_block::handler_for_block,
     cil::span_source_info,
     codegen_error::{CodegenError, MethodCodegenError},
+    export::DotnetExport,
     r#type::get_type,
     rustc_middle::dep_graph::DepContext,
     utilis::field_descrptor,
@@ -31,7 +32,7 @@ use rustc_middle::{
         mono::MonoItem,
         Local, LocalDecl, Statement, Terminator,
     },
-    ty::{Instance, ParamEnv, TyCtxt, TyKind},
+    ty::{Instance, InstanceKind, ParamEnv, TyCtxt, TyKind},
 };
 type LocalDefList = Vec<LocalDef>;
 type ArgsDebugInfo = Vec<Option<StringIdx>>;
@@ -527,8 +528,21 @@ pub fn add_fn<'tcx, 'asm, 'a: 'asm>(
     let adjust = check_align_adjust(&mir.local_decls, ctx.tcx(), &ctx.instance(), mir.arg_count);
 
     let main_module = ctx.main_module();
-    let method = MethodDef::from_v1(&method, ctx, main_module);
-    ctx.new_method(method);
+    let mut method = MethodDef::from_v1(&method, ctx, main_module);
+    // `#[inline(never)]` becomes `NoInlining`, so neither cilly nor the .NET JIT inline this function.
+    if let InstanceKind::Item(def_id) = ctx.instance().def {
+        if matches!(
+            ctx.tcx().codegen_fn_attrs(def_id).inline,
+            rustc_attr_ir::InlineAttr::Never
+        ) {
+            method.set_no_inlining(true);
+        }
+    }
+    let method = ctx.new_method(method);
+    // Functions marked with `#[dotnet::export]` also get a public alias with a readable name.
+    if let Some(export) = DotnetExport::of_fn(ctx.tcx(), ctx.instance()) {
+        crate::export::export_fn(&export, method, ctx);
+    }
     drop(timer);
     Ok(())
     //todo!("Can't add function")
@@ -963,6 +963,12 @@ cargo_test! {benchmarks,bench}
 // TODO: This trips up some post-link sanity checks, investigate.
 cargo_test! {glam_test,unstable}
 cargo_test! {fastrand_test,stable}
+cargo_test! {gc_handles,stable}
+cargo_test! {delegates,stable}
+cargo_test! {arrays,stable}
+cargo_test! {tasks,stable}
+cargo_test! {bindgen,stable}
+cargo_test! {mstring,stable}
 
 #[cfg(target_os = "windows")]
 const IS_DOTNET_PRESENT: &bool = &true;
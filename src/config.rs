@@ -63,7 +63,6 @@ config_flag! {REMOVE_UNSUED_LOCALS,false,"Turns on the local removal optimizatio
 config_flag! {CHECK_ALLOCATIONS,false,"Turns on allocation checks/debug info."}
 config_flag! {VERIFY_METHODS,false,"Typechecks all methods"}
 
-config_flag! {SPLIT_LOCAL_STRUCTS,false,"Turns on the struct spliting optimzation."}
 config_flag! {ALLOW_MISCOMPILATIONS,true,"Should the codegen continue working after it encoutnered a miscompilation?"}
 config_flag! {INSERT_MIR_DEBUG_COMMENTS,false,"Tells the codegen to insert comments containing the MIR statemtens after each one of them."}
 config_flag! {PRINT_LOCAL_TYPES,false,"Prints local types of all compiled MIR functions."}
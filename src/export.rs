@@ -0,0 +1,244 @@
+use cilly::v2::{
+    cilnode::MethodKind, Access, Assembly, BasicBlock, CILNode, CILRoot, ClassDef, ClassRef,
+    MethodDef, MethodDefIdx, MethodImpl, Type,
+};
+use rustc_middle::ty::{AdtDef, GenericArgsRef, Instance, InstanceKind, TyCtxt};
+use rustc_span::{def_id::DefId, Symbol};
+/// Name of the tool the export attribute belongs to. Crates using `#[dotnet::export]` must contain `#![feature(register_tool)]` and `#![register_tool(dotnet)]`.
+pub const EXPORT_TOOL: &str = "dotnet";
+/// Name of the export attribute.
+pub const EXPORT_ATTR: &str = "export";
+/// The arguments of a `#[dotnet::export(namespace = "Acme.Geo", class = "Mesh", name = "Simplify")]` attribute.
+///
+/// `namespace` defaults to the name of the crate. `class` is required for functions, and ignored for types. `name`
+/// defaults to the Rust name of the item, converted to `UpperCamelCase` for functions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DotnetExport {
+    namespace: String,
+    class: Option<String>,
+    name: String,
+}
+impl DotnetExport {
+    /// Reads the export attribute of the item `def_id`, if present. Malformed arguments are reported as errors, and ignored.
+    pub fn of_item(tcx: TyCtxt, def_id: DefId) -> Option<Self> {
+        let path = [Symbol::intern(EXPORT_TOOL), Symbol::intern(EXPORT_ATTR)];
+        let attr = tcx.get_attrs_by_path(def_id, &path).next()?;
+        let mut namespace = None;
+        let mut class = None;
+        let mut name = None;
+        for arg in attr.meta_item_list().unwrap_or_default() {
+            let key = arg.ident().map(|ident| ident.name.to_string());
+            let Some(value) = arg.value_str() else {
+                tcx.dcx().span_err(
+                    arg.span(),
+                    "the arguments of `#[dotnet::export]` must have the form `key = \"value\"`",
+                );
+                continue;
+            };
+            let value = value.to_string();
+            match key.as_deref() {
+                Some("namespace") => namespace = Some(value),
+                Some("class") => class = Some(value),
+                Some("name") => name = Some(value),
+                _ => {
+                    tcx.dcx().span_err(
+                        arg.span(),
+                        "unknown `#[dotnet::export]` argument, expected `namespace`, `class` or `name`",
+                    );
+                }
+            }
+        }
+        let namespace = namespace.unwrap_or_else(|| tcx.crate_name(def_id.krate).to_string());
+        let name = name.unwrap_or_else(|| tcx.item_name(def_id).to_string());
+        Some(Self {
+            namespace,
+            class,
+            name,
+        })
+    }
+    /// Reads the export attribute of the function `instance`, if present. Reports an error, and returns `None`, if the
+    /// function can't be exported.
+    pub fn of_fn<'tcx>(tcx: TyCtxt<'tcx>, instance: Instance<'tcx>) -> Option<Self> {
+        let InstanceKind::Item(def_id) = instance.def else {
+            return None;
+        };
+        let export = Self::of_item(tcx, def_id)?;
+        if !instance.args.is_empty() {
+            tcx.dcx().span_err(
+                tcx.def_span(def_id),
+                "generic functions can't be exported to .NET",
+            );
+            return None;
+        }
+        if tcx.asyncness(def_id).is_async() {
+            tcx.dcx().span_err(
+                tcx.def_span(def_id),
+                "exported `async fn`s can't be awaited from .NET: export a function returning the `Task` created by `mycorrhiza::system::threading::tasks::future_to_task` instead",
+            );
+            return None;
+        }
+        if export.class.is_none() {
+            tcx.dcx().span_err(
+                tcx.def_span(def_id),
+                "`#[dotnet::export]` of a function requires a `class` argument",
+            );
+            return None;
+        }
+        Some(export)
+    }
+    /// Reads the export attribute of the type `adt`, if present. Reports an error, and returns `None`, if the type
+    /// can't be exported.
+    pub fn of_adt<'tcx>(
+        tcx: TyCtxt<'tcx>,
+        adt: AdtDef<'tcx>,
+        gargs: GenericArgsRef<'tcx>,
+    ) -> Option<Self> {
+        let export = Self::of_item(tcx, adt.did())?;
+        if !(adt.is_struct() && adt.repr().c() && gargs.is_empty()) {
+            tcx.dcx().span_err(
+                tcx.def_span(adt.did()),
+                "only non-generic `#[repr(C)]` structs can be exported to .NET",
+            );
+            return None;
+        }
+        Some(export)
+    }
+    /// The full .NET name of an exported type.
+    #[must_use]
+    pub fn type_name(&self) -> String {
+        format!("{}.{}", self.namespace, self.name)
+    }
+    /// The full .NET name of the class containing an exported function.
+    #[must_use]
+    pub fn class_name(&self) -> String {
+        // Checked by `Self::of_fn`.
+        let class = self.class.as_ref().expect("exported function has no class");
+        format!("{}.{class}", self.namespace)
+    }
+    /// The name of an exported function, in `UpperCamelCase`.
+    #[must_use]
+    pub fn method_name(&self) -> String {
+        to_upper_camel_case(&self.name)
+    }
+}
+/// Converts a `snake_case` Rust name to an `UpperCamelCase` .NET one.
+fn to_upper_camel_case(name: &str) -> String {
+    name.split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+/// Exports the Rust function `method`, by adding a public static method forwarding to it, to the class described by
+/// `export`. The forwarding method has the same signature and argument names as `method`.
+pub fn export_fn(export: &DotnetExport, method: MethodDefIdx, asm: &mut Assembly) -> MethodDefIdx {
+    let class_name = asm.alloc_string(export.class_name());
+    let cref = asm.alloc_class_ref(ClassRef::new(class_name, None, false, [].into()));
+    let class = match asm.class_ref_to_def(cref) {
+        Some(class) => class,
+        None => asm.class_def(ClassDef::new(
+            class_name,
+            false,
+            0,
+            None,
+            vec![],
+            vec![],
+            // Nothing in the assembly calls the exported methods, so both they and their class must be extern.
+            Access::Extern,
+            None,
+            None,
+        )),
+    };
+    let target = asm.method_def(method);
+    let sig = target.sig();
+    let arg_names = target.arg_names().to_vec();
+    let output = *asm[sig].output();
+    let args: Box<[_]> = (0..u32::try_from(arg_names.len()).unwrap())
+        .map(|arg| asm.alloc_node(CILNode::LdArg(arg)))
+        .collect();
+    let ret = if output == Type::Void {
+        let call = asm.alloc_root(CILRoot::Call(Box::new((*method, args))));
+        vec![call, asm.alloc_root(CILRoot::VoidRet)]
+    } else {
+        let call = asm.alloc_node(CILNode::Call(Box::new((*method, args))));
+        vec![asm.alloc_root(CILRoot::Ret(call))]
+    };
+    let name = asm.alloc_string(export.method_name());
+    asm.new_method(MethodDef::new(
+        Access::Extern,
+        class,
+        name,
+        sig,
+        MethodKind::Static,
+        MethodImpl::MethodBody {
+            blocks: vec![BasicBlock::new(ret, 0, None)],
+            locals: vec![],
+        },
+        arg_names,
+    ))
+}
+#[test]
+fn upper_camel_case() {
+    assert_eq!(to_upper_camel_case("simplify"), "Simplify");
+    assert_eq!(to_upper_camel_case("simplify_mesh"), "SimplifyMesh");
+    assert_eq!(to_upper_camel_case("_private__name_"), "PrivateName");
+    assert_eq!(to_upper_camel_case("AlreadyCamel"), "AlreadyCamel");
+}
+#[test]
+fn exported_names() {
+    use cilly::v2::{Float, Int};
+    let mut asm = Assembly::default();
+    let main_module = asm.main_module();
+    let sig = asm.sig(
+        [Type::Int(Int::U32), Type::Float(Float::F32)],
+        Type::Int(Int::U32),
+    );
+    let arg = asm.alloc_node(CILNode::LdArg(0));
+    let ret = asm.alloc_root(CILRoot::Ret(arg));
+    let name = asm.alloc_string("_ZN4mesh8simplify17h0123456789abcdefE");
+    let arg_names = vec![
+        Some(asm.alloc_string("vertex_count")),
+        Some(asm.alloc_string("tolerance")),
+    ];
+    let target = asm.new_method(MethodDef::new(
+        Access::Private,
+        main_module,
+        name,
+        sig,
+        MethodKind::Static,
+        MethodImpl::MethodBody {
+            blocks: vec![BasicBlock::new(vec![ret], 0, None)],
+            locals: vec![],
+        },
+        arg_names.clone(),
+    ));
+    let export = DotnetExport {
+        namespace: "Acme.Geo".into(),
+        class: Some("Mesh".into()),
+        name: "simplify_mesh".into(),
+    };
+    let exported = export_fn(&export, target, &mut asm);
+    let exported = asm.method_def(exported).clone();
+    assert_eq!(&asm[exported.name()], "SimplifyMesh");
+    assert_eq!(exported.sig(), sig);
+    assert_eq!(exported.arg_names(), &arg_names[..]);
+    assert_eq!(exported.access(), &Access::Extern);
+    let class = &asm[exported.class()];
+    assert_eq!(&asm[class.name()], "Acme.Geo.Mesh");
+    assert_eq!(class.access(), &Access::Extern);
+    // Exporting a second function reuses the class.
+    let second = export_fn(&export, target, &mut asm);
+    assert_eq!(asm.method_def(second).class(), exported.class());
+    // Exported structs keep their namespace, and their Rust name.
+    let point = DotnetExport {
+        namespace: "Acme.Geo".into(),
+        class: None,
+        name: "Point".into(),
+    };
+    assert_eq!(point.type_name(), "Acme.Geo.Point");
+}
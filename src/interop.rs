@@ -1,5 +1,10 @@
-use crate::{utilis::garg_to_string, IString};
-use rustc_middle::ty::{GenericArg, TyCtxt};
+use crate::{
+    r#type::{INTEROP_ARR_TPE_NAME, INTEROP_CLASS_TPE_NAME},
+    utilis::garg_to_string,
+    IString,
+};
+use rustc_middle::ty::{AdtDef, GenericArg, List, Ty, TyCtxt, TyKind};
+use rustc_span::Span;
 pub struct AssemblyRef {
     name: IString,
 }
@@ -17,3 +22,165 @@ impl AssemblyRef {
         }
     }
 }
+/// Checks if `ty` is a naked reference to a managed object or array(as opposed to a GC handle, or a managed value type).
+#[must_use]
+pub fn is_naked_managed_ref<'tcx>(ty: Ty<'tcx>, tcx: TyCtxt<'tcx>) -> bool {
+    match ty.kind() {
+        TyKind::Adt(def, _) => {
+            let name = tcx.item_name(def.did());
+            name.as_str() == INTEROP_CLASS_TPE_NAME || name.as_str() == INTEROP_ARR_TPE_NAME
+        }
+        _ => false,
+    }
+}
+/// Checks if `ty` holds a naked managed reference by value: if it is one, or an enum, tuple, array or transparent
+/// wrapper containing one. Such values are usually temporaries(like `Option<Object>`, or a tuple of call arguments),
+/// which live on the stack.
+fn holds_naked_managed_ref<'tcx>(ty: Ty<'tcx>, tcx: TyCtxt<'tcx>) -> bool {
+    match ty.kind() {
+        _ if is_naked_managed_ref(ty, tcx) => true,
+        TyKind::Tuple(elems) => elems.iter().any(|elem| holds_naked_managed_ref(elem, tcx)),
+        TyKind::Slice(elem) | TyKind::Array(elem, _) => holds_naked_managed_ref(*elem, tcx),
+        TyKind::Adt(def, subst) if def.is_enum() || def.repr().transparent() => {
+            def.all_fields().any(|field| {
+                let field_ty = tcx.normalize_erasing_regions(
+                    rustc_middle::ty::TypingEnv::fully_monomorphized(),
+                    field.ty(tcx, subst),
+                );
+                holds_naked_managed_ref(field_ty, tcx)
+            })
+        }
+        _ => false,
+    }
+}
+/// Checks if a raw pointer of type `ty` points to naked managed references. Heap allocations(`Box`, `Vec`, `Rc`, ...)
+/// are reached through raw pointers, so this is what storing managed references on the heap looks like.
+fn points_to_naked_managed_ref<'tcx>(ty: Ty<'tcx>, tcx: TyCtxt<'tcx>) -> bool {
+    let TyKind::RawPtr(pointee, _) = ty.kind() else {
+        return false;
+    };
+    holds_naked_managed_ref(*pointee, tcx)
+}
+/// Warns about `field`, a field of `owner`, if it is of type `field_ty`, and stores a naked managed reference. If
+/// `by_value_allowed` is set, only pointers to naked managed references are reported.
+///
+/// The GC only knows about managed references stored on the stack, or in managed objects. A copy stored in a Rust
+/// aggregate may be moved to memory the GC does not scan, and a copy stored on the heap always is. Once the GC
+/// collects or moves the object, such a reference dangles. GC handles(like `mycorrhiza::class::Class`) must be used
+/// instead.
+fn check_field<'tcx>(
+    owner: Ty<'tcx>,
+    field: &str,
+    field_ty: Ty<'tcx>,
+    span: Option<Span>,
+    by_value_allowed: bool,
+    tcx: TyCtxt<'tcx>,
+) {
+    let storage = if holds_naked_managed_ref(field_ty, tcx) {
+        if by_value_allowed {
+            return;
+        }
+        "a Rust aggregate"
+    } else if points_to_naked_managed_ref(field_ty, tcx) {
+        "memory not tracked by the .NET GC"
+    } else {
+        return;
+    };
+    let msg = format!(
+        "`{owner}` stores a naked managed reference(`{field_ty}`) in {storage}, in {field}. \
+        The GC can't track it, so it may dangle once the object is collected or moved. \
+        Use a GC handle(`mycorrhiza::class::Class`, `Weak` or `Pinned`) instead."
+    );
+    match span {
+        Some(span) => tcx.dcx().span_warn(span, msg),
+        None => tcx.dcx().warn(msg),
+    }
+}
+/// Diagnoses naked managed references stored in the fields of the ADT `adt_ty`. Enums and transparent wrappers(like
+/// `Option`, `Result` or `ManuallyDrop`) are mostly used by value, so they may contain naked references, but not
+/// pointers to them.
+pub fn check_adt_fields<'tcx>(
+    adt_ty: Ty<'tcx>,
+    def: AdtDef<'tcx>,
+    subst: &'tcx List<GenericArg<'tcx>>,
+    tcx: TyCtxt<'tcx>,
+) {
+    let by_value_allowed = def.is_enum() || def.repr().transparent();
+    for field in def.all_fields() {
+        let field_ty = tcx.normalize_erasing_regions(
+            rustc_middle::ty::TypingEnv::fully_monomorphized(),
+            field.ty(tcx, subst),
+        );
+        check_field(
+            adt_ty,
+            &format!("the field `{}`", field.name),
+            field_ty,
+            Some(tcx.def_span(field.did)),
+            by_value_allowed,
+            tcx,
+        );
+    }
+}
+/// Diagnoses pointers to naked managed references stored in the tuple `tuple_ty`. Like enums, tuples are mostly
+/// temporaries(e.g. the arguments of a managed call), so they may contain naked references by value.
+pub fn check_tuple_fields<'tcx>(tuple_ty: Ty<'tcx>, tcx: TyCtxt<'tcx>) {
+    let TyKind::Tuple(elems) = tuple_ty.kind() else {
+        return;
+    };
+    for (index, elem) in elems.iter().enumerate() {
+        check_field(
+            tuple_ty,
+            &format!("the field `{index}`"),
+            elem,
+            None,
+            true,
+            tcx,
+        );
+    }
+}
+/// Diagnoses naked managed references captured by value by the closure or coroutine `closure_ty`. Captures by
+/// reference point to a local, which the GC does track.
+pub fn check_captures<'tcx>(
+    closure_ty: Ty<'tcx>,
+    upvars: impl Iterator<Item = Ty<'tcx>>,
+    span: Span,
+    tcx: TyCtxt<'tcx>,
+) {
+    for (index, upvar) in upvars.enumerate() {
+        check_field(
+            closure_ty,
+            &format!("the capture `{index}`"),
+            upvar,
+            Some(span),
+            false,
+            tcx,
+        );
+    }
+}
+/// Diagnoses naked managed references held across a suspension point of the coroutine `coroutine_ty`. They are
+/// saved in the coroutine itself, which is often moved to the heap(e.g. when it is boxed or spawned).
+pub fn check_saved_locals<'tcx>(
+    coroutine_ty: Ty<'tcx>,
+    def_id: rustc_span::def_id::DefId,
+    args: rustc_middle::ty::CoroutineArgs<TyCtxt<'tcx>>,
+    tcx: TyCtxt<'tcx>,
+) {
+    use rustc_middle::ty::CoroutineArgsExt;
+    let mut saved: Vec<Ty<'tcx>> = Vec::new();
+    for local in args.state_tys(def_id, tcx).flatten() {
+        // The same local may be saved in multiple states.
+        if !saved.contains(&local) {
+            saved.push(local);
+        }
+    }
+    for local in saved {
+        check_field(
+            coroutine_ty,
+            "a local held across an `await`",
+            local,
+            Some(tcx.def_span(def_id)),
+            false,
+            tcx,
+        );
+    }
+}
@@ -80,6 +80,7 @@
 
 // References to internal rustc crates.
 extern crate rustc_abi;
+extern crate rustc_attr_ir;
 
 extern crate rustc_codegen_ssa;
 extern crate rustc_const_eval;
@@ -128,6 +129,8 @@ mod constant;
 
 /// Implementation of compiletime features neccessary for interop.
 mod comptime;
+/// Exporting Rust functions and types as a named, public .NET API.
+pub mod export;
 /// Signature of a function (inputs)->output
 pub mod function_sig;
 /// Interop type handling.
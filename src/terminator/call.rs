@@ -4,17 +4,25 @@ use crate::{
     interop::AssemblyRef,
     operand::operand_address,
     utilis::{
-        garg_to_string, CTOR_FN_NAME, MANAGED_CALL_FN_NAME, MANAGED_CALL_VIRT_FN_NAME,
-        MANAGED_CHECKED_CAST, MANAGED_IS_INST, MANAGED_LD_ELEM_REF, MANAGED_LD_LEN,
-        MANAGED_LD_NULL,
+        garg_to_string, CTOR_FN_NAME, CTOR_TUPLE_FN_NAME, MANAGED_ARR_PIN,
+        MANAGED_CALL_ACCESSOR_FN_NAME, MANAGED_CALL_FN_NAME, MANAGED_CALL_TUPLE_FN_NAME,
+        MANAGED_CALL_VIRT_FN_NAME, MANAGED_CALL_VIRT_TUPLE_FN_NAME, MANAGED_CHECKED_CAST,
+        MANAGED_DELEGATE_FROM_FN, MANAGED_IS_INST, MANAGED_LD_ELEM_REF, MANAGED_LD_FLD,
+        MANAGED_LD_LEN, MANAGED_LD_NULL, MANAGED_LD_STATIC_FLD, MANAGED_NEW_ARR,
+        MANAGED_NEW_MD_ARR, MANAGED_ST_FLD, MANAGED_ST_STATIC_FLD, MANAGED_TRY_CALL_TUPLE_FN_NAME,
+        MANAGED_TRY_CALL_VIRT_TUPLE_FN_NAME,
     },
+    IString,
 };
 use cilly::{
     call, call_virt,
     cil_node::{CILNode, CallOpArgs},
     cil_root::CILRoot,
-    conv_usize, ld_field,
-    v2::{cilnode::MethodKind, ClassRef, FieldDesc, FnSig, Int},
+    conv_isize, conv_usize, ld_field,
+    v2::{
+        cilnode::MethodKind, Access, BasicBlock, ClassRef, ClassRefIdx, Const, FieldDesc, FnSig,
+        Int, MethodDef, MethodImpl, MethodRefIdx, StaticFieldDesc,
+    },
     IntoAsmIndex,
 };
 use cilly::{v2::MethodRef, Type};
@@ -24,6 +32,7 @@ use rustc_middle::{
     ty::{GenericArg, Instance, ParamEnv, Ty, TyKind},
 };
 use rustc_span::source_map::Spanned;
+use std::num::NonZeroU8;
 fn argc_from_fn_name(function_name: &str, prefix: &str) -> u32 {
     let argc_start = function_name.find(prefix).unwrap() + (prefix.len());
     let argc_end = argc_start + function_name[argc_start..].find('_').unwrap();
@@ -253,6 +262,571 @@ fn call_ctor<'tcx>(
         )
     }
 }
+/// Returns the class a managed intrinsic operates on, decoded from its first 3 generic arguments(assembly, class path, is valuetype).
+fn managed_class<'tcx>(
+    subst_ref: &[GenericArg<'tcx>],
+    ctx: &mut MethodCompileCtx<'tcx, '_>,
+) -> ClassRefIdx {
+    let asm = AssemblyRef::decode_assembly_ref(subst_ref[0], ctx.tcx());
+    let asm = asm.name().map(|name| ctx.alloc_string(name));
+    let class_name = garg_to_string(subst_ref[1], ctx.tcx());
+    let class_name = ctx.alloc_string(class_name);
+    let is_valuetype = crate::utilis::garag_to_bool(subst_ref[2], ctx.tcx());
+    ctx.alloc_class_ref(ClassRef::new(class_name, asm, is_valuetype, [].into()))
+}
+/// Unpacks a tuple of managed call arguments, returning the types and values of its elements. Zero-sized elements are skipped.
+fn tuple_args<'tcx>(
+    tuple: &Operand<'tcx>,
+    ctx: &mut MethodCompileCtx<'tcx, '_>,
+) -> (Vec<Type>, Vec<CILNode>) {
+    let tuple_ty = ctx.monomorphize(tuple.ty(ctx.body(), ctx.tcx()));
+    let TyKind::Tuple(elements) = tuple_ty.kind() else {
+        panic!("Managed call arguments must be passed in a tuple, but got {tuple_ty:?}")
+    };
+    let tuple_type = ctx.type_from_cache(tuple_ty);
+    let mut types = Vec::new();
+    let mut values = Vec::new();
+    for (index, element) in elements.iter().enumerate() {
+        let element_type = ctx.type_from_cache(element);
+        if element_type == Type::Void {
+            continue;
+        }
+        let field_descriptor = FieldDesc::new(
+            tuple_type.as_class_ref().expect("Invalid tuple type"),
+            ctx.alloc_string(format!("Item{}", index + 1)),
+            element_type,
+        );
+        values.push(ld_field!(
+            crate::operand::handle_operand(tuple, ctx),
+            ctx.alloc_field(field_descriptor)
+        ));
+        types.push(element_type);
+    }
+    (types, values)
+}
+/// Calls a managed function with any number of arguments, passed in a tuple(used for interop)
+fn call_managed_tuple<'tcx>(
+    subst_ref: &[GenericArg<'tcx>],
+    args: &[Spanned<Operand<'tcx>>],
+    destination: &Place<'tcx>,
+    is_virtual: bool,
+    ctx: &mut MethodCompileCtx<'tcx, '_>,
+) -> CILRoot {
+    assert_eq!(
+        args.len(),
+        1,
+        "N-ary managed calls take all their arguments in a single tuple"
+    );
+    let class = managed_class(subst_ref, ctx);
+    let managed_fn_name = garg_to_string(ctx.monomorphize(subst_ref[3]), ctx.tcx());
+    let is_static = crate::utilis::garag_to_bool(subst_ref[4], ctx.tcx());
+    let ret = ctx.type_from_cache(
+        ctx.monomorphize(subst_ref[5])
+            .as_type()
+            .expect("Expceted generic type but got something that was not a type!"),
+    );
+    emit_managed_tuple_call(
+        class,
+        managed_fn_name,
+        is_static,
+        ret,
+        &args[0].node,
+        destination,
+        is_virtual,
+        ctx,
+    )
+}
+/// Calls the accessor of a property or event(e.g. `get_Length` or `add_Exited`) with any number of arguments, passed
+/// in a tuple(used for interop). Instance accessors of classes are called virtually. Value types pass a pointer to
+/// themselves as the first argument instead, and their accessors are called directly, since they can't be overridden.
+fn call_managed_accessor<'tcx>(
+    subst_ref: &[GenericArg<'tcx>],
+    args: &[Spanned<Operand<'tcx>>],
+    destination: &Place<'tcx>,
+    ctx: &mut MethodCompileCtx<'tcx, '_>,
+) -> CILRoot {
+    assert_eq!(
+        args.len(),
+        1,
+        "Managed accessor calls take all their arguments in a single tuple"
+    );
+    let class = managed_class(subst_ref, ctx);
+    let is_valuetype = crate::utilis::garag_to_bool(subst_ref[2], ctx.tcx());
+    let accessor = garg_to_string(ctx.monomorphize(subst_ref[3]), ctx.tcx());
+    let member = garg_to_string(ctx.monomorphize(subst_ref[4]), ctx.tcx());
+    let is_static = crate::utilis::garag_to_bool(subst_ref[5], ctx.tcx());
+    let ret = ctx.type_from_cache(
+        ctx.monomorphize(subst_ref[6])
+            .as_type()
+            .expect("Expceted generic type but got something that was not a type!"),
+    );
+    emit_managed_tuple_call(
+        class,
+        format!("{accessor}_{member}").into(),
+        is_static,
+        ret,
+        &args[0].node,
+        destination,
+        !is_static && !is_valuetype,
+        ctx,
+    )
+}
+/// Emits a call to the managed method `managed_fn_name` of `class`, passing it the elements of the tuple `tuple`.
+#[allow(clippy::too_many_arguments)]
+fn emit_managed_tuple_call<'tcx>(
+    class: ClassRefIdx,
+    managed_fn_name: IString,
+    is_static: bool,
+    ret: Type,
+    tuple: &Operand<'tcx>,
+    destination: &Place<'tcx>,
+    is_virtual: bool,
+    ctx: &mut MethodCompileCtx<'tcx, '_>,
+) -> CILRoot {
+    let (inputs, call_args) = tuple_args(tuple, ctx);
+    let call = MethodRef::new(
+        class,
+        ctx.alloc_string(managed_fn_name),
+        ctx.sig(inputs, ret),
+        if is_static {
+            MethodKind::Static
+        } else {
+            MethodKind::Instance
+        },
+        vec![].into(),
+    );
+    let site = ctx.alloc_methodref(call);
+    match (ret == Type::Void, is_virtual) {
+        (true, false) => CILRoot::Call {
+            site,
+            args: call_args.into(),
+        },
+        (true, true) => CILRoot::CallVirt {
+            site,
+            args: call_args.into(),
+        },
+        (false, false) => crate::place::place_set(destination, call!(site, call_args), ctx),
+        (false, true) => crate::place::place_set(destination, call_virt!(site, call_args), ctx),
+    }
+}
+/// Loads or stores a field of an external class(used for interop). The field is given by the generic arguments
+/// `ASSEMBLY`, `CLASS_PATH`, `IS_VALUETYPE`, `FIELD` and the field type `T`. Instance field accessors take the object
+/// (or, for value types, a pointer to it) as their first argument, and stores take the new value as their last one.
+fn managed_field_access<'tcx>(
+    subst_ref: &[GenericArg<'tcx>],
+    args: &[Spanned<Operand<'tcx>>],
+    destination: &Place<'tcx>,
+    is_static: bool,
+    is_store: bool,
+    ctx: &mut MethodCompileCtx<'tcx, '_>,
+) -> CILRoot {
+    let owner = managed_class(subst_ref, ctx);
+    let field_name = garg_to_string(ctx.monomorphize(subst_ref[3]), ctx.tcx());
+    let field_name = ctx.alloc_string(field_name);
+    let tpe = ctx.type_from_cache(
+        ctx.monomorphize(subst_ref[4])
+            .as_type()
+            .expect("Expceted generic type but got something that was not a type!"),
+    );
+    assert_ne!(tpe, Type::Void, "Managed fields may not be zero-sized");
+    assert_eq!(
+        args.len(),
+        usize::from(!is_static) + usize::from(is_store),
+        "Wrong number of arguments passed to a managed field accessor"
+    );
+    let mut args = args
+        .iter()
+        .map(|arg| crate::operand::handle_operand(&arg.node, ctx));
+    if is_static {
+        let descr = Box::new(StaticFieldDesc::new(owner, field_name, tpe));
+        if is_store {
+            CILRoot::SetStaticField {
+                descr,
+                value: args.next().unwrap(),
+            }
+        } else {
+            crate::place::place_set(destination, CILNode::LDStaticField(descr), ctx)
+        }
+    } else {
+        let field = ctx.alloc_field(FieldDesc::new(owner, field_name, tpe));
+        let addr = args.next().unwrap();
+        if is_store {
+            CILRoot::SetField {
+                addr: Box::new(addr),
+                value: Box::new(args.next().unwrap()),
+                desc: field,
+            }
+        } else {
+            crate::place::place_set(destination, ld_field!(addr, field), ctx)
+        }
+    }
+}
+/// Calls a managed function with any number of arguments, passed in a tuple, catching any exception it throws(used for interop).
+/// The result is written to the pointer passed as the second argument, and the exception(or null) is returned.
+fn try_call_managed_tuple<'tcx>(
+    subst_ref: &[GenericArg<'tcx>],
+    args: &[Spanned<Operand<'tcx>>],
+    destination: &Place<'tcx>,
+    is_virtual: bool,
+    ctx: &mut MethodCompileCtx<'tcx, '_>,
+) -> CILRoot {
+    assert_eq!(
+        args.len(),
+        2,
+        "Managed try calls take their arguments in a tuple, and a pointer to their result"
+    );
+    let class = managed_class(subst_ref, ctx);
+    let class_name = garg_to_string(subst_ref[1], ctx.tcx());
+    let managed_fn_name = garg_to_string(ctx.monomorphize(subst_ref[3]), ctx.tcx());
+    let is_static = crate::utilis::garag_to_bool(subst_ref[4], ctx.tcx());
+    let ret = ctx.type_from_cache(
+        ctx.monomorphize(subst_ref[5])
+            .as_type()
+            .expect("Expceted generic type but got something that was not a type!"),
+    );
+    let (inputs, call_args) = tuple_args(&args[0].node, ctx);
+    let kind = match (is_static, is_virtual) {
+        (true, _) => MethodKind::Static,
+        (false, false) => MethodKind::Instance,
+        (false, true) => MethodKind::Virtual,
+    };
+    let target = MethodRef::new(
+        class,
+        ctx.alloc_string(managed_fn_name.clone()),
+        ctx.sig(inputs.clone(), ret),
+        kind,
+        vec![].into(),
+    );
+    let target = ctx.alloc_methodref(target);
+    // Classes with the same name may come from different assemblies, so the wrapper name includes the assembly, like `[asm]class` in IL.
+    let full_class_name = match AssemblyRef::decode_assembly_ref(subst_ref[0], ctx.tcx()).name() {
+        Some(asm) => format!("[{asm}]{class_name}"),
+        None => class_name.into(),
+    };
+    let wrapper_name = format!(
+        "try_{}_{managed_fn_name}{}",
+        cilly::utilis::escape_class_name(&full_class_name),
+        if is_virtual { "_virt" } else { "" }
+    );
+    let wrapper = try_call_wrapper(wrapper_name, target, &inputs, ret, ctx);
+    let mut wrapper_args = vec![crate::operand::handle_operand(&args[1].node, ctx)];
+    wrapper_args.extend(call_args);
+    crate::place::place_set(destination, call!(wrapper, wrapper_args), ctx)
+}
+/// Defines a static method, which calls `target` inside a protected region. The method writes the result of the call to
+/// its first argument, and returns the exception thrown by `target`, or null if it returned normally.
+fn try_call_wrapper(
+    name: String,
+    target: MethodRefIdx,
+    inputs: &[Type],
+    ret: Type,
+    ctx: &mut MethodCompileCtx<'_, '_>,
+) -> MethodRefIdx {
+    let exception = ClassRef::exception(ctx);
+    let ret_ptr = ctx.nptr(ret);
+    let sig = ctx.sig(
+        [ret_ptr]
+            .into_iter()
+            .chain(inputs.iter().copied())
+            .collect::<Vec<_>>(),
+        Type::ClassRef(exception),
+    );
+    let args: Box<[_]> = (1..=inputs.len() as u32)
+        .map(|arg| ctx.alloc_node(cilly::v2::CILNode::LdArg(arg)))
+        .collect();
+    let call = if ret == Type::Void {
+        ctx.alloc_root(cilly::v2::CILRoot::Call(Box::new((target, args))))
+    } else {
+        let value = ctx.alloc_node(cilly::v2::CILNode::Call(Box::new((target, args))));
+        let ret_ptr = ctx.alloc_node(cilly::v2::CILNode::LdArg(0));
+        ctx.alloc_root(cilly::v2::CILRoot::StInd(Box::new((
+            ret_ptr, value, ret, false,
+        ))))
+    };
+    let exit_try_success = ctx.alloc_root(cilly::v2::CILRoot::ExitSpecialRegion {
+        target: 2,
+        source: 0,
+    });
+    let get_exception = ctx.alloc_node(cilly::v2::CILNode::GetException);
+    let set_exception = ctx.alloc_root(cilly::v2::CILRoot::StLoc(0, get_exception));
+    let exit_try_faliure = ctx.alloc_root(cilly::v2::CILRoot::ExitSpecialRegion {
+        target: 3,
+        source: 0,
+    });
+    let null = ctx.alloc_node(Const::Null(exception));
+    let ret_null = ctx.alloc_root(cilly::v2::CILRoot::Ret(null));
+    let ld_exception = ctx.alloc_node(cilly::v2::CILNode::LdLoc(0));
+    let ret_exception = ctx.alloc_root(cilly::v2::CILRoot::Ret(ld_exception));
+    let exception_local = (
+        Some(ctx.alloc_string("exception")),
+        ctx.alloc_type(Type::ClassRef(exception)),
+    );
+    let name = ctx.alloc_string(name);
+    let def = MethodDef::new(
+        Access::Public,
+        ctx.main_module(),
+        name,
+        sig,
+        MethodKind::Static,
+        MethodImpl::MethodBody {
+            blocks: vec![
+                BasicBlock::new(
+                    vec![call, exit_try_success],
+                    0,
+                    Some(vec![BasicBlock::new(
+                        vec![set_exception, exit_try_faliure],
+                        1,
+                        None,
+                    )]),
+                ),
+                BasicBlock::new(vec![ret_null], 2, None),
+                BasicBlock::new(vec![ret_exception], 3, None),
+            ],
+            locals: vec![exception_local],
+        },
+        vec![None; inputs.len() + 1],
+    );
+    *ctx.new_method(def)
+}
+/// Creates a delegate which calls a Rust closure(used for interop). The closure is passed as a pointer to its boxed
+/// environment, a function which calls it, and a function which drops it.
+fn delegate_from_fn<'tcx>(
+    subst_ref: &[GenericArg<'tcx>],
+    args: &[Spanned<Operand<'tcx>>],
+    destination: &Place<'tcx>,
+    ctx: &mut MethodCompileCtx<'tcx, '_>,
+) -> CILRoot {
+    assert_eq!(
+        args.len(),
+        3,
+        "Delegates are created from an invoke function, an environment pointer and a drop function"
+    );
+    let asm = AssemblyRef::decode_assembly_ref(subst_ref[0], ctx.tcx());
+    let asm = asm.name().map(|name| ctx.alloc_string(name));
+    let class_name = garg_to_string(subst_ref[1], ctx.tcx());
+    let class_name = ctx.alloc_string(class_name);
+    let delegate = ctx.alloc_class_ref(ClassRef::new(class_name, asm, false, [].into()));
+    let ret = ctx.type_from_cache(
+        ctx.monomorphize(subst_ref[2])
+            .as_type()
+            .expect("Expceted generic type but got something that was not a type!"),
+    );
+    let arg_tuple = ctx.monomorphize(
+        subst_ref[3]
+            .as_type()
+            .expect("Expceted generic type but got something that was not a type!"),
+    );
+    let TyKind::Tuple(elements) = arg_tuple.kind() else {
+        panic!("Delegate arguments must be described by a tuple, but got {arg_tuple:?}")
+    };
+    let inputs: Vec<_> = elements
+        .iter()
+        .map(|element| ctx.type_from_cache(element))
+        .filter(|tpe| *tpe != Type::Void)
+        .collect();
+    let holder = cilly::v2::builtins::delegate::closure_holder(ctx, &inputs, ret);
+    let invoke = cilly::v2::builtins::delegate::closure_holder_invoke(ctx, holder, &inputs, ret);
+    let void_ptr = ctx.nptr(Type::Void);
+    let invoke_sig = ctx.sig(
+        [void_ptr]
+            .into_iter()
+            .chain(inputs.iter().copied())
+            .collect::<Vec<_>>(),
+        ret,
+    );
+    let drop_sig = ctx.sig([void_ptr], Type::Void);
+    let holder_ctor = ctx.class_ref(holder).clone().ctor(
+        &[Type::FnPtr(invoke_sig), void_ptr, Type::FnPtr(drop_sig)],
+        ctx,
+    );
+    let holder_obj = CILNode::NewObj(Box::new(CallOpArgs {
+        site: holder_ctor,
+        args: args
+            .iter()
+            .map(|arg| crate::operand::handle_operand(&arg.node, ctx))
+            .collect(),
+    }));
+    let delegate_ctor = ctx
+        .class_ref(delegate)
+        .clone()
+        .ctor(&[Type::PlatformObject, Type::Int(Int::ISize)], ctx);
+    crate::place::place_set(
+        destination,
+        CILNode::NewObj(Box::new(CallOpArgs {
+            site: delegate_ctor,
+            args: [holder_obj, conv_isize!(CILNode::LDFtn(invoke))].into(),
+        })),
+        ctx,
+    )
+}
+/// Allocates a new managed array(used for interop). One-dimensional arrays are created using `newarr`, and take their
+/// length as the only argument. Multidimensional arrays are created using `Array.CreateInstance`, and take an `int[]`
+/// of their lengths.
+fn new_managed_arr<'tcx>(
+    subst_ref: &[GenericArg<'tcx>],
+    args: &[Spanned<Operand<'tcx>>],
+    destination: &Place<'tcx>,
+    multidimensional: bool,
+    ctx: &mut MethodCompileCtx<'tcx, '_>,
+) -> CILRoot {
+    assert_eq!(
+        args.len(),
+        1,
+        "Managed arrays are allocated from their length(s) only"
+    );
+    let elem = ctx.type_from_cache(
+        ctx.monomorphize(subst_ref[0])
+            .as_type()
+            .expect("Expceted generic type but got something that was not a type!"),
+    );
+    let lengths = crate::operand::handle_operand(&args[0].node, ctx);
+    if !multidimensional {
+        let len = cilly::v2::CILNode::from_v1(&lengths, ctx);
+        let len = ctx.alloc_node(len);
+        let elem = ctx.alloc_type(elem);
+        let new_arr = ctx.alloc_node(cilly::v2::CILNode::NewArr { elem, len });
+        return crate::place::place_set(destination, CILNode::V2(new_arr), ctx);
+    }
+    let dims = crate::r#type::garag_to_usize(subst_ref[1], ctx.tcx());
+    let type_type = ClassRef::type_type(ctx);
+    let runtime_handle = ClassRef::runtime_type_hadle(ctx);
+    let get_type_from_handle = ctx.alloc_string("GetTypeFromHandle");
+    let get_type_from_handle = ctx.class_ref(type_type).clone().static_mref(
+        &[Type::ClassRef(runtime_handle)],
+        Type::ClassRef(type_type),
+        get_type_from_handle,
+        ctx,
+    );
+    let array = ClassRef::new(
+        ctx.alloc_string("System.Array"),
+        Some(ctx.alloc_string("System.Runtime")),
+        false,
+        [].into(),
+    );
+    let create_instance = ctx.alloc_string("CreateInstance");
+    let int_arr = ctx.alloc_type(Type::Int(Int::I32));
+    let create_instance = array.static_mref(
+        &[
+            Type::ClassRef(type_type),
+            Type::PlatformArray {
+                elem: int_arr,
+                dims: NonZeroU8::new(1).unwrap(),
+            },
+        ],
+        Type::ClassRef(ctx.alloc_class_ref(array.clone())),
+        create_instance,
+        ctx,
+    );
+    let created = call!(
+        create_instance,
+        [
+            call!(get_type_from_handle, [CILNode::LDTypeToken(elem.into())]),
+            lengths
+        ]
+    );
+    // `Array.CreateInstance` returns a `System.Array`, which needs to be cast to the right array type.
+    let created = cilly::v2::CILNode::from_v1(&created, ctx);
+    let created = ctx.alloc_node(created);
+    let elem = ctx.alloc_type(elem);
+    let arr_tpe = ctx.alloc_type(Type::PlatformArray {
+        elem,
+        dims: NonZeroU8::new(dims.try_into().expect("Too many array dimensions"))
+            .expect("Arrays must have at least one dimension"),
+    });
+    let cast = ctx.alloc_node(cilly::v2::CILNode::CheckedCast(created, arr_tpe));
+    crate::place::place_set(destination, CILNode::V2(cast), ctx)
+}
+/// Pins a managed array(like a C# `fixed` statement), and returns the address of its first element. The array is
+/// stored in a pinned local of the current method, so it stays pinned until that method returns.
+fn pin_managed_arr<'tcx>(
+    subst_ref: &[GenericArg<'tcx>],
+    args: &[Spanned<Operand<'tcx>>],
+    destination: &Place<'tcx>,
+    ctx: &mut MethodCompileCtx<'tcx, '_>,
+) -> CILRoot {
+    assert_eq!(args.len(), 1, "Only a managed array can be pinned");
+    let elem = ctx.type_from_cache(
+        ctx.monomorphize(subst_ref[0])
+            .as_type()
+            .expect("Expceted generic type but got something that was not a type!"),
+    );
+    let dims = crate::r#type::garag_to_usize(subst_ref[1], ctx.tcx());
+    let elem_idx = ctx.alloc_type(elem);
+    let arr_tpe = ctx.alloc_type(Type::PlatformArray {
+        elem: elem_idx,
+        dims: NonZeroU8::new(dims.try_into().expect("Too many array dimensions"))
+            .expect("Arrays must have at least one dimension"),
+    });
+    let pinned = ctx.alloc_type(Type::Pinned(arr_tpe));
+    // `MemoryMarshal.GetArrayDataReference(Array)` returns a reference to the first element of an array of any rank,
+    // even if the array is empty.
+    let array = ClassRef::new(
+        ctx.alloc_string("System.Array"),
+        Some(ctx.alloc_string("System.Runtime")),
+        false,
+        [].into(),
+    );
+    let array = ctx.alloc_class_ref(array);
+    let marshal = ClassRef::new(
+        ctx.alloc_string("System.Runtime.InteropServices.MemoryMarshal"),
+        Some(ctx.alloc_string("System.Runtime")),
+        false,
+        [].into(),
+    );
+    let data_ref = ctx.nref(Type::Int(Int::U8));
+    let get_array_data_reference = ctx.alloc_string("GetArrayDataReference");
+    let get_array_data_reference = marshal.static_mref(
+        &[Type::ClassRef(array)],
+        data_ref,
+        get_array_data_reference,
+        ctx,
+    );
+    let arr = crate::operand::handle_operand(&args[0].node, ctx);
+    let elem_ptr = ctx.nptr(elem);
+    let addr = CILNode::TemporaryLocal(Box::new((
+        pinned,
+        [CILRoot::SetTMPLocal { value: arr }].into(),
+        CILNode::CastPtr {
+            val: Box::new(CILNode::MRefToRawPtr(Box::new(call!(
+                get_array_data_reference,
+                [CILNode::LoadTMPLocal]
+            )))),
+            new_ptr: Box::new(elem_ptr),
+        },
+    )));
+    crate::place::place_set(destination, addr, ctx)
+}
+/// Creates a new managed object using a constructor with any number of arguments, passed in a tuple.
+fn call_ctor_tuple<'tcx>(
+    subst_ref: &[GenericArg<'tcx>],
+    args: &[Spanned<Operand<'tcx>>],
+    destination: &Place<'tcx>,
+    ctx: &mut MethodCompileCtx<'tcx, '_>,
+) -> CILRoot {
+    assert_eq!(
+        args.len(),
+        1,
+        "N-ary constructors take all their arguments in a single tuple"
+    );
+    let tpe = managed_class(subst_ref, ctx);
+    let (mut inputs, call_args) = tuple_args(&args[0].node, ctx);
+    inputs.insert(0, Type::ClassRef(tpe));
+    let ctor = MethodRef::new(
+        tpe,
+        ctx.alloc_string(".ctor"),
+        ctx.sig(inputs, Type::Void),
+        MethodKind::Constructor,
+        vec![].into(),
+    );
+    crate::place::place_set(
+        destination,
+        CILNode::NewObj(Box::new(CallOpArgs {
+            site: ctx.alloc_methodref(ctor),
+            args: call_args.into(),
+        })),
+        ctx,
+    )
+}
 pub fn call_closure<'tcx>(
     args: &[Spanned<Operand<'tcx>>],
     destination: &Place<'tcx>,
@@ -463,8 +1037,70 @@ pub fn call<'tcx>(
         );
     }
     let mut signature = call_info.sig().clone();
-    // Checks if function is "magic"
-    if function_name.contains(CTOR_FN_NAME) {
+    // Checks if function is "magic". The N-ary intrinsics must be checked first, since their names contain the names of the fixed-arity ones.
+    if function_name.contains(CTOR_TUPLE_FN_NAME) {
+        return vec![call_ctor_tuple(subst_ref, args, destination, ctx)];
+    } else if function_name.contains(MANAGED_CALL_ACCESSOR_FN_NAME) {
+        return vec![call_managed_accessor(subst_ref, args, destination, ctx)];
+    } else if function_name.contains(MANAGED_LD_STATIC_FLD) {
+        return vec![managed_field_access(
+            subst_ref,
+            args,
+            destination,
+            true,
+            false,
+            ctx,
+        )];
+    } else if function_name.contains(MANAGED_ST_STATIC_FLD) {
+        return vec![managed_field_access(
+            subst_ref,
+            args,
+            destination,
+            true,
+            true,
+            ctx,
+        )];
+    } else if function_name.contains(MANAGED_LD_FLD) {
+        return vec![managed_field_access(
+            subst_ref,
+            args,
+            destination,
+            false,
+            false,
+            ctx,
+        )];
+    } else if function_name.contains(MANAGED_ST_FLD) {
+        return vec![managed_field_access(
+            subst_ref,
+            args,
+            destination,
+            false,
+            true,
+            ctx,
+        )];
+    } else if function_name.contains(MANAGED_CALL_VIRT_TUPLE_FN_NAME) {
+        return vec![call_managed_tuple(subst_ref, args, destination, true, ctx)];
+    } else if function_name.contains(MANAGED_CALL_TUPLE_FN_NAME) {
+        return vec![call_managed_tuple(subst_ref, args, destination, false, ctx)];
+    } else if function_name.contains(MANAGED_TRY_CALL_VIRT_TUPLE_FN_NAME) {
+        return vec![try_call_managed_tuple(
+            subst_ref,
+            args,
+            destination,
+            true,
+            ctx,
+        )];
+    } else if function_name.contains(MANAGED_TRY_CALL_TUPLE_FN_NAME) {
+        return vec![try_call_managed_tuple(
+            subst_ref,
+            args,
+            destination,
+            false,
+            ctx,
+        )];
+    } else if function_name.contains(MANAGED_DELEGATE_FROM_FN) {
+        return vec![delegate_from_fn(subst_ref, args, destination, ctx)];
+    } else if function_name.contains(CTOR_FN_NAME) {
         assert!(
             !call_info.split_last_tuple(),
             "Constructors may not use the `rust_call` calling convention!"
@@ -549,6 +1185,12 @@ pub fn call<'tcx>(
             CILNode::IsInst(Box::new((input, tpe))),
             ctx,
         )];
+    } else if function_name.contains(MANAGED_ARR_PIN) {
+        return vec![pin_managed_arr(subst_ref, args, destination, ctx)];
+    } else if function_name.contains(MANAGED_NEW_MD_ARR) {
+        return vec![new_managed_arr(subst_ref, args, destination, true, ctx)];
+    } else if function_name.contains(MANAGED_NEW_ARR) {
+        return vec![new_managed_arr(subst_ref, args, destination, false, ctx)];
     } else if function_name.contains(MANAGED_LD_ELEM_REF) {
         assert!(
             !call_info.split_last_tuple(),
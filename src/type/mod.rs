@@ -71,6 +71,7 @@ fn get_adt<'tcx>(
         ctx.alloc_class_ref(cref)
     } else {
         let cref = ctx.alloc_class_ref(cref);
+        crate::interop::check_adt_fields(adt_ty, def, subst, ctx.tcx());
         let def = match def.adt_kind() {
             AdtKind::Struct => struct_(name, def, adt_ty, subst, ctx),
             AdtKind::Enum => enum_(name, def, adt_ty, subst, ctx),
@@ -120,6 +121,12 @@ pub fn get_type<'tcx>(ty: Ty<'tcx>, ctx: &mut MethodCompileCtx<'tcx, '_>) -> Typ
             let cref = ctx.alloc_class_ref(ClassRef::new(name, None, true, [].into()));
             // If there is no defition of this closure present, create the closure.
             if ctx.class_ref_to_def(cref).is_none() {
+                crate::interop::check_captures(
+                    ty,
+                    closure.upvar_tys().iter(),
+                    ctx.tcx().def_span(*def),
+                    ctx.tcx(),
+                );
                 let type_def = closure_typedef(&fields, layout.layout, ctx, name);
                 ctx.class_def(type_def);
             }
@@ -194,6 +201,7 @@ pub fn get_type<'tcx>(ty: Ty<'tcx>, ctx: &mut MethodCompileCtx<'tcx, '_>) -> Typ
                 let cref = ClassRef::new(name, None, true, [].into());
                 // This only checks if a refernce to this class has already been allocated. In theory, allocating a class reference beforhand could break this, and make it not add the type definition
                 if !ctx.contains_ref(&cref) {
+                    crate::interop::check_tuple_fields(ty, ctx.tcx());
                     let layout = ctx.layout_of(ty);
                     let _ = tuple_typedef(&types, layout.layout, ctx, name);
                 }
@@ -306,6 +314,13 @@ pub fn get_type<'tcx>(ty: Ty<'tcx>, ctx: &mut MethodCompileCtx<'tcx, '_>) -> Typ
             let cref = ctx.alloc_class_ref(ClassRef::new(name, None, true, [].into()));
             // If there is no defition of this coroutine present, create the coroutine.
             if ctx.class_ref_to_def(cref).is_none() {
+                crate::interop::check_captures(
+                    ty,
+                    coroutine_args.upvar_tys().iter(),
+                    ctx.tcx().def_span(*defid),
+                    ctx.tcx(),
+                );
+                crate::interop::check_saved_locals(ty, *defid, coroutine_args, ctx.tcx());
                 let mut type_def = closure_typedef(&fields, layout.layout, ctx, name);
                 handle_tag(&layout.layout, ctx, ty, type_def.fields_mut());
                 ctx.class_def(type_def);
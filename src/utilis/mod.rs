@@ -14,13 +14,45 @@ pub mod adt;
 pub const CTOR_FN_NAME: &str = "rustc_clr_interop_managed_ctor";
 pub const MANAGED_CALL_FN_NAME: &str = "rustc_clr_interop_managed_call";
 pub const MANAGED_CALL_VIRT_FN_NAME: &str = "rustc_clr_interop_managed_call_virt";
+pub const CTOR_TUPLE_FN_NAME: &str = "rustc_clr_interop_managed_ctor_tuple";
+pub const MANAGED_CALL_TUPLE_FN_NAME: &str = "rustc_clr_interop_managed_call_tuple";
+pub const MANAGED_CALL_VIRT_TUPLE_FN_NAME: &str = "rustc_clr_interop_managed_call_virt_tuple";
+pub const MANAGED_TRY_CALL_TUPLE_FN_NAME: &str = "rustc_clr_interop_managed_try_call_tuple";
+pub const MANAGED_TRY_CALL_VIRT_TUPLE_FN_NAME: &str =
+    "rustc_clr_interop_managed_try_call_virt_tuple";
+pub const MANAGED_DELEGATE_FROM_FN: &str = "rustc_clr_interop_delegate_from_fn";
 pub const MANAGED_LD_LEN: &str = "rustc_clr_interop_managed_ld_len";
 pub const MANAGED_LD_NULL: &str = "rustc_clr_interop_managed_ld_null";
 pub const MANAGED_CHECKED_CAST: &str = "rustc_clr_interop_managed_checked_cast";
 pub const MANAGED_IS_INST: &str = "rustc_clr_interop_managed_is_inst";
 pub const MANAGED_LD_ELEM_REF: &str = "rustc_clr_interop_managed_ld_elem_ref";
+pub const MANAGED_NEW_ARR: &str = "rustc_clr_interop_managed_new_arr";
+pub const MANAGED_NEW_MD_ARR: &str = "rustc_clr_interop_managed_new_md_arr";
+pub const MANAGED_ARR_PIN: &str = "rustc_clr_interop_managed_arr_pin";
+pub const MANAGED_CALL_ACCESSOR_FN_NAME: &str = "rustc_clr_interop_managed_call_accessor";
+pub const MANAGED_LD_FLD: &str = "rustc_clr_interop_managed_ld_fld";
+pub const MANAGED_ST_FLD: &str = "rustc_clr_interop_managed_st_fld";
+pub const MANAGED_LD_STATIC_FLD: &str = "rustc_clr_interop_managed_ld_static_fld";
+pub const MANAGED_ST_STATIC_FLD: &str = "rustc_clr_interop_managed_st_static_fld";
 pub fn is_function_magic(name: &str) -> bool {
-    name.contains(CTOR_FN_NAME) || name.contains(MANAGED_CALL_FN_NAME)
+    name.contains(CTOR_FN_NAME)
+        || name.contains(MANAGED_CALL_FN_NAME)
+        || name.contains(MANAGED_TRY_CALL_TUPLE_FN_NAME)
+        || name.contains(MANAGED_TRY_CALL_VIRT_TUPLE_FN_NAME)
+        || name.contains(MANAGED_DELEGATE_FROM_FN)
+        || name.contains(MANAGED_ARR_PIN)
+        || name.contains(MANAGED_CALL_ACCESSOR_FN_NAME)
+        || name.contains(MANAGED_LD_FLD)
+        || name.contains(MANAGED_ST_FLD)
+        || name.contains(MANAGED_LD_STATIC_FLD)
+        || name.contains(MANAGED_ST_STATIC_FLD)
+        || name.contains(MANAGED_LD_LEN)
+        || name.contains(MANAGED_LD_NULL)
+        || name.contains(MANAGED_CHECKED_CAST)
+        || name.contains(MANAGED_IS_INST)
+        || name.contains(MANAGED_LD_ELEM_REF)
+        || name.contains(MANAGED_NEW_ARR)
+        || name.contains(MANAGED_NEW_MD_ARR)
 }
 pub fn as_adt(ty: Ty) -> Option<(AdtDef, &List<GenericArg>)> {
     match ty.kind() {
@@ -33,6 +65,10 @@ pub fn adt_name<'tcx>(
     tcx: TyCtxt<'tcx>,
     gargs: &'tcx List<GenericArg<'tcx>>,
 ) -> crate::IString {
+    // Types marked with `#[dotnet::export]` keep their readable name.
+    if let Some(export) = crate::export::DotnetExport::of_adt(tcx, adt, gargs) {
+        return export.type_name().into();
+    }
     //TODO: find a better way to get adt name!
     let _gdef_str = if gargs
         .iter()
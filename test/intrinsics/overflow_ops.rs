@@ -25,4 +25,46 @@ fn main() {
         mul_with_overflow(1_000_000_000u32, 10),
         black_box((1410065408, true))
     );
+    test_eq!(
+        mul_with_overflow(black_box(5i128), 0),
+        black_box((0, false))
+    );
+    test_eq!(
+        mul_with_overflow(black_box(0i128), -7),
+        black_box((0, false))
+    );
+    test_eq!(
+        mul_with_overflow(black_box(6i128), 7),
+        black_box((42, false))
+    );
+    test_eq!(
+        mul_with_overflow(black_box(-6i128), 7),
+        black_box((-42, false))
+    );
+    test_eq!(
+        mul_with_overflow(black_box(-6i128), -7),
+        black_box((42, false))
+    );
+    test_eq!(
+        mul_with_overflow(black_box(i128::MAX), 2),
+        black_box((-2, true))
+    );
+    test_eq!(
+        mul_with_overflow(black_box(i128::MIN), -1),
+        black_box((i128::MIN, true))
+    );
+    test_eq!(
+        mul_with_overflow(black_box(i128::MIN), 1),
+        black_box((i128::MIN, false))
+    );
+    test_eq!(
+        mul_with_overflow(black_box(-1i128), i128::MIN),
+        black_box((i128::MIN, true))
+    );
+    test_eq!(black_box(i128::MAX).checked_mul(2), None);
+    test_eq!(black_box(1i128 << 63).checked_mul(1 << 63), None);
+    test_eq!(
+        black_box(1i128 << 62).checked_mul(1 << 63),
+        Some(1i128 << 125)
+    );
 }
@@ -18,6 +18,7 @@
 #![feature(unboxed_closures)]
 include!("../common.rs");
 type Object = RustcCLRInteropManagedClass<"System.Runtime", "System.Object">;
+type Exception = RustcCLRInteropManagedClass<"System.Runtime", "System.Exception">;
 type MString = RustcCLRInteropManagedClass<"System.Runtime", "System.String">;
 //struct RustcCLRInteropVirtualMethodDef<const name:&'static str,const access:u8>{pd:core::marker::PhantomData<Func>}
 struct RustcCLRInteropFieldDef<T, const ACCESS: u8, const IS_STATIC: bool> {
@@ -65,6 +66,15 @@ struct RustcCLRInteropManagedClass<const ASSEMBLY: &'static str, const CLASS_PAT
     prevent_construction: usize,
 }
 #[derive(Clone, Copy)]
+#[repr(C)]
+struct RustcCLRInteropManagedStruct<
+    const ASSEMBLY: &'static str,
+    const CLASS_PATH: &'static str,
+    const SIZE: usize,
+> {
+    size_hint: [u8; SIZE],
+}
+#[derive(Clone, Copy)]
 struct RustcCLRInteropManagedChar {
     size: u16,
 }
@@ -133,6 +143,130 @@ fn rustc_clr_interop_managed_ctor3_<
 ) -> RustcCLRInteropManagedClass<ASSEMBLY, CLASS_PATH> {
     core::intrinsics::abort();
 }
+#[allow(dead_code)]
+#[inline(never)]
+fn rustc_clr_interop_managed_call_tuple_<
+    const ASSEMBLY: &'static str,
+    const CLASS_PATH: &'static str,
+    const IS_VALUETYPE: bool,
+    const METHOD: &'static str,
+    const IS_STATIC: bool,
+    Ret,
+    Args,
+>(
+    args: Args,
+) -> Ret {
+    core::intrinsics::abort();
+}
+#[allow(dead_code)]
+#[inline(never)]
+fn rustc_clr_interop_managed_call_virt_tuple_<
+    const ASSEMBLY: &'static str,
+    const CLASS_PATH: &'static str,
+    const IS_VALUETYPE: bool,
+    const METHOD: &'static str,
+    const IS_STATIC: bool,
+    Ret,
+    Args,
+>(
+    args: Args,
+) -> Ret {
+    core::intrinsics::abort();
+}
+#[allow(dead_code)]
+#[inline(never)]
+fn rustc_clr_interop_managed_ctor_tuple_<
+    const ASSEMBLY: &'static str,
+    const CLASS_PATH: &'static str,
+    const IS_VALUETYPE: bool,
+    Args,
+>(
+    args: Args,
+) -> RustcCLRInteropManagedClass<ASSEMBLY, CLASS_PATH> {
+    core::intrinsics::abort();
+}
+#[allow(dead_code)]
+#[inline(never)]
+fn rustc_clr_interop_managed_call_accessor<
+    const ASSEMBLY: &'static str,
+    const CLASS_PATH: &'static str,
+    const IS_VALUETYPE: bool,
+    const ACCESSOR: &'static str,
+    const MEMBER: &'static str,
+    const IS_STATIC: bool,
+    Ret,
+    Args,
+>(
+    args: Args,
+) -> Ret {
+    core::intrinsics::abort();
+}
+#[allow(dead_code)]
+#[inline(never)]
+fn rustc_clr_interop_managed_ld_fld<
+    const ASSEMBLY: &'static str,
+    const CLASS_PATH: &'static str,
+    const IS_VALUETYPE: bool,
+    const FIELD: &'static str,
+    T,
+    Obj,
+>(
+    obj: Obj,
+) -> T {
+    core::intrinsics::abort();
+}
+#[allow(dead_code)]
+#[inline(never)]
+fn rustc_clr_interop_managed_st_fld<
+    const ASSEMBLY: &'static str,
+    const CLASS_PATH: &'static str,
+    const IS_VALUETYPE: bool,
+    const FIELD: &'static str,
+    T,
+    Obj,
+>(
+    obj: Obj,
+    value: T,
+) {
+    core::intrinsics::abort();
+}
+#[allow(dead_code)]
+#[inline(never)]
+fn rustc_clr_interop_managed_ld_static_fld<
+    const ASSEMBLY: &'static str,
+    const CLASS_PATH: &'static str,
+    const IS_VALUETYPE: bool,
+    const FIELD: &'static str,
+    T,
+>() -> T {
+    core::intrinsics::abort();
+}
+#[allow(dead_code)]
+#[inline(never)]
+fn rustc_clr_interop_managed_ld_null<T>() -> T {
+    core::intrinsics::abort();
+}
+#[allow(dead_code)]
+#[inline(never)]
+fn rustc_clr_interop_managed_is_inst<DST, SRC>(src: SRC) -> bool {
+    core::intrinsics::abort();
+}
+#[allow(dead_code)]
+#[inline(never)]
+fn rustc_clr_interop_managed_try_call_tuple_<
+    const ASSEMBLY: &'static str,
+    const CLASS_PATH: &'static str,
+    const IS_VALUETYPE: bool,
+    const METHOD: &'static str,
+    const IS_STATIC: bool,
+    Ret,
+    Args,
+>(
+    args: Args,
+    ret: *mut Ret,
+) -> Exception {
+    core::intrinsics::abort();
+}
 //struct RustcCLRInteropManagedBool;
 fn main() {
     let chr: *mut RustcCLRInteropManagedChar = core::ptr::null_mut();
@@ -160,6 +294,9 @@ fn main() {
     //test_ctors();
     console_writeline();
     new_helloworld();
+    tuple_calls();
+    accessors();
+    try_calls();
 }
 fn test_ctors() {
     let obj = black_box(rustc_clr_interop_managed_ctor0_::<
@@ -214,3 +351,238 @@ fn new_helloworld() {
         RustcCLRInteropManagedChar,
     >(sb, m_char);
 }
+fn tuple_calls() {
+    let clamped = rustc_clr_interop_managed_call_tuple_::<
+        "System.Runtime",
+        "System.Math",
+        false,
+        "Clamp",
+        true,
+        i32,
+        (i32, i32, i32),
+    >((black_box(100), 0, 10));
+    test_eq!(clamped, 10);
+    rustc_clr_interop_managed_call_tuple_::<
+        "System.Console",
+        "System.Console",
+        false,
+        "WriteLine",
+        true,
+        (),
+        (i32,),
+    >((clamped,));
+    let sb = rustc_clr_interop_managed_ctor_tuple_::<
+        "System.Runtime",
+        "System.Text.StringBuilder",
+        false,
+        (i32,),
+    >((16,));
+    let m_char = unsafe { core::mem::transmute::<u16, RustcCLRInteropManagedChar>(black_box(64)) };
+    rustc_clr_interop_managed_call_tuple_::<
+        "System.Runtime",
+        "System.Text.StringBuilder",
+        false,
+        "Append",
+        false,
+        StringBuilder,
+        (StringBuilder, RustcCLRInteropManagedChar),
+    >((sb, m_char));
+    // `ToString` is virtual, so this calls the override of `StringBuilder`.
+    let string = rustc_clr_interop_managed_call_virt_tuple_::<
+        "System.Runtime",
+        "System.Object",
+        false,
+        "ToString",
+        false,
+        MString,
+        (StringBuilder,),
+    >((sb,));
+    let len = rustc_clr_interop_managed_call_tuple_::<
+        "System.Runtime",
+        "System.String",
+        false,
+        "get_Length",
+        false,
+        i32,
+        (MString,),
+    >((string,));
+    test_eq!(len, 1);
+}
+type Vector3 =
+    RustcCLRInteropManagedStruct<"System.Numerics.Vectors", "System.Numerics.Vector3", 12>;
+type DateTime = RustcCLRInteropManagedStruct<"System.Runtime", "System.DateTime", 8>;
+type AppDomain = RustcCLRInteropManagedClass<"System.Runtime", "System.AppDomain">;
+type EventHandler = RustcCLRInteropManagedClass<"System.Runtime", "System.EventHandler">;
+fn accessors() {
+    // Instance properties of a class
+    let sb =
+        rustc_clr_interop_managed_ctor0_::<"System.Runtime", "System.Text.StringBuilder", false>();
+    let m_char = unsafe { core::mem::transmute::<u16, RustcCLRInteropManagedChar>(black_box(64)) };
+    rustc_clr_interop_managed_call_tuple_::<
+        "System.Runtime",
+        "System.Text.StringBuilder",
+        false,
+        "Append",
+        false,
+        StringBuilder,
+        (StringBuilder, RustcCLRInteropManagedChar, i32),
+    >((sb, m_char, 3));
+    let len = rustc_clr_interop_managed_call_accessor::<
+        "System.Runtime",
+        "System.Text.StringBuilder",
+        false,
+        "get",
+        "Length",
+        false,
+        i32,
+        (StringBuilder,),
+    >((sb,));
+    test_eq!(len, 3);
+    rustc_clr_interop_managed_call_accessor::<
+        "System.Runtime",
+        "System.Text.StringBuilder",
+        false,
+        "set",
+        "Length",
+        false,
+        (),
+        (StringBuilder, i32),
+    >((sb, 1));
+    let len = rustc_clr_interop_managed_call_accessor::<
+        "System.Runtime",
+        "System.Text.StringBuilder",
+        false,
+        "get",
+        "Length",
+        false,
+        i32,
+        (StringBuilder,),
+    >((sb,));
+    test_eq!(len, 1);
+    // Fields of a value type
+    let mut one = rustc_clr_interop_managed_call_accessor::<
+        "System.Numerics.Vectors",
+        "System.Numerics.Vector3",
+        true,
+        "get",
+        "One",
+        true,
+        Vector3,
+        (),
+    >(());
+    let x = rustc_clr_interop_managed_ld_fld::<
+        "System.Numerics.Vectors",
+        "System.Numerics.Vector3",
+        true,
+        "X",
+        f32,
+        &Vector3,
+    >(&one);
+    test_eq!(x, 1.0);
+    rustc_clr_interop_managed_st_fld::<
+        "System.Numerics.Vectors",
+        "System.Numerics.Vector3",
+        true,
+        "Y",
+        f32,
+        &mut Vector3,
+    >(&mut one, black_box(2.5));
+    let y = rustc_clr_interop_managed_ld_fld::<
+        "System.Numerics.Vectors",
+        "System.Numerics.Vector3",
+        true,
+        "Y",
+        f32,
+        &Vector3,
+    >(&one);
+    test_eq!(y, 2.5);
+    // A static field, and an instance property of a value type
+    let epoch = rustc_clr_interop_managed_ld_static_fld::<
+        "System.Runtime",
+        "System.DateTime",
+        true,
+        "UnixEpoch",
+        DateTime,
+    >();
+    let year = rustc_clr_interop_managed_call_accessor::<
+        "System.Runtime",
+        "System.DateTime",
+        true,
+        "get",
+        "Year",
+        false,
+        i32,
+        (&DateTime,),
+    >((&epoch,));
+    test_eq!(year, 1970);
+    // Events. Subscribing a null handler does nothing, but still calls the accessors.
+    let domain = rustc_clr_interop_managed_call_accessor::<
+        "System.Runtime",
+        "System.AppDomain",
+        false,
+        "get",
+        "CurrentDomain",
+        true,
+        AppDomain,
+        (),
+    >(());
+    let handler = rustc_clr_interop_managed_ld_null::<EventHandler>();
+    rustc_clr_interop_managed_call_accessor::<
+        "System.Runtime",
+        "System.AppDomain",
+        false,
+        "add",
+        "ProcessExit",
+        false,
+        (),
+        (AppDomain, EventHandler),
+    >((domain, handler));
+    rustc_clr_interop_managed_call_accessor::<
+        "System.Runtime",
+        "System.AppDomain",
+        false,
+        "remove",
+        "ProcessExit",
+        false,
+        (),
+        (AppDomain, EventHandler),
+    >((domain, handler));
+}
+type ArgumentNullException =
+    RustcCLRInteropManagedClass<"System.Runtime", "System.ArgumentNullException">;
+fn try_calls() {
+    // A call returning normally writes its result, and returns null.
+    let mut clamped = 0;
+    let exception = rustc_clr_interop_managed_try_call_tuple_::<
+        "System.Runtime",
+        "System.Math",
+        false,
+        "Clamp",
+        true,
+        i32,
+        (i32, i32, i32),
+    >((black_box(100), 0, 10), &mut clamped);
+    test!(!rustc_clr_interop_managed_is_inst::<Exception, Exception>(
+        exception
+    ));
+    test_eq!(clamped, 10);
+    // A call throwing an exception returns it.
+    let mut parsed = 0;
+    let exception = rustc_clr_interop_managed_try_call_tuple_::<
+        "System.Runtime",
+        "System.Int32",
+        false,
+        "Parse",
+        true,
+        i32,
+        (MString,),
+    >(
+        (rustc_clr_interop_managed_ld_null::<MString>(),),
+        &mut parsed,
+    );
+    test!(rustc_clr_interop_managed_is_inst::<
+        ArgumentNullException,
+        Exception,
+    >(exception));
+    test_eq!(parsed, 0);
+}